@@ -0,0 +1,190 @@
+use garage_ray_simple::*;
+
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Instant;
+
+const USAGE: &str = "Usage: garage_ray_cli [OPTIONS] [SCENE]
+
+SCENE is either the name of a built-in scene or a path to a scene file (default: cornel_box)
+
+Options:
+    -w, --width <PIXELS>      Image width (default: 800)
+    -h, --height <PIXELS>     Image height (default: 800)
+    -s, --samples <COUNT>     Samples per pixel (default: 1000)
+    -d, --max-depth <COUNT>   Maximum number of bounces per path (default: 50)
+        --seed <NUMBER>       Seed for the random numbers, equal seeds give identical images (default: 0)
+        --sampler <NAME>      Sample pattern, one of random, stratified, cmj, sobol or bluenoise
+                              (default: sobol)
+    -o, --output <PATH>       Output image, format is taken from the extension (default: output.png).
+                              .exr and .hdr images keep the linear values without tone mapping
+    -t, --tonemap <NAME>      Tone mapper, one of clamp, reinhard or aces (default: clamp)
+    -e, --exposure <STOPS>    Exposure adjustment applied before tone mapping (default: 0)
+        --float-exr           Write EXR images with 32 bit floats instead of halfs
+        --help                Print this message";
+
+struct Options {
+    scene: String,
+    width: u32,
+    height: u32,
+    samples: i32,
+    max_depth: i32,
+    seed: u64,
+    sample_pattern: SamplePattern,
+    output: String,
+    tone_mapping: ToneMapping,
+    exr_pixel_type: ExrPixelType,
+}
+
+fn parse_value<T: std::str::FromStr>(option: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("missing value for '{}'", option))?;
+    value
+        .parse()
+        .map_err(|_| format!("invalid value '{}' for '{}'", value, option))
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        scene: String::from("cornel_box"),
+        width: 800,
+        height: 800,
+        samples: 1000,
+        max_depth: 50,
+        seed: 0,
+        sample_pattern: SamplePattern::Sobol,
+        output: String::from("output.png"),
+        tone_mapping: ToneMapping::default(),
+        exr_pixel_type: ExrPixelType::Half,
+    };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-w" | "--width" => options.width = parse_value(&arg, args.next())?,
+            "-h" | "--height" => options.height = parse_value(&arg, args.next())?,
+            "-s" | "--samples" => options.samples = parse_value(&arg, args.next())?,
+            "-d" | "--max-depth" => options.max_depth = parse_value(&arg, args.next())?,
+            "--seed" => options.seed = parse_value(&arg, args.next())?,
+            "--sampler" => {
+                let name = args
+                    .next()
+                    .ok_or_else(|| format!("missing value for '{}'", arg))?;
+                options.sample_pattern = name.parse()?;
+            }
+            "-o" | "--output" => options.output = parse_value(&arg, args.next())?,
+            "-t" | "--tonemap" => {
+                let name = args
+                    .next()
+                    .ok_or_else(|| format!("missing value for '{}'", arg))?;
+                options.tone_mapping.tone_mapper = name.parse()?;
+            }
+            "-e" | "--exposure" => options.tone_mapping.exposure = parse_value(&arg, args.next())?,
+            "--float-exr" => options.exr_pixel_type = ExrPixelType::Float,
+            "--help" => {
+                println!("{}", USAGE);
+                std::process::exit(0);
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown option '{}'", arg)),
+            _ => options.scene = arg,
+        }
+    }
+
+    if options.width == 0 || options.height == 0 {
+        return Err(String::from("image size must be at least 1x1"));
+    }
+    if options.samples < 1 {
+        return Err(String::from("at least one sample per pixel is needed"));
+    }
+    Ok(options)
+}
+
+fn load(name: &str, aspect: f32) -> Result<Scene, String> {
+    if BUILTIN_SCENES.contains(&name) {
+        return builtin_scene(name, aspect)
+            .map_err(|err| format!("failed to build scene '{}': {}", name, err));
+    }
+    load_scene(name, aspect).map_err(|err| {
+        format!(
+            "failed to load scene '{}': {}\nBuilt-in scenes are: {}",
+            name,
+            err,
+            BUILTIN_SCENES.join(", ")
+        )
+    })
+}
+
+fn run(options: Options) -> Result<(), String> {
+    let now = Instant::now();
+    let scene = load(&options.scene, options.width as f32 / options.height as f32)?;
+    let world = BVHNode::build(scene.world, 0.0, 1.0);
+    eprintln!(
+        "Loaded scene '{}' in {:.2} seconds",
+        options.scene,
+        now.elapsed().as_secs_f32()
+    );
+
+    let settings = SamplerSettings {
+        pattern: options.sample_pattern,
+        seed: options.seed,
+        samples_per_pixel: options.samples,
+    };
+    let now = Instant::now();
+    let total_tiles = image_tiles(options.width, options.height).len();
+    // The frame and the number of finished tiles
+    let progress = Mutex::new((FrameBuffer::new(options.width, options.height), 0));
+    render_tiles(
+        options.width,
+        options.height,
+        &settings,
+        0,
+        options.samples,
+        options.max_depth,
+        &world,
+        &scene.lights,
+        &scene.environment,
+        &scene.camera,
+        &|tile, sums| {
+            let mut progress = progress.lock().unwrap();
+            let (frame, finished) = &mut *progress;
+            for ((x, y), sum) in tile.pixels().zip(sums) {
+                frame.set(x, y, sum / options.samples as f32);
+            }
+            *finished += 1;
+            eprint!(
+                "\rRendering: {:3}% ({}/{} tiles, {:.0} seconds)",
+                100 * *finished / total_tiles,
+                finished,
+                total_tiles,
+                now.elapsed().as_secs_f32()
+            );
+            std::io::stderr().flush().ok();
+            true
+        },
+    );
+    let (frame, _) = progress.into_inner().unwrap();
+    eprintln!();
+    eprintln!("Render took {:.2} seconds", now.elapsed().as_secs_f32());
+
+    frame.save(
+        Path::new(&options.output),
+        &options.tone_mapping,
+        options.exr_pixel_type,
+    )?;
+    eprintln!("Saved {}", options.output);
+    Ok(())
+}
+
+fn main() {
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("error: {}\nRun with --help for usage", err);
+            std::process::exit(2);
+        }
+    };
+    if let Err(err) = run(options) {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,481 @@
+use crate::{invalid_data, Image};
+use std::io::{Read, Write};
+use std::path::Path;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ExrPixelType {
+    Half,
+    Float,
+}
+
+impl ExrPixelType {
+    fn id(self) -> i32 {
+        match self {
+            ExrPixelType::Half => 1,
+            ExrPixelType::Float => 2,
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            ExrPixelType::Half => 2,
+            ExrPixelType::Float => 4,
+        }
+    }
+}
+
+// A set of R, G and B channels. The unnamed layer is the main image, others are written
+// as "name.R", "name.G" and "name.B"
+pub struct ExrLayer<'a> {
+    pub name: &'a str,
+    pub pixels: &'a [[f32; 3]],
+}
+
+struct Channel<'a> {
+    name: String,
+    pixels: &'a [[f32; 3]],
+    component: usize,
+}
+
+// Uncompressed single part scanline OpenEXR image
+pub fn write_exr<W: Write>(
+    writer: &mut W,
+    width: usize,
+    height: usize,
+    pixel_type: ExrPixelType,
+    layers: &[ExrLayer],
+) -> std::io::Result<()> {
+    let mut channels = Vec::with_capacity(3 * layers.len());
+    for layer in layers {
+        crate::check_size(width, height, layer.pixels)?;
+        for (component, suffix) in ["R", "G", "B"].iter().enumerate() {
+            let name = if layer.name.is_empty() {
+                suffix.to_string()
+            } else {
+                format!("{}.{}", layer.name, suffix)
+            };
+            channels.push(Channel {
+                name,
+                pixels: layer.pixels,
+                component,
+            });
+        }
+    }
+    // Readers expect the channel list in alphabetical order
+    channels.sort_by(|a, b| a.name.cmp(&b.name));
+    if channels.windows(2).any(|pair| pair[0].name == pair[1].name) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "EXR layer names must be unique",
+        ));
+    }
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&[0x76, 0x2f, 0x31, 0x01]);
+    // Version 2, with the flag for names longer than 31 characters if needed
+    let long_names = channels.iter().any(|channel| channel.name.len() > 31);
+    let flags: u32 = if long_names { 0x400 } else { 0 };
+    header.extend_from_slice(&(2 | flags).to_le_bytes());
+
+    let mut channel_list = Vec::new();
+    for channel in &channels {
+        channel_list.extend_from_slice(channel.name.as_bytes());
+        channel_list.push(0);
+        channel_list.extend_from_slice(&pixel_type.id().to_le_bytes());
+        // pLinear and reserved bytes
+        channel_list.extend_from_slice(&[0, 0, 0, 0]);
+        // x and y sampling
+        channel_list.extend_from_slice(&1_i32.to_le_bytes());
+        channel_list.extend_from_slice(&1_i32.to_le_bytes());
+    }
+    channel_list.push(0);
+    let window = [0, 0, width as i32 - 1, height as i32 - 1]
+        .iter()
+        .flat_map(|v| v.to_le_bytes().to_vec())
+        .collect::<Vec<u8>>();
+    write_attribute(&mut header, "channels", "chlist", &channel_list);
+    // No compression
+    write_attribute(&mut header, "compression", "compression", &[0]);
+    write_attribute(&mut header, "dataWindow", "box2i", &window);
+    write_attribute(&mut header, "displayWindow", "box2i", &window);
+    // Increasing y
+    write_attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+    write_attribute(
+        &mut header,
+        "pixelAspectRatio",
+        "float",
+        &1.0_f32.to_le_bytes(),
+    );
+    write_attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+    write_attribute(
+        &mut header,
+        "screenWindowWidth",
+        "float",
+        &1.0_f32.to_le_bytes(),
+    );
+    header.push(0);
+    writer.write_all(&header)?;
+
+    // Every scanline is its y, its size and then all values of each channel in turn
+    let line_size = channels.len() * width * pixel_type.size();
+    let first_line = header.len() + 8 * height;
+    for y in 0..height {
+        let offset = (first_line + y * (8 + line_size)) as u64;
+        writer.write_all(&offset.to_le_bytes())?;
+    }
+    let mut line = Vec::with_capacity(line_size);
+    for y in 0..height {
+        line.clear();
+        for channel in &channels {
+            for pixel in &channel.pixels[y * width..(y + 1) * width] {
+                let value = pixel[channel.component];
+                match pixel_type {
+                    ExrPixelType::Half => line.extend_from_slice(&to_half(value).to_le_bytes()),
+                    ExrPixelType::Float => line.extend_from_slice(&value.to_le_bytes()),
+                }
+            }
+        }
+        writer.write_all(&(y as i32).to_le_bytes())?;
+        writer.write_all(&(line_size as i32).to_le_bytes())?;
+        writer.write_all(&line)?;
+    }
+    writer.flush()
+}
+
+pub fn save_exr(
+    path: &Path,
+    width: usize,
+    height: usize,
+    pixel_type: ExrPixelType,
+    layers: &[ExrLayer],
+) -> std::io::Result<()> {
+    write_exr(&mut crate::create(path)?, width, height, pixel_type, layers)
+}
+
+// Compression schemes the reader can decode, with the number of scanlines in each block
+#[derive(Clone, Copy, PartialEq)]
+enum Compression {
+    None,
+    Rle,
+    Zips,
+    Zip,
+}
+
+impl Compression {
+    fn from_id(id: u8) -> Option<Compression> {
+        match id {
+            0 => Some(Compression::None),
+            1 => Some(Compression::Rle),
+            2 => Some(Compression::Zips),
+            3 => Some(Compression::Zip),
+            _ => None,
+        }
+    }
+
+    fn block_lines(self) -> usize {
+        match self {
+            Compression::Zip => 16,
+            _ => 1,
+        }
+    }
+}
+
+struct ChannelInfo {
+    name: String,
+    // 0 for unsigned int, 1 for half and 2 for float
+    kind: i32,
+}
+
+impl ChannelInfo {
+    fn size(&self) -> usize {
+        if self.kind == 1 {
+            2
+        } else {
+            4
+        }
+    }
+
+    fn value(&self, bytes: &[u8]) -> f32 {
+        match self.kind {
+            0 => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32,
+            1 => from_half(u16::from_le_bytes([bytes[0], bytes[1]])),
+            _ => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        }
+    }
+}
+
+// Single part scanline OpenEXR image, uncompressed or compressed with RLE, ZIPS or ZIP.
+// The R, G and B channels of the unnamed layer are read, or Y for grayscale images
+pub fn read_exr<R: Read>(reader: &mut R) -> std::io::Result<Image> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    let mut input = ByteReader { data: &data, at: 0 };
+    if input.bytes(4)? != [0x76, 0x2f, 0x31, 0x01] {
+        return Err(invalid_data("not an OpenEXR image"));
+    }
+    let version = input.i32()?;
+    // Tiled, deep and multi part images
+    if version & 0x1a00 != 0 {
+        return Err(invalid_data(
+            "only single part scanline EXR images are supported",
+        ));
+    }
+
+    let mut channels = Vec::new();
+    let mut compression = None;
+    let mut window = None;
+    loop {
+        let name = input.string()?;
+        if name.is_empty() {
+            break;
+        }
+        let _kind = input.string()?;
+        let size = input.i32()? as usize;
+        let mut value = ByteReader {
+            data: input.bytes(size)?,
+            at: 0,
+        };
+        match name.as_str() {
+            "channels" => loop {
+                let name = value.string()?;
+                if name.is_empty() {
+                    break;
+                }
+                let kind = value.i32()?;
+                value.bytes(4)?;
+                if value.i32()? != 1 || value.i32()? != 1 {
+                    return Err(invalid_data("subsampled EXR channels are not supported"));
+                }
+                channels.push(ChannelInfo { name, kind });
+            },
+            "compression" => {
+                let id = value.bytes(1)?[0];
+                compression =
+                    Some(Compression::from_id(id).ok_or_else(|| {
+                        invalid_data(format!("unsupported EXR compression {}", id))
+                    })?);
+            }
+            "dataWindow" => {
+                window = Some([value.i32()?, value.i32()?, value.i32()?, value.i32()?]);
+            }
+            _ => {}
+        }
+    }
+    let compression = compression.ok_or_else(|| invalid_data("EXR compression is missing"))?;
+    let [x_min, y_min, x_max, y_max] =
+        window.ok_or_else(|| invalid_data("EXR data window is missing"))?;
+    if x_max < x_min || y_max < y_min {
+        return Err(invalid_data("EXR data window is empty"));
+    }
+    let width = (x_max - x_min + 1) as usize;
+    let height = (y_max - y_min + 1) as usize;
+
+    // Which channel, if any, feeds each of R, G and B
+    let find = |name: &str| channels.iter().position(|channel| channel.name == name);
+    let sources = match (find("R"), find("G"), find("B"), find("Y")) {
+        (Some(r), Some(g), Some(b), _) => [r, g, b],
+        (_, _, _, Some(y)) => [y, y, y],
+        _ => return Err(invalid_data("EXR image has no R, G and B or Y channels")),
+    };
+    let line_size = channels.iter().map(|channel| channel.size()).sum::<usize>() * width;
+
+    let block_lines = compression.block_lines();
+    let blocks = height.div_ceil(block_lines);
+    let mut offsets = Vec::with_capacity(blocks);
+    for _ in 0..blocks {
+        offsets.push(input.u64()? as usize);
+    }
+    let mut pixels = vec![[0.0; 3]; width * height];
+    let mut line_values = vec![0.0; channels.len()];
+    for offset in offsets {
+        let mut block = ByteReader {
+            data: &data,
+            at: offset,
+        };
+        let first_line = block.i32()? - y_min;
+        let size = block.i32()? as usize;
+        if first_line < 0 || first_line as usize >= height {
+            return Err(invalid_data("EXR block is outside the data window"));
+        }
+        let first_line = first_line as usize;
+        let lines = block_lines.min(height - first_line);
+        let packed = block.bytes(size)?;
+        let unpacked;
+        let block_data = if size == lines * line_size || compression == Compression::None {
+            packed
+        } else {
+            unpacked = decompress(compression, packed, lines * line_size)?;
+            &unpacked[..]
+        };
+        if block_data.len() != lines * line_size {
+            return Err(invalid_data("EXR block has the wrong size"));
+        }
+
+        // Each scanline holds all values of each channel in turn
+        for (line, bytes) in block_data.chunks(line_size).enumerate() {
+            let row = &mut pixels[(first_line + line) * width..][..width];
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let mut start = 0;
+                for (value, channel) in line_values.iter_mut().zip(channels.iter()) {
+                    *value = channel.value(&bytes[start + x * channel.size()..]);
+                    start += width * channel.size();
+                }
+                *pixel = sources.map(|source| line_values[source]);
+            }
+        }
+    }
+    Ok(Image {
+        width,
+        height,
+        pixels,
+    })
+}
+
+pub fn load_exr(path: &Path) -> std::io::Result<Image> {
+    read_exr(&mut crate::open(path)?)
+}
+
+struct ByteReader<'a> {
+    data: &'a [u8],
+    at: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn bytes(&mut self, count: usize) -> std::io::Result<&'a [u8]> {
+        if self.at + count > self.data.len() {
+            return Err(invalid_data("EXR image is truncated"));
+        }
+        let bytes = &self.data[self.at..self.at + count];
+        self.at += count;
+        Ok(bytes)
+    }
+
+    fn i32(&mut self) -> std::io::Result<i32> {
+        let bytes = self.bytes(4)?;
+        Ok(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn u64(&mut self) -> std::io::Result<u64> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.bytes(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn string(&mut self) -> std::io::Result<String> {
+        let end = self.data[self.at..]
+            .iter()
+            .position(|byte| *byte == 0)
+            .ok_or_else(|| invalid_data("EXR image is truncated"))?;
+        let string = String::from_utf8_lossy(&self.data[self.at..self.at + end]).into_owned();
+        self.at += end + 1;
+        Ok(string)
+    }
+}
+
+// RLE and ZIP both compress bytes that were split into two interleaved halves and delta
+// encoded
+fn decompress(compression: Compression, packed: &[u8], size: usize) -> std::io::Result<Vec<u8>> {
+    let mut bytes = match compression {
+        Compression::Rle => {
+            let mut bytes = Vec::with_capacity(size);
+            let mut i = 0;
+            while i < packed.len() {
+                let count = packed[i] as i8;
+                if count < 0 {
+                    let end = i + 1 + (-(count as i32)) as usize;
+                    if end > packed.len() {
+                        return Err(invalid_data("EXR RLE block is truncated"));
+                    }
+                    bytes.extend_from_slice(&packed[i + 1..end]);
+                    i = end;
+                } else {
+                    if i + 1 >= packed.len() {
+                        return Err(invalid_data("EXR RLE block is truncated"));
+                    }
+                    bytes.extend(std::iter::repeat_n(packed[i + 1], count as usize + 1));
+                    i += 2;
+                }
+            }
+            bytes
+        }
+        _ => inflate::inflate_bytes_zlib(packed).map_err(invalid_data)?,
+    };
+    if bytes.len() != size {
+        return Err(invalid_data("EXR block has the wrong size"));
+    }
+    for i in 1..bytes.len() {
+        bytes[i] = bytes[i - 1].wrapping_add(bytes[i]).wrapping_sub(128);
+    }
+    let (first, second) = bytes.split_at(size.div_ceil(2));
+    let mut interleaved = Vec::with_capacity(size);
+    for (i, byte) in first.iter().enumerate() {
+        interleaved.push(*byte);
+        if let Some(byte) = second.get(i) {
+            interleaved.push(*byte);
+        }
+    }
+    Ok(interleaved)
+}
+
+fn write_attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(kind.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    header.extend_from_slice(value);
+}
+
+fn from_half(half: u16) -> f32 {
+    let sign = ((half as u32) & 0x8000) << 16;
+    let exponent = ((half >> 10) & 0x1f) as u32;
+    let mantissa = (half & 0x03ff) as u32;
+    let bits = match exponent {
+        0 if mantissa == 0 => sign,
+        // Subnormal half, normalized as a float
+        0 => {
+            let shift = mantissa.leading_zeros() - 21;
+            sign | ((113 - shift) << 23) | ((mantissa << shift) & 0x03ff) << 13
+        }
+        0x1f => sign | 0x7f80_0000 | (mantissa << 13),
+        _ => sign | ((exponent + 112) << 23) | (mantissa << 13),
+    };
+    f32::from_bits(bits)
+}
+
+// IEEE 754 half precision bits, rounding to nearest even
+fn to_half(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x007f_ffff;
+
+    if exponent == 0xff {
+        // Infinity stays infinity, NaN keeps a mantissa bit set
+        let nan = if mantissa != 0 { 0x0200 } else { 0 };
+        return sign | 0x7c00 | nan;
+    }
+    let half_exponent = exponent - 127 + 15;
+    if half_exponent >= 0x1f {
+        // Too large, becomes infinity
+        return sign | 0x7c00;
+    }
+    if half_exponent <= 0 {
+        // Subnormal half or zero
+        if half_exponent < -10 {
+            return sign;
+        }
+        let mantissa = mantissa | 0x0080_0000;
+        let shift = (14 - half_exponent) as u32;
+        let half_mantissa = mantissa >> shift;
+        let remainder = mantissa & ((1 << shift) - 1);
+        let halfway = 1 << (shift - 1);
+        let round_up = remainder > halfway || (remainder == halfway && half_mantissa & 1 == 1);
+        return sign | (half_mantissa + round_up as u32) as u16;
+    }
+    let half = ((half_exponent as u32) << 10) | (mantissa >> 13);
+    let remainder = mantissa & 0x1fff;
+    let round_up = remainder > 0x1000 || (remainder == 0x1000 && half & 1 == 1);
+    // A carry out of the mantissa correctly bumps the exponent, up to infinity
+    sign | (half + round_up as u32) as u16
+}
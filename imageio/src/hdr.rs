@@ -0,0 +1,265 @@
+use crate::{invalid_data, Image};
+use std::io::{BufRead, Read, Write};
+use std::path::Path;
+
+// Scanlines outside this range can't be run length encoded
+const MIN_RLE_WIDTH: usize = 8;
+const MAX_RLE_WIDTH: usize = 0x7fff;
+const MIN_RUN: usize = 4;
+const MAX_RUN: usize = 127;
+const MAX_LITERAL: usize = 128;
+
+// Radiance RGBE image, with run length encoded scanlines where the width allows it
+pub fn write_hdr<W: Write>(
+    writer: &mut W,
+    width: usize,
+    height: usize,
+    pixels: &[[f32; 3]],
+) -> std::io::Result<()> {
+    crate::check_size(width, height, pixels)?;
+    write!(
+        writer,
+        "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+        height, width
+    )?;
+
+    let mut components: [Vec<u8>; 4] = Default::default();
+    for row in pixels.chunks(width) {
+        let rgbe = row.iter().map(|pixel| to_rgbe(*pixel));
+        if !(MIN_RLE_WIDTH..=MAX_RLE_WIDTH).contains(&width) {
+            for value in rgbe {
+                writer.write_all(&value)?;
+            }
+            continue;
+        }
+
+        // Each component of the scanline is encoded separately
+        for component in components.iter_mut() {
+            component.clear();
+        }
+        for value in rgbe {
+            for (component, byte) in components.iter_mut().zip(value.iter()) {
+                component.push(*byte);
+            }
+        }
+        writer.write_all(&[2, 2, (width >> 8) as u8, (width & 0xff) as u8])?;
+        for component in &components {
+            write_rle(writer, component)?;
+        }
+    }
+    writer.flush()
+}
+
+pub fn save_hdr(
+    path: &Path,
+    width: usize,
+    height: usize,
+    pixels: &[[f32; 3]],
+) -> std::io::Result<()> {
+    write_hdr(&mut crate::create(path)?, width, height, pixels)
+}
+
+// Radiance RGBE image in the usual top to bottom orientation, with flat, old style or new
+// style run length encoded scanlines
+pub fn read_hdr<R: BufRead>(reader: &mut R) -> std::io::Result<Image> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    if !line.starts_with("#?") {
+        return Err(invalid_data("not a Radiance HDR image"));
+    }
+    // Header variables up to an empty line, then the resolution
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(invalid_data("HDR header is truncated"));
+        }
+        let variable = line.trim();
+        if variable.is_empty() {
+            break;
+        }
+        if let Some(format) = variable.strip_prefix("FORMAT=") {
+            if format != "32-bit_rle_rgbe" {
+                return Err(invalid_data(format!("unsupported HDR format '{}'", format)));
+            }
+        }
+    }
+    line.clear();
+    reader.read_line(&mut line)?;
+    let resolution = line.split_whitespace().collect::<Vec<_>>();
+    let (height, width) = match resolution.as_slice() {
+        ["-Y", height, "+X", width] => (height.parse::<usize>(), width.parse::<usize>()),
+        _ => {
+            return Err(invalid_data(format!(
+                "unsupported HDR orientation '{}'",
+                line.trim()
+            )))
+        }
+    };
+    let (height, width) = match (height, width) {
+        (Ok(height), Ok(width)) if width > 0 && height > 0 => (height, width),
+        _ => return Err(invalid_data("invalid HDR resolution")),
+    };
+
+    let mut pixels = Vec::with_capacity(width * height);
+    let mut scanline = vec![[0u8; 4]; width];
+    for _ in 0..height {
+        read_scanline(reader, &mut scanline)?;
+        pixels.extend(scanline.iter().map(|rgbe| from_rgbe(*rgbe)));
+    }
+    Ok(Image {
+        width,
+        height,
+        pixels,
+    })
+}
+
+pub fn load_hdr(path: &Path) -> std::io::Result<Image> {
+    read_hdr(&mut crate::open(path)?)
+}
+
+fn read_scanline<R: Read>(reader: &mut R, scanline: &mut [[u8; 4]]) -> std::io::Result<()> {
+    let width = scanline.len();
+    let mut first = [0u8; 4];
+    reader.read_exact(&mut first)?;
+    let new_rle = first[0] == 2 && first[1] == 2 && first[2] & 0x80 == 0;
+    if !(MIN_RLE_WIDTH..=MAX_RLE_WIDTH).contains(&width) || !new_rle {
+        return read_old_scanline(reader, first, scanline);
+    }
+    if ((first[2] as usize) << 8 | first[3] as usize) != width {
+        return Err(invalid_data("HDR scanline width mismatch"));
+    }
+    // Each component of the scanline is encoded separately
+    for component in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let mut count = [0u8; 1];
+            reader.read_exact(&mut count)?;
+            let (count, run) = if count[0] > 128 {
+                (count[0] as usize - 128, true)
+            } else {
+                (count[0] as usize, false)
+            };
+            if count == 0 || x + count > width {
+                return Err(invalid_data("bad HDR run length"));
+            }
+            if run {
+                let mut value = [0u8; 1];
+                reader.read_exact(&mut value)?;
+                for pixel in &mut scanline[x..x + count] {
+                    pixel[component] = value[0];
+                }
+            } else {
+                let mut values = [0u8; MAX_LITERAL];
+                reader.read_exact(&mut values[..count])?;
+                for (pixel, value) in scanline[x..x + count].iter_mut().zip(values.iter()) {
+                    pixel[component] = *value;
+                }
+            }
+            x += count;
+        }
+    }
+    Ok(())
+}
+
+// Flat pixels, where a 1, 1, 1 pixel repeats the previous pixel a number of times
+fn read_old_scanline<R: Read>(
+    reader: &mut R,
+    first: [u8; 4],
+    scanline: &mut [[u8; 4]],
+) -> std::io::Result<()> {
+    let mut pixel = first;
+    let mut x = 0;
+    let mut shift = 0;
+    loop {
+        if pixel[0] == 1 && pixel[1] == 1 && pixel[2] == 1 {
+            if x == 0 {
+                return Err(invalid_data("HDR run without a pixel to repeat"));
+            }
+            let count = (pixel[3] as usize) << shift;
+            if x + count > scanline.len() {
+                return Err(invalid_data("bad HDR run length"));
+            }
+            let previous = scanline[x - 1];
+            for value in &mut scanline[x..x + count] {
+                *value = previous;
+            }
+            x += count;
+            shift += 8;
+        } else {
+            scanline[x] = pixel;
+            x += 1;
+            shift = 0;
+        }
+        if x == scanline.len() {
+            return Ok(());
+        }
+        reader.read_exact(&mut pixel)?;
+    }
+}
+
+fn from_rgbe(rgbe: [u8; 4]) -> [f32; 3] {
+    if rgbe[3] == 0 {
+        return [0.0; 3];
+    }
+    // Values are taken from the middle of the range the byte stands for
+    let scale = 2.0_f32.powi(rgbe[3] as i32 - 136);
+    [
+        (rgbe[0] as f32 + 0.5) * scale,
+        (rgbe[1] as f32 + 0.5) * scale,
+        (rgbe[2] as f32 + 0.5) * scale,
+    ]
+}
+
+// Shared exponent encoding. Negative and NaN values become black
+fn to_rgbe(pixel: [f32; 3]) -> [u8; 4] {
+    let [r, g, b] = pixel.map(|c| {
+        if c.is_nan() {
+            0.0
+        } else {
+            c.clamp(0.0, 1.0e38)
+        }
+    });
+    let v = r.max(g).max(b);
+    if v < 1.0e-32 {
+        return [0, 0, 0, 0];
+    }
+    // v = m * 2^e with m in [0.5, 1)
+    let e = ((v.to_bits() >> 23) & 0xff) as i32 - 126;
+    let scale = 2.0_f32.powi(8 - e);
+    [
+        (r * scale) as u8,
+        (g * scale) as u8,
+        (b * scale) as u8,
+        (e + 128) as u8,
+    ]
+}
+
+// Runs are a count above 128 followed by the repeated byte, literals a count followed by
+// that many bytes
+fn write_rle<W: Write>(writer: &mut W, data: &[u8]) -> std::io::Result<()> {
+    let run_length = |start: usize| {
+        data[start..]
+            .iter()
+            .take(MAX_RUN)
+            .take_while(|byte| **byte == data[start])
+            .count()
+    };
+
+    let mut i = 0;
+    while i < data.len() {
+        let run = run_length(i);
+        if run >= MIN_RUN {
+            writer.write_all(&[128 + run as u8, data[i]])?;
+            i += run;
+            continue;
+        }
+        // Literal bytes up to the next run worth encoding
+        let start = i;
+        while i < data.len() && i - start < MAX_LITERAL && run_length(i) < MIN_RUN {
+            i += 1;
+        }
+        writer.write_all(&[(i - start) as u8])?;
+        writer.write_all(&data[start..i])?;
+    }
+    Ok(())
+}
@@ -0,0 +1,60 @@
+// High dynamic range image readers and writers shared by the renderers. Pixels are linear
+// RGB, stored row by row starting at the top of the image
+mod exr;
+mod hdr;
+
+pub use exr::*;
+pub use hdr::*;
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<[f32; 3]>,
+}
+
+// Reads a .hdr or .exr image, the format is picked from the file extension
+pub fn load_image(path: &Path) -> std::io::Result<Image> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("hdr") | Some("pic") => load_hdr(path),
+        Some("exr") => load_exr(path),
+        _ => Err(invalid_data(format!(
+            "unsupported image format for '{}'",
+            path.display()
+        ))),
+    }
+}
+
+fn open(path: &Path) -> std::io::Result<BufReader<File>> {
+    Ok(BufReader::new(File::open(path)?))
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(error: E) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, error)
+}
+
+fn create(path: &Path) -> std::io::Result<BufWriter<File>> {
+    Ok(BufWriter::new(File::create(path)?))
+}
+
+fn check_size(width: usize, height: usize, pixels: &[[f32; 3]]) -> std::io::Result<()> {
+    if width == 0 || height == 0 || pixels.len() != width * height {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!(
+                "{} pixels don't make a {}x{} image",
+                pixels.len(),
+                width,
+                height
+            ),
+        ));
+    }
+    Ok(())
+}
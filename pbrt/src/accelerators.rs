@@ -1,5 +1,80 @@
-use crate::math::Vec3;
+mod bvh;
 
-pub trait Primitive {
-    fn world_bounds(&self) -> Vec3;
+pub use bvh::BVHAccel;
+
+use crate::core::{MediumInterface, SurfaceInteraction};
+use crate::lights::AreaLight;
+use crate::materials::Material;
+use crate::math::*;
+use crate::ray::Ray;
+use crate::shapes::Shape;
+use std::sync::Arc;
+
+pub trait Primitive: Send + Sync {
+    fn world_bounds(&self) -> Bounds3Df;
+    // Shortens ray.t_max to the closest hit found
+    fn intersect<'a>(&'a self, ray: &Ray) -> Option<SurfaceInteraction<'a>>;
+    fn intersect_p(&self, ray: &Ray) -> bool;
+    // Light emitted by the surface of the primitive, aggregates never have one
+    fn area_light(&self) -> Option<&dyn AreaLight> {
+        None
+    }
+    // None makes the surface only a boundary between media, aggregates never have one
+    fn material(&self) -> Option<&dyn Material> {
+        None
+    }
+}
+
+pub struct GeometricPrimitive {
+    shape: Arc<Shape>,
+    material: Option<Arc<dyn Material>>,
+    area_light: Option<Arc<dyn AreaLight>>,
+    medium_interface: MediumInterface,
+}
+
+impl GeometricPrimitive {
+    pub fn new(
+        shape: Arc<Shape>,
+        material: Option<Arc<dyn Material>>,
+        area_light: Option<Arc<dyn AreaLight>>,
+        medium_interface: MediumInterface,
+    ) -> GeometricPrimitive {
+        GeometricPrimitive {
+            shape,
+            material,
+            area_light,
+            medium_interface,
+        }
+    }
+}
+
+impl Primitive for GeometricPrimitive {
+    fn world_bounds(&self) -> Bounds3Df {
+        self.shape.world_bound()
+    }
+
+    fn intersect<'a>(&'a self, ray: &Ray) -> Option<SurfaceInteraction<'a>> {
+        let (t_hit, mut isect) = self.shape.intersect(ray, true)?;
+        ray.t_max.set(t_hit);
+        isect.primitive = Some(self);
+        // Shapes that don't separate media are inside the medium of the ray
+        isect.interaction.medium_interface = if self.medium_interface.is_medium_transition() {
+            self.medium_interface.clone()
+        } else {
+            MediumInterface::from_medium(ray.medium.clone())
+        };
+        Some(isect)
+    }
+
+    fn intersect_p(&self, ray: &Ray) -> bool {
+        self.shape.intersect_p(ray, true)
+    }
+
+    fn area_light(&self) -> Option<&dyn AreaLight> {
+        self.area_light.as_deref()
+    }
+
+    fn material(&self) -> Option<&dyn Material> {
+        self.material.as_deref()
+    }
 }
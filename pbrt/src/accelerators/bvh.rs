@@ -0,0 +1,386 @@
+use super::Primitive;
+use crate::core::{ParamSet, SurfaceInteraction};
+use crate::math::*;
+use crate::ray::Ray;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SplitMethod {
+    // Surface area heuristic
+    SAH,
+    Middle,
+    EqualCounts,
+}
+
+// Number of candidate split planes tested per node with the surface area heuristic
+const N_BUCKETS: usize = 12;
+
+struct BVHPrimitiveInfo {
+    primitive_number: usize,
+    bounds: Bounds3Df,
+    centroid: Point3,
+}
+
+impl BVHPrimitiveInfo {
+    fn new(primitive_number: usize, bounds: Bounds3Df) -> BVHPrimitiveInfo {
+        BVHPrimitiveInfo {
+            primitive_number,
+            bounds,
+            centroid: Point3::from_vec((bounds.min.to_vec() + bounds.max.to_vec()) * 0.5),
+        }
+    }
+}
+
+enum BVHBuildNode {
+    Leaf {
+        bounds: Bounds3Df,
+        first_prim_offset: usize,
+        n_primitives: usize,
+    },
+    Interior {
+        bounds: Bounds3Df,
+        children: [Box<BVHBuildNode>; 2],
+        split_axis: usize,
+    },
+}
+
+impl BVHBuildNode {
+    fn bounds(&self) -> Bounds3Df {
+        match self {
+            BVHBuildNode::Leaf { bounds, .. } => *bounds,
+            BVHBuildNode::Interior { bounds, .. } => *bounds,
+        }
+    }
+}
+
+// Depth first layout, the first child of an interior node directly follows it
+struct LinearBVHNode {
+    bounds: Bounds3Df,
+    // First primitive for leaves, second child for interior nodes
+    offset: u32,
+    // Zero for interior nodes
+    n_primitives: u16,
+    axis: u8,
+}
+
+pub struct BVHAccel {
+    max_prims_in_node: usize,
+    split_method: SplitMethod,
+    primitives: Vec<Box<dyn Primitive>>,
+    nodes: Vec<LinearBVHNode>,
+}
+
+impl BVHAccel {
+    pub fn new(
+        primitives: Vec<Box<dyn Primitive>>,
+        max_prims_in_node: usize,
+        split_method: SplitMethod,
+    ) -> BVHAccel {
+        let mut bvh = BVHAccel {
+            max_prims_in_node: std::cmp::min(max_prims_in_node, 255),
+            split_method,
+            primitives: Vec::new(),
+            nodes: Vec::new(),
+        };
+        if primitives.is_empty() {
+            return bvh;
+        }
+
+        let mut primitive_info = primitives
+            .iter()
+            .enumerate()
+            .map(|(i, primitive)| BVHPrimitiveInfo::new(i, primitive.world_bounds()))
+            .collect::<Vec<BVHPrimitiveInfo>>();
+        let mut ordered_prims = Vec::with_capacity(primitives.len());
+        let mut total_nodes = 0;
+        let root = bvh.recursive_build(&mut primitive_info, &mut total_nodes, &mut ordered_prims);
+
+        // Move the primitives into the order the leaves reference them
+        let mut primitives = primitives.into_iter().map(Some).collect::<Vec<_>>();
+        bvh.primitives = ordered_prims
+            .iter()
+            .map(|i| primitives[*i].take().unwrap())
+            .collect();
+
+        bvh.nodes = Vec::with_capacity(total_nodes);
+        bvh.flatten_bvh_tree(&root);
+        bvh
+    }
+
+    pub fn create(primitives: Vec<Box<dyn Primitive>>, params: &ParamSet) -> BVHAccel {
+        let split_method_name = params.find_one_string("splitmethod", "sah");
+        let split_method = match split_method_name.as_str() {
+            "sah" => SplitMethod::SAH,
+            "middle" => SplitMethod::Middle,
+            "equal" => SplitMethod::EqualCounts,
+            _ => {
+                eprintln!(
+                    "warning: BVH split method \"{}\" unknown, using \"sah\"",
+                    split_method_name
+                );
+                SplitMethod::SAH
+            }
+        };
+        let max_prims_in_node = params.find_one_int("maxnodeprims", 4);
+        BVHAccel::new(
+            primitives,
+            std::cmp::max(max_prims_in_node, 1) as usize,
+            split_method,
+        )
+    }
+
+    fn recursive_build(
+        &self,
+        primitive_info: &mut [BVHPrimitiveInfo],
+        total_nodes: &mut usize,
+        ordered_prims: &mut Vec<usize>,
+    ) -> BVHBuildNode {
+        *total_nodes += 1;
+        let bounds = primitive_info
+            .iter()
+            .fold(Bounds3Df::default(), |bounds, info| {
+                union_bounds_3d(&bounds, &info.bounds)
+            });
+        let n_primitives = primitive_info.len();
+        let make_leaf = |ordered_prims: &mut Vec<usize>, primitive_info: &[BVHPrimitiveInfo]| {
+            let first_prim_offset = ordered_prims.len();
+            ordered_prims.extend(primitive_info.iter().map(|info| info.primitive_number));
+            BVHBuildNode::Leaf {
+                bounds,
+                first_prim_offset,
+                n_primitives,
+            }
+        };
+        if n_primitives == 1 {
+            return make_leaf(ordered_prims, primitive_info);
+        }
+
+        // Choose split dimension based on the extent of the centroids
+        let centroid_bounds = primitive_info
+            .iter()
+            .fold(Bounds3Df::default(), |bounds, info| {
+                union_3d_with_point(&bounds, &info.centroid)
+            });
+        let dim = centroid_bounds.maximum_extent() as usize;
+        if centroid_bounds.max[dim] == centroid_bounds.min[dim] {
+            // All centroids coincide so there is no way to split them
+            return make_leaf(ordered_prims, primitive_info);
+        }
+
+        let mid = match self.split_method {
+            SplitMethod::Middle => {
+                let p_mid = (centroid_bounds.min[dim] + centroid_bounds.max[dim]) / 2.0;
+                let mid = partition(primitive_info, |info| info.centroid[dim] < p_mid);
+                if mid == 0 || mid == n_primitives {
+                    // Lots of overlapping bounds, split into equal counts instead
+                    split_equal_counts(primitive_info, dim)
+                } else {
+                    mid
+                }
+            }
+            SplitMethod::EqualCounts => split_equal_counts(primitive_info, dim),
+            SplitMethod::SAH => {
+                if n_primitives <= 2 {
+                    split_equal_counts(primitive_info, dim)
+                } else {
+                    let bucket_index = |info: &BVHPrimitiveInfo| {
+                        let b = (N_BUCKETS as f32 * centroid_bounds.offset(&info.centroid)[dim])
+                            as usize;
+                        std::cmp::min(b, N_BUCKETS - 1)
+                    };
+
+                    // Initialize the buckets for the split candidates
+                    let mut counts = [0; N_BUCKETS];
+                    let mut bucket_bounds = [Bounds3Df::default(); N_BUCKETS];
+                    for info in primitive_info.iter() {
+                        let b = bucket_index(info);
+                        counts[b] += 1;
+                        bucket_bounds[b] = union_bounds_3d(&bucket_bounds[b], &info.bounds);
+                    }
+
+                    // Compute costs for splitting after each bucket
+                    let mut min_cost = f32::INFINITY;
+                    let mut min_cost_split_bucket = 0;
+                    for i in 0..N_BUCKETS - 1 {
+                        let (below, above) = bucket_bounds.split_at(i + 1);
+                        let (count_below, count_above) = counts.split_at(i + 1);
+                        let b0 = below.iter().fold(Bounds3Df::default(), |b, bounds| {
+                            union_bounds_3d(&b, bounds)
+                        });
+                        let b1 = above.iter().fold(Bounds3Df::default(), |b, bounds| {
+                            union_bounds_3d(&b, bounds)
+                        });
+                        let count0 = count_below.iter().sum::<usize>();
+                        let count1 = count_above.iter().sum::<usize>();
+                        // Empty sides have inverted default bounds, skip them
+                        if count0 == 0 || count1 == 0 {
+                            continue;
+                        }
+                        let cost = 1.0
+                            + (count0 as f32 * b0.surface_area()
+                                + count1 as f32 * b1.surface_area())
+                                / bounds.surface_area();
+                        if cost < min_cost {
+                            min_cost = cost;
+                            min_cost_split_bucket = i;
+                        }
+                    }
+
+                    // Either split at the selected bucket or create a leaf
+                    let leaf_cost = n_primitives as f32;
+                    if n_primitives > self.max_prims_in_node || min_cost < leaf_cost {
+                        partition(primitive_info, |info| {
+                            bucket_index(info) <= min_cost_split_bucket
+                        })
+                    } else {
+                        return make_leaf(ordered_prims, primitive_info);
+                    }
+                }
+            }
+        };
+
+        let (below, above) = primitive_info.split_at_mut(mid);
+        let children = [
+            Box::new(self.recursive_build(below, total_nodes, ordered_prims)),
+            Box::new(self.recursive_build(above, total_nodes, ordered_prims)),
+        ];
+        BVHBuildNode::Interior {
+            bounds: union_bounds_3d(&children[0].bounds(), &children[1].bounds()),
+            children,
+            split_axis: dim,
+        }
+    }
+
+    fn flatten_bvh_tree(&mut self, node: &BVHBuildNode) -> usize {
+        let offset = self.nodes.len();
+        match node {
+            BVHBuildNode::Leaf {
+                bounds,
+                first_prim_offset,
+                n_primitives,
+            } => self.nodes.push(LinearBVHNode {
+                bounds: *bounds,
+                offset: *first_prim_offset as u32,
+                n_primitives: *n_primitives as u16,
+                axis: 0,
+            }),
+            BVHBuildNode::Interior {
+                bounds,
+                children,
+                split_axis,
+            } => {
+                self.nodes.push(LinearBVHNode {
+                    bounds: *bounds,
+                    offset: 0,
+                    n_primitives: 0,
+                    axis: *split_axis as u8,
+                });
+                self.flatten_bvh_tree(&children[0]);
+                let second_child_offset = self.flatten_bvh_tree(&children[1]);
+                self.nodes[offset].offset = second_child_offset as u32;
+            }
+        }
+        offset
+    }
+
+    // Visits the nodes front to back, stopping at the first hit when any_hit is set
+    fn traverse<'a>(
+        &'a self,
+        ray: &Ray,
+        any_hit: bool,
+        mut hit: impl FnMut(&'a dyn Primitive) -> bool,
+    ) -> bool {
+        if self.nodes.is_empty() {
+            return false;
+        }
+        let inv_dir = Vec3::new(1.0 / ray.d.x, 1.0 / ray.d.y, 1.0 / ray.d.z);
+        let dir_is_neg = [
+            (inv_dir.x < 0.0) as i32,
+            (inv_dir.y < 0.0) as i32,
+            (inv_dir.z < 0.0) as i32,
+        ];
+
+        let mut found = false;
+        let mut nodes_to_visit = Vec::with_capacity(64);
+        let mut current_node_index = 0;
+        loop {
+            let node = &self.nodes[current_node_index];
+            if node
+                .bounds
+                .intersect_p_precomputed(ray, inv_dir, &dir_is_neg)
+            {
+                if node.n_primitives > 0 {
+                    let first = node.offset as usize;
+                    for primitive in &self.primitives[first..first + node.n_primitives as usize] {
+                        if hit(primitive.as_ref()) {
+                            found = true;
+                            if any_hit {
+                                return true;
+                            }
+                        }
+                    }
+                    match nodes_to_visit.pop() {
+                        Some(index) => current_node_index = index,
+                        None => break,
+                    }
+                } else if dir_is_neg[node.axis as usize] != 0 {
+                    // Visit the second child first as it is closer along the ray
+                    nodes_to_visit.push(current_node_index + 1);
+                    current_node_index = node.offset as usize;
+                } else {
+                    nodes_to_visit.push(node.offset as usize);
+                    current_node_index += 1;
+                }
+            } else {
+                match nodes_to_visit.pop() {
+                    Some(index) => current_node_index = index,
+                    None => break,
+                }
+            }
+        }
+        found
+    }
+}
+
+impl Primitive for BVHAccel {
+    fn world_bounds(&self) -> Bounds3Df {
+        self.nodes
+            .first()
+            .map_or_else(Bounds3Df::default, |root| root.bounds)
+    }
+
+    fn intersect<'a>(&'a self, ray: &Ray) -> Option<SurfaceInteraction<'a>> {
+        let mut isect = None;
+        self.traverse(ray, false, |primitive| match primitive.intersect(ray) {
+            Some(hit) => {
+                isect = Some(hit);
+                true
+            }
+            None => false,
+        });
+        isect
+    }
+
+    fn intersect_p(&self, ray: &Ray) -> bool {
+        self.traverse(ray, true, |primitive| primitive.intersect_p(ray))
+    }
+}
+
+// Moves the elements matching pred to the front, returns how many there are
+fn partition<T>(items: &mut [T], pred: impl Fn(&T) -> bool) -> usize {
+    let mut first = 0;
+    for i in 0..items.len() {
+        if pred(&items[i]) {
+            items.swap(first, i);
+            first += 1;
+        }
+    }
+    first
+}
+
+fn split_equal_counts(primitive_info: &mut [BVHPrimitiveInfo], dim: usize) -> usize {
+    let mid = primitive_info.len() / 2;
+    primitive_info.select_nth_unstable_by(mid, |a, b| {
+        a.centroid[dim].partial_cmp(&b.centroid[dim]).unwrap()
+    });
+    mid
+}
@@ -1,9 +1,19 @@
 use crate::core::Film;
+use crate::math::Point2;
 use crate::ray::RayDifferential;
 
-pub trait Camera {
-    fn film(&self) -> Film;
+mod perspective;
+
+pub use perspective::PerspectiveCamera;
+
+pub trait Camera: Send + Sync {
+    fn film(&self) -> &Film;
     fn generate_ray_differential(&self, sample: CameraSample) -> (RayDifferential, f32);
 }
 
-pub struct CameraSample;
+#[derive(Clone, Copy)]
+pub struct CameraSample {
+    pub p_film: Point2,
+    pub p_lens: Point2,
+    pub time: f32,
+}
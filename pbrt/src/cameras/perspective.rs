@@ -0,0 +1,159 @@
+use super::*;
+use crate::core::sampling::concentric_sample_disk;
+use crate::core::{Medium, ParamSet};
+use crate::math::*;
+use std::sync::Arc;
+
+pub struct PerspectiveCamera {
+    camera_to_world: Transform,
+    raster_to_camera: Transform,
+    shutter_open: f32,
+    shutter_close: f32,
+    lens_radius: f32,
+    focal_distance: f32,
+    // Camera space offsets between neighbouring pixels
+    dx_camera: Vec3,
+    dy_camera: Vec3,
+    film: Film,
+    // Medium the camera is in
+    medium: Option<Arc<dyn Medium>>,
+}
+
+impl PerspectiveCamera {
+    pub fn new(
+        camera_to_world: Transform,
+        screen_window: Bounds2Df,
+        shutter_open: f32,
+        shutter_close: f32,
+        lens_radius: f32,
+        focal_distance: f32,
+        fov: f32,
+        film: Film,
+        medium: Option<Arc<dyn Medium>>,
+    ) -> PerspectiveCamera {
+        let camera_to_screen = perspective(fov, 1e-2, 1000.0);
+        let screen_to_raster = scale(
+            film.full_resolution.x as f32,
+            film.full_resolution.y as f32,
+            1.0,
+        ) * scale(
+            1.0 / (screen_window.max.x - screen_window.min.x),
+            1.0 / (screen_window.min.y - screen_window.max.y),
+            1.0,
+        ) * translate(vec3(-screen_window.min.x, -screen_window.max.y, 0.0));
+        let raster_to_camera = camera_to_screen.inverse() * screen_to_raster.inverse();
+
+        let origin = raster_to_camera.transform_point(Point3::new(0.0, 0.0, 0.0));
+        let dx_camera = raster_to_camera.transform_point(Point3::new(1.0, 0.0, 0.0)) - origin;
+        let dy_camera = raster_to_camera.transform_point(Point3::new(0.0, 1.0, 0.0)) - origin;
+
+        PerspectiveCamera {
+            camera_to_world,
+            raster_to_camera,
+            shutter_open,
+            shutter_close,
+            lens_radius,
+            focal_distance,
+            dx_camera,
+            dy_camera,
+            film,
+            medium,
+        }
+    }
+
+    pub fn create(
+        params: &ParamSet,
+        camera_to_world: Transform,
+        film: Film,
+        medium: Option<Arc<dyn Medium>>,
+    ) -> PerspectiveCamera {
+        let shutter_open = params.find_one_float("shutteropen", 0.0);
+        let shutter_close = params.find_one_float("shutterclose", 1.0);
+        let lens_radius = params.find_one_float("lensradius", 0.0);
+        let focal_distance = params.find_one_float("focaldistance", 1e6);
+
+        let frame = params.find_one_float(
+            "frameaspectratio",
+            film.full_resolution.x as f32 / film.full_resolution.y as f32,
+        );
+        // The field of view spans the shorter image axis
+        let mut screen_window = if frame > 1.0 {
+            Bounds2Df::from_two_points(Point2::new(-frame, -1.0), Point2::new(frame, 1.0))
+        } else {
+            Bounds2Df::from_two_points(
+                Point2::new(-1.0, -1.0 / frame),
+                Point2::new(1.0, 1.0 / frame),
+            )
+        };
+        if let Some([x_min, x_max, y_min, y_max]) = params.find_floats("screenwindow") {
+            screen_window = Bounds2Df::from_two_points(
+                Point2::new(*x_min, *y_min),
+                Point2::new(*x_max, *y_max),
+            );
+        }
+
+        let mut fov = params.find_one_float("fov", 90.0);
+        let half_fov = params.find_one_float("halffov", -1.0);
+        if half_fov > 0.0 {
+            fov = 2.0 * half_fov;
+        }
+
+        PerspectiveCamera::new(
+            camera_to_world,
+            screen_window,
+            min(shutter_open, shutter_close),
+            max(shutter_open, shutter_close),
+            lens_radius,
+            focal_distance,
+            fov,
+            film,
+            medium,
+        )
+    }
+
+    // Bends a camera space direction through the lens so it passes the plane of focus
+    fn through_lens(&self, p_lens: Point2, dir: Vec3) -> (Point3, Vec3) {
+        let ft = self.focal_distance / dir.z;
+        let p_focus = Point3::from_vec(dir * ft);
+        let o = Point3::new(p_lens.x, p_lens.y, 0.0);
+        (o, (p_focus - o).normalize())
+    }
+}
+
+impl Camera for PerspectiveCamera {
+    fn film(&self) -> &Film {
+        &self.film
+    }
+
+    fn generate_ray_differential(&self, sample: CameraSample) -> (RayDifferential, f32) {
+        let p_film = Point3::new(sample.p_film.x, sample.p_film.y, 0.0);
+        let p_camera = self.raster_to_camera.transform_point(p_film).to_vec();
+        let dir = p_camera.normalize();
+        let dx_dir = (p_camera + self.dx_camera).normalize();
+        let dy_dir = (p_camera + self.dy_camera).normalize();
+
+        let mut ray = RayDifferential::new(Point3::new(0.0, 0.0, 0.0), dir);
+        if self.lens_radius > 0.0 {
+            let p_lens = concentric_sample_disk(sample.p_lens);
+            let p_lens = Point2::new(self.lens_radius * p_lens.x, self.lens_radius * p_lens.y);
+            let (o, d) = self.through_lens(p_lens, dir);
+            let (rx_o, rx_d) = self.through_lens(p_lens, dx_dir);
+            let (ry_o, ry_d) = self.through_lens(p_lens, dy_dir);
+            ray.ray.o = o;
+            ray.ray.d = d;
+            ray.rxOrigin = rx_o;
+            ray.rxDirection = rx_d;
+            ray.ryOrigin = ry_o;
+            ray.ryDirection = ry_d;
+        } else {
+            ray.rxOrigin = ray.ray.o;
+            ray.ryOrigin = ray.ray.o;
+            ray.rxDirection = dx_dir;
+            ray.ryDirection = dy_dir;
+        }
+        ray.ray.time = lerp(sample.time, self.shutter_open, self.shutter_close);
+        ray.hasDifferentials = true;
+        ray.ray.medium = self.medium.clone();
+        (self.camera_to_world.transform_ray_differential(&ray), 1.0)
+    }
+}
@@ -1,15 +1,29 @@
+pub mod api;
 mod film;
+mod imageio;
 mod interaction;
+pub mod lightdistrib;
+pub mod lowdiscrepancy;
 mod medium;
+pub mod microfacet;
 mod parallel;
+mod paramset;
+pub mod parser;
+mod progress;
 pub mod reflection;
+pub mod rng;
+pub mod sampling;
 mod scene;
+pub mod texture;
 
 pub use film::Film;
-pub use film::FilmTile;
-pub use interaction::{Interaction, Shading, SurfaceInteraction};
-pub use medium::Medium;
-pub use medium::MediumInterface;
-pub use parallel::parallel_for_2d;
+pub use interaction::{
+    offset_ray_origin, Interaction, MediumInteraction, Shading, SurfaceInteraction,
+};
+pub use medium::{HenyeyGreenstein, Medium, MediumInterface, PhaseFunction};
+pub use parallel::{parallel_for_2d, ParallelOptions};
+pub use paramset::{ParamSet, ParamValue, TextureMap, TextureParams};
+pub use progress::ProgressReporter;
 pub use reflection::BSDF;
+pub use rng::{ONE_MINUS_EPSILON, RNG};
 pub use scene::Scene;
@@ -0,0 +1,928 @@
+use crate::accelerators::{BVHAccel, GeometricPrimitive, Primitive};
+use crate::cameras::{Camera, PerspectiveCamera};
+use crate::core::imageio::read_image;
+use crate::core::texture::{create_mapping_2d, Texture, TextureValue, UVMapping2D};
+use crate::core::{
+    Film, Medium, MediumInterface, ParallelOptions, ParamSet, Scene, TextureMap, TextureParams,
+};
+use crate::filters::*;
+use crate::integrators::{
+    DirectLightingIntegrator, Integrator, PathIntegrator, VolPathIntegrator, WhittedIntegrator,
+};
+use crate::lights::{
+    AreaLight, DiffuseAreaLight, DistantLight, InfiniteAreaLight, Light, PointLight, SpotLight,
+};
+use crate::materials::*;
+use crate::math::*;
+use crate::media::{GridDensityMedium, HomogeneousMedium};
+use crate::samplers::{HaltonSampler, Sampler, SobolSampler, StratifiedSampler};
+use crate::shapes::Sphere;
+use crate::spectrum::Spectrum;
+use crate::textures::*;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+
+// Scene construction state driven by the directives of a scene file, see api.cpp in pbrt-v3
+// Problems in the scene description are reported as warnings and the offending directive is skipped
+
+const MATERIAL_TYPES: [&str; 15] = [
+    "",
+    "none",
+    "matte",
+    "plastic",
+    "glass",
+    "mirror",
+    "metal",
+    "substrate",
+    "uber",
+    "translucent",
+    "disney",
+    "fourier",
+    "hair",
+    "kdsubsurface",
+    "subsurface",
+];
+
+// A named object with its parameters, remembered until it is needed
+struct Directive {
+    name: String,
+    params: ParamSet,
+    location: String,
+}
+
+impl Directive {
+    fn new(name: &str) -> Directive {
+        Directive {
+            name: String::from(name),
+            params: ParamSet::new(),
+            location: String::new(),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct GraphicsState {
+    // None for the "none" material
+    material: Option<Arc<dyn Material>>,
+    named_materials: HashMap<String, Option<Arc<dyn Material>>>,
+    float_textures: TextureMap<f32>,
+    spectrum_textures: TextureMap<Spectrum>,
+    area_light: Option<Rc<Directive>>,
+    reverse_orientation: bool,
+    // Names of the media set by MediumInterface, empty for vacuum
+    current_inside_medium: String,
+    current_outside_medium: String,
+}
+
+impl Default for GraphicsState {
+    fn default() -> Self {
+        let float_textures = HashMap::new();
+        let spectrum_textures = HashMap::new();
+        let matte = MatteMaterial::create(&TextureParams::new(
+            &ParamSet::new(),
+            &float_textures,
+            &spectrum_textures,
+            "",
+        ));
+        GraphicsState {
+            material: Some(Arc::new(matte)),
+            named_materials: HashMap::new(),
+            float_textures,
+            spectrum_textures,
+            area_light: None,
+            reverse_orientation: false,
+            current_inside_medium: String::new(),
+            current_outside_medium: String::new(),
+        }
+    }
+}
+
+// Everything outside of the world block, along with what the world block produced
+struct RenderOptions {
+    camera: Directive,
+    camera_to_world: Transform,
+    // Outside medium when the camera was declared
+    camera_medium: String,
+    film: Directive,
+    sampler: Directive,
+    filter: Directive,
+    integrator: Directive,
+    accelerator: Directive,
+    primitives: Vec<Box<dyn Primitive>>,
+    lights: Vec<Arc<dyn Light>>,
+    named_media: HashMap<String, Arc<dyn Medium>>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            camera: Directive::new("perspective"),
+            camera_to_world: Transform::default(),
+            camera_medium: String::new(),
+            film: Directive::new("image"),
+            sampler: Directive::new("halton"),
+            filter: Directive::new("box"),
+            integrator: Directive::new("path"),
+            accelerator: Directive::new("bvh"),
+            primitives: Vec::new(),
+            lights: Vec::new(),
+            named_media: HashMap::new(),
+        }
+    }
+}
+
+pub struct SceneBuilder {
+    // Overrides the film filename when set
+    output: Option<String>,
+    parallel: ParallelOptions,
+    location: String,
+    in_world_block: bool,
+    current_transform: Transform,
+    named_coordinate_systems: HashMap<String, Transform>,
+    graphics_state: GraphicsState,
+    pushed_graphics_states: Vec<GraphicsState>,
+    pushed_transforms: Vec<Transform>,
+    // Marks which of the pushed transforms came from TransformBegin rather than AttributeBegin
+    pushed_transform_only: Vec<bool>,
+    render_options: RenderOptions,
+    // Directory of the first scene file, relative file names in parameters are looked up there
+    search_directory: Option<PathBuf>,
+}
+
+impl SceneBuilder {
+    pub fn new(output: Option<String>, parallel: ParallelOptions) -> SceneBuilder {
+        SceneBuilder {
+            output,
+            parallel,
+            location: String::new(),
+            in_world_block: false,
+            current_transform: Transform::default(),
+            named_coordinate_systems: HashMap::new(),
+            graphics_state: GraphicsState::default(),
+            pushed_graphics_states: Vec::new(),
+            pushed_transforms: Vec::new(),
+            pushed_transform_only: Vec::new(),
+            render_options: RenderOptions::default(),
+            search_directory: None,
+        }
+    }
+
+    // Position in the scene file of the directive being processed, used in warnings
+    pub fn set_location(&mut self, location: String) {
+        self.location = location;
+    }
+
+    // Only the first call has an effect, as in pbrt
+    pub fn set_search_directory(&mut self, directory: &Path) {
+        if self.search_directory.is_none() {
+            self.search_directory = Some(directory.to_path_buf());
+        }
+    }
+
+    fn resolve_filename(&self, filename: &str) -> String {
+        match &self.search_directory {
+            Some(directory) if Path::new(filename).is_relative() => {
+                directory.join(filename).to_string_lossy().into_owned()
+            }
+            _ => filename.to_string(),
+        }
+    }
+
+    pub fn warning(&self, message: &str) {
+        warning_at(&self.location, message);
+    }
+
+    fn directive(&self, name: &str, params: ParamSet) -> Directive {
+        Directive {
+            name: String::from(name),
+            params,
+            location: self.location.clone(),
+        }
+    }
+
+    fn verify_options(&self, directive: &str) -> bool {
+        if self.in_world_block {
+            self.warning(&format!(
+                "'{}' is not allowed inside a world block, ignoring it",
+                directive
+            ));
+        }
+        !self.in_world_block
+    }
+
+    fn verify_world(&self, directive: &str) -> bool {
+        if !self.in_world_block {
+            self.warning(&format!(
+                "'{}' is only allowed inside a world block, ignoring it",
+                directive
+            ));
+        }
+        self.in_world_block
+    }
+
+    // Transformations
+    pub fn identity(&mut self) {
+        self.current_transform = Transform::default();
+    }
+
+    pub fn translate(&mut self, delta: Vec3) {
+        self.current_transform = self.current_transform * translate(delta);
+    }
+
+    pub fn scale(&mut self, x: f32, y: f32, z: f32) {
+        self.current_transform = self.current_transform * scale(x, y, z);
+    }
+
+    pub fn rotate(&mut self, angle: f32, axis: Vec3) {
+        if axis.is_zero() {
+            self.warning("rotation axis is zero, ignoring 'Rotate'");
+            return;
+        }
+        self.current_transform = self.current_transform * rotate_axis(angle, axis.normalize());
+    }
+
+    pub fn look_at(&mut self, eye: Point3, look: Point3, up: Vec3) {
+        match look_at(eye, look, up) {
+            Some(transform) => self.current_transform = self.current_transform * transform,
+            None => self.warning("up vector and viewing direction are parallel, ignoring 'LookAt'"),
+        }
+    }
+
+    fn matrix_transform(&self, m: [f32; 16], directive: &str) -> Option<Transform> {
+        match Transform::from_columns(m) {
+            Some(transform) => Some(transform),
+            None => {
+                self.warning(&format!(
+                    "matrix is not invertible, ignoring '{}'",
+                    directive
+                ));
+                None
+            }
+        }
+    }
+
+    pub fn transform(&mut self, m: [f32; 16]) {
+        if let Some(transform) = self.matrix_transform(m, "Transform") {
+            self.current_transform = transform;
+        }
+    }
+
+    pub fn concat_transform(&mut self, m: [f32; 16]) {
+        if let Some(transform) = self.matrix_transform(m, "ConcatTransform") {
+            self.current_transform = self.current_transform * transform;
+        }
+    }
+
+    pub fn coordinate_system(&mut self, name: &str) {
+        self.named_coordinate_systems
+            .insert(String::from(name), self.current_transform);
+    }
+
+    pub fn coord_sys_transform(&mut self, name: &str) {
+        match self.named_coordinate_systems.get(name) {
+            Some(transform) => self.current_transform = *transform,
+            None => self.warning(&format!("unknown coordinate system \"{}\"", name)),
+        }
+    }
+
+    // Rendering options
+    pub fn camera(&mut self, name: &str, params: ParamSet) {
+        if !self.verify_options("Camera") {
+            return;
+        }
+        self.render_options.camera = self.directive(name, params);
+        self.render_options.camera_to_world = self.current_transform.inverse();
+        self.render_options.camera_medium = self.graphics_state.current_outside_medium.clone();
+        self.named_coordinate_systems
+            .insert(String::from("camera"), self.render_options.camera_to_world);
+    }
+
+    pub fn film(&mut self, name: &str, params: ParamSet) {
+        if self.verify_options("Film") {
+            self.render_options.film = self.directive(name, params);
+        }
+    }
+
+    pub fn sampler(&mut self, name: &str, params: ParamSet) {
+        if self.verify_options("Sampler") {
+            self.render_options.sampler = self.directive(name, params);
+        }
+    }
+
+    pub fn pixel_filter(&mut self, name: &str, params: ParamSet) {
+        if self.verify_options("PixelFilter") {
+            self.render_options.filter = self.directive(name, params);
+        }
+    }
+
+    pub fn accelerator(&mut self, name: &str, params: ParamSet) {
+        if self.verify_options("Accelerator") {
+            self.render_options.accelerator = self.directive(name, params);
+        }
+    }
+
+    pub fn integrator(&mut self, name: &str, params: ParamSet) {
+        if self.verify_options("Integrator") {
+            self.render_options.integrator = self.directive(name, params);
+        }
+    }
+
+    // Media can be declared anywhere, they are placed with the current transform
+    pub fn make_named_medium(&mut self, name: &str, params: ParamSet) {
+        let medium_type = params.find_one_string("type", "");
+        if medium_type.is_empty() {
+            self.warning(&format!("no \"string type\" given for medium \"{}\"", name));
+            return;
+        }
+        let preset = params.find_one_string("preset", "");
+        if !preset.is_empty() {
+            self.warning(&format!(
+                "medium preset \"{}\" is not supported, using sigma_a and sigma_s",
+                preset
+            ));
+        }
+        let medium: Arc<dyn Medium> = match medium_type.as_str() {
+            "homogeneous" => Arc::new(HomogeneousMedium::create(&params)),
+            "heterogeneous" => match GridDensityMedium::create(&params, &self.current_transform) {
+                Ok(medium) => Arc::new(medium),
+                Err(err) => {
+                    self.warning(&format!("{}, ignoring medium \"{}\"", err, name));
+                    return;
+                }
+            },
+            _ => {
+                self.warning(&format!(
+                    "medium \"{}\" is not supported, ignoring it",
+                    medium_type
+                ));
+                return;
+            }
+        };
+        warn_unused(
+            &params,
+            &self.location,
+            &format!("medium \"{}\"", medium_type),
+        );
+        if self
+            .render_options
+            .named_media
+            .insert(String::from(name), medium)
+            .is_some()
+        {
+            self.warning(&format!("named medium \"{}\" redefined", name));
+        }
+    }
+
+    // Media inside and outside of the following shapes, and the camera's outside medium
+    pub fn medium_interface(&mut self, inside: &str, outside: &str) {
+        self.graphics_state.current_inside_medium = String::from(inside);
+        self.graphics_state.current_outside_medium = String::from(outside);
+    }
+
+    pub fn world_begin(&mut self) {
+        if !self.verify_options("WorldBegin") {
+            return;
+        }
+        self.in_world_block = true;
+        self.current_transform = Transform::default();
+        self.named_coordinate_systems
+            .insert(String::from("world"), self.current_transform);
+    }
+
+    // Attributes
+    pub fn attribute_begin(&mut self) {
+        if !self.verify_world("AttributeBegin") {
+            return;
+        }
+        self.pushed_graphics_states
+            .push(self.graphics_state.clone());
+        self.pushed_transforms.push(self.current_transform);
+        self.pushed_transform_only.push(false);
+    }
+
+    pub fn attribute_end(&mut self) {
+        if !self.verify_world("AttributeEnd") {
+            return;
+        }
+        match self.pushed_transform_only.last() {
+            None => {
+                self.warning("unmatched 'AttributeEnd', ignoring it");
+                return;
+            }
+            Some(true) => {
+                self.warning("mismatched nesting: 'AttributeEnd' closes a 'TransformBegin'")
+            }
+            Some(false) => {}
+        }
+        self.pop();
+    }
+
+    pub fn transform_begin(&mut self) {
+        if !self.verify_world("TransformBegin") {
+            return;
+        }
+        self.pushed_transforms.push(self.current_transform);
+        self.pushed_transform_only.push(true);
+    }
+
+    pub fn transform_end(&mut self) {
+        if !self.verify_world("TransformEnd") {
+            return;
+        }
+        match self.pushed_transform_only.last() {
+            None => {
+                self.warning("unmatched 'TransformEnd', ignoring it");
+                return;
+            }
+            Some(false) => {
+                self.warning("mismatched nesting: 'TransformEnd' closes an 'AttributeBegin'")
+            }
+            Some(true) => {}
+        }
+        self.pop();
+    }
+
+    fn pop(&mut self) {
+        if let Some(false) = self.pushed_transform_only.pop() {
+            self.graphics_state = self.pushed_graphics_states.pop().unwrap();
+        }
+        self.current_transform = self.pushed_transforms.pop().unwrap();
+    }
+
+    pub fn reverse_orientation(&mut self) {
+        if self.verify_world("ReverseOrientation") {
+            self.graphics_state.reverse_orientation = !self.graphics_state.reverse_orientation;
+        }
+    }
+
+    pub fn texture(&mut self, name: &str, texture_type: &str, class: &str, params: ParamSet) {
+        if !self.verify_world("Texture") {
+            return;
+        }
+        let redefined = match texture_type {
+            "float" => match self.make_texture(class, &params) {
+                Some(texture) => self
+                    .graphics_state
+                    .float_textures
+                    .insert(String::from(name), texture)
+                    .is_some(),
+                None => return,
+            },
+            "spectrum" | "color" => match self.make_texture(class, &params) {
+                Some(texture) => self
+                    .graphics_state
+                    .spectrum_textures
+                    .insert(String::from(name), texture)
+                    .is_some(),
+                None => return,
+            },
+            _ => {
+                self.warning(&format!(
+                    "unknown texture type \"{}\" for texture \"{}\", expected \"float\" or \"spectrum\"",
+                    texture_type, name
+                ));
+                return;
+            }
+        };
+        if redefined {
+            self.warning(&format!("texture \"{}\" redefined", name));
+        }
+    }
+
+    fn make_texture<T: TextureValue>(
+        &self,
+        class: &str,
+        params: &ParamSet,
+    ) -> Option<Arc<dyn Texture<T>>> {
+        let texture_params = TextureParams::new(
+            params,
+            &self.graphics_state.float_textures,
+            &self.graphics_state.spectrum_textures,
+            &self.location,
+        );
+        let mapping = || {
+            create_mapping_2d(&self.current_transform, params).unwrap_or_else(|err| {
+                self.warning(&format!("{}, using \"uv\"", err));
+                Box::new(UVMapping2D::new(1.0, 1.0, 0.0, 0.0))
+            })
+        };
+        let texture: Arc<dyn Texture<T>> = match class {
+            "constant" => Arc::new(ConstantTexture::new(
+                T::find_one(params, "value").unwrap_or_else(|| T::from_value(1.0)),
+            )),
+            "scale" => Arc::new(ScaleTexture::create(&texture_params)),
+            "mix" => Arc::new(MixTexture::create(&texture_params)),
+            "checkerboard" => {
+                let dimension = params.find_one_int("dimension", 2);
+                if dimension != 2 {
+                    self.warning(&format!(
+                        "{} dimensional checkerboard texture is not supported",
+                        dimension
+                    ));
+                    return None;
+                }
+                Arc::new(CheckerboardTexture::create(mapping(), &texture_params))
+            }
+            "imagemap" => {
+                let filename = params.find_one_string("filename", "");
+                let image = match read_image(&self.resolve_filename(&filename)) {
+                    Ok(image) => Some(image),
+                    Err(err) => {
+                        self.warning(&format!("{}, using a constant texture", err));
+                        None
+                    }
+                };
+                Arc::new(ImageTexture::create(mapping(), &texture_params, image))
+            }
+            _ => {
+                self.warning(&format!(
+                    "{} texture \"{}\" is not supported, ignoring it",
+                    T::TYPE_NAME,
+                    class
+                ));
+                return None;
+            }
+        };
+        warn_unused(params, &self.location, &format!("texture \"{}\"", class));
+        Some(texture)
+    }
+
+    fn verify_material_type(&self, material_type: &str) -> bool {
+        let known = MATERIAL_TYPES.contains(&material_type);
+        if !known {
+            self.warning(&format!("unknown material type \"{}\"", material_type));
+        }
+        known
+    }
+
+    fn make_material(&self, name: &str, params: &ParamSet) -> Option<Arc<dyn Material>> {
+        let texture_params = TextureParams::new(
+            params,
+            &self.graphics_state.float_textures,
+            &self.graphics_state.spectrum_textures,
+            &self.location,
+        );
+        let material: Arc<dyn Material> = match name {
+            "" | "none" => return None,
+            "matte" => Arc::new(MatteMaterial::create(&texture_params)),
+            "plastic" => Arc::new(PlasticMaterial::create(&texture_params)),
+            "glass" => Arc::new(GlassMaterial::create(&texture_params)),
+            "mirror" => Arc::new(MirrorMaterial::create(&texture_params)),
+            "metal" => Arc::new(MetalMaterial::create(&texture_params)),
+            "substrate" => Arc::new(SubstrateMaterial::create(&texture_params)),
+            _ => {
+                self.warning(&format!(
+                    "material \"{}\" is not supported, using \"matte\"",
+                    name
+                ));
+                return GraphicsState::default().material;
+            }
+        };
+        warn_unused(params, &self.location, &format!("material \"{}\"", name));
+        Some(material)
+    }
+
+    pub fn material(&mut self, name: &str, params: ParamSet) {
+        if self.verify_world("Material") && self.verify_material_type(name) {
+            self.graphics_state.material = self.make_material(name, &params);
+        }
+    }
+
+    pub fn make_named_material(&mut self, name: &str, params: ParamSet) {
+        if !self.verify_world("MakeNamedMaterial") {
+            return;
+        }
+        let material_type = params.find_one_string("type", "");
+        if material_type.is_empty() {
+            self.warning(&format!(
+                "no \"string type\" given for named material \"{}\"",
+                name
+            ));
+            return;
+        }
+        if !self.verify_material_type(&material_type) {
+            return;
+        }
+        let material = self.make_material(&material_type, &params);
+        if self
+            .graphics_state
+            .named_materials
+            .insert(String::from(name), material)
+            .is_some()
+        {
+            self.warning(&format!("named material \"{}\" redefined", name));
+        }
+    }
+
+    pub fn named_material(&mut self, name: &str) {
+        if !self.verify_world("NamedMaterial") {
+            return;
+        }
+        match self.graphics_state.named_materials.get(name) {
+            Some(material) => self.graphics_state.material = material.clone(),
+            None => self.warning(&format!("named material \"{}\" is not defined", name)),
+        }
+    }
+
+    pub fn light_source(&mut self, name: &str, params: ParamSet) {
+        if !self.verify_world("LightSource") {
+            return;
+        }
+        let light_to_world = &self.current_transform;
+        let light: Arc<dyn Light> = match name {
+            "point" => Arc::new(PointLight::create(light_to_world, &params)),
+            "spot" => Arc::new(SpotLight::create(light_to_world, &params)),
+            "distant" => Arc::new(DistantLight::create(light_to_world, &params)),
+            "infinite" | "exinfinite" => {
+                let map_name = params.find_one_string("mapname", "");
+                let map = if map_name.is_empty() {
+                    None
+                } else {
+                    match read_image(&self.resolve_filename(&map_name)) {
+                        Ok(map) => Some(map),
+                        Err(err) => {
+                            self.warning(&format!("{}, using a constant environment", err));
+                            None
+                        }
+                    }
+                };
+                Arc::new(InfiniteAreaLight::create(light_to_world, &params, map))
+            }
+            _ => {
+                self.warning(&format!(
+                    "light source \"{}\" is not supported, ignoring it",
+                    name
+                ));
+                return;
+            }
+        };
+        warn_unused(
+            &params,
+            &self.location,
+            &format!("light source \"{}\"", name),
+        );
+        self.render_options.lights.push(light);
+    }
+
+    pub fn area_light_source(&mut self, name: &str, params: ParamSet) {
+        if self.verify_world("AreaLightSource") {
+            self.graphics_state.area_light = Some(Rc::new(self.directive(name, params)));
+        }
+    }
+
+    pub fn shape(&mut self, name: &str, params: ParamSet) {
+        if !self.verify_world("Shape") {
+            return;
+        }
+        let object_to_world = self.current_transform;
+        let world_to_object = object_to_world.inverse();
+        let reverse_orientation = self.graphics_state.reverse_orientation;
+        let shape = Arc::new(match name {
+            "sphere" => Sphere::create(
+                object_to_world,
+                world_to_object,
+                reverse_orientation,
+                &params,
+            ),
+            _ => {
+                self.warning(&format!("shape \"{}\" is not supported, ignoring it", name));
+                return;
+            }
+        });
+        warn_unused(&params, &self.location, &format!("shape \"{}\"", name));
+        let mut area_light = None;
+        if let Some(directive) = self.graphics_state.area_light.clone() {
+            if directive.name == "diffuse" {
+                let light = Arc::new(DiffuseAreaLight::create(&directive.params, shape.clone()));
+                warn_unused(
+                    &directive.params,
+                    &directive.location,
+                    "area light \"diffuse\"",
+                );
+                self.render_options.lights.push(light.clone());
+                area_light = Some(light);
+            } else {
+                self.warning(&format!(
+                    "area light \"{}\" is not supported, the shape will not emit light",
+                    directive.name
+                ));
+            }
+        }
+        let medium_interface = MediumInterface::new(
+            self.render_options
+                .named_medium(&self.graphics_state.current_inside_medium, &self.location),
+            self.render_options
+                .named_medium(&self.graphics_state.current_outside_medium, &self.location),
+        );
+        self.render_options
+            .primitives
+            .push(Box::new(GeometricPrimitive::new(
+                shape,
+                self.graphics_state.material.clone(),
+                area_light.map(|light| light as Arc<dyn AreaLight>),
+                medium_interface,
+            )));
+    }
+
+    pub fn world_end(&mut self) {
+        if !self.verify_world("WorldEnd") {
+            return;
+        }
+        if !self.pushed_transform_only.is_empty() {
+            self.warning("missing end to 'AttributeBegin' or 'TransformBegin'");
+        }
+
+        let render_options = std::mem::take(&mut self.render_options);
+        match render_options.make_integrator(self.output.as_deref()) {
+            Some(integrator) => {
+                let scene = render_options.make_scene();
+                integrator.render(&scene, &self.parallel);
+            }
+            None => self.warning("unable to create the integrator, skipping rendering"),
+        }
+
+        self.in_world_block = false;
+        self.current_transform = Transform::default();
+        self.graphics_state = GraphicsState::default();
+        self.pushed_graphics_states.clear();
+        self.pushed_transforms.clear();
+        self.pushed_transform_only.clear();
+        self.named_coordinate_systems.clear();
+    }
+}
+
+impl RenderOptions {
+    // None for vacuum, or with a warning for a medium that was never declared
+    fn named_medium(&self, name: &str, location: &str) -> Option<Arc<dyn Medium>> {
+        if name.is_empty() {
+            return None;
+        }
+        let medium = self.named_media.get(name).cloned();
+        if medium.is_none() {
+            warning_at(
+                location,
+                &format!("named medium \"{}\" is not defined, using vacuum", name),
+            );
+        }
+        medium
+    }
+
+    fn make_filter(&self) -> Option<Box<dyn Filter>> {
+        let filter = &self.filter;
+        let result: Box<dyn Filter> = match filter.name.as_str() {
+            "box" => Box::new(BoxFilter::create(&filter.params)),
+            "triangle" => Box::new(TriangleFilter::create(&filter.params)),
+            "gaussian" => Box::new(GaussianFilter::create(&filter.params)),
+            "mitchell" => Box::new(MitchellFilter::create(&filter.params)),
+            "sinc" => Box::new(LanczosSincFilter::create(&filter.params)),
+            _ => {
+                warning_at(
+                    &filter.location,
+                    &format!("filter \"{}\" is not supported", filter.name),
+                );
+                return None;
+            }
+        };
+        warn_unused(&filter.params, &filter.location, "filter");
+        Some(result)
+    }
+
+    fn make_film(&self, output: Option<&str>) -> Option<Film> {
+        let filter = self.make_filter()?;
+        let film = &self.film;
+        let result = match film.name.as_str() {
+            "image" => Film::create(&film.params, filter, output),
+            _ => {
+                warning_at(
+                    &film.location,
+                    &format!("film \"{}\" is not supported", film.name),
+                );
+                return None;
+            }
+        };
+        warn_unused(&film.params, &film.location, "film");
+        Some(result)
+    }
+
+    fn make_camera(&self, output: Option<&str>) -> Option<Box<dyn Camera>> {
+        let film = self.make_film(output)?;
+        let camera = &self.camera;
+        let result: Box<dyn Camera> = match camera.name.as_str() {
+            "perspective" => Box::new(PerspectiveCamera::create(
+                &camera.params,
+                self.camera_to_world,
+                film,
+                self.named_medium(&self.camera_medium, &camera.location),
+            )),
+            _ => {
+                warning_at(
+                    &camera.location,
+                    &format!("camera \"{}\" is not supported", camera.name),
+                );
+                return None;
+            }
+        };
+        warn_unused(&camera.params, &camera.location, "camera");
+        Some(result)
+    }
+
+    fn make_sampler(&self, sample_bounds: Bounds2Di) -> Option<Box<dyn Sampler>> {
+        let sampler = &self.sampler;
+        let result: Box<dyn Sampler> = match sampler.name.as_str() {
+            "halton" => Box::new(HaltonSampler::create(&sampler.params, sample_bounds)),
+            "sobol" => Box::new(SobolSampler::create(&sampler.params, sample_bounds)),
+            "stratified" => Box::new(StratifiedSampler::create(&sampler.params)),
+            _ => {
+                warning_at(
+                    &sampler.location,
+                    &format!("sampler \"{}\" is not supported", sampler.name),
+                );
+                return None;
+            }
+        };
+        warn_unused(&sampler.params, &sampler.location, "sampler");
+        Some(result)
+    }
+
+    fn make_integrator(&self, output: Option<&str>) -> Option<Box<dyn Integrator>> {
+        let camera = self.make_camera(output)?;
+        let sampler = self.make_sampler(camera.film().get_sample_bounds())?;
+        let integrator = &self.integrator;
+        let result: Box<dyn Integrator> = match integrator.name.as_str() {
+            "directlighting" => Box::new(DirectLightingIntegrator::create(
+                &integrator.params,
+                sampler,
+                camera,
+            )),
+            "path" => Box::new(PathIntegrator::create(&integrator.params, sampler, camera)),
+            "volpath" => Box::new(VolPathIntegrator::create(
+                &integrator.params,
+                sampler,
+                camera,
+            )),
+            "whitted" => Box::new(WhittedIntegrator::create(
+                &integrator.params,
+                sampler,
+                camera,
+            )),
+            _ => {
+                warning_at(
+                    &integrator.location,
+                    &format!("integrator \"{}\" is not supported", integrator.name),
+                );
+                return None;
+            }
+        };
+        warn_unused(&integrator.params, &integrator.location, "integrator");
+        if self.lights.is_empty() {
+            warning_at(
+                &integrator.location,
+                "no light sources defined in scene, rendering a black image",
+            );
+        }
+        Some(result)
+    }
+
+    fn make_scene(self) -> Scene {
+        let accelerator = &self.accelerator;
+        let aggregate = match accelerator.name.as_str() {
+            "bvh" => BVHAccel::create(self.primitives, &accelerator.params),
+            _ => {
+                warning_at(
+                    &accelerator.location,
+                    &format!(
+                        "accelerator \"{}\" is not supported, using \"bvh\"",
+                        accelerator.name
+                    ),
+                );
+                BVHAccel::create(self.primitives, &ParamSet::new())
+            }
+        };
+        warn_unused(&accelerator.params, &accelerator.location, "accelerator");
+        Scene::new(self.lights, Box::new(aggregate))
+    }
+}
+
+pub(crate) fn warning_at(location: &str, message: &str) {
+    if location.is_empty() {
+        eprintln!("warning: {}", message);
+    } else {
+        eprintln!("{}: warning: {}", location, message);
+    }
+}
+
+fn warn_unused(params: &ParamSet, location: &str, owner: &str) {
+    for param in params.unused() {
+        warning_at(
+            location,
+            &format!(
+                "parameter \"{} {}\" is not used by the {}",
+                param.value.type_name(),
+                param.name,
+                owner
+            ),
+        );
+    }
+}
@@ -1,38 +1,291 @@
+use crate::core::imageio;
+use crate::core::ParamSet;
+use crate::filters::Filter;
 use crate::math::*;
 use crate::spectrum::Spectrum;
+use std::sync::Mutex;
 
-pub struct Film {}
+// Resolution of the precomputed filter values, per axis
+const FILTER_TABLE_WIDTH: usize = 16;
+
+#[derive(Clone, Copy, Default)]
+struct Pixel {
+    rgb: [f32; 3],
+    filter_weight_sum: f32,
+}
+
+pub struct Film {
+    pub full_resolution: Point2i,
+    pub filter: Box<dyn Filter>,
+    pub filename: String,
+    pub cropped_pixel_bounds: Bounds2Di,
+    pixels: Mutex<Vec<Pixel>>,
+    filter_table: Vec<f32>,
+    scale: f32,
+    max_sample_luminance: f32,
+}
 
 impl Film {
+    pub fn new(
+        full_resolution: Point2i,
+        crop_window: Bounds2Df,
+        filter: Box<dyn Filter>,
+        filename: String,
+        scale: f32,
+        max_sample_luminance: f32,
+    ) -> Film {
+        let cropped_pixel_bounds = Bounds2Di::from_two_points(
+            Point2i::new(
+                (full_resolution.x as f32 * crop_window.min.x).ceil() as i32,
+                (full_resolution.y as f32 * crop_window.min.y).ceil() as i32,
+            ),
+            Point2i::new(
+                (full_resolution.x as f32 * crop_window.max.x).ceil() as i32,
+                (full_resolution.y as f32 * crop_window.max.y).ceil() as i32,
+            ),
+        );
+        let pixels = vec![Pixel::default(); cropped_pixel_bounds.area() as usize];
+
+        // Only one quadrant is stored as filters are symmetric
+        let radius = filter.radius();
+        let mut filter_table = Vec::with_capacity(FILTER_TABLE_WIDTH * FILTER_TABLE_WIDTH);
+        for y in 0..FILTER_TABLE_WIDTH {
+            for x in 0..FILTER_TABLE_WIDTH {
+                let p = Point2::new(
+                    (x as f32 + 0.5) * radius.x / FILTER_TABLE_WIDTH as f32,
+                    (y as f32 + 0.5) * radius.y / FILTER_TABLE_WIDTH as f32,
+                );
+                filter_table.push(filter.evaluate(p));
+            }
+        }
+
+        Film {
+            full_resolution,
+            filter,
+            filename,
+            cropped_pixel_bounds,
+            pixels: Mutex::new(pixels),
+            filter_table,
+            scale,
+            max_sample_luminance,
+        }
+    }
+
+    // The filename given on the command line takes priority over the one in the scene
+    pub fn create(params: &ParamSet, filter: Box<dyn Filter>, output: Option<&str>) -> Film {
+        let filename = params.find_one_string("filename", "pbrt.png");
+        let filename = output.map_or(filename, String::from);
+        let x_resolution = params.find_one_int("xresolution", 1280);
+        let y_resolution = params.find_one_int("yresolution", 720);
+
+        let crop_window = match params.find_floats("cropwindow") {
+            Some([x0, x1, y0, y1]) => Bounds2Df::from_two_points(
+                Point2::new(
+                    clamp(min(*x0, *x1), 0.0, 1.0),
+                    clamp(min(*y0, *y1), 0.0, 1.0),
+                ),
+                Point2::new(
+                    clamp(max(*x0, *x1), 0.0, 1.0),
+                    clamp(max(*y0, *y1), 0.0, 1.0),
+                ),
+            ),
+            Some(values) => {
+                eprintln!(
+                    "warning: \"cropwindow\" needs four values, found {}, using the whole image",
+                    values.len()
+                );
+                Bounds2Df::from_two_points(Point2::new(0.0, 0.0), Point2::new(1.0, 1.0))
+            }
+            None => Bounds2Df::from_two_points(Point2::new(0.0, 0.0), Point2::new(1.0, 1.0)),
+        };
+
+        let scale = params.find_one_float("scale", 1.0);
+        let max_sample_luminance = params.find_one_float("maxsampleluminance", f32::INFINITY);
+        Film::new(
+            Point2i::new(x_resolution, y_resolution),
+            crop_window,
+            filter,
+            filename,
+            scale,
+            max_sample_luminance,
+        )
+    }
+
+    // Pixels whose samples can contribute to the cropped image, including the filter extent
     pub fn get_sample_bounds(&self) -> Bounds2Di {
-        Bounds2Di::default()
+        let radius = self.filter.radius();
+        let bounds = &self.cropped_pixel_bounds;
+        Bounds2Di::from_two_points(
+            Point2i::new(
+                (bounds.min.x as f32 + 0.5 - radius.x).floor() as i32,
+                (bounds.min.y as f32 + 0.5 - radius.y).floor() as i32,
+            ),
+            Point2i::new(
+                (bounds.max.x as f32 - 0.5 + radius.x).ceil() as i32,
+                (bounds.max.y as f32 - 0.5 + radius.y).ceil() as i32,
+            ),
+        )
     }
 
-    pub fn get_film_tile(&self, bounds: Bounds2Di) -> FilmTile {
-        FilmTile {}
+    pub fn get_film_tile(&self, sample_bounds: Bounds2Di) -> FilmTile<'_> {
+        // Bound image pixels that samples in sample_bounds contribute to
+        let radius = self.filter.radius();
+        let p0 = Point2i::new(
+            (sample_bounds.min.x as f32 - 0.5 - radius.x).ceil() as i32,
+            (sample_bounds.min.y as f32 - 0.5 - radius.y).ceil() as i32,
+        );
+        let p1 = Point2i::new(
+            (sample_bounds.max.x as f32 - 0.5 + radius.x).floor() as i32 + 1,
+            (sample_bounds.max.y as f32 - 0.5 + radius.y).floor() as i32 + 1,
+        );
+        let tile_pixel_bounds = intersect_2d(
+            &Bounds2Di::from_two_points(p0, p1),
+            &self.cropped_pixel_bounds,
+        );
+        FilmTile::new(
+            tile_pixel_bounds,
+            radius,
+            &self.filter_table,
+            self.max_sample_luminance,
+        )
     }
 
-    pub fn merge_film_tile(&self, tile: FilmTile) {}
+    pub fn merge_film_tile(&self, tile: FilmTile) {
+        let mut pixels = self.pixels.lock().unwrap();
+        for p in tile.pixel_bounds.iter() {
+            let tile_pixel = tile.pixel(p);
+            let pixel = &mut pixels[self.pixel_offset(p)];
+            for (sum, c) in pixel.rgb.iter_mut().zip(tile_pixel.rgb.iter()) {
+                *sum += c;
+            }
+            pixel.filter_weight_sum += tile_pixel.filter_weight_sum;
+        }
+    }
+
+    fn pixel_offset(&self, p: Point2i) -> usize {
+        let width = self.cropped_pixel_bounds.max.x - self.cropped_pixel_bounds.min.x;
+        ((p.y - self.cropped_pixel_bounds.min.y) * width + (p.x - self.cropped_pixel_bounds.min.x))
+            as usize
+    }
+
+    // Final linear RGB values of the cropped image, row by row
+    pub fn get_pixels(&self) -> Vec<[f32; 3]> {
+        let pixels = self.pixels.lock().unwrap();
+        pixels
+            .iter()
+            .map(|pixel| {
+                if pixel.filter_weight_sum == 0.0 {
+                    return [0.0; 3];
+                }
+                let inv_weight = 1.0 / pixel.filter_weight_sum;
+                let mut rgb = pixel.rgb;
+                for c in rgb.iter_mut() {
+                    *c = max(0.0, *c * inv_weight) * self.scale;
+                }
+                rgb
+            })
+            .collect()
+    }
+
+    pub fn write_image(&self) {
+        let rgb = self.get_pixels();
+        let resolution = Point2i::from_vec(self.cropped_pixel_bounds.diagonal());
+        match imageio::write_image(&self.filename, &rgb, resolution) {
+            Ok(()) => println!("Wrote {}", self.filename),
+            Err(err) => eprintln!("error: {}", err),
+        }
+    }
+}
 
-    pub fn write_image(&self) {}
+// Part of the film a single thread accumulates samples into, before merging it back
+pub struct FilmTile<'a> {
+    pixel_bounds: Bounds2Di,
+    filter_radius: Vec2,
+    inv_filter_radius: Vec2,
+    filter_table: &'a [f32],
+    pixels: Vec<Pixel>,
+    max_sample_luminance: f32,
 }
 
-pub struct FilmTile {}
+impl<'a> FilmTile<'a> {
+    fn new(
+        pixel_bounds: Bounds2Di,
+        filter_radius: Vec2,
+        filter_table: &'a [f32],
+        max_sample_luminance: f32,
+    ) -> FilmTile<'a> {
+        FilmTile {
+            pixel_bounds,
+            filter_radius,
+            inv_filter_radius: Vec2::new(1.0 / filter_radius.x, 1.0 / filter_radius.y),
+            filter_table,
+            pixels: vec![Pixel::default(); max(0, pixel_bounds.area()) as usize],
+            max_sample_luminance,
+        }
+    }
 
-impl FilmTile {
-    pub fn add_sample(&self, film: &Film, color: Spectrum, ray_weight: f32) -> () {}
+    fn pixel_offset(&self, p: Point2i) -> usize {
+        let width = self.pixel_bounds.max.x - self.pixel_bounds.min.x;
+        ((p.y - self.pixel_bounds.min.y) * width + (p.x - self.pixel_bounds.min.x)) as usize
+    }
 
-    pub fn iter(&self) -> FilmTileIterator {
-        FilmTileIterator
+    fn pixel(&self, p: Point2i) -> &Pixel {
+        &self.pixels[self.pixel_offset(p)]
     }
-}
 
-pub struct FilmTileIterator;
+    pub fn add_sample(&mut self, p_film: Point2, radiance: Spectrum, sample_weight: f32) {
+        let mut radiance = radiance;
+        let luminance = radiance.y();
+        if luminance > self.max_sample_luminance {
+            radiance *= self.max_sample_luminance / luminance;
+        }
+        let rgb = radiance.to_rgb();
+
+        // Compute sample's raster bounds
+        let p_film_discrete = Point2::new(p_film.x - 0.5, p_film.y - 0.5);
+        let p0 = Point2i::new(
+            max(
+                (p_film_discrete.x - self.filter_radius.x).ceil() as i32,
+                self.pixel_bounds.min.x,
+            ),
+            max(
+                (p_film_discrete.y - self.filter_radius.y).ceil() as i32,
+                self.pixel_bounds.min.y,
+            ),
+        );
+        let p1 = Point2i::new(
+            min(
+                (p_film_discrete.x + self.filter_radius.x).floor() as i32 + 1,
+                self.pixel_bounds.max.x,
+            ),
+            min(
+                (p_film_discrete.y + self.filter_radius.y).floor() as i32 + 1,
+                self.pixel_bounds.max.y,
+            ),
+        );
 
-impl Iterator for FilmTileIterator {
-    type Item = Point2i;
+        // Precompute x and y filter table offsets
+        let table_offset = |p: i32, p_discrete: f32, inv_radius: f32| {
+            let f = ((p as f32 - p_discrete) * inv_radius * FILTER_TABLE_WIDTH as f32).abs();
+            std::cmp::min(f.floor() as usize, FILTER_TABLE_WIDTH - 1)
+        };
+        let ifx = (p0.x..p1.x)
+            .map(|x| table_offset(x, p_film_discrete.x, self.inv_filter_radius.x))
+            .collect::<Vec<usize>>();
+        let ify = (p0.y..p1.y)
+            .map(|y| table_offset(y, p_film_discrete.y, self.inv_filter_radius.y))
+            .collect::<Vec<usize>>();
 
-    fn next(&mut self) -> Option<Point2i> {
-        None
+        for (y, offset_y) in (p0.y..p1.y).zip(ify.iter()) {
+            for (x, offset_x) in (p0.x..p1.x).zip(ifx.iter()) {
+                let filter_weight = self.filter_table[offset_y * FILTER_TABLE_WIDTH + offset_x];
+                let offset = self.pixel_offset(Point2i::new(x, y));
+                let pixel = &mut self.pixels[offset];
+                for (sum, c) in pixel.rgb.iter_mut().zip(rgb.iter()) {
+                    *sum += c * sample_weight * filter_weight;
+                }
+                pixel.filter_weight_sum += filter_weight;
+            }
+        }
     }
 }
@@ -0,0 +1,129 @@
+use crate::math::*;
+use std::io::Write;
+use std::path::Path;
+
+// Writes linear RGB pixels, the format is picked from the file extension
+pub fn write_image(filename: &str, rgb: &[[f32; 3]], resolution: Point2i) -> Result<(), String> {
+    let extension = Path::new(filename)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase());
+    let result = match extension.as_deref() {
+        Some("exr") => write_exr(filename, rgb, resolution),
+        Some("hdr") => write_hdr(filename, rgb, resolution),
+        Some("pfm") => write_pfm(filename, rgb, resolution),
+        Some("png") => write_png(filename, rgb, resolution),
+        _ => return Err(format!("unsupported image format for \"{}\"", filename)),
+    };
+    result.map_err(|err| format!("unable to write \"{}\": {}", filename, err))
+}
+
+// Reads linear RGB pixels and the resolution, 8 bit images are taken to be sRGB encoded
+pub fn read_image(filename: &str) -> Result<(Vec<[f32; 3]>, Point2i), String> {
+    let extension = Path::new(filename)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase());
+    let result = match extension.as_deref() {
+        Some("exr") | Some("hdr") => garage_ray_imageio::load_image(Path::new(filename))
+            .map(|image| {
+                (
+                    image.pixels,
+                    Point2i::new(image.width as i32, image.height as i32),
+                )
+            })
+            .map_err(|err| err.to_string()),
+        Some("png") | Some("tga") => read_8bit(filename),
+        _ => return Err(format!("unsupported image format for \"{}\"", filename)),
+    };
+    result.map_err(|err| format!("unable to read \"{}\": {}", filename, err))
+}
+
+fn read_8bit(filename: &str) -> Result<(Vec<[f32; 3]>, Point2i), String> {
+    let image = image::open(filename)
+        .map_err(|err| err.to_string())?
+        .to_rgb();
+    let resolution = Point2i::new(image.width() as i32, image.height() as i32);
+    let rgb = image
+        .pixels()
+        .map(|pixel| {
+            pixel
+                .0
+                .map(|value| inverse_gamma_correct(value as f32 / 255.0))
+        })
+        .collect();
+    Ok((rgb, resolution))
+}
+
+pub fn gamma_correct(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        12.92 * value
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+pub fn inverse_gamma_correct(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn to_byte(value: f32) -> u8 {
+    clamp(255.0 * gamma_correct(value) + 0.5, 0.0, 255.0) as u8
+}
+
+fn write_png(filename: &str, rgb: &[[f32; 3]], resolution: Point2i) -> Result<(), String> {
+    let bytes = rgb
+        .iter()
+        .flat_map(|pixel| pixel.iter().map(|value| to_byte(*value)))
+        .collect::<Vec<u8>>();
+    image::save_buffer(
+        filename,
+        &bytes,
+        resolution.x as u32,
+        resolution.y as u32,
+        image::ColorType::RGB(8),
+    )
+    .map_err(|err| err.to_string())
+}
+
+fn write_exr(filename: &str, rgb: &[[f32; 3]], resolution: Point2i) -> Result<(), String> {
+    let layer = garage_ray_imageio::ExrLayer {
+        name: "",
+        pixels: rgb,
+    };
+    garage_ray_imageio::save_exr(
+        Path::new(filename),
+        resolution.x as usize,
+        resolution.y as usize,
+        garage_ray_imageio::ExrPixelType::Half,
+        &[layer],
+    )
+    .map_err(|err| err.to_string())
+}
+
+fn write_hdr(filename: &str, rgb: &[[f32; 3]], resolution: Point2i) -> Result<(), String> {
+    garage_ray_imageio::save_hdr(
+        Path::new(filename),
+        resolution.x as usize,
+        resolution.y as usize,
+        rgb,
+    )
+    .map_err(|err| err.to_string())
+}
+
+// Portable float map, stored bottom to top in little endian
+fn write_pfm(filename: &str, rgb: &[[f32; 3]], resolution: Point2i) -> Result<(), String> {
+    let width = resolution.x as usize;
+    let mut data = format!("PF\n{} {}\n-1\n", resolution.x, resolution.y).into_bytes();
+    for row in rgb.chunks(width).rev() {
+        for value in row.iter().flatten() {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    let mut file = std::fs::File::create(filename).map_err(|err| err.to_string())?;
+    file.write_all(&data).map_err(|err| err.to_string())
+}
@@ -1,9 +1,15 @@
+use crate::accelerators::Primitive;
 use crate::core::*;
 use crate::math::*;
-use crate::ray::RayDifferential;
+use crate::ray::{Ray, RayDifferential};
 use crate::shapes::*;
 use crate::spectrum::Spectrum;
+use std::sync::Arc;
 
+// Fraction of the distance to the target a shadow ray stops short of
+const SHADOW_EPSILON: f32 = 0.0001;
+
+#[derive(Clone)]
 pub struct Interaction {
     pub p: Point3,
     pub time: f32,
@@ -14,11 +20,98 @@ pub struct Interaction {
 }
 
 impl Interaction {
-    pub fn is_surface_interaction(&self) -> bool {
-        self.n.is_zero()
+    // Point without a direction of interest, like a sampled position on a light
+    pub fn new(p: Point3, n: Normal3f, p_error: Vec3, time: f32) -> Interaction {
+        Interaction {
+            p,
+            time,
+            p_error,
+            wo: Vec3::zero(),
+            n,
+            medium_interface: MediumInterface::default(),
+        }
+    }
+
+    // Medium on the side of the surface w points to
+    pub fn get_medium(&self, w: &Vec3) -> Option<Arc<dyn Medium>> {
+        if dot(*w, self.n) > 0.0 {
+            self.medium_interface.outside.clone()
+        } else {
+            self.medium_interface.inside.clone()
+        }
+    }
+
+    pub fn spawn_ray(&self, d: Vec3) -> Ray {
+        let origin = offset_ray_origin(self.p, self.p_error, self.n, d);
+        let mut ray = Ray::new(origin, d);
+        ray.time = self.time;
+        ray.medium = self.get_medium(&d);
+        ray
+    }
+
+    // Ray towards another interaction which stops before reaching it
+    pub fn spawn_ray_to(&self, it: &Interaction) -> Ray {
+        let origin = offset_ray_origin(self.p, self.p_error, self.n, it.p - self.p);
+        let target = offset_ray_origin(it.p, it.p_error, it.n, origin - it.p);
+        let d = target - origin;
+        let mut ray = Ray::new(origin, d);
+        ray.t_max.set(1.0 - SHADOW_EPSILON);
+        ray.time = self.time;
+        ray.medium = self.get_medium(&d);
+        ray
     }
 }
 
+// Point inside a medium where light scatters, it has no normal and the same medium on
+// both sides
+pub struct MediumInteraction {
+    pub interaction: Interaction,
+    pub phase: HenyeyGreenstein,
+}
+
+impl MediumInteraction {
+    pub fn new(
+        p: Point3,
+        wo: Vec3,
+        time: f32,
+        medium: Option<Arc<dyn Medium>>,
+        phase: HenyeyGreenstein,
+    ) -> MediumInteraction {
+        MediumInteraction {
+            interaction: Interaction {
+                p,
+                time,
+                p_error: Vec3::zero(),
+                wo,
+                n: Normal3f::zero(),
+                medium_interface: MediumInterface::from_medium(medium),
+            },
+            phase,
+        }
+    }
+}
+
+// Moves p past its error bounds on the side of the normal w points to, so rays leaving it
+// don't intersect the surface again
+pub fn offset_ray_origin(p: Point3, p_error: Vec3, n: Normal3f, w: Vec3) -> Point3 {
+    let d = dot(Vec3::new(n.x.abs(), n.y.abs(), n.z.abs()), p_error);
+    let mut offset = n * d;
+    if dot(w, n) < 0.0 {
+        offset = -offset;
+    }
+    let mut po = p + offset;
+    // Round offset point away from p
+    for i in 0..3 {
+        if offset[i] > 0.0 {
+            po[i] = next_float_up(po[i]);
+        } else if offset[i] < 0.0 {
+            po[i] = next_float_down(po[i]);
+        }
+    }
+    po
+}
+
+#[derive(Clone)]
 pub struct Shading {
     pub n: Normal3f,
     pub dpdu: Vec3,
@@ -35,8 +128,11 @@ pub struct SurfaceInteraction<'a> {
     pub dndu: Normal3f,
     pub dndv: Normal3f,
     pub shape: &'a Shape,
+    // Primitive that was hit, set by the primitive once the shape reported the hit
+    pub primitive: Option<&'a dyn Primitive>,
     pub shading: Shading,
-    pub bsdf: BSDF,
+    // Set by the material in compute_scattering_functions, None where nothing scatters
+    pub bsdf: Option<BSDF>,
 }
 
 impl<'a> SurfaceInteraction<'a> {
@@ -51,7 +147,7 @@ impl<'a> SurfaceInteraction<'a> {
         dndv: Normal3f,
         time: f32,
         shape: &Shape,
-    ) -> SurfaceInteraction {
+    ) -> SurfaceInteraction<'_> {
         let mut n = dpdu.cross(dpdv).normalize();
         // null check add
         if shape.reverse_orientation ^ shape.transform_swaps_handedness {
@@ -64,7 +160,7 @@ impl<'a> SurfaceInteraction<'a> {
                 p_error,
                 wo,
                 time,
-                medium_interface: MediumInterface {},
+                medium_interface: MediumInterface::default(),
             },
             uv,
             dpdu,
@@ -72,6 +168,7 @@ impl<'a> SurfaceInteraction<'a> {
             dndu,
             dndv,
             shape,
+            primitive: None,
             shading: Shading {
                 n,
                 dpdu,
@@ -79,14 +176,27 @@ impl<'a> SurfaceInteraction<'a> {
                 dndu,
                 dndv,
             },
-            bsdf: BSDF {},
+            bsdf: None,
         }
     }
 
-    pub fn compute_scattering_functions(&self, ray: &RayDifferential /* Memory Arena */) {}
+    // Lets the material of the hit primitive set bsdf
+    pub fn compute_scattering_functions(
+        &mut self,
+        _ray: &RayDifferential, /* Memory Arena */
+        allow_multiple_lobes: bool,
+    ) {
+        if let Some(material) = self.primitive.and_then(|primitive| primitive.material()) {
+            material.compute_scattering_functions(self, allow_multiple_lobes);
+        }
+    }
 
+    // Radiance emitted from the hit point in direction w if an area light was hit
     pub fn light_emission(&self, w: &Vec3) -> Spectrum {
-        Spectrum::new()
+        match self.primitive.and_then(|primitive| primitive.area_light()) {
+            Some(area_light) => area_light.light_emitted(&self.interaction, w),
+            None => Spectrum::new(),
+        }
     }
 
     pub fn set_shading_geometry(
@@ -96,7 +206,7 @@ impl<'a> SurfaceInteraction<'a> {
         dndus: Normal3f,
         dndvs: Normal3f,
         orientation_is_authorative: bool,
-    ) -> () {
+    ) {
         self.shading.n = dpdus.cross(dpdvs).normalize();
         if self.shape.reverse_orientation ^ self.shape.transform_swaps_handedness {
             self.shading.n = -self.shading.n;
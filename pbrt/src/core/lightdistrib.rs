@@ -0,0 +1,63 @@
+use crate::core::sampling::Distribution1D;
+use crate::core::Scene;
+use crate::math::*;
+
+// Probabilities of picking each of the scene's lights for a shadow ray from a point
+pub trait LightDistribution: Send + Sync {
+    fn lookup(&self, p: &Point3) -> &Distribution1D;
+}
+
+// Every light is equally likely
+pub struct UniformLightDistribution {
+    distribution: Distribution1D,
+}
+
+impl UniformLightDistribution {
+    pub fn new(scene: &Scene) -> UniformLightDistribution {
+        UniformLightDistribution {
+            distribution: Distribution1D::new(&vec![1.0; scene.lights.len()]),
+        }
+    }
+}
+
+impl LightDistribution for UniformLightDistribution {
+    fn lookup(&self, _p: &Point3) -> &Distribution1D {
+        &self.distribution
+    }
+}
+
+// Lights are picked proportionally to their emitted power, ignoring where the point is
+pub struct PowerLightDistribution {
+    distribution: Distribution1D,
+}
+
+impl PowerLightDistribution {
+    pub fn new(scene: &Scene) -> PowerLightDistribution {
+        let light_power = scene
+            .lights
+            .iter()
+            .map(|light| light.power().y())
+            .collect::<Vec<_>>();
+        PowerLightDistribution {
+            distribution: Distribution1D::new(&light_power),
+        }
+    }
+}
+
+impl LightDistribution for PowerLightDistribution {
+    fn lookup(&self, _p: &Point3) -> &Distribution1D {
+        &self.distribution
+    }
+}
+
+// The "lightsamplestrategy" parameter of the integrators, the error names an unknown strategy
+pub fn create_light_sample_distribution(
+    name: &str,
+    scene: &Scene,
+) -> Result<Box<dyn LightDistribution>, String> {
+    match name {
+        "uniform" => Ok(Box::new(UniformLightDistribution::new(scene))),
+        "power" => Ok(Box::new(PowerLightDistribution::new(scene))),
+        _ => Err(format!("light sample distribution \"{}\" unknown", name)),
+    }
+}
@@ -0,0 +1,321 @@
+use crate::core::rng::{ONE_MINUS_EPSILON, RNG};
+use crate::core::sampling::shuffle;
+use crate::math::*;
+use std::sync::OnceLock;
+
+pub const PRIME_TABLE_SIZE: usize = 1000;
+// van der Corput plus the dimensions in SOBOL_INITIAL
+pub const NUM_SOBOL_DIMENSIONS: usize = 21;
+pub const SOBOL_MATRIX_SIZE: usize = 52;
+
+// The first PRIME_TABLE_SIZE primes
+pub fn primes() -> &'static [u64] {
+    static PRIMES: OnceLock<Vec<u64>> = OnceLock::new();
+    PRIMES.get_or_init(|| {
+        let mut primes: Vec<u64> = Vec::with_capacity(PRIME_TABLE_SIZE);
+        let mut candidate = 2;
+        while primes.len() < PRIME_TABLE_SIZE {
+            if primes
+                .iter()
+                .take_while(|p| *p * *p <= candidate)
+                .all(|p| candidate % p != 0)
+            {
+                primes.push(candidate);
+            }
+            candidate += 1;
+        }
+        primes
+    })
+}
+
+// Reflects the digits of a in the base of the base_index-th prime around the radix point
+pub fn radical_inverse(base_index: usize, a: u64) -> f32 {
+    if base_index == 0 {
+        // Base two is a bit reversal
+        return ((a.reverse_bits() as f64 * 5.421_010_862_427_522e-20) as f32)
+            .min(ONE_MINUS_EPSILON);
+    }
+    let base = primes()[base_index];
+    let inv_base = 1.0 / base as f32;
+    let mut reversed_digits: u64 = 0;
+    let mut inv_base_n = 1.0;
+    let mut a = a;
+    while a != 0 {
+        let next = a / base;
+        let digit = a - next * base;
+        reversed_digits = reversed_digits * base + digit;
+        inv_base_n *= inv_base;
+        a = next;
+    }
+    (reversed_digits as f32 * inv_base_n).min(ONE_MINUS_EPSILON)
+}
+
+// Maps the first n_digits reversed digits of inverse back to the index they came from
+pub fn inverse_radical_inverse(base: u64, mut inverse: u64, n_digits: u32) -> u64 {
+    let mut index = 0;
+    for _ in 0..n_digits {
+        let digit = inverse % base;
+        inverse /= base;
+        index = index * base + digit;
+    }
+    index
+}
+
+// Radical inverse with the digits remapped by perm, including the infinite tail of zeros
+pub fn scrambled_radical_inverse(base_index: usize, a: u64, perm: &[u16]) -> f32 {
+    let base = primes()[base_index];
+    let inv_base = 1.0 / base as f32;
+    let mut reversed_digits: u64 = 0;
+    let mut inv_base_n = 1.0;
+    let mut a = a;
+    while a != 0 {
+        let next = a / base;
+        let digit = a - next * base;
+        reversed_digits = reversed_digits * base + u64::from(perm[digit as usize]);
+        inv_base_n *= inv_base;
+        a = next;
+    }
+    (inv_base_n * (reversed_digits as f32 + inv_base * f32::from(perm[0]) / (1.0 - inv_base)))
+        .min(ONE_MINUS_EPSILON)
+}
+
+// Random digit permutations for every prime base, stored one after the other
+pub fn radical_inverse_permutations() -> &'static [u16] {
+    static PERMUTATIONS: OnceLock<Vec<u16>> = OnceLock::new();
+    PERMUTATIONS.get_or_init(|| {
+        let mut rng = RNG::default();
+        let mut permutations = Vec::with_capacity(primes().iter().sum::<u64>() as usize);
+        for &prime in primes() {
+            let start = permutations.len();
+            permutations.extend(0..prime as u16);
+            shuffle(&mut permutations[start..], 1, &mut rng);
+        }
+        permutations
+    })
+}
+
+// Offset of the permutation for base_index in radical_inverse_permutations
+pub fn permutation_offset(base_index: usize) -> usize {
+    static OFFSETS: OnceLock<Vec<usize>> = OnceLock::new();
+    OFFSETS.get_or_init(|| {
+        primes()
+            .iter()
+            .scan(0, |sum, prime| {
+                let offset = *sum;
+                *sum += *prime as usize;
+                Some(offset)
+            })
+            .collect()
+    })[base_index]
+}
+
+fn extended_gcd(a: i64, b: i64) -> (i64, i64) {
+    if b == 0 {
+        return (1, 0);
+    }
+    let d = a / b;
+    let (xp, yp) = extended_gcd(b, a % b);
+    (yp, xp - d * yp)
+}
+
+pub fn multiplicative_inverse(a: i64, n: i64) -> i64 {
+    let (x, _) = extended_gcd(a, n);
+    x.rem_euclid(n)
+}
+
+// Joe and Kuo's primitive polynomials and initial direction numbers for the first Sobol
+// dimensions after the van der Corput one: (degree, coefficients, initial numbers)
+const SOBOL_INITIAL: [(u32, u32, &[u32]); NUM_SOBOL_DIMENSIONS - 1] = [
+    (1, 0, &[1]),
+    (2, 1, &[1, 3]),
+    (3, 1, &[1, 3, 1]),
+    (3, 2, &[1, 1, 1]),
+    (4, 1, &[1, 1, 3, 3]),
+    (4, 4, &[1, 3, 5, 13]),
+    (5, 2, &[1, 1, 5, 5, 17]),
+    (5, 4, &[1, 1, 5, 5, 5]),
+    (5, 7, &[1, 1, 7, 11, 19]),
+    (5, 11, &[1, 1, 5, 1, 1]),
+    (5, 13, &[1, 1, 1, 3, 11]),
+    (5, 14, &[1, 3, 5, 5, 31]),
+    (6, 1, &[1, 3, 3, 9, 7, 49]),
+    (6, 13, &[1, 1, 1, 15, 21, 21]),
+    (6, 16, &[1, 3, 1, 13, 27, 49]),
+    (6, 19, &[1, 1, 1, 15, 7, 5]),
+    (6, 22, &[1, 3, 1, 15, 13, 25]),
+    (6, 25, &[1, 1, 5, 5, 19, 61]),
+    (7, 1, &[1, 3, 7, 11, 23, 15, 103]),
+    (7, 4, &[1, 3, 7, 13, 13, 15, 69]),
+];
+
+// Generator matrices of the Sobol sequence, SOBOL_MATRIX_SIZE columns per dimension with
+// the most significant bit first. Only the dimensions with Joe and Kuo's direction numbers
+// are generated, made up ones would only stratify well in 1D and not in 2D projections
+pub fn sobol_matrices() -> &'static [u32] {
+    static MATRICES: OnceLock<Vec<u32>> = OnceLock::new();
+    MATRICES.get_or_init(|| {
+        let mut matrices = Vec::with_capacity(NUM_SOBOL_DIMENSIONS * SOBOL_MATRIX_SIZE);
+        // van der Corput
+        for column in 0..SOBOL_MATRIX_SIZE {
+            matrices.push(if column < 32 { 1 << (31 - column) } else { 0 });
+        }
+
+        for &(degree, coefficients, initial) in SOBOL_INITIAL.iter() {
+            let s = degree as usize;
+            let mut v = [0u32; SOBOL_MATRIX_SIZE];
+            for k in 0..s {
+                v[k] = initial[k] << (31 - k);
+            }
+            for k in s..SOBOL_MATRIX_SIZE {
+                v[k] = v[k - s] ^ (v[k - s] >> s);
+                for l in 1..s {
+                    if (coefficients >> (s - 1 - l)) & 1 != 0 {
+                        v[k] ^= v[k - l];
+                    }
+                }
+            }
+            matrices.extend_from_slice(&v);
+        }
+        matrices
+    })
+}
+
+pub fn sobol_sample(index: i64, dimension: usize) -> f32 {
+    let matrix =
+        &sobol_matrices()[dimension * SOBOL_MATRIX_SIZE..(dimension + 1) * SOBOL_MATRIX_SIZE];
+    let mut v = 0;
+    let mut a = index as u64;
+    let mut column = 0;
+    while a != 0 && column < SOBOL_MATRIX_SIZE {
+        if a & 1 != 0 {
+            v ^= matrix[column];
+        }
+        a >>= 1;
+        column += 1;
+    }
+    (v as f32 * (1.0 / 4_294_967_296.0)).min(ONE_MINUS_EPSILON)
+}
+
+// Finds the Sobol samples that land in given pixels of a 2^m x 2^m grid laid over the
+// first two dimensions. Every run of 4^m consecutive indices puts exactly one sample in
+// each pixel, the low 2m bits of the index pick the pixel through an invertible linear map
+#[derive(Clone)]
+pub struct SobolPixelIndexer {
+    m: u32,
+    // Inverse of the map from the low index bits to the pixel, one column per pixel bit
+    inverse: Vec<u64>,
+}
+
+impl SobolPixelIndexer {
+    pub fn new(m: u32) -> SobolPixelIndexer {
+        let n = 2 * m as usize;
+        let matrices = sobol_matrices();
+        // Pixel bits for index bit k: x in the high half, y in the low half
+        let column = |k: usize| -> u64 {
+            if m == 0 {
+                return 0;
+            }
+            let x = u64::from(matrices[k] >> (32 - m));
+            let y = u64::from(matrices[SOBOL_MATRIX_SIZE + k] >> (32 - m));
+            (x << m) | y
+        };
+
+        // Gauss-Jordan elimination over GF(2) on rows [A | I], with A's rows read off
+        // its columns
+        let mut rows: Vec<(u64, u64)> = (0..n)
+            .map(|row| {
+                let bits = (0..n).fold(0, |bits, k| bits | (((column(k) >> row) & 1) << k));
+                (bits, 1 << row)
+            })
+            .collect();
+        for pivot in 0..n {
+            let found = (pivot..n)
+                .find(|row| (rows[*row].0 >> pivot) & 1 != 0)
+                .expect("Sobol pixel map is not invertible");
+            rows.swap(pivot, found);
+            for row in 0..n {
+                if row != pivot && (rows[row].0 >> pivot) & 1 != 0 {
+                    rows[row].0 ^= rows[pivot].0;
+                    rows[row].1 ^= rows[pivot].1;
+                }
+            }
+        }
+        let inverse = (0..n)
+            .map(|bit| (0..n).fold(0, |col, k| col | (((rows[k].1 >> bit) & 1) << k)))
+            .collect();
+        SobolPixelIndexer { m, inverse }
+    }
+
+    // Index of the frame-th sample inside pixel p, relative to the grid origin
+    pub fn index(&self, frame: i64, p: Point2i) -> i64 {
+        if self.m == 0 {
+            return frame;
+        }
+        let m = self.m;
+        let n = 2 * m as usize;
+        let matrices = sobol_matrices();
+        // Contribution of the high index bits, which the low bits have to cancel out
+        let mut delta = 0u64;
+        let mut high = frame as u64;
+        let mut k = n;
+        while high != 0 && k < SOBOL_MATRIX_SIZE {
+            if high & 1 != 0 {
+                let x = u64::from(matrices[k] >> (32 - m));
+                let y = u64::from(matrices[SOBOL_MATRIX_SIZE + k] >> (32 - m));
+                delta ^= (x << m) | y;
+            }
+            high >>= 1;
+            k += 1;
+        }
+        let target = (((p.x as u64) << m) | p.y as u64) ^ delta;
+        let mut low = 0u64;
+        for (bit, column) in self.inverse.iter().enumerate() {
+            if (target >> bit) & 1 != 0 {
+                low ^= column;
+            }
+        }
+        (((frame as u64) << n) | low) as i64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sobol_dimensions_stratify_in_1d() {
+        // The first 2^m points of every dimension land one in each interval of size 2^-m
+        let m = 10;
+        for dimension in 0..NUM_SOBOL_DIMENSIONS {
+            let mut hit = vec![false; 1 << m];
+            for index in 0..1 << m {
+                let bin = (sobol_sample(index, dimension) * (1 << m) as f32) as usize;
+                assert!(
+                    !hit[bin],
+                    "dimension {} repeats interval {}",
+                    dimension, bin
+                );
+                hit[bin] = true;
+            }
+        }
+    }
+
+    #[test]
+    fn sobol_matrices_are_full_rank() {
+        // Direction numbers are odd, so the lowest set bit of column k is bit 31 - k and the
+        // first 32 columns are independent
+        let matrices = sobol_matrices();
+        assert_eq!(matrices.len(), NUM_SOBOL_DIMENSIONS * SOBOL_MATRIX_SIZE);
+        for dimension in 0..NUM_SOBOL_DIMENSIONS {
+            for column in 0..32 {
+                let v = matrices[dimension * SOBOL_MATRIX_SIZE + column];
+                assert_eq!(
+                    v.trailing_zeros(),
+                    31 - column as u32,
+                    "dimension {}",
+                    dimension
+                );
+            }
+        }
+    }
+}
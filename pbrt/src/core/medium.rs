@@ -1,3 +1,96 @@
-pub trait Medium {}
+use crate::core::MediumInteraction;
+use crate::math::*;
+use crate::ray::Ray;
+use crate::samplers::Sampler;
+use crate::spectrum::Spectrum;
+use std::f32::consts::PI;
+use std::sync::Arc;
 
-pub struct MediumInterface {}
+// Participating medium that absorbs, scatters and attenuates light along rays passing
+// through it
+pub trait Medium: Send + Sync {
+    // Transmittance from the ray's origin to t_max
+    fn tr(&self, ray: &Ray, sampler: &mut dyn Sampler) -> Spectrum;
+    // Samples where the ray scatters before t_max, None if it makes it to t_max. The weight
+    // is the transmittance, times sigma_s at a scattering point, divided by the density of
+    // sampling that outcome
+    fn sample(&self, ray: &Ray, sampler: &mut dyn Sampler)
+        -> (Spectrum, Option<MediumInteraction>);
+}
+
+// Media on the two sides of a surface, None for vacuum. Surfaces that don't separate
+// different media take the medium of the ray that hit them
+#[derive(Clone, Default)]
+pub struct MediumInterface {
+    pub inside: Option<Arc<dyn Medium>>,
+    pub outside: Option<Arc<dyn Medium>>,
+}
+
+impl MediumInterface {
+    pub fn new(inside: Option<Arc<dyn Medium>>, outside: Option<Arc<dyn Medium>>) -> Self {
+        MediumInterface { inside, outside }
+    }
+
+    // Same medium on both sides
+    pub fn from_medium(medium: Option<Arc<dyn Medium>>) -> Self {
+        MediumInterface {
+            inside: medium.clone(),
+            outside: medium,
+        }
+    }
+
+    pub fn is_medium_transition(&self) -> bool {
+        match (&self.inside, &self.outside) {
+            (Some(inside), Some(outside)) => !Arc::ptr_eq(inside, outside),
+            (None, None) => false,
+            _ => true,
+        }
+    }
+}
+
+// Angular distribution of light scattered in a medium, wo and wi both point away from the
+// scattering point
+pub trait PhaseFunction {
+    fn p(&self, wo: &Vec3, wi: &Vec3) -> f32;
+    // Samples wi, returning the value of the phase function, which is also its pdf
+    fn sample_p(&self, wo: &Vec3, u: &Point2) -> (f32, Vec3);
+}
+
+// Henyey-Greenstein phase function with the cosine of the angle between wo and wi
+pub fn phase_hg(cos_theta: f32, g: f32) -> f32 {
+    let denom = 1.0 + g * g + 2.0 * g * cos_theta;
+    1.0 / (4.0 * PI) * (1.0 - g * g) / (denom * denom.sqrt())
+}
+
+// Scatters forward for positive g and backward for negative g, g is in (-1, 1)
+#[derive(Clone, Copy)]
+pub struct HenyeyGreenstein {
+    pub g: f32,
+}
+
+impl HenyeyGreenstein {
+    pub fn new(g: f32) -> HenyeyGreenstein {
+        HenyeyGreenstein { g }
+    }
+}
+
+impl PhaseFunction for HenyeyGreenstein {
+    fn p(&self, wo: &Vec3, wi: &Vec3) -> f32 {
+        phase_hg(dot(*wo, *wi), self.g)
+    }
+
+    fn sample_p(&self, wo: &Vec3, u: &Point2) -> (f32, Vec3) {
+        let g = self.g;
+        let cos_theta = if g.abs() < 1e-3 {
+            1.0 - 2.0 * u[0]
+        } else {
+            let sqr_term = (1.0 - g * g) / (1.0 + g - 2.0 * g * u[0]);
+            -(1.0 + g * g - sqr_term * sqr_term) / (2.0 * g)
+        };
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * u[1];
+        let (_, v1, v2) = coordinate_system(*wo);
+        let wi = spherical_direction_in(sin_theta, cos_theta, phi, v1, v2, *wo);
+        (phase_hg(cos_theta, g), wi)
+    }
+}
@@ -0,0 +1,359 @@
+use crate::core::reflection::*;
+use crate::math::*;
+use std::f32::consts::PI;
+
+// Distribution of microfacet normals around +z of the shading frame
+pub trait MicrofacetDistribution: Send + Sync {
+    // Differential area of microfacets with normal wh
+    fn d(&self, wh: &Vec3) -> f32;
+    // Invisible masked microfacet area per visible microfacet area
+    fn lambda(&self, w: &Vec3) -> f32;
+    fn sample_wh(&self, wo: &Vec3, u: &Point2) -> Vec3;
+    // Sample only the microfacets visible from wo instead of the full distribution
+    fn sample_visible_area(&self) -> bool;
+
+    // Fraction of microfacets visible from w
+    fn g1(&self, w: &Vec3) -> f32 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    // Fraction of microfacets visible from both directions
+    fn g(&self, wo: &Vec3, wi: &Vec3) -> f32 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    fn pdf(&self, wo: &Vec3, wh: &Vec3) -> f32 {
+        if self.sample_visible_area() {
+            self.d(wh) * self.g1(wo) * dot(*wo, *wh).abs() / abs_cos_theta(wo)
+        } else {
+            self.d(wh) * abs_cos_theta(wh)
+        }
+    }
+}
+
+// Anisotropic alpha along the projection of w onto the tangent plane
+fn projected_alpha(w: &Vec3, alpha_x: f32, alpha_y: f32) -> f32 {
+    (cos2_phi(w) * alpha_x * alpha_x + sin2_phi(w) * alpha_y * alpha_y).sqrt()
+}
+
+// Azimuth of a sample from an anisotropic distribution, with the ellipse given by the alphas
+fn sample_anisotropic_phi(u: f32, alpha_x: f32, alpha_y: f32) -> f32 {
+    let phi = (alpha_y / alpha_x * (2.0 * PI * u + 0.5 * PI).tan()).atan();
+    if u > 0.5 {
+        phi + PI
+    } else {
+        phi
+    }
+}
+
+// Gaussian distribution of microfacet slopes
+pub struct BeckmannDistribution {
+    alpha_x: f32,
+    alpha_y: f32,
+    sample_visible_area: bool,
+}
+
+impl BeckmannDistribution {
+    pub fn new(alpha_x: f32, alpha_y: f32, sample_visible_area: bool) -> BeckmannDistribution {
+        BeckmannDistribution {
+            alpha_x: alpha_x.max(0.001),
+            alpha_y: alpha_y.max(0.001),
+            sample_visible_area,
+        }
+    }
+
+    // Maps a user facing roughness in [0, 1] to alpha, so the roughness changes look uniform
+    pub fn roughness_to_alpha(roughness: f32) -> f32 {
+        let x = roughness.max(1e-3).ln();
+        1.62142
+            + 0.819955 * x
+            + 0.1734 * x * x
+            + 0.0171201 * x * x * x
+            + 0.000640711 * x * x * x * x
+    }
+}
+
+// Slopes of the visible microfacets for the unit roughness distribution seen at cos_theta_i
+fn beckmann_sample_11(cos_theta_i: f32, u1: f32, u2: f32) -> (f32, f32) {
+    // Special case (normal incidence)
+    if cos_theta_i > 0.9999 {
+        let r = (-(1.0 - u1).ln()).sqrt();
+        let phi = 2.0 * PI * u2;
+        return (r * phi.cos(), r * phi.sin());
+    }
+
+    let sin_theta_i = (1.0 - cos_theta_i * cos_theta_i).max(0.0).sqrt();
+    let tan_theta_i = sin_theta_i / cos_theta_i;
+    let cot_theta_i = 1.0 / tan_theta_i;
+
+    // Search interval for the inverse of the slope x CDF, which is parametrized with erf
+    let mut a = -1.0;
+    let mut c = erf(cot_theta_i);
+    let sample_x = u1.max(1e-6);
+
+    // Start with a good guess to need less Newton iterations
+    let theta_i = cos_theta_i.acos();
+    let fit = 1.0 + theta_i * (-0.876 + theta_i * (0.4265 - 0.0594 * theta_i));
+    let mut b = c - (1.0 + c) * (1.0 - sample_x).powf(fit);
+
+    let sqrt_pi_inv = 1.0 / PI.sqrt();
+    let normalization =
+        1.0 / (1.0 + c + sqrt_pi_inv * tan_theta_i * (-cot_theta_i * cot_theta_i).exp());
+
+    for _ in 1..10 {
+        // Fall back to bisection if b left the bracket
+        if !(b >= a && b <= c) {
+            b = 0.5 * (a + c);
+        }
+
+        let inv_erf = erf_inv(b);
+        let value = normalization
+            * (1.0 + b + sqrt_pi_inv * tan_theta_i * (-inv_erf * inv_erf).exp())
+            - sample_x;
+        let derivative = normalization * (1.0 - inv_erf * tan_theta_i);
+        if value.abs() < 1e-5 {
+            break;
+        }
+
+        if value > 0.0 {
+            c = b;
+        } else {
+            a = b;
+        }
+        b -= value / derivative;
+    }
+
+    (erf_inv(b), erf_inv(2.0 * u2.max(1e-6) - 1.0))
+}
+
+// Samples a visible microfacet normal by stretching the configuration to unit roughness
+fn beckmann_sample(wi: &Vec3, alpha_x: f32, alpha_y: f32, u1: f32, u2: f32) -> Vec3 {
+    let wi_stretched = vec3(alpha_x * wi.x, alpha_y * wi.y, wi.z).normalize();
+
+    let (slope_x, slope_y) = beckmann_sample_11(cos_theta(&wi_stretched), u1, u2);
+
+    // Rotate, then unstretch
+    let (sin_phi, cos_phi) = (sin_phi(&wi_stretched), cos_phi(&wi_stretched));
+    let rotated_x = cos_phi * slope_x - sin_phi * slope_y;
+    let rotated_y = sin_phi * slope_x + cos_phi * slope_y;
+
+    vec3(-alpha_x * rotated_x, -alpha_y * rotated_y, 1.0).normalize()
+}
+
+impl MicrofacetDistribution for BeckmannDistribution {
+    fn d(&self, wh: &Vec3) -> f32 {
+        let tan2_theta = tan2_theta(wh);
+        if tan2_theta.is_infinite() {
+            return 0.0;
+        }
+        let cos4_theta = cos2_theta(wh) * cos2_theta(wh);
+        (-tan2_theta
+            * (cos2_phi(wh) / (self.alpha_x * self.alpha_x)
+                + sin2_phi(wh) / (self.alpha_y * self.alpha_y)))
+            .exp()
+            / (PI * self.alpha_x * self.alpha_y * cos4_theta)
+    }
+
+    fn lambda(&self, w: &Vec3) -> f32 {
+        let abs_tan_theta = tan_theta(w).abs();
+        if abs_tan_theta.is_infinite() {
+            return 0.0;
+        }
+        // Rational approximation of the erfc based exact value
+        let alpha = projected_alpha(w, self.alpha_x, self.alpha_y);
+        let a = 1.0 / (alpha * abs_tan_theta);
+        if a >= 1.6 {
+            return 0.0;
+        }
+        (1.0 - 1.259 * a + 0.396 * a * a) / (3.535 * a + 2.181 * a * a)
+    }
+
+    fn sample_wh(&self, wo: &Vec3, u: &Point2) -> Vec3 {
+        if self.sample_visible_area {
+            let flip = wo.z < 0.0;
+            let wh = beckmann_sample(
+                &if flip { -*wo } else { *wo },
+                self.alpha_x,
+                self.alpha_y,
+                u.x,
+                u.y,
+            );
+            return if flip { -wh } else { wh };
+        }
+
+        let log_sample = (1.0 - u.x).ln();
+        let (tan2_theta, phi) = if self.alpha_x == self.alpha_y {
+            (-self.alpha_x * self.alpha_x * log_sample, u.y * 2.0 * PI)
+        } else {
+            let phi = sample_anisotropic_phi(u.y, self.alpha_x, self.alpha_y);
+            let (sin_phi, cos_phi) = phi.sin_cos();
+            let tan2_theta = -log_sample
+                / (cos_phi * cos_phi / (self.alpha_x * self.alpha_x)
+                    + sin_phi * sin_phi / (self.alpha_y * self.alpha_y));
+            (tan2_theta, phi)
+        };
+
+        let cos_theta = 1.0 / (1.0 + tan2_theta).sqrt();
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let wh = spherical_direction(sin_theta, cos_theta, phi);
+        if same_hemisphere(wo, &wh) {
+            wh
+        } else {
+            -wh
+        }
+    }
+
+    fn sample_visible_area(&self) -> bool {
+        self.sample_visible_area
+    }
+}
+
+// Microfacet distribution of ellipsoids, also known as GGX
+pub struct TrowbridgeReitzDistribution {
+    alpha_x: f32,
+    alpha_y: f32,
+    sample_visible_area: bool,
+}
+
+impl TrowbridgeReitzDistribution {
+    pub fn new(
+        alpha_x: f32,
+        alpha_y: f32,
+        sample_visible_area: bool,
+    ) -> TrowbridgeReitzDistribution {
+        TrowbridgeReitzDistribution {
+            alpha_x: alpha_x.max(0.001),
+            alpha_y: alpha_y.max(0.001),
+            sample_visible_area,
+        }
+    }
+
+    // Maps a user facing roughness in [0, 1] to alpha, so the roughness changes look uniform
+    pub fn roughness_to_alpha(roughness: f32) -> f32 {
+        let x = roughness.max(1e-3).ln();
+        1.62142
+            + 0.819955 * x
+            + 0.1734 * x * x
+            + 0.0171201 * x * x * x
+            + 0.000640711 * x * x * x * x
+    }
+}
+
+// Slopes of the visible microfacets for the unit roughness distribution seen at cos_theta
+fn trowbridge_reitz_sample_11(cos_theta: f32, u1: f32, u2: f32) -> (f32, f32) {
+    // Special case (normal incidence)
+    if cos_theta > 0.9999 {
+        let r = (u1 / (1.0 - u1)).sqrt();
+        let phi = 2.0 * PI * u2;
+        return (r * phi.cos(), r * phi.sin());
+    }
+
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let tan_theta = sin_theta / cos_theta;
+    let a = 1.0 / tan_theta;
+    let g1 = 2.0 / (1.0 + (1.0 + 1.0 / (a * a)).sqrt());
+
+    // Sample slope x
+    let a = 2.0 * u1 / g1 - 1.0;
+    let tmp = (1.0 / (a * a - 1.0)).min(1e10);
+    let b = tan_theta;
+    let d = (b * b * tmp * tmp - (a * a - b * b) * tmp).max(0.0).sqrt();
+    let slope_x_1 = b * tmp - d;
+    let slope_x_2 = b * tmp + d;
+    let slope_x = if a < 0.0 || slope_x_2 > 1.0 / tan_theta {
+        slope_x_1
+    } else {
+        slope_x_2
+    };
+
+    // Sample slope y
+    let (s, u2) = if u2 > 0.5 {
+        (1.0, 2.0 * (u2 - 0.5))
+    } else {
+        (-1.0, 2.0 * (0.5 - u2))
+    };
+    let z = (u2 * (u2 * (u2 * 0.27385 - 0.73369) + 0.46341))
+        / (u2 * (u2 * (u2 * 0.093073 + 0.309420) - 1.0) + 0.597999);
+    let slope_y = s * z * (1.0 + slope_x * slope_x).sqrt();
+
+    (slope_x, slope_y)
+}
+
+// Samples a visible microfacet normal by stretching the configuration to unit roughness
+fn trowbridge_reitz_sample(wi: &Vec3, alpha_x: f32, alpha_y: f32, u1: f32, u2: f32) -> Vec3 {
+    let wi_stretched = vec3(alpha_x * wi.x, alpha_y * wi.y, wi.z).normalize();
+
+    let (slope_x, slope_y) = trowbridge_reitz_sample_11(cos_theta(&wi_stretched), u1, u2);
+
+    // Rotate, then unstretch
+    let (sin_phi, cos_phi) = (sin_phi(&wi_stretched), cos_phi(&wi_stretched));
+    let rotated_x = cos_phi * slope_x - sin_phi * slope_y;
+    let rotated_y = sin_phi * slope_x + cos_phi * slope_y;
+
+    vec3(-alpha_x * rotated_x, -alpha_y * rotated_y, 1.0).normalize()
+}
+
+impl MicrofacetDistribution for TrowbridgeReitzDistribution {
+    fn d(&self, wh: &Vec3) -> f32 {
+        let tan2_theta = tan2_theta(wh);
+        if tan2_theta.is_infinite() {
+            return 0.0;
+        }
+        let cos4_theta = cos2_theta(wh) * cos2_theta(wh);
+        let e = (cos2_phi(wh) / (self.alpha_x * self.alpha_x)
+            + sin2_phi(wh) / (self.alpha_y * self.alpha_y))
+            * tan2_theta;
+        1.0 / (PI * self.alpha_x * self.alpha_y * cos4_theta * (1.0 + e) * (1.0 + e))
+    }
+
+    fn lambda(&self, w: &Vec3) -> f32 {
+        let abs_tan_theta = tan_theta(w).abs();
+        if abs_tan_theta.is_infinite() {
+            return 0.0;
+        }
+        let alpha = projected_alpha(w, self.alpha_x, self.alpha_y);
+        let alpha2_tan2_theta = (alpha * abs_tan_theta) * (alpha * abs_tan_theta);
+        (-1.0 + (1.0 + alpha2_tan2_theta).sqrt()) / 2.0
+    }
+
+    fn sample_wh(&self, wo: &Vec3, u: &Point2) -> Vec3 {
+        if self.sample_visible_area {
+            let flip = wo.z < 0.0;
+            let wh = trowbridge_reitz_sample(
+                &if flip { -*wo } else { *wo },
+                self.alpha_x,
+                self.alpha_y,
+                u.x,
+                u.y,
+            );
+            return if flip { -wh } else { wh };
+        }
+
+        let (tan2_theta, phi) = if self.alpha_x == self.alpha_y {
+            (
+                self.alpha_x * self.alpha_x * u.x / (1.0 - u.x),
+                2.0 * PI * u.y,
+            )
+        } else {
+            let phi = sample_anisotropic_phi(u.y, self.alpha_x, self.alpha_y);
+            let (sin_phi, cos_phi) = phi.sin_cos();
+            let alpha2 = 1.0
+                / (cos_phi * cos_phi / (self.alpha_x * self.alpha_x)
+                    + sin_phi * sin_phi / (self.alpha_y * self.alpha_y));
+            (alpha2 * u.x / (1.0 - u.x), phi)
+        };
+
+        let cos_theta = 1.0 / (1.0 + tan2_theta).sqrt();
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let wh = spherical_direction(sin_theta, cos_theta, phi);
+        if same_hemisphere(wo, &wh) {
+            wh
+        } else {
+            -wh
+        }
+    }
+
+    fn sample_visible_area(&self) -> bool {
+        self.sample_visible_area
+    }
+}
@@ -1,10 +1,209 @@
+use super::ProgressReporter;
 use crate::math::*;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 
-pub fn parallel_for_2d(func: &Fn(Point2i) -> (), num_tiles: Point2i) {
-    // TODO: actually implement me parallely
-    for y in 0..num_tiles.y {
-        for x in 0..num_tiles.x {
-            func(Point2i::new(x, y));
+// Order tiles are handed out in, workers start at the front of the list
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TileOrder {
+    Scanline,
+    // Outwards from the center of the image, where the interesting part usually is
+    Spiral,
+    // Along a Hilbert curve, consecutive tiles stay close to each other
+    Hilbert,
+}
+
+impl std::str::FromStr for TileOrder {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<TileOrder, String> {
+        match name {
+            "scanline" => Ok(TileOrder::Scanline),
+            "spiral" => Ok(TileOrder::Spiral),
+            "hilbert" => Ok(TileOrder::Hilbert),
+            _ => Err(format!(
+                "unknown tile order '{}', expected scanline, spiral or hilbert",
+                name
+            )),
         }
     }
 }
+
+#[derive(Clone)]
+pub struct ParallelOptions {
+    // 0 uses one thread per core, 1 runs everything on the calling thread which is
+    // easiest to debug
+    pub n_threads: usize,
+    pub tile_order: TileOrder,
+    pub quiet: bool,
+    // Set on Ctrl-C or when a tile panics. Once set no more tiles are started, the ones
+    // already running still finish
+    pub cancel: Arc<AtomicBool>,
+}
+
+impl Default for ParallelOptions {
+    fn default() -> Self {
+        ParallelOptions {
+            n_threads: 0,
+            tile_order: TileOrder::Spiral,
+            quiet: false,
+            cancel: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
+
+impl ParallelOptions {
+    pub fn thread_count(&self) -> usize {
+        if self.n_threads > 0 {
+            self.n_threads
+        } else {
+            std::thread::available_parallelism().map_or(1, |count| count.get())
+        }
+    }
+
+    pub fn cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+}
+
+// Every tile of the num_tiles grid, in the requested order
+pub fn tile_order(order: TileOrder, num_tiles: Point2i) -> Vec<Point2i> {
+    let count = (num_tiles.x.max(0) * num_tiles.y.max(0)) as usize;
+    let mut tiles = Vec::with_capacity(count);
+    if count == 0 {
+        return tiles;
+    }
+    let inside = |p: Point2i| p.x >= 0 && p.y >= 0 && p.x < num_tiles.x && p.y < num_tiles.y;
+    match order {
+        TileOrder::Scanline => {
+            for y in 0..num_tiles.y {
+                for x in 0..num_tiles.x {
+                    tiles.push(Point2i::new(x, y));
+                }
+            }
+        }
+        TileOrder::Spiral => {
+            // Legs of length 1, 1, 2, 2, 3, 3... turning right after each one
+            let mut p = Point2i::new((num_tiles.x - 1) / 2, (num_tiles.y - 1) / 2);
+            let directions = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+            let mut leg = 0;
+            while tiles.len() < count {
+                let (dx, dy) = directions[leg % 4];
+                for _ in 0..leg / 2 + 1 {
+                    if inside(p) {
+                        tiles.push(p);
+                    }
+                    p = Point2i::new(p.x + dx, p.y + dy);
+                }
+                leg += 1;
+            }
+        }
+        TileOrder::Hilbert => {
+            // Curve over the enclosing power of two square, skipping tiles outside the grid
+            let size = (num_tiles.x.max(num_tiles.y) as u32).next_power_of_two() as i32;
+            for d in 0..size * size {
+                let p = hilbert_point(size, d);
+                if inside(p) {
+                    tiles.push(p);
+                }
+            }
+        }
+    }
+    tiles
+}
+
+// Point at distance d along the Hilbert curve filling a size x size square
+fn hilbert_point(size: i32, d: i32) -> Point2i {
+    let (mut x, mut y) = (0, 0);
+    let mut t = d;
+    let mut s = 1;
+    while s < size {
+        let rx = 1 & (t / 2);
+        let ry = 1 & (t ^ rx);
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - x;
+                y = s - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        x += s * rx;
+        y += s * ry;
+        t /= 4;
+        s *= 2;
+    }
+    Point2i::new(x, y)
+}
+
+// Sets the cancel flag if a tile panics, so the other workers stop early
+struct CancelOnPanic<'a>(&'a AtomicBool);
+
+impl<'a> Drop for CancelOnPanic<'a> {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            self.0.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+// Calls func once for every tile of the num_tiles grid. Tiles are dealt out round robin to
+// one queue per worker in the requested order, and a worker whose queue runs dry steals from
+// the back of the others. Returns false if rendering was cancelled before all tiles ran
+pub fn parallel_for_2d(
+    func: &(dyn Fn(Point2i) + Sync),
+    num_tiles: Point2i,
+    options: &ParallelOptions,
+    progress: &ProgressReporter,
+) -> bool {
+    let tiles = tile_order(options.tile_order, num_tiles);
+    let n_threads = options.thread_count().min(tiles.len()).max(1);
+
+    if n_threads == 1 {
+        for tile in tiles {
+            if options.cancelled() {
+                return false;
+            }
+            func(tile);
+            progress.update(1);
+        }
+        return true;
+    }
+
+    let queues: Vec<Mutex<VecDeque<Point2i>>> = (0..n_threads)
+        .map(|_| Mutex::new(VecDeque::new()))
+        .collect();
+    for (i, tile) in tiles.into_iter().enumerate() {
+        queues[i % n_threads].lock().unwrap().push_back(tile);
+    }
+    let next_tile = |worker: usize| {
+        if let Some(tile) = queues[worker].lock().unwrap().pop_front() {
+            return Some(tile);
+        }
+        (1..n_threads).find_map(|offset| {
+            queues[(worker + offset) % n_threads]
+                .lock()
+                .unwrap()
+                .pop_back()
+        })
+    };
+
+    std::thread::scope(|scope| {
+        for worker in 0..n_threads {
+            let next_tile = &next_tile;
+            scope.spawn(move || {
+                let _guard = CancelOnPanic(&options.cancel);
+                while !options.cancelled() {
+                    match next_tile(worker) {
+                        Some(tile) => {
+                            func(tile);
+                            progress.update(1);
+                        }
+                        None => break,
+                    }
+                }
+            });
+        }
+    });
+    !options.cancelled()
+}
@@ -0,0 +1,257 @@
+use crate::core::api::warning_at;
+use crate::core::texture::{Texture, TextureValue};
+use crate::math::*;
+use crate::spectrum::*;
+use crate::textures::ConstantTexture;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+// Typed values of a single parameter from a scene file, e.g. "float radius" [ 2 ]
+#[derive(Clone)]
+pub enum ParamValue {
+    Int(Vec<i32>),
+    Float(Vec<f32>),
+    Bool(Vec<bool>),
+    String(Vec<String>),
+    // point2, vector2 and normal values are checked but none of the supported shapes reads them
+    Point2,
+    Vector2,
+    Point3(Vec<Point3>),
+    Vector3(Vec<Vec3>),
+    Normal,
+    Rgb(Vec<[f32; 3]>),
+    Xyz(Vec<[f32; 3]>),
+    // Pairs of temperature in Kelvin and scale
+    Blackbody(Vec<[f32; 2]>),
+    // Pairs of wavelength in nm and value
+    SampledSpectrum(Vec<[f32; 2]>),
+    Texture(String),
+}
+
+impl ParamValue {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            ParamValue::Int(_) => "integer",
+            ParamValue::Float(_) => "float",
+            ParamValue::Bool(_) => "bool",
+            ParamValue::String(_) => "string",
+            ParamValue::Point2 => "point2",
+            ParamValue::Vector2 => "vector2",
+            ParamValue::Point3(_) => "point3",
+            ParamValue::Vector3(_) => "vector3",
+            ParamValue::Normal => "normal",
+            ParamValue::Rgb(_) => "rgb",
+            ParamValue::Xyz(_) => "xyz",
+            ParamValue::Blackbody(_) => "blackbody",
+            ParamValue::SampledSpectrum(_) => "spectrum",
+            ParamValue::Texture(_) => "texture",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Param {
+    pub name: String,
+    pub value: ParamValue,
+    // Used to warn about parameters nobody asked for, which are usually typos
+    looked_up: Cell<bool>,
+}
+
+#[derive(Clone, Default)]
+pub struct ParamSet {
+    params: Vec<Param>,
+}
+
+impl ParamSet {
+    pub fn new() -> ParamSet {
+        ParamSet::default()
+    }
+
+    // Later parameters with the same name replace earlier ones
+    pub fn add(&mut self, name: &str, value: ParamValue) {
+        self.params.retain(|param| param.name != name);
+        self.params.push(Param {
+            name: String::from(name),
+            value,
+            looked_up: Cell::new(false),
+        });
+    }
+
+    pub fn find(&self, name: &str) -> Option<&ParamValue> {
+        let param = self.params.iter().find(|param| param.name == name)?;
+        param.looked_up.set(true);
+        Some(&param.value)
+    }
+
+    pub fn find_ints(&self, name: &str) -> Option<&[i32]> {
+        match self.find(name) {
+            Some(ParamValue::Int(values)) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn find_floats(&self, name: &str) -> Option<&[f32]> {
+        match self.find(name) {
+            Some(ParamValue::Float(values)) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn find_point3s(&self, name: &str) -> Option<&[Point3]> {
+        match self.find(name) {
+            Some(ParamValue::Point3(values)) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn find_one_int(&self, name: &str, default: i32) -> i32 {
+        match self.find_ints(name) {
+            Some([value]) => *value,
+            _ => default,
+        }
+    }
+
+    pub fn find_one_float(&self, name: &str, default: f32) -> f32 {
+        match self.find_floats(name) {
+            Some([value]) => *value,
+            _ => default,
+        }
+    }
+
+    pub fn find_one_bool(&self, name: &str, default: bool) -> bool {
+        match self.find(name) {
+            Some(ParamValue::Bool(values)) if values.len() == 1 => values[0],
+            _ => default,
+        }
+    }
+
+    pub fn find_one_string(&self, name: &str, default: &str) -> String {
+        match self.find(name) {
+            Some(ParamValue::String(values)) if values.len() == 1 => values[0].clone(),
+            _ => String::from(default),
+        }
+    }
+
+    pub fn find_one_point3(&self, name: &str, default: Point3) -> Point3 {
+        match self.find_point3s(name) {
+            Some([value]) => *value,
+            _ => default,
+        }
+    }
+
+    pub fn find_one_vector3(&self, name: &str, default: Vec3) -> Vec3 {
+        match self.find(name) {
+            Some(ParamValue::Vector3(values)) if values.len() == 1 => values[0],
+            _ => default,
+        }
+    }
+
+    // Any of the spectral parameter types, RGB values are treated as reflectances
+    pub fn find_spectra(&self, name: &str) -> Option<Vec<Spectrum>> {
+        match self.find(name)? {
+            ParamValue::Rgb(values) => Some(
+                values
+                    .iter()
+                    .map(|rgb| Spectrum::from_rgb(*rgb, SpectrumType::Reflectance))
+                    .collect(),
+            ),
+            ParamValue::Xyz(values) => Some(
+                values
+                    .iter()
+                    .map(|xyz| Spectrum::from_xyz(*xyz, SpectrumType::Reflectance))
+                    .collect(),
+            ),
+            ParamValue::Blackbody(values) => Some(
+                values
+                    .iter()
+                    .map(|[temperature, scale]| {
+                        let lambda = (360..=830).map(|l| l as f32).collect::<Vec<f32>>();
+                        let emission = lambda
+                            .iter()
+                            .map(|l| blackbody_normalized(*l, *temperature))
+                            .collect::<Vec<f32>>();
+                        Spectrum::from_sampled(&lambda, &emission) * *scale
+                    })
+                    .collect(),
+            ),
+            ParamValue::SampledSpectrum(samples) => {
+                let lambda = samples.iter().map(|s| s[0]).collect::<Vec<f32>>();
+                let values = samples.iter().map(|s| s[1]).collect::<Vec<f32>>();
+                Some(vec![Spectrum::from_sampled(&lambda, &values)])
+            }
+            _ => None,
+        }
+    }
+
+    pub fn find_one_spectrum(&self, name: &str, default: Spectrum) -> Spectrum {
+        match self.find_spectra(name) {
+            Some(spectra) if spectra.len() == 1 => spectra[0],
+            _ => default,
+        }
+    }
+
+    pub fn find_texture(&self, name: &str) -> Option<&str> {
+        match self.find(name) {
+            Some(ParamValue::Texture(texture)) => Some(texture),
+            _ => None,
+        }
+    }
+
+    pub fn unused(&self) -> impl Iterator<Item = &Param> {
+        self.params.iter().filter(|param| !param.looked_up.get())
+    }
+}
+
+pub type TextureMap<T> = HashMap<String, Arc<dyn Texture<T>>>;
+
+// Parameters of a material or texture, which may name textures defined earlier in the scene
+// instead of giving a value
+pub struct TextureParams<'a> {
+    pub params: &'a ParamSet,
+    pub float_textures: &'a TextureMap<f32>,
+    pub spectrum_textures: &'a TextureMap<Spectrum>,
+    location: &'a str,
+}
+
+impl<'a> TextureParams<'a> {
+    pub fn new(
+        params: &'a ParamSet,
+        float_textures: &'a TextureMap<f32>,
+        spectrum_textures: &'a TextureMap<Spectrum>,
+        location: &'a str,
+    ) -> TextureParams<'a> {
+        TextureParams {
+            params,
+            float_textures,
+            spectrum_textures,
+            location,
+        }
+    }
+
+    pub fn warning(&self, message: &str) {
+        warning_at(self.location, message);
+    }
+
+    pub fn get_texture<T: TextureValue>(&self, name: &str, default: T) -> Arc<dyn Texture<T>> {
+        self.get_texture_or_none(name)
+            .unwrap_or_else(|| Arc::new(ConstantTexture::new(default)))
+    }
+
+    // The named texture or a constant one for a value, None if the parameter isn't given
+    pub fn get_texture_or_none<T: TextureValue>(&self, name: &str) -> Option<Arc<dyn Texture<T>>> {
+        if let Some(texture) = self.params.find_texture(name) {
+            match T::textures(self).get(texture) {
+                Some(texture) => return Some(texture.clone()),
+                None => self.warning(&format!(
+                    "{} texture \"{}\" for parameter \"{}\" is not defined",
+                    T::TYPE_NAME,
+                    texture,
+                    name
+                )),
+            }
+        }
+        T::find_one(self.params, name)
+            .map(|value| Arc::new(ConstantTexture::new(value)) as Arc<dyn Texture<T>>)
+    }
+}
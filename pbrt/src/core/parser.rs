@@ -0,0 +1,902 @@
+use crate::core::api::SceneBuilder;
+use crate::core::{ParamSet, ParamValue};
+use crate::math::*;
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+
+// Reader for the pbrt-v3 scene description format
+// Directives are forwarded to the SceneBuilder, which does the actual scene construction
+
+const MAX_INCLUDE_DEPTH: usize = 32;
+
+pub struct ParseError {
+    pub file: PathBuf,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.file.display(), line, self.message),
+            None => write!(f, "{}: {}", self.file.display(), self.message),
+        }
+    }
+}
+
+impl std::fmt::Debug for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+pub fn parse_file(path: &Path, builder: &mut SceneBuilder) -> Result<(), ParseError> {
+    builder.set_search_directory(path.parent().unwrap_or_else(|| Path::new("")));
+    parse_file_with_depth(path, builder, 0)
+}
+
+fn parse_file_with_depth(
+    path: &Path,
+    builder: &mut SceneBuilder,
+    include_depth: usize,
+) -> Result<(), ParseError> {
+    let source = std::fs::read_to_string(path).map_err(|err| ParseError {
+        file: path.to_path_buf(),
+        line: None,
+        message: format!("unable to read file: {}", err),
+    })?;
+    Parser {
+        tokenizer: Tokenizer::new(&source),
+        peeked: None,
+        path,
+        include_depth,
+    }
+    .parse(builder)
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum TokenKind {
+    Identifier,
+    String,
+    Number,
+    OpenBracket,
+    CloseBracket,
+}
+
+struct Token<'a> {
+    kind: TokenKind,
+    // Strings have their quotes removed and escapes resolved
+    text: Cow<'a, str>,
+    line: usize,
+}
+
+impl<'a> Token<'a> {
+    fn describe(&self) -> String {
+        match self.kind {
+            TokenKind::String => format!("string \"{}\"", self.text),
+            _ => format!("'{}'", self.text),
+        }
+    }
+}
+
+struct Tokenizer<'a> {
+    source: &'a str,
+    pos: usize,
+    line: usize,
+}
+
+impl<'a> Tokenizer<'a> {
+    fn new(source: &'a str) -> Tokenizer<'a> {
+        Tokenizer {
+            source,
+            pos: 0,
+            line: 1,
+        }
+    }
+
+    fn peek_char(&self) -> Option<char> {
+        self.source[self.pos..].chars().next()
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let c = self.peek_char()?;
+        self.pos += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_whitespace_and_comments(&mut self) {
+        while let Some(c) = self.peek_char() {
+            if c == '#' {
+                while let Some(c) = self.next_char() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            } else if c.is_whitespace() {
+                self.next_char();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn read_string(&mut self) -> Result<Cow<'a, str>, String> {
+        let start = self.pos;
+        let mut unescaped: Option<String> = None;
+        loop {
+            let end = self.pos;
+            match self.next_char() {
+                None | Some('\n') => return Err(String::from("unterminated string")),
+                Some('"') => {
+                    return Ok(match unescaped {
+                        Some(text) => Cow::Owned(text),
+                        None => Cow::Borrowed(&self.source[start..end]),
+                    })
+                }
+                Some('\\') => {
+                    let text =
+                        unescaped.get_or_insert_with(|| String::from(&self.source[start..end]));
+                    match self.next_char() {
+                        Some('n') => text.push('\n'),
+                        Some('t') => text.push('\t'),
+                        Some('r') => text.push('\r'),
+                        Some('b') => text.push('\u{8}'),
+                        Some('f') => text.push('\u{c}'),
+                        Some(c @ '\\') | Some(c @ '\'') | Some(c @ '"') => text.push(c),
+                        Some(c) => return Err(format!("unexpected escape sequence '\\{}'", c)),
+                        None => return Err(String::from("unterminated string")),
+                    }
+                }
+                Some(c) => {
+                    if let Some(text) = unescaped.as_mut() {
+                        text.push(c);
+                    }
+                }
+            }
+        }
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token<'a>>, (usize, String)> {
+        self.skip_whitespace_and_comments();
+        let line = self.line;
+        let start = self.pos;
+        let c = match self.next_char() {
+            Some(c) => c,
+            None => return Ok(None),
+        };
+        let (kind, text) = match c {
+            '[' => (TokenKind::OpenBracket, Cow::Borrowed("[")),
+            ']' => (TokenKind::CloseBracket, Cow::Borrowed("]")),
+            '"' => (
+                TokenKind::String,
+                self.read_string().map_err(|err| (line, err))?,
+            ),
+            _ if c.is_ascii_alphabetic() => {
+                while let Some(c) = self.peek_char() {
+                    if c.is_ascii_alphanumeric() || c == '_' {
+                        self.next_char();
+                    } else {
+                        break;
+                    }
+                }
+                (
+                    TokenKind::Identifier,
+                    Cow::Borrowed(&self.source[start..self.pos]),
+                )
+            }
+            _ if c.is_ascii_digit() || c == '-' || c == '+' || c == '.' => {
+                while let Some(c) = self.peek_char() {
+                    if c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '+' {
+                        self.next_char();
+                    } else {
+                        break;
+                    }
+                }
+                (
+                    TokenKind::Number,
+                    Cow::Borrowed(&self.source[start..self.pos]),
+                )
+            }
+            _ => return Err((line, format!("unexpected character '{}'", c))),
+        };
+        Ok(Some(Token { kind, text, line }))
+    }
+}
+
+struct Parser<'a> {
+    tokenizer: Tokenizer<'a>,
+    peeked: Option<Token<'a>>,
+    path: &'a Path,
+    include_depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error<T>(&self, line: usize, message: String) -> Result<T, ParseError> {
+        Err(ParseError {
+            file: self.path.to_path_buf(),
+            line: Some(line),
+            message,
+        })
+    }
+
+    fn next(&mut self) -> Result<Option<Token<'a>>, ParseError> {
+        if let Some(token) = self.peeked.take() {
+            return Ok(Some(token));
+        }
+        match self.tokenizer.next_token() {
+            Ok(token) => Ok(token),
+            Err((line, message)) => self.error(line, message),
+        }
+    }
+
+    fn peek_kind(&mut self) -> Result<Option<TokenKind>, ParseError> {
+        if self.peeked.is_none() {
+            self.peeked = self.next()?;
+        }
+        Ok(self.peeked.as_ref().map(|token| token.kind))
+    }
+
+    fn expect(&mut self, kind: TokenKind, what: &str) -> Result<Token<'a>, ParseError> {
+        match self.next()? {
+            Some(token) if token.kind == kind => Ok(token),
+            Some(token) => self.error(
+                token.line,
+                format!("expected {}, found {}", what, token.describe()),
+            ),
+            None => self.error(
+                self.tokenizer.line,
+                format!("expected {}, found end of file", what),
+            ),
+        }
+    }
+
+    fn expect_string(&mut self) -> Result<String, ParseError> {
+        Ok(self
+            .expect(TokenKind::String, "a string")?
+            .text
+            .into_owned())
+    }
+
+    fn parse_number(&self, token: &Token) -> Result<f32, ParseError> {
+        match token.text.parse() {
+            Ok(value) => Ok(value),
+            Err(_) => self.error(token.line, format!("invalid number '{}'", token.text)),
+        }
+    }
+
+    fn expect_number(&mut self) -> Result<f32, ParseError> {
+        let token = self.expect(TokenKind::Number, "a number")?;
+        self.parse_number(&token)
+    }
+
+    fn expect_numbers<const N: usize>(&mut self) -> Result<[f32; N], ParseError> {
+        let mut values = [0.0; N];
+        for value in values.iter_mut() {
+            *value = self.expect_number()?;
+        }
+        Ok(values)
+    }
+
+    // Number lists in directives are usually bracketed, but pbrt accepts them both ways
+    fn expect_number_list<const N: usize>(&mut self) -> Result<[f32; N], ParseError> {
+        if self.peek_kind()? == Some(TokenKind::OpenBracket) {
+            self.next()?;
+            let values = self.expect_numbers()?;
+            self.expect(TokenKind::CloseBracket, "']'")?;
+            Ok(values)
+        } else {
+            self.expect_numbers()
+        }
+    }
+
+    // Values of a parameter are either a single token or a bracketed list of them
+    fn parse_values(&mut self) -> Result<Vec<Token<'a>>, ParseError> {
+        let token = match self.next()? {
+            Some(token) => token,
+            None => {
+                return self.error(
+                    self.tokenizer.line,
+                    String::from("expected parameter values, found end of file"),
+                )
+            }
+        };
+        match token.kind {
+            TokenKind::String | TokenKind::Number => Ok(vec![token]),
+            TokenKind::OpenBracket => {
+                let mut values = Vec::new();
+                loop {
+                    let value = self.expect_value_or_close_bracket()?;
+                    if value.kind == TokenKind::CloseBracket {
+                        return Ok(values);
+                    }
+                    values.push(value);
+                }
+            }
+            _ => self.error(
+                token.line,
+                format!("expected parameter values, found {}", token.describe()),
+            ),
+        }
+    }
+
+    fn expect_value_or_close_bracket(&mut self) -> Result<Token<'a>, ParseError> {
+        match self.next()? {
+            Some(token)
+                if token.kind == TokenKind::String
+                    || token.kind == TokenKind::Number
+                    || token.kind == TokenKind::CloseBracket =>
+            {
+                Ok(token)
+            }
+            Some(token) => self.error(
+                token.line,
+                format!("expected a value or ']', found {}", token.describe()),
+            ),
+            None => self.error(
+                self.tokenizer.line,
+                String::from("unterminated parameter list"),
+            ),
+        }
+    }
+
+    fn parse_param_set(&mut self) -> Result<ParamSet, ParseError> {
+        let mut params = ParamSet::new();
+        while self.peek_kind()? == Some(TokenKind::String) {
+            let declaration = self.next()?.unwrap();
+            let values = self.parse_values()?;
+            let (name, value) = self.parse_param(&declaration, &values)?;
+            params.add(name, value);
+        }
+        Ok(params)
+    }
+
+    fn parse_param<'t>(
+        &self,
+        declaration: &'t Token,
+        values: &[Token],
+    ) -> Result<(&'t str, ParamValue), ParseError> {
+        let line = declaration.line;
+        let mut words = declaration.text.split_whitespace();
+        let (type_name, name) = match (words.next(), words.next(), words.next()) {
+            (Some(type_name), Some(name), None) => (type_name, name),
+            _ => {
+                return self.error(
+                    line,
+                    format!(
+                        "parameter declaration \"{}\" should be \"<type> <name>\"",
+                        declaration.text
+                    ),
+                )
+            }
+        };
+
+        let numbers = |count: usize| -> Result<Vec<f32>, ParseError> {
+            if let Some(value) = values.iter().find(|value| value.kind != TokenKind::Number) {
+                return self.error(
+                    value.line,
+                    format!(
+                        "parameter \"{}\" of type {} expects numbers, found {}",
+                        name,
+                        type_name,
+                        value.describe()
+                    ),
+                );
+            }
+            if values.is_empty() || !values.len().is_multiple_of(count) {
+                return self.error(
+                    line,
+                    format!(
+                        "parameter \"{}\" of type {} expects a multiple of {} values, found {}",
+                        name,
+                        type_name,
+                        count,
+                        values.len()
+                    ),
+                );
+            }
+            values
+                .iter()
+                .map(|value| self.parse_number(value))
+                .collect()
+        };
+        let strings = || -> Result<Vec<String>, ParseError> {
+            if let Some(value) = values.iter().find(|value| value.kind != TokenKind::String) {
+                return self.error(
+                    value.line,
+                    format!(
+                        "parameter \"{}\" of type {} expects strings, found {}",
+                        name,
+                        type_name,
+                        value.describe()
+                    ),
+                );
+            }
+            if values.is_empty() {
+                return self.error(line, format!("parameter \"{}\" has no values", name));
+            }
+            Ok(values.iter().map(|value| value.text.to_string()).collect())
+        };
+        let single_string = || -> Result<String, ParseError> {
+            let mut strings = strings()?;
+            if strings.len() != 1 {
+                return self.error(
+                    line,
+                    format!(
+                        "parameter \"{}\" of type {} expects a single string, found {}",
+                        name,
+                        type_name,
+                        strings.len()
+                    ),
+                );
+            }
+            Ok(strings.remove(0))
+        };
+
+        let value = match type_name {
+            "integer" => {
+                let mut ints = Vec::with_capacity(values.len());
+                for value in numbers(1)? {
+                    if value.fract() != 0.0 {
+                        return self.error(
+                            line,
+                            format!(
+                                "parameter \"{}\" of type integer has non integer value {}",
+                                name, value
+                            ),
+                        );
+                    }
+                    ints.push(value as i32);
+                }
+                ParamValue::Int(ints)
+            }
+            "float" => ParamValue::Float(numbers(1)?),
+            "point2" => {
+                numbers(2)?;
+                ParamValue::Point2
+            }
+            "vector2" => {
+                numbers(2)?;
+                ParamValue::Vector2
+            }
+            "point" | "point3" => ParamValue::Point3(
+                numbers(3)?
+                    .chunks(3)
+                    .map(|p| Point3::new(p[0], p[1], p[2]))
+                    .collect(),
+            ),
+            "vector" | "vector3" => ParamValue::Vector3(
+                numbers(3)?
+                    .chunks(3)
+                    .map(|v| vec3(v[0], v[1], v[2]))
+                    .collect(),
+            ),
+            "normal" | "normal3" => {
+                numbers(3)?;
+                ParamValue::Normal
+            }
+            "rgb" | "color" => {
+                ParamValue::Rgb(numbers(3)?.chunks(3).map(|c| [c[0], c[1], c[2]]).collect())
+            }
+            "xyz" => ParamValue::Xyz(numbers(3)?.chunks(3).map(|c| [c[0], c[1], c[2]]).collect()),
+            "blackbody" => {
+                ParamValue::Blackbody(numbers(2)?.chunks(2).map(|b| [b[0], b[1]]).collect())
+            }
+            "spectrum" => {
+                if values.first().map(|value| value.kind) == Some(TokenKind::String) {
+                    let file = single_string()?;
+                    ParamValue::SampledSpectrum(self.read_spectrum_file(line, &file)?)
+                } else {
+                    ParamValue::SampledSpectrum(
+                        numbers(2)?.chunks(2).map(|s| [s[0], s[1]]).collect(),
+                    )
+                }
+            }
+            "bool" => {
+                let mut bools = Vec::with_capacity(values.len());
+                for value in strings()? {
+                    match value.as_str() {
+                        "true" => bools.push(true),
+                        "false" => bools.push(false),
+                        _ => {
+                            return self.error(
+                                line,
+                                format!(
+                                    "parameter \"{}\" of type bool expects \"true\" or \"false\", found \"{}\"",
+                                    name, value
+                                ),
+                            )
+                        }
+                    }
+                }
+                ParamValue::Bool(bools)
+            }
+            "string" => ParamValue::String(strings()?),
+            "texture" => ParamValue::Texture(single_string()?),
+            _ => {
+                return self.error(
+                    line,
+                    format!("unknown type '{}' for parameter \"{}\"", type_name, name),
+                )
+            }
+        };
+        Ok((name, value))
+    }
+
+    // Wavelength and value pairs, relative paths are resolved against the scene file
+    fn read_spectrum_file(&self, line: usize, file: &str) -> Result<Vec<[f32; 2]>, ParseError> {
+        let path = self.resolve(file);
+        let source = match std::fs::read_to_string(&path) {
+            Ok(source) => source,
+            Err(err) => {
+                return self.error(
+                    line,
+                    format!(
+                        "unable to read spectrum file \"{}\": {}",
+                        path.display(),
+                        err
+                    ),
+                )
+            }
+        };
+        let mut values = Vec::new();
+        for text in source
+            .lines()
+            .flat_map(|line| line.split('#').next().unwrap().split_whitespace())
+        {
+            match text.parse::<f32>() {
+                Ok(value) => values.push(value),
+                Err(_) => {
+                    return self.error(
+                        line,
+                        format!(
+                            "invalid number '{}' in spectrum file \"{}\"",
+                            text,
+                            path.display()
+                        ),
+                    )
+                }
+            }
+        }
+        if values.is_empty() || !values.len().is_multiple_of(2) {
+            return self.error(
+                line,
+                format!(
+                    "spectrum file \"{}\" should contain wavelength and value pairs",
+                    path.display()
+                ),
+            );
+        }
+        Ok(values.chunks(2).map(|s| [s[0], s[1]]).collect())
+    }
+
+    fn resolve(&self, file: &str) -> PathBuf {
+        self.path
+            .parent()
+            .map_or_else(|| PathBuf::from(file), |dir| dir.join(file))
+    }
+
+    fn parse(&mut self, builder: &mut SceneBuilder) -> Result<(), ParseError> {
+        while let Some(token) = self.next()? {
+            if token.kind != TokenKind::Identifier {
+                return self.error(
+                    token.line,
+                    format!("expected a directive, found {}", token.describe()),
+                );
+            }
+            builder.set_location(format!("{}:{}", self.path.display(), token.line));
+            match token.text.as_ref() {
+                "Identity" => builder.identity(),
+                "Translate" => {
+                    let [x, y, z] = self.expect_numbers()?;
+                    builder.translate(vec3(x, y, z));
+                }
+                "Scale" => {
+                    let [x, y, z] = self.expect_numbers()?;
+                    builder.scale(x, y, z);
+                }
+                "Rotate" => {
+                    let [angle, x, y, z] = self.expect_numbers()?;
+                    builder.rotate(angle, vec3(x, y, z));
+                }
+                "LookAt" => {
+                    let [ex, ey, ez, lx, ly, lz, ux, uy, uz] = self.expect_numbers()?;
+                    builder.look_at(
+                        Point3::new(ex, ey, ez),
+                        Point3::new(lx, ly, lz),
+                        vec3(ux, uy, uz),
+                    );
+                }
+                "Transform" => {
+                    let m: [f32; 16] = self.expect_number_list()?;
+                    builder.transform(m);
+                }
+                "ConcatTransform" => {
+                    let m: [f32; 16] = self.expect_number_list()?;
+                    builder.concat_transform(m);
+                }
+                "CoordinateSystem" => {
+                    let name = self.expect_string()?;
+                    builder.coordinate_system(&name);
+                }
+                "CoordSysTransform" => {
+                    let name = self.expect_string()?;
+                    builder.coord_sys_transform(&name);
+                }
+                "ReverseOrientation" => builder.reverse_orientation(),
+                "Camera" => {
+                    let name = self.expect_string()?;
+                    let params = self.parse_param_set()?;
+                    builder.camera(&name, params);
+                }
+                "Film" => {
+                    let name = self.expect_string()?;
+                    let params = self.parse_param_set()?;
+                    builder.film(&name, params);
+                }
+                "Sampler" => {
+                    let name = self.expect_string()?;
+                    let params = self.parse_param_set()?;
+                    builder.sampler(&name, params);
+                }
+                "PixelFilter" => {
+                    let name = self.expect_string()?;
+                    let params = self.parse_param_set()?;
+                    builder.pixel_filter(&name, params);
+                }
+                "Accelerator" => {
+                    let name = self.expect_string()?;
+                    let params = self.parse_param_set()?;
+                    builder.accelerator(&name, params);
+                }
+                "Integrator" | "SurfaceIntegrator" => {
+                    let name = self.expect_string()?;
+                    let params = self.parse_param_set()?;
+                    builder.integrator(&name, params);
+                }
+                "WorldBegin" => builder.world_begin(),
+                "WorldEnd" => builder.world_end(),
+                "AttributeBegin" => builder.attribute_begin(),
+                "AttributeEnd" => builder.attribute_end(),
+                "TransformBegin" => builder.transform_begin(),
+                "TransformEnd" => builder.transform_end(),
+                "Texture" => {
+                    let name = self.expect_string()?;
+                    let texture_type = self.expect_string()?;
+                    let class = self.expect_string()?;
+                    let params = self.parse_param_set()?;
+                    builder.texture(&name, &texture_type, &class, params);
+                }
+                "Material" => {
+                    let name = self.expect_string()?;
+                    let params = self.parse_param_set()?;
+                    builder.material(&name, params);
+                }
+                "MakeNamedMaterial" => {
+                    let name = self.expect_string()?;
+                    let params = self.parse_param_set()?;
+                    builder.make_named_material(&name, params);
+                }
+                "NamedMaterial" => {
+                    let name = self.expect_string()?;
+                    builder.named_material(&name);
+                }
+                "LightSource" => {
+                    let name = self.expect_string()?;
+                    let params = self.parse_param_set()?;
+                    builder.light_source(&name, params);
+                }
+                "AreaLightSource" => {
+                    let name = self.expect_string()?;
+                    let params = self.parse_param_set()?;
+                    builder.area_light_source(&name, params);
+                }
+                "Shape" => {
+                    let name = self.expect_string()?;
+                    let params = self.parse_param_set()?;
+                    builder.shape(&name, params);
+                }
+                "Include" => {
+                    let file = self.expect_string()?;
+                    if self.include_depth >= MAX_INCLUDE_DEPTH {
+                        return self.error(
+                            token.line,
+                            format!("includes are nested too deeply, giving up on \"{}\"", file),
+                        );
+                    }
+                    // Relative paths are resolved against the including file
+                    let path = self.resolve(&file);
+                    parse_file_with_depth(&path, builder, self.include_depth + 1)?;
+                }
+                "ActiveTransform" => {
+                    let which = self.expect(TokenKind::Identifier, "StartTime, EndTime or All")?;
+                    builder.warning(&format!(
+                        "motion blur is not supported yet, ignoring 'ActiveTransform {}'",
+                        which.text
+                    ));
+                }
+                "MakeNamedMedium" => {
+                    let name = self.expect_string()?;
+                    let params = self.parse_param_set()?;
+                    builder.make_named_medium(&name, params);
+                }
+                "MediumInterface" => {
+                    let inside = self.expect_string()?;
+                    // A single name puts the same medium on both sides
+                    let outside = if self.peek_kind()? == Some(TokenKind::String) {
+                        self.expect_string()?
+                    } else {
+                        inside.clone()
+                    };
+                    builder.medium_interface(&inside, &outside);
+                }
+                "TransformTimes" | "ObjectBegin" | "ObjectEnd" | "ObjectInstance" => {
+                    builder.warning(&format!(
+                        "directive '{}' is not supported yet, skipping it",
+                        token.text
+                    ));
+                    self.skip_arguments()?;
+                }
+                _ => {
+                    builder.warning(&format!("unknown directive '{}', skipping it", token.text));
+                    self.skip_arguments()?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Arguments never contain identifiers, so everything up to the next one is skipped
+    fn skip_arguments(&mut self) -> Result<(), ParseError> {
+        while let Some(kind) = self.peek_kind()? {
+            if kind == TokenKind::Identifier {
+                break;
+            }
+            self.next()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::ParallelOptions;
+    use crate::spectrum::{Spectrum, SpectrumType};
+
+    fn parser(source: &str) -> Parser<'_> {
+        Parser {
+            tokenizer: Tokenizer::new(source),
+            peeked: None,
+            path: Path::new("test.pbrt"),
+            include_depth: 0,
+        }
+    }
+
+    fn parse(source: &str) -> Result<(), ParseError> {
+        let mut builder = SceneBuilder::new(None, ParallelOptions::default());
+        parser(source).parse(&mut builder)
+    }
+
+    fn params(source: &str) -> Result<ParamSet, ParseError> {
+        parser(source).parse_param_set()
+    }
+
+    #[test]
+    fn tokens_skip_comments_and_resolve_escapes() {
+        let mut tokenizer = Tokenizer::new("Shape # comment \"sphere\"\n\"a\\\"b\\n\" [ -1.5e2 ]");
+        let mut next = || tokenizer.next_token().unwrap().unwrap();
+        let token = next();
+        assert_eq!(
+            (token.kind, token.text.as_ref(), token.line),
+            (TokenKind::Identifier, "Shape", 1)
+        );
+        let token = next();
+        assert_eq!(
+            (token.kind, token.text.as_ref(), token.line),
+            (TokenKind::String, "a\"b\n", 2)
+        );
+        assert_eq!(next().kind, TokenKind::OpenBracket);
+        let token = next();
+        assert_eq!(
+            (token.kind, token.text.as_ref()),
+            (TokenKind::Number, "-1.5e2")
+        );
+        assert_eq!(next().kind, TokenKind::CloseBracket);
+        assert!(tokenizer.next_token().unwrap().is_none());
+    }
+
+    #[test]
+    fn parameters_are_typed() {
+        let params = params(
+            r#""integer pixelsamples" 16 "float fov" [ 45 ] "bool twosided" "true"
+               "string filename" "out.exr" "point from" [ 1 2 3 ] "rgb Kd" [ .5 .25 1 ]
+               "normal N" [ 0 0 1 ] "texture reflectance" "checks""#,
+        )
+        .unwrap();
+        assert_eq!(params.find_one_int("pixelsamples", 0), 16);
+        assert_eq!(params.find_one_float("fov", 0.0), 45.0);
+        assert!(params.find_one_bool("twosided", false));
+        assert_eq!(params.find_one_string("filename", ""), "out.exr");
+        assert_eq!(
+            params.find_one_point3("from", Point3::new(0.0, 0.0, 0.0)),
+            Point3::new(1.0, 2.0, 3.0)
+        );
+        assert_eq!(
+            params.find_one_spectrum("Kd", Spectrum::new()),
+            Spectrum::from_rgb([0.5, 0.25, 1.0], SpectrumType::Reflectance)
+        );
+        assert_eq!(
+            params.find("N").map(|value| value.type_name()),
+            Some("normal")
+        );
+        assert_eq!(params.find_texture("reflectance"), Some("checks"));
+    }
+
+    #[test]
+    fn bad_parameters_are_reported_with_their_line() {
+        let error = |source: &str| params(source).err().expect(source);
+        let err = error("\"float fov\" 45\n\"integer pixelsamples\" 1.5");
+        assert_eq!(err.line, Some(2));
+        assert!(err.message.contains("non integer"), "{}", err);
+        assert!(error("\"point from\" [ 1 2 ]")
+            .message
+            .contains("multiple of 3"));
+        assert!(error("\"bool twosided\" \"yes\"")
+            .message
+            .contains("\"true\" or \"false\""));
+        assert!(error("\"float\" 1").message.contains("<type> <name>"));
+        assert!(error("\"quaternion q\" 1").message.contains("unknown type"));
+        assert!(error("\"float fov\" [ 45").message.contains("unterminated"));
+    }
+
+    #[test]
+    fn directives_are_parsed() {
+        parse(
+            r#"LookAt 0 0 5  0 0 0  0 1 0
+               Camera "perspective" "float fov" 45
+               Sampler "halton" "integer pixelsamples" 4
+               Film "image" "integer xresolution" 8 "integer yresolution" 8
+               WorldBegin
+               AttributeBegin
+                 Translate 1 0 0
+                 ConcatTransform [ 1 0 0 0  0 1 0 0  0 0 1 0  0 0 0 1 ]
+                 Material "matte" "rgb Kd" [ .5 .5 .5 ]
+                 Shape "sphere" "float radius" 1
+               AttributeEnd"#,
+        )
+        .unwrap();
+        // Unknown directives are skipped along with their arguments
+        parse("WorldBegin\nFancyDirective \"x\" [ 1 2 ]\nAttributeBegin\nAttributeEnd").unwrap();
+
+        let err = parse("WorldBegin\n\"sphere\"").unwrap_err();
+        assert_eq!(err.line, Some(2));
+        assert!(err.message.contains("expected a directive"), "{}", err);
+        let err = parse("Translate 1 0").unwrap_err();
+        assert!(err.message.contains("end of file"), "{}", err);
+    }
+
+    #[test]
+    fn includes_are_resolved_against_the_including_file() {
+        let dir = std::env::temp_dir().join(format!("garage_ray_parser_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("parts")).unwrap();
+        std::fs::write(dir.join("scene.pbrt"), "Include \"parts/camera.pbrt\"").unwrap();
+        std::fs::write(
+            dir.join("parts/camera.pbrt"),
+            "Camera \"perspective\"\nInclude \"loop.pbrt\"",
+        )
+        .unwrap();
+        std::fs::write(dir.join("parts/loop.pbrt"), "Include \"loop.pbrt\"").unwrap();
+
+        let mut builder = SceneBuilder::new(None, ParallelOptions::default());
+        let err = parse_file(&dir.join("scene.pbrt"), &mut builder).unwrap_err();
+        std::fs::remove_dir_all(&dir).unwrap();
+        // Getting to the include loop means both relative paths were found
+        assert!(err.message.contains("nested too deeply"), "{}", err);
+        assert!(err.file.ends_with("parts/loop.pbrt"));
+    }
+}
@@ -0,0 +1,72 @@
+use std::io::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+const BAR_WIDTH: u64 = 40;
+
+// Progress bar on stderr with elapsed and estimated remaining time, updated from any thread
+pub struct ProgressReporter {
+    title: String,
+    total_work: u64,
+    work_done: AtomicU64,
+    start: Instant,
+    quiet: bool,
+    // Last bar length printed, also keeps lines from different threads apart
+    printed: Mutex<u64>,
+}
+
+impl ProgressReporter {
+    pub fn new(total_work: u64, title: &str, quiet: bool) -> ProgressReporter {
+        ProgressReporter {
+            title: String::from(title),
+            total_work: total_work.max(1),
+            work_done: AtomicU64::new(0),
+            start: Instant::now(),
+            quiet,
+            printed: Mutex::new(u64::MAX),
+        }
+    }
+
+    pub fn update(&self, work: u64) {
+        let done = self.work_done.fetch_add(work, Ordering::Relaxed) + work;
+        if self.quiet {
+            return;
+        }
+        let filled = done.min(self.total_work) * BAR_WIDTH / self.total_work;
+        let mut printed = self.printed.lock().unwrap();
+        if *printed == filled {
+            return;
+        }
+        *printed = filled;
+
+        let elapsed = self.start.elapsed().as_secs_f32();
+        let fraction = done as f32 / self.total_work as f32;
+        let remaining = elapsed / fraction.max(1e-6) - elapsed;
+        eprint!(
+            "\r{}: [{}{}] ({:.1}s|{:.1}s)  ",
+            self.title,
+            "+".repeat(filled as usize),
+            " ".repeat((BAR_WIDTH - filled) as usize),
+            elapsed,
+            remaining.max(0.0)
+        );
+        std::io::stderr().flush().ok();
+    }
+
+    pub fn elapsed_seconds(&self) -> f32 {
+        self.start.elapsed().as_secs_f32()
+    }
+
+    pub fn done(&self) {
+        if !self.quiet {
+            let _printed = self.printed.lock().unwrap();
+            eprintln!(
+                "\r{}: finished in {:.1}s{}",
+                self.title,
+                self.elapsed_seconds(),
+                " ".repeat(BAR_WIDTH as usize + 20)
+            );
+        }
+    }
+}
@@ -1,23 +1,402 @@
+// The BxDF flags keep the names used in the book
+#![allow(non_camel_case_types)]
+
+use crate::core::rng::ONE_MINUS_EPSILON;
+use crate::core::sampling::*;
+use crate::core::SurfaceInteraction;
 use crate::math::*;
 use crate::spectrum::Spectrum;
 use bitmask::bitmask;
 
+mod fresnel_blend;
+mod lambertian;
+mod microfacet;
+mod oren_nayar;
+mod specular;
+
+pub use fresnel_blend::FresnelBlend;
+pub use lambertian::LambertianReflection;
+pub use microfacet::{MicrofacetReflection, MicrofacetTransmission};
+pub use oren_nayar::OrenNayar;
+pub use specular::{FresnelSpecular, SpecularReflection, SpecularTransmission};
+
 bitmask! {
     pub mask BxDFType: u32 where flags BSDF_TYPES {
         BSDF_REFLECTION = 1 << 0,
         BSDF_SPECULAR = 1 << 1,
         BSDF_TRANSMISSION = 1 << 2,
+        BSDF_DIFFUSE = 1 << 3,
+        BSDF_GLOSSY = 1 << 4,
+        BSDF_ALL = (1 << 0) | (1 << 1) | (1 << 2) | (1 << 3) | (1 << 4),
+    }
+}
+
+// Trigonometry of directions in the shading frame, where the normal is +z
+
+pub fn cos_theta(w: &Vec3) -> f32 {
+    w.z
+}
+
+pub fn cos2_theta(w: &Vec3) -> f32 {
+    w.z * w.z
+}
+
+pub fn abs_cos_theta(w: &Vec3) -> f32 {
+    w.z.abs()
+}
+
+pub fn sin2_theta(w: &Vec3) -> f32 {
+    (1.0 - cos2_theta(w)).max(0.0)
+}
+
+pub fn sin_theta(w: &Vec3) -> f32 {
+    sin2_theta(w).sqrt()
+}
+
+pub fn tan_theta(w: &Vec3) -> f32 {
+    sin_theta(w) / cos_theta(w)
+}
+
+pub fn tan2_theta(w: &Vec3) -> f32 {
+    sin2_theta(w) / cos2_theta(w)
+}
+
+pub fn cos_phi(w: &Vec3) -> f32 {
+    let sin_theta = sin_theta(w);
+    if sin_theta == 0.0 {
+        1.0
+    } else {
+        clamp(w.x / sin_theta, -1.0, 1.0)
+    }
+}
+
+pub fn sin_phi(w: &Vec3) -> f32 {
+    let sin_theta = sin_theta(w);
+    if sin_theta == 0.0 {
+        0.0
+    } else {
+        clamp(w.y / sin_theta, -1.0, 1.0)
     }
 }
 
-pub struct BSDF {}
+pub fn cos2_phi(w: &Vec3) -> f32 {
+    cos_phi(w) * cos_phi(w)
+}
+
+pub fn sin2_phi(w: &Vec3) -> f32 {
+    sin_phi(w) * sin_phi(w)
+}
+
+pub fn same_hemisphere(w: &Vec3, wp: &Vec3) -> bool {
+    w.z * wp.z > 0.0
+}
+
+// Mirror direction of wo about n
+pub fn reflect(wo: &Vec3, n: &Vec3) -> Vec3 {
+    -*wo + 2.0 * dot(*wo, *n) * *n
+}
+
+// Direction of wi refracted through the surface with normal n on the side of wi, eta is the
+// ratio of the indices of refraction of the incident and transmitted side. None on total
+// internal reflection
+pub fn refract(wi: &Vec3, n: &Normal3f, eta: f32) -> Option<Vec3> {
+    let cos_theta_i = dot(*n, *wi);
+    let sin2_theta_i = (1.0 - cos_theta_i * cos_theta_i).max(0.0);
+    let sin2_theta_t = eta * eta * sin2_theta_i;
+    if sin2_theta_t >= 1.0 {
+        return None;
+    }
+    let cos_theta_t = (1.0 - sin2_theta_t).sqrt();
+    Some(eta * -*wi + (eta * cos_theta_i - cos_theta_t) * *n)
+}
+
+// Fresnel reflectance of unpolarized light between two dielectrics, a negative cos_theta_i
+// means the light arrives from the side of eta_t
+pub fn fr_dielectric(cos_theta_i: f32, eta_i: f32, eta_t: f32) -> f32 {
+    let mut cos_theta_i = clamp(cos_theta_i, -1.0, 1.0);
+    let (eta_i, eta_t) = if cos_theta_i > 0.0 {
+        (eta_i, eta_t)
+    } else {
+        cos_theta_i = cos_theta_i.abs();
+        (eta_t, eta_i)
+    };
+
+    // Snell's law
+    let sin_theta_i = (1.0 - cos_theta_i * cos_theta_i).max(0.0).sqrt();
+    let sin_theta_t = eta_i / eta_t * sin_theta_i;
+    // Total internal reflection
+    if sin_theta_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_theta_t = (1.0 - sin_theta_t * sin_theta_t).max(0.0).sqrt();
+
+    let r_parl = ((eta_t * cos_theta_i) - (eta_i * cos_theta_t))
+        / ((eta_t * cos_theta_i) + (eta_i * cos_theta_t));
+    let r_perp = ((eta_i * cos_theta_i) - (eta_t * cos_theta_t))
+        / ((eta_i * cos_theta_i) + (eta_t * cos_theta_t));
+    (r_parl * r_parl + r_perp * r_perp) / 2.0
+}
+
+// Fresnel reflectance of unpolarized light from a dielectric onto a conductor with the
+// absorption coefficient k
+pub fn fr_conductor(cos_theta_i: f32, eta_i: Spectrum, eta_t: Spectrum, k: Spectrum) -> Spectrum {
+    let cos_theta_i = clamp(cos_theta_i, -1.0, 1.0);
+    let eta = eta_t / eta_i;
+    let etak = k / eta_i;
+
+    let cos_theta_i2 = Spectrum::from_value(cos_theta_i * cos_theta_i);
+    let sin_theta_i2 = Spectrum::from_value(1.0 - cos_theta_i * cos_theta_i);
+    let eta2 = eta * eta;
+    let etak2 = etak * etak;
+
+    let t0 = eta2 - etak2 - sin_theta_i2;
+    let a2_plus_b2 = (t0 * t0 + 4.0 * eta2 * etak2).sqrt();
+    let t1 = a2_plus_b2 + cos_theta_i2;
+    let a = (0.5 * (a2_plus_b2 + t0)).sqrt();
+    let t2 = 2.0 * cos_theta_i * a;
+    let rs = (t1 - t2) / (t1 + t2);
+
+    let t3 = cos_theta_i2 * a2_plus_b2 + sin_theta_i2 * sin_theta_i2;
+    let t4 = t2 * sin_theta_i2;
+    let rp = rs * (t3 - t4) / (t3 + t4);
+
+    0.5 * (rp + rs)
+}
+
+// Fraction of the light reflected at a surface
+pub trait Fresnel: Send + Sync {
+    fn evaluate(&self, cos_theta_i: f32) -> Spectrum;
+}
+
+pub struct FresnelConductor {
+    eta_i: Spectrum,
+    eta_t: Spectrum,
+    k: Spectrum,
+}
+
+impl FresnelConductor {
+    pub fn new(eta_i: Spectrum, eta_t: Spectrum, k: Spectrum) -> FresnelConductor {
+        FresnelConductor { eta_i, eta_t, k }
+    }
+}
+
+impl Fresnel for FresnelConductor {
+    fn evaluate(&self, cos_theta_i: f32) -> Spectrum {
+        fr_conductor(cos_theta_i.abs(), self.eta_i, self.eta_t, self.k)
+    }
+}
+
+pub struct FresnelDielectric {
+    eta_i: f32,
+    eta_t: f32,
+}
+
+impl FresnelDielectric {
+    pub fn new(eta_i: f32, eta_t: f32) -> FresnelDielectric {
+        FresnelDielectric { eta_i, eta_t }
+    }
+}
+
+impl Fresnel for FresnelDielectric {
+    fn evaluate(&self, cos_theta_i: f32) -> Spectrum {
+        Spectrum::from_value(fr_dielectric(cos_theta_i, self.eta_i, self.eta_t))
+    }
+}
+
+// Reflects everything
+pub struct FresnelNoOp {}
+
+impl Fresnel for FresnelNoOp {
+    fn evaluate(&self, _cos_theta_i: f32) -> Spectrum {
+        Spectrum::from_value(1.0)
+    }
+}
+
+// Scattering of a single lobe, all directions are in the shading frame
+pub trait BxDF: Send + Sync {
+    fn bxdf_type(&self) -> BxDFType;
+    fn f(&self, wo: &Vec3, wi: &Vec3) -> Spectrum;
+
+    fn matches_flags(&self, flags: BxDFType) -> bool {
+        flags.contains(self.bxdf_type())
+    }
+
+    // Value, sampled incident direction, its pdf and the type of the lobe that was sampled.
+    // The default samples the cosine weighted hemisphere on the side of wo
+    fn sample_f(&self, wo: &Vec3, u: &Point2) -> (Spectrum, Vec3, f32, BxDFType) {
+        let mut wi = cosine_sample_hemisphere(*u);
+        if wo.z < 0.0 {
+            wi.z *= -1.0;
+        }
+        (self.f(wo, &wi), wi, self.pdf(wo, &wi), self.bxdf_type())
+    }
+
+    fn pdf(&self, wo: &Vec3, wi: &Vec3) -> f32 {
+        if same_hemisphere(wo, wi) {
+            cosine_hemisphere_pdf(abs_cos_theta(wi))
+        } else {
+            0.0
+        }
+    }
+}
+
+const MAX_BXDFS: usize = 8;
+
+// Collection of BxDFs at a surface point, evaluated in the shading frame
+pub struct BSDF {
+    // Relative index of refraction over the boundary, 1 for opaque surfaces
+    pub eta: f32,
+    ns: Normal3f,
+    ng: Normal3f,
+    ss: Vec3,
+    ts: Vec3,
+    bxdfs: Vec<Box<dyn BxDF>>,
+}
 
 impl BSDF {
-    pub fn f(&self, wo: &Vec3, wi: &Vec3, flags: BxDFType) -> Spectrum {
-        Spectrum::new()
+    pub fn new(si: &SurfaceInteraction, eta: f32) -> BSDF {
+        let ns = si.shading.n;
+        let ss = si.shading.dpdu.normalize();
+        BSDF {
+            eta,
+            ns,
+            ng: si.interaction.n,
+            ss,
+            ts: ns.cross(ss),
+            bxdfs: Vec::with_capacity(MAX_BXDFS),
+        }
+    }
+
+    pub fn add(&mut self, bxdf: Box<dyn BxDF>) {
+        assert!(
+            self.bxdfs.len() < MAX_BXDFS,
+            "a BSDF holds at most {} BxDFs",
+            MAX_BXDFS
+        );
+        self.bxdfs.push(bxdf);
+    }
+
+    pub fn num_components(&self, flags: BxDFType) -> usize {
+        self.bxdfs
+            .iter()
+            .filter(|bxdf| bxdf.matches_flags(flags))
+            .count()
+    }
+
+    pub fn world_to_local(&self, v: &Vec3) -> Vec3 {
+        vec3(dot(*v, self.ss), dot(*v, self.ts), dot(*v, self.ns))
+    }
+
+    pub fn local_to_world(&self, v: &Vec3) -> Vec3 {
+        self.ss * v.x + self.ts * v.y + self.ns * v.z
+    }
+
+    // Sum of the matching lobes on the side of the geometric normal wi_world is on, so light
+    // doesn't leak through where the shading normal disagrees with the geometry
+    fn f_local(
+        &self,
+        wo_world: &Vec3,
+        wi_world: &Vec3,
+        wo: &Vec3,
+        wi: &Vec3,
+        flags: BxDFType,
+    ) -> Spectrum {
+        let reflect = dot(*wi_world, self.ng) * dot(*wo_world, self.ng) > 0.0;
+        let mut f = Spectrum::new();
+        for bxdf in self.bxdfs.iter() {
+            if bxdf.matches_flags(flags)
+                && ((reflect && bxdf.bxdf_type().contains(BSDF_TYPES::BSDF_REFLECTION))
+                    || (!reflect && bxdf.bxdf_type().contains(BSDF_TYPES::BSDF_TRANSMISSION)))
+            {
+                f += bxdf.f(wo, wi);
+            }
+        }
+        f
+    }
+
+    pub fn f(&self, wo_world: &Vec3, wi_world: &Vec3, flags: BxDFType) -> Spectrum {
+        let wo = self.world_to_local(wo_world);
+        let wi = self.world_to_local(wi_world);
+        if wo.z == 0.0 {
+            return Spectrum::new();
+        }
+        self.f_local(wo_world, wi_world, &wo, &wi, flags)
+    }
+
+    // Samples one of the matching lobes and returns the value of all of them with the
+    // combined pdf, unless a specular lobe was picked
+    pub fn sample_f(
+        &self,
+        wo_world: &Vec3,
+        u: &Point2,
+        flags: BxDFType,
+    ) -> (Spectrum, Vec3, f32, BxDFType) {
+        let none = (Spectrum::new(), Vec3::zero(), 0.0, BxDFType::none());
+        let matching_comps = self.num_components(flags);
+        if matching_comps == 0 {
+            return none;
+        }
+        let comp = ((u.x * matching_comps as f32) as usize).min(matching_comps - 1);
+        let (index, bxdf) = self
+            .bxdfs
+            .iter()
+            .enumerate()
+            .filter(|(_, bxdf)| bxdf.matches_flags(flags))
+            .nth(comp)
+            .unwrap();
+
+        // Reuse the first dimension, which only picked the component
+        let u_remapped = Point2::new(
+            (u.x * matching_comps as f32 - comp as f32).min(ONE_MINUS_EPSILON),
+            u.y,
+        );
+        let wo = self.world_to_local(wo_world);
+        if wo.z == 0.0 {
+            return none;
+        }
+        let (mut f, wi, mut pdf, sampled_type) = bxdf.sample_f(&wo, &u_remapped);
+        if pdf == 0.0 {
+            return none;
+        }
+        let wi_world = self.local_to_world(&wi);
+
+        let specular = bxdf.bxdf_type().contains(BSDF_TYPES::BSDF_SPECULAR);
+        if !specular && matching_comps > 1 {
+            for (i, other) in self.bxdfs.iter().enumerate() {
+                if i != index && other.matches_flags(flags) {
+                    pdf += other.pdf(&wo, &wi);
+                }
+            }
+        }
+        if matching_comps > 1 {
+            pdf /= matching_comps as f32;
+        }
+
+        if !specular {
+            f = self.f_local(wo_world, &wi_world, &wo, &wi, flags);
+        }
+        (f, wi_world, pdf, sampled_type)
     }
 
-    pub fn sample_f(&self, wo: &Vec3, u: &Point2, flags: BxDFType) -> (Spectrum, Vec3, f32) {
-        (Spectrum::new(), Vec3::new(0.0, 0.0, 0.0), 0.0)
+    pub fn pdf(&self, wo_world: &Vec3, wi_world: &Vec3, flags: BxDFType) -> f32 {
+        if self.bxdfs.is_empty() {
+            return 0.0;
+        }
+        let wo = self.world_to_local(wo_world);
+        let wi = self.world_to_local(wi_world);
+        if wo.z == 0.0 {
+            return 0.0;
+        }
+        let mut pdf = 0.0;
+        let mut matching_comps = 0;
+        for bxdf in self.bxdfs.iter().filter(|bxdf| bxdf.matches_flags(flags)) {
+            matching_comps += 1;
+            pdf += bxdf.pdf(&wo, &wi);
+        }
+        if matching_comps > 0 {
+            pdf / matching_comps as f32
+        } else {
+            0.0
+        }
     }
 }
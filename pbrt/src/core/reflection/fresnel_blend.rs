@@ -0,0 +1,85 @@
+use super::*;
+use crate::core::microfacet::MicrofacetDistribution;
+
+// Ashikhmin-Shirley model of a diffuse substrate under a glossy coating, the diffuse part
+// gets less light at grazing angles where the coating reflects more
+pub struct FresnelBlend {
+    rd: Spectrum,
+    rs: Spectrum,
+    distribution: Box<dyn MicrofacetDistribution>,
+}
+
+impl FresnelBlend {
+    pub fn new(
+        rd: Spectrum,
+        rs: Spectrum,
+        distribution: Box<dyn MicrofacetDistribution>,
+    ) -> FresnelBlend {
+        FresnelBlend {
+            rd,
+            rs,
+            distribution,
+        }
+    }
+
+    fn schlick_fresnel(&self, cos_theta: f32) -> Spectrum {
+        self.rs + pow5(1.0 - cos_theta) * (Spectrum::from_value(1.0) - self.rs)
+    }
+}
+
+fn pow5(v: f32) -> f32 {
+    (v * v) * (v * v) * v
+}
+
+impl BxDF for FresnelBlend {
+    fn bxdf_type(&self) -> BxDFType {
+        BSDF_TYPES::BSDF_REFLECTION | BSDF_TYPES::BSDF_GLOSSY
+    }
+
+    fn f(&self, wo: &Vec3, wi: &Vec3) -> Spectrum {
+        let diffuse = (28.0 / (23.0 * std::f32::consts::PI))
+            * self.rd
+            * (Spectrum::from_value(1.0) - self.rs)
+            * (1.0 - pow5(1.0 - 0.5 * abs_cos_theta(wi)))
+            * (1.0 - pow5(1.0 - 0.5 * abs_cos_theta(wo)));
+        let wh = wi + wo;
+        if wh.is_zero() {
+            return Spectrum::new();
+        }
+        let wh = wh.normalize();
+        let specular = self.distribution.d(&wh)
+            / (4.0 * dot(*wi, wh).abs() * abs_cos_theta(wi).max(abs_cos_theta(wo)))
+            * self.schlick_fresnel(dot(*wi, wh));
+        diffuse + specular
+    }
+
+    // Picks the diffuse or the glossy lobe with the first dimension
+    fn sample_f(&self, wo: &Vec3, u: &Point2) -> (Spectrum, Vec3, f32, BxDFType) {
+        let wi = if u.x < 0.5 {
+            let u = Point2::new((2.0 * u.x).min(ONE_MINUS_EPSILON), u.y);
+            let mut wi = cosine_sample_hemisphere(u);
+            if wo.z < 0.0 {
+                wi.z *= -1.0;
+            }
+            wi
+        } else {
+            let u = Point2::new((2.0 * (u.x - 0.5)).min(ONE_MINUS_EPSILON), u.y);
+            let wh = self.distribution.sample_wh(wo, &u);
+            let wi = reflect(wo, &wh);
+            if !same_hemisphere(wo, &wi) {
+                return (Spectrum::new(), wi, 0.0, self.bxdf_type());
+            }
+            wi
+        };
+        (self.f(wo, &wi), wi, self.pdf(wo, &wi), self.bxdf_type())
+    }
+
+    fn pdf(&self, wo: &Vec3, wi: &Vec3) -> f32 {
+        if !same_hemisphere(wo, wi) {
+            return 0.0;
+        }
+        let wh = (wo + wi).normalize();
+        let pdf_wh = self.distribution.pdf(wo, &wh);
+        0.5 * (cosine_hemisphere_pdf(abs_cos_theta(wi)) + pdf_wh / (4.0 * dot(*wo, wh)))
+    }
+}
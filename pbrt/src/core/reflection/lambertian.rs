@@ -0,0 +1,22 @@
+use super::*;
+
+// Perfectly diffuse reflection, scattering equally in all directions of the hemisphere
+pub struct LambertianReflection {
+    r: Spectrum,
+}
+
+impl LambertianReflection {
+    pub fn new(r: Spectrum) -> LambertianReflection {
+        LambertianReflection { r }
+    }
+}
+
+impl BxDF for LambertianReflection {
+    fn bxdf_type(&self) -> BxDFType {
+        BSDF_TYPES::BSDF_REFLECTION | BSDF_TYPES::BSDF_DIFFUSE
+    }
+
+    fn f(&self, _wo: &Vec3, _wi: &Vec3) -> Spectrum {
+        self.r * std::f32::consts::FRAC_1_PI
+    }
+}
@@ -0,0 +1,181 @@
+use super::*;
+use crate::core::microfacet::MicrofacetDistribution;
+
+// Torrance-Sparrow reflection from a surface of perfectly specular microfacets
+pub struct MicrofacetReflection {
+    r: Spectrum,
+    distribution: Box<dyn MicrofacetDistribution>,
+    fresnel: Box<dyn Fresnel>,
+}
+
+impl MicrofacetReflection {
+    pub fn new(
+        r: Spectrum,
+        distribution: Box<dyn MicrofacetDistribution>,
+        fresnel: Box<dyn Fresnel>,
+    ) -> MicrofacetReflection {
+        MicrofacetReflection {
+            r,
+            distribution,
+            fresnel,
+        }
+    }
+}
+
+impl BxDF for MicrofacetReflection {
+    fn bxdf_type(&self) -> BxDFType {
+        BSDF_TYPES::BSDF_REFLECTION | BSDF_TYPES::BSDF_GLOSSY
+    }
+
+    fn f(&self, wo: &Vec3, wi: &Vec3) -> Spectrum {
+        let cos_theta_o = abs_cos_theta(wo);
+        let cos_theta_i = abs_cos_theta(wi);
+        let wh = wi + wo;
+        // Degenerate cases for grazing angles
+        if cos_theta_i == 0.0 || cos_theta_o == 0.0 || wh.is_zero() {
+            return Spectrum::new();
+        }
+        let wh = wh.normalize();
+        // The Fresnel term is evaluated on the side of the macro surface
+        let f = self
+            .fresnel
+            .evaluate(dot(*wi, face_forward(wh, vec3(0.0, 0.0, 1.0))));
+        self.r * self.distribution.d(&wh) * self.distribution.g(wo, wi) * f
+            / (4.0 * cos_theta_i * cos_theta_o)
+    }
+
+    fn sample_f(&self, wo: &Vec3, u: &Point2) -> (Spectrum, Vec3, f32, BxDFType) {
+        let none = (Spectrum::new(), Vec3::zero(), 0.0, self.bxdf_type());
+        if wo.z == 0.0 {
+            return none;
+        }
+        let wh = self.distribution.sample_wh(wo, u);
+        // Should be rare
+        if dot(*wo, wh) < 0.0 {
+            return none;
+        }
+        let wi = reflect(wo, &wh);
+        if !same_hemisphere(wo, &wi) {
+            return none;
+        }
+        // Change of variables from the half vector to the reflected direction
+        let pdf = self.distribution.pdf(wo, &wh) / (4.0 * dot(*wo, wh));
+        (self.f(wo, &wi), wi, pdf, self.bxdf_type())
+    }
+
+    fn pdf(&self, wo: &Vec3, wi: &Vec3) -> f32 {
+        if !same_hemisphere(wo, wi) {
+            return 0.0;
+        }
+        let wh = (wo + wi).normalize();
+        self.distribution.pdf(wo, &wh) / (4.0 * dot(*wo, wh))
+    }
+}
+
+// Walter et al. transmission through a rough dielectric boundary, eta_a is the index of
+// refraction above the surface and eta_b the one below it
+pub struct MicrofacetTransmission {
+    t: Spectrum,
+    distribution: Box<dyn MicrofacetDistribution>,
+    eta_a: f32,
+    eta_b: f32,
+    fresnel: FresnelDielectric,
+}
+
+impl MicrofacetTransmission {
+    pub fn new(
+        t: Spectrum,
+        distribution: Box<dyn MicrofacetDistribution>,
+        eta_a: f32,
+        eta_b: f32,
+    ) -> MicrofacetTransmission {
+        MicrofacetTransmission {
+            t,
+            distribution,
+            eta_a,
+            eta_b,
+            fresnel: FresnelDielectric::new(eta_a, eta_b),
+        }
+    }
+
+    // Ratio of the indices of refraction of the side of wi over the side of wo
+    fn eta(&self, wo: &Vec3) -> f32 {
+        if cos_theta(wo) > 0.0 {
+            self.eta_b / self.eta_a
+        } else {
+            self.eta_a / self.eta_b
+        }
+    }
+}
+
+impl BxDF for MicrofacetTransmission {
+    fn bxdf_type(&self) -> BxDFType {
+        BSDF_TYPES::BSDF_TRANSMISSION | BSDF_TYPES::BSDF_GLOSSY
+    }
+
+    fn f(&self, wo: &Vec3, wi: &Vec3) -> Spectrum {
+        if same_hemisphere(wo, wi) {
+            return Spectrum::new();
+        }
+        let cos_theta_o = cos_theta(wo);
+        let cos_theta_i = cos_theta(wi);
+        if cos_theta_i == 0.0 || cos_theta_o == 0.0 {
+            return Spectrum::new();
+        }
+
+        // Generalized half vector of the refraction, always in the upper hemisphere
+        let eta = self.eta(wo);
+        let mut wh = (wo + wi * eta).normalize();
+        if wh.z < 0.0 {
+            wh = -wh;
+        }
+        // Both directions have to be on different sides of the microfacet too
+        if dot(*wo, wh) * dot(*wi, wh) > 0.0 {
+            return Spectrum::new();
+        }
+
+        let f = self.fresnel.evaluate(dot(*wo, wh));
+        let sqrt_denom = dot(*wo, wh) + eta * dot(*wi, wh);
+        // The eta^2 of the change in solid angle cancels with the 1/eta^2 radiance scaling
+        (Spectrum::from_value(1.0) - f)
+            * self.t
+            * (self.distribution.d(&wh)
+                * self.distribution.g(wo, wi)
+                * dot(*wi, wh).abs()
+                * dot(*wo, wh).abs()
+                / (cos_theta_i * cos_theta_o * sqrt_denom * sqrt_denom))
+                .abs()
+    }
+
+    fn sample_f(&self, wo: &Vec3, u: &Point2) -> (Spectrum, Vec3, f32, BxDFType) {
+        let none = (Spectrum::new(), Vec3::zero(), 0.0, self.bxdf_type());
+        if wo.z == 0.0 {
+            return none;
+        }
+        let wh = self.distribution.sample_wh(wo, u);
+        // Should be rare
+        if dot(*wo, wh) < 0.0 {
+            return none;
+        }
+        let wi = match refract(wo, &wh, 1.0 / self.eta(wo)) {
+            Some(wi) => wi,
+            None => return none,
+        };
+        (self.f(wo, &wi), wi, self.pdf(wo, &wi), self.bxdf_type())
+    }
+
+    fn pdf(&self, wo: &Vec3, wi: &Vec3) -> f32 {
+        if same_hemisphere(wo, wi) {
+            return 0.0;
+        }
+        let eta = self.eta(wo);
+        let wh = (wo + wi * eta).normalize();
+        if dot(*wo, wh) * dot(*wi, wh) > 0.0 {
+            return 0.0;
+        }
+        // Change of variables from the half vector to the refracted direction
+        let sqrt_denom = dot(*wo, wh) + eta * dot(*wi, wh);
+        let dwh_dwi = ((eta * eta * dot(*wi, wh)) / (sqrt_denom * sqrt_denom)).abs();
+        self.distribution.pdf(wo, &wh) * dwh_dwi
+    }
+}
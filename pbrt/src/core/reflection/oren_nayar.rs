@@ -0,0 +1,49 @@
+use super::*;
+
+// Diffuse reflection from a surface of V-shaped Lambertian microfacets, which looks flatter
+// than Lambertian reflection as the facets get rougher
+pub struct OrenNayar {
+    r: Spectrum,
+    a: f32,
+    b: f32,
+}
+
+impl OrenNayar {
+    // sigma is the standard deviation of the facet angle in degrees
+    pub fn new(r: Spectrum, sigma: f32) -> OrenNayar {
+        let sigma = sigma.to_radians();
+        let sigma2 = sigma * sigma;
+        OrenNayar {
+            r,
+            a: 1.0 - (sigma2 / (2.0 * (sigma2 + 0.33))),
+            b: 0.45 * sigma2 / (sigma2 + 0.09),
+        }
+    }
+}
+
+impl BxDF for OrenNayar {
+    fn bxdf_type(&self) -> BxDFType {
+        BSDF_TYPES::BSDF_REFLECTION | BSDF_TYPES::BSDF_DIFFUSE
+    }
+
+    fn f(&self, wo: &Vec3, wi: &Vec3) -> Spectrum {
+        let sin_theta_i = sin_theta(wi);
+        let sin_theta_o = sin_theta(wo);
+
+        // Cosine of the azimuth difference, the azimuths are meaningless at the normal
+        let mut max_cos = 0.0;
+        if sin_theta_i > 1e-4 && sin_theta_o > 1e-4 {
+            let d_cos = cos_phi(wi) * cos_phi(wo) + sin_phi(wi) * sin_phi(wo);
+            max_cos = d_cos.max(0.0);
+        }
+
+        // Sine of the larger and tangent of the smaller polar angle
+        let (sin_alpha, tan_beta) = if abs_cos_theta(wi) > abs_cos_theta(wo) {
+            (sin_theta_o, sin_theta_i / abs_cos_theta(wi))
+        } else {
+            (sin_theta_i, sin_theta_o / abs_cos_theta(wo))
+        };
+
+        self.r * std::f32::consts::FRAC_1_PI * (self.a + self.b * max_cos * sin_alpha * tan_beta)
+    }
+}
@@ -0,0 +1,139 @@
+use super::*;
+
+// Perfect mirror, the only direction it scatters to can't be found by chance, so f is zero
+// and all of the reflection comes from sample_f
+pub struct SpecularReflection {
+    r: Spectrum,
+    fresnel: Box<dyn Fresnel>,
+}
+
+impl SpecularReflection {
+    pub fn new(r: Spectrum, fresnel: Box<dyn Fresnel>) -> SpecularReflection {
+        SpecularReflection { r, fresnel }
+    }
+}
+
+impl BxDF for SpecularReflection {
+    fn bxdf_type(&self) -> BxDFType {
+        BSDF_TYPES::BSDF_REFLECTION | BSDF_TYPES::BSDF_SPECULAR
+    }
+
+    fn f(&self, _wo: &Vec3, _wi: &Vec3) -> Spectrum {
+        Spectrum::new()
+    }
+
+    fn sample_f(&self, wo: &Vec3, _u: &Point2) -> (Spectrum, Vec3, f32, BxDFType) {
+        let wi = vec3(-wo.x, -wo.y, wo.z);
+        let f = self.fresnel.evaluate(cos_theta(&wi)) * self.r / abs_cos_theta(&wi);
+        (f, wi, 1.0, self.bxdf_type())
+    }
+
+    fn pdf(&self, _wo: &Vec3, _wi: &Vec3) -> f32 {
+        0.0
+    }
+}
+
+// Perfect refraction through a dielectric boundary, eta_a is the index of refraction above
+// the surface and eta_b the one below it
+pub struct SpecularTransmission {
+    t: Spectrum,
+    eta_a: f32,
+    eta_b: f32,
+    fresnel: FresnelDielectric,
+}
+
+impl SpecularTransmission {
+    pub fn new(t: Spectrum, eta_a: f32, eta_b: f32) -> SpecularTransmission {
+        SpecularTransmission {
+            t,
+            eta_a,
+            eta_b,
+            fresnel: FresnelDielectric::new(eta_a, eta_b),
+        }
+    }
+}
+
+// Refracts wo through the dielectric boundary, None on total internal reflection. Returns the
+// refracted direction and the squared ratio of the indices of refraction, which scales radiance
+fn refract_specular(wo: &Vec3, eta_a: f32, eta_b: f32) -> Option<(Vec3, f32)> {
+    let entering = cos_theta(wo) > 0.0;
+    let (eta_i, eta_t) = if entering {
+        (eta_a, eta_b)
+    } else {
+        (eta_b, eta_a)
+    };
+    let wi = refract(wo, &face_forward(vec3(0.0, 0.0, 1.0), *wo), eta_i / eta_t)?;
+    Some((wi, (eta_i * eta_i) / (eta_t * eta_t)))
+}
+
+impl BxDF for SpecularTransmission {
+    fn bxdf_type(&self) -> BxDFType {
+        BSDF_TYPES::BSDF_TRANSMISSION | BSDF_TYPES::BSDF_SPECULAR
+    }
+
+    fn f(&self, _wo: &Vec3, _wi: &Vec3) -> Spectrum {
+        Spectrum::new()
+    }
+
+    fn sample_f(&self, wo: &Vec3, _u: &Point2) -> (Spectrum, Vec3, f32, BxDFType) {
+        let (wi, eta2) = match refract_specular(wo, self.eta_a, self.eta_b) {
+            Some(refracted) => refracted,
+            None => return (Spectrum::new(), Vec3::zero(), 0.0, self.bxdf_type()),
+        };
+        let mut ft = self.t * (Spectrum::from_value(1.0) - self.fresnel.evaluate(cos_theta(&wi)));
+        // Radiance gets compressed into a smaller solid angle entering a denser medium
+        ft *= eta2;
+        (ft / abs_cos_theta(&wi), wi, 1.0, self.bxdf_type())
+    }
+
+    fn pdf(&self, _wo: &Vec3, _wi: &Vec3) -> f32 {
+        0.0
+    }
+}
+
+// Specular reflection and transmission of a dielectric in one BxDF, choosing between them
+// proportionally to the Fresnel reflectance
+pub struct FresnelSpecular {
+    r: Spectrum,
+    t: Spectrum,
+    eta_a: f32,
+    eta_b: f32,
+}
+
+impl FresnelSpecular {
+    pub fn new(r: Spectrum, t: Spectrum, eta_a: f32, eta_b: f32) -> FresnelSpecular {
+        FresnelSpecular { r, t, eta_a, eta_b }
+    }
+}
+
+impl BxDF for FresnelSpecular {
+    fn bxdf_type(&self) -> BxDFType {
+        BSDF_TYPES::BSDF_REFLECTION | BSDF_TYPES::BSDF_TRANSMISSION | BSDF_TYPES::BSDF_SPECULAR
+    }
+
+    fn f(&self, _wo: &Vec3, _wi: &Vec3) -> Spectrum {
+        Spectrum::new()
+    }
+
+    fn sample_f(&self, wo: &Vec3, u: &Point2) -> (Spectrum, Vec3, f32, BxDFType) {
+        let fr = fr_dielectric(cos_theta(wo), self.eta_a, self.eta_b);
+        if u.x < fr {
+            let wi = vec3(-wo.x, -wo.y, wo.z);
+            let sampled_type = BSDF_TYPES::BSDF_SPECULAR | BSDF_TYPES::BSDF_REFLECTION;
+            return (fr * self.r / abs_cos_theta(&wi), wi, fr, sampled_type);
+        }
+
+        let sampled_type = BSDF_TYPES::BSDF_SPECULAR | BSDF_TYPES::BSDF_TRANSMISSION;
+        let (wi, eta2) = match refract_specular(wo, self.eta_a, self.eta_b) {
+            Some(refracted) => refracted,
+            None => return (Spectrum::new(), Vec3::zero(), 0.0, sampled_type),
+        };
+        let mut ft = self.t * (1.0 - fr);
+        ft *= eta2;
+        (ft / abs_cos_theta(&wi), wi, 1.0 - fr, sampled_type)
+    }
+
+    fn pdf(&self, _wo: &Vec3, _wi: &Vec3) -> f32 {
+        0.0
+    }
+}
@@ -0,0 +1,61 @@
+// Largest float below one, uniform samples are kept under it
+pub const ONE_MINUS_EPSILON: f32 = 0.99999994;
+
+const PCG32_DEFAULT_STATE: u64 = 0x853c_49e6_748f_ea9b;
+const PCG32_DEFAULT_STREAM: u64 = 0xda3e_39cb_94b9_5bdb;
+const PCG32_MULT: u64 = 0x5851_f42d_4c95_7f2d;
+
+// PCG32 generator, with the same sequences as pbrt's
+#[derive(Clone)]
+pub struct RNG {
+    state: u64,
+    inc: u64,
+}
+
+impl Default for RNG {
+    fn default() -> Self {
+        RNG {
+            state: PCG32_DEFAULT_STATE,
+            inc: PCG32_DEFAULT_STREAM,
+        }
+    }
+}
+
+impl RNG {
+    pub fn new(sequence_index: u64) -> RNG {
+        let mut rng = RNG::default();
+        rng.set_sequence(sequence_index);
+        rng
+    }
+
+    pub fn set_sequence(&mut self, sequence_index: u64) {
+        self.state = 0;
+        self.inc = (sequence_index << 1) | 1;
+        self.uniform_u32();
+        self.state = self.state.wrapping_add(PCG32_DEFAULT_STATE);
+        self.uniform_u32();
+    }
+
+    pub fn uniform_u32(&mut self) -> u32 {
+        let old_state = self.state;
+        self.state = old_state.wrapping_mul(PCG32_MULT).wrapping_add(self.inc);
+        let xor_shifted = (((old_state >> 18) ^ old_state) >> 27) as u32;
+        let rot = (old_state >> 59) as u32;
+        xor_shifted.rotate_right(rot)
+    }
+
+    // Uniform in [0, bound) without modulo bias
+    pub fn uniform_u32_below(&mut self, bound: u32) -> u32 {
+        let threshold = bound.wrapping_neg() % bound;
+        loop {
+            let r = self.uniform_u32();
+            if r >= threshold {
+                return r % bound;
+            }
+        }
+    }
+
+    pub fn uniform_float(&mut self) -> f32 {
+        (self.uniform_u32() as f32 * (1.0 / 4_294_967_296.0)).min(ONE_MINUS_EPSILON)
+    }
+}
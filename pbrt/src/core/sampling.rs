@@ -0,0 +1,311 @@
+use crate::core::rng::{ONE_MINUS_EPSILON, RNG};
+use crate::math::*;
+
+pub fn concentric_sample_disk(u: Point2) -> Point2 {
+    // Map uniform random numbers to [-1, 1]^2
+    let u_offset = Vec2::new(2.0 * u.x - 1.0, 2.0 * u.y - 1.0);
+    if u_offset.x == 0.0 && u_offset.y == 0.0 {
+        return Point2::new(0.0, 0.0);
+    }
+
+    // Apply concentric mapping to point
+    let (r, theta) = if u_offset.x.abs() > u_offset.y.abs() {
+        (
+            u_offset.x,
+            std::f32::consts::FRAC_PI_4 * (u_offset.y / u_offset.x),
+        )
+    } else {
+        (
+            u_offset.y,
+            std::f32::consts::FRAC_PI_2 - std::f32::consts::FRAC_PI_4 * (u_offset.x / u_offset.y),
+        )
+    };
+    Point2::new(r * theta.cos(), r * theta.sin())
+}
+
+pub fn stratified_sample_1d(samples: &mut [f32], rng: &mut RNG, jitter: bool) {
+    let inv_n_samples = 1.0 / samples.len() as f32;
+    for (i, sample) in samples.iter_mut().enumerate() {
+        let delta = if jitter { rng.uniform_float() } else { 0.5 };
+        *sample = ((i as f32 + delta) * inv_n_samples).min(ONE_MINUS_EPSILON);
+    }
+}
+
+// samples holds nx * ny points, filled row by row
+pub fn stratified_sample_2d(
+    samples: &mut [Point2],
+    nx: usize,
+    ny: usize,
+    rng: &mut RNG,
+    jitter: bool,
+) {
+    let dx = 1.0 / nx as f32;
+    let dy = 1.0 / ny as f32;
+    for y in 0..ny {
+        for x in 0..nx {
+            let jx = if jitter { rng.uniform_float() } else { 0.5 };
+            let jy = if jitter { rng.uniform_float() } else { 0.5 };
+            samples[y * nx + x] = Point2::new(
+                ((x as f32 + jx) * dx).min(ONE_MINUS_EPSILON),
+                ((y as f32 + jy) * dy).min(ONE_MINUS_EPSILON),
+            );
+        }
+    }
+}
+
+// Stratifies each dimension separately, then decorrelates them with random permutations
+pub fn latin_hypercube(samples: &mut [Point2], rng: &mut RNG) {
+    let n_samples = samples.len();
+    let inv_n_samples = 1.0 / n_samples as f32;
+    for (i, sample) in samples.iter_mut().enumerate() {
+        *sample = Point2::new(
+            ((i as f32 + rng.uniform_float()) * inv_n_samples).min(ONE_MINUS_EPSILON),
+            ((i as f32 + rng.uniform_float()) * inv_n_samples).min(ONE_MINUS_EPSILON),
+        );
+    }
+    for j in 0..n_samples {
+        let other = j + rng.uniform_u32_below((n_samples - j) as u32) as usize;
+        let x = samples[j].x;
+        samples[j].x = samples[other].x;
+        samples[other].x = x;
+    }
+    for j in 0..n_samples {
+        let other = j + rng.uniform_u32_below((n_samples - j) as u32) as usize;
+        let y = samples[j].y;
+        samples[j].y = samples[other].y;
+        samples[other].y = y;
+    }
+}
+
+// Shuffles samples in blocks of n_dimensions values
+pub fn shuffle<T>(samples: &mut [T], n_dimensions: usize, rng: &mut RNG) {
+    let count = samples.len() / n_dimensions;
+    for i in 0..count {
+        let other = i + rng.uniform_u32_below((count - i) as u32) as usize;
+        for j in 0..n_dimensions {
+            samples.swap(n_dimensions * i + j, n_dimensions * other + j);
+        }
+    }
+}
+
+pub fn uniform_sample_sphere(u: Point2) -> Vec3 {
+    let z = 1.0 - 2.0 * u.x;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * std::f32::consts::PI * u.y;
+    Vec3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+// Malley's method: project uniform disk samples up to the hemisphere around +z
+pub fn cosine_sample_hemisphere(u: Point2) -> Vec3 {
+    let d = concentric_sample_disk(u);
+    let z = (1.0 - d.x * d.x - d.y * d.y).max(0.0).sqrt();
+    Vec3::new(d.x, d.y, z)
+}
+
+pub fn cosine_hemisphere_pdf(cos_theta: f32) -> f32 {
+    cos_theta * std::f32::consts::FRAC_1_PI
+}
+
+pub fn uniform_cone_pdf(cos_theta_max: f32) -> f32 {
+    1.0 / (2.0 * std::f32::consts::PI * (1.0 - cos_theta_max))
+}
+
+// Weight of a sample from strategy f when strategy g could also have produced it
+pub fn power_heuristic(nf: i32, f_pdf: f32, ng: i32, g_pdf: f32) -> f32 {
+    let f = nf as f32 * f_pdf;
+    let g = ng as f32 * g_pdf;
+    if f.is_infinite() {
+        return 1.0;
+    }
+    (f * f) / (f * f + g * g)
+}
+
+// Piecewise constant 1D function that can be sampled proportionally to its value
+pub struct Distribution1D {
+    pub func: Vec<f32>,
+    pub cdf: Vec<f32>,
+    pub func_int: f32,
+}
+
+impl Distribution1D {
+    pub fn new(func: &[f32]) -> Distribution1D {
+        let n = func.len();
+        let mut cdf = vec![0.0; n + 1];
+        for i in 1..=n {
+            cdf[i] = cdf[i - 1] + func[i - 1] / n as f32;
+        }
+        let func_int = cdf[n];
+        for (i, value) in cdf.iter_mut().enumerate().skip(1) {
+            // A function that is zero everywhere is sampled uniformly
+            *value = if func_int == 0.0 {
+                i as f32 / n as f32
+            } else {
+                *value / func_int
+            };
+        }
+        Distribution1D {
+            func: func.to_vec(),
+            cdf,
+            func_int,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.func.len()
+    }
+
+    // Returns the sample in [0, 1), its pdf and the index of the segment it falls in
+    pub fn sample_continuous(&self, u: f32) -> (f32, f32, usize) {
+        let offset = find_interval(self.cdf.len(), |index| self.cdf[index] <= u);
+        let mut du = u - self.cdf[offset];
+        if self.cdf[offset + 1] - self.cdf[offset] > 0.0 {
+            du /= self.cdf[offset + 1] - self.cdf[offset];
+        }
+        let pdf = if self.func_int > 0.0 {
+            self.func[offset] / self.func_int
+        } else {
+            0.0
+        };
+        ((offset as f32 + du) / self.count() as f32, pdf, offset)
+    }
+
+    // Picks a segment with probability proportional to its value, returns it with that probability
+    pub fn sample_discrete(&self, u: f32) -> (usize, f32) {
+        let offset = find_interval(self.cdf.len(), |index| self.cdf[index] <= u);
+        (offset, self.discrete_pdf(offset))
+    }
+
+    pub fn discrete_pdf(&self, index: usize) -> f32 {
+        if self.func_int == 0.0 {
+            return 0.0;
+        }
+        self.func[index] / (self.func_int * self.count() as f32)
+    }
+}
+
+// Largest index in [0, size - 2] for which pred holds, pred must be true at 0 and switch
+// to false at most once
+pub fn find_interval(size: usize, pred: impl Fn(usize) -> bool) -> usize {
+    let mut first = 0;
+    let mut len = size;
+    while len > 0 {
+        let half = len >> 1;
+        let middle = first + half;
+        if pred(middle) {
+            first = middle + 1;
+            len -= half + 1;
+        } else {
+            len = half;
+        }
+    }
+    clamp(first as i64 - 1, 0, size as i64 - 2) as usize
+}
+
+// Piecewise constant function over [0, 1]^2 tabulated on nu x nv cells, row by row
+pub struct Distribution2D {
+    conditional: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    pub fn new(func: &[f32], nu: usize, nv: usize) -> Distribution2D {
+        let conditional = func
+            .chunks(nu)
+            .take(nv)
+            .map(Distribution1D::new)
+            .collect::<Vec<_>>();
+        let marginal_func = conditional
+            .iter()
+            .map(|distribution| distribution.func_int)
+            .collect::<Vec<_>>();
+        Distribution2D {
+            conditional,
+            marginal: Distribution1D::new(&marginal_func),
+        }
+    }
+
+    pub fn sample_continuous(&self, u: &Point2) -> (Point2, f32) {
+        let (d1, pdf1, v) = self.marginal.sample_continuous(u.y);
+        let (d0, pdf0, _) = self.conditional[v].sample_continuous(u.x);
+        (Point2::new(d0, d1), pdf0 * pdf1)
+    }
+
+    pub fn pdf(&self, p: &Point2) -> f32 {
+        let nu = self.conditional[0].count();
+        let nv = self.marginal.count();
+        let iu = clamp((p.x * nu as f32) as i64, 0, nu as i64 - 1) as usize;
+        let iv = clamp((p.y * nv as f32) as i64, 0, nv as i64 - 1) as usize;
+        if self.marginal.func_int == 0.0 {
+            return 0.0;
+        }
+        self.conditional[iv].func[iu] / self.marginal.func_int
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn discrete_pdf_matches_samples() {
+        let func = [0.0, 1.0, 3.0, 0.0, 2.0];
+        let distribution = Distribution1D::new(&func);
+        let total: f32 = func.iter().sum();
+        let sum: f32 = (0..func.len()).map(|i| distribution.discrete_pdf(i)).sum();
+        assert!((sum - 1.0).abs() < 1e-6);
+
+        let n = 1000;
+        let mut counts = [0; 5];
+        for i in 0..n {
+            let u = (i as f32 + 0.5) / n as f32;
+            let (offset, pdf) = distribution.sample_discrete(u);
+            assert_eq!(pdf, func[offset] / total);
+            let (x, _, continuous_offset) = distribution.sample_continuous(u);
+            assert_eq!(continuous_offset, offset);
+            assert_eq!((x * func.len() as f32) as usize, offset);
+            counts[offset] += 1;
+        }
+        for (count, value) in counts.iter().zip(&func) {
+            assert!((*count as f32 / n as f32 - value / total).abs() < 0.002);
+        }
+    }
+
+    #[test]
+    fn distribution_2d_pdf_matches_samples() {
+        let (nu, nv) = (8, 6);
+        // Some cells are empty and must never be sampled
+        let func: Vec<f32> = (0..nu * nv)
+            .map(|i| ((i % nu * 7 + i / nu * 3) % 5) as f32)
+            .collect();
+        let distribution = Distribution2D::new(&func, nu, nv);
+        let total: f32 = func.iter().sum();
+
+        let n = 256;
+        let mut counts = vec![0; nu * nv];
+        for i in 0..n * n {
+            let u = Point2::new(
+                ((i % n) as f32 + 0.5) / n as f32,
+                ((i / n) as f32 + 0.5) / n as f32,
+            );
+            let (p, pdf) = distribution.sample_continuous(&u);
+            assert!(pdf > 0.0);
+            assert!((pdf - distribution.pdf(&p)).abs() <= 1e-5 * pdf);
+            counts[(p.y * nv as f32) as usize * nu + (p.x * nu as f32) as usize] += 1;
+        }
+        for (count, value) in counts.iter().zip(&func) {
+            assert!((*count as f32 / (n * n) as f32 - value / total).abs() < 0.005);
+        }
+
+        // Integrating the pdf over the cells gives one
+        let integral: f32 = (0..nu * nv)
+            .map(|i| {
+                let p = Point2::new(
+                    ((i % nu) as f32 + 0.5) / nu as f32,
+                    ((i / nu) as f32 + 0.5) / nv as f32,
+                );
+                distribution.pdf(&p) / (nu * nv) as f32
+            })
+            .sum();
+        assert!((integral - 1.0).abs() < 1e-5);
+    }
+}
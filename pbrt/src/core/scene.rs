@@ -1,33 +1,71 @@
 use crate::accelerators;
 use crate::core::*;
 use crate::lights;
-use crate::math::Vec3;
+use crate::math::Bounds3Df;
 use crate::ray::*;
+use crate::samplers::Sampler;
+use crate::spectrum::Spectrum;
+use std::sync::Arc;
 
 pub struct Scene {
-    pub lights: Vec<Box<lights::Light>>,
-    aggregate: Box<accelerators::Primitive>,
+    pub lights: Vec<Arc<dyn lights::Light>>,
+    aggregate: Box<dyn accelerators::Primitive>,
 
-    world_bound: Vec3,
+    world_bound: Bounds3Df,
 }
 
 impl Scene {
-    fn new(lights: Vec<Box<lights::Light>>, aggregate: Box<accelerators::Primitive>) -> Scene {
+    pub fn new(
+        lights: Vec<Arc<dyn lights::Light>>,
+        aggregate: Box<dyn accelerators::Primitive>,
+    ) -> Scene {
         let world_bound = aggregate.world_bounds();
-
-        for light in &lights {
-            light.preprocess();
-        }
-
-        Scene {
+        let scene = Scene {
             lights,
             aggregate,
             world_bound,
+        };
+        // Lights may need to know about the finished scene, like its extent
+        for light in &scene.lights {
+            light.preprocess(&scene);
         }
+        scene
+    }
+
+    pub fn world_bound(&self) -> &Bounds3Df {
+        &self.world_bound
     }
 
-    pub fn intersect(&self, ray: &Ray) -> Option<SurfaceInteraction> {
-        None // TODO: implement me
+    pub fn intersect(&self, ray: &Ray) -> Option<SurfaceInteraction<'_>> {
+        self.aggregate.intersect(ray)
+    }
+
+    // Cheaper test for any intersection along the ray, used for shadow rays
+    pub fn intersect_p(&self, ray: &Ray) -> bool {
+        self.aggregate.intersect_p(ray)
+    }
+
+    // First intersection with a surface that has a material, passing through the surfaces
+    // that only separate media, along with the transmittance up to it
+    pub fn intersect_tr(
+        &self,
+        ray: &Ray,
+        sampler: &mut dyn Sampler,
+    ) -> (Option<SurfaceInteraction<'_>>, Spectrum) {
+        let mut ray = ray.clone();
+        let mut tr = Spectrum::from_value(1.0);
+        loop {
+            let isect = self.intersect(&ray);
+            if let Some(medium) = &ray.medium {
+                tr *= medium.tr(&ray, sampler);
+            }
+            match isect {
+                None => return (None, tr),
+                Some(isect) if isect.primitive.and_then(|p| p.material()).is_some() => {
+                    return (Some(isect), tr)
+                }
+                Some(isect) => ray = isect.interaction.spawn_ray(ray.d),
+            }
+        }
     }
-    // IntersectP 24
 }
@@ -0,0 +1,187 @@
+use crate::core::{ParamSet, SurfaceInteraction, TextureMap, TextureParams};
+use crate::math::*;
+use crate::spectrum::{Spectrum, SpectrumType};
+use std::f32::consts::PI;
+use std::ops::{Add, Mul};
+
+// Spatially varying value of a material parameter, T is f32 or Spectrum
+pub trait Texture<T>: Send + Sync {
+    fn evaluate(&self, si: &SurfaceInteraction) -> T;
+}
+
+// Values a texture can produce, f32 or Spectrum
+pub trait TextureValue:
+    Copy + Send + Sync + Add<Output = Self> + Mul<Output = Self> + Mul<f32, Output = Self> + 'static
+{
+    // Texture type in scene files
+    const TYPE_NAME: &'static str;
+
+    fn from_value(value: f32) -> Self;
+    fn from_rgb(rgb: [f32; 3]) -> Self;
+    // A constant value given for the parameter
+    fn find_one(params: &ParamSet, name: &str) -> Option<Self>;
+    // The named textures of this type
+    fn textures<'a>(params: &TextureParams<'a>) -> &'a TextureMap<Self>;
+}
+
+impl TextureValue for f32 {
+    const TYPE_NAME: &'static str = "float";
+
+    fn from_value(value: f32) -> f32 {
+        value
+    }
+
+    fn from_rgb(rgb: [f32; 3]) -> f32 {
+        (rgb[0] + rgb[1] + rgb[2]) / 3.0
+    }
+
+    fn find_one(params: &ParamSet, name: &str) -> Option<f32> {
+        match params.find_floats(name) {
+            Some([value]) => Some(*value),
+            _ => None,
+        }
+    }
+
+    fn textures<'a>(params: &TextureParams<'a>) -> &'a TextureMap<f32> {
+        params.float_textures
+    }
+}
+
+impl TextureValue for Spectrum {
+    const TYPE_NAME: &'static str = "spectrum";
+
+    fn from_value(value: f32) -> Spectrum {
+        Spectrum::from_value(value)
+    }
+
+    fn from_rgb(rgb: [f32; 3]) -> Spectrum {
+        Spectrum::from_rgb(rgb, SpectrumType::Reflectance)
+    }
+
+    fn find_one(params: &ParamSet, name: &str) -> Option<Spectrum> {
+        match params.find_spectra(name) {
+            Some(spectra) if spectra.len() == 1 => Some(spectra[0]),
+            _ => None,
+        }
+    }
+
+    fn textures<'a>(params: &TextureParams<'a>) -> &'a TextureMap<Spectrum> {
+        params.spectrum_textures
+    }
+}
+
+// Maps a surface point to the (s, t) coordinates 2D textures are defined over
+pub trait TextureMapping2D: Send + Sync {
+    fn map(&self, si: &SurfaceInteraction) -> Point2;
+}
+
+// Scaled and offset surface parameterization
+pub struct UVMapping2D {
+    su: f32,
+    sv: f32,
+    du: f32,
+    dv: f32,
+}
+
+impl UVMapping2D {
+    pub fn new(su: f32, sv: f32, du: f32, dv: f32) -> UVMapping2D {
+        UVMapping2D { su, sv, du, dv }
+    }
+}
+
+impl TextureMapping2D for UVMapping2D {
+    fn map(&self, si: &SurfaceInteraction) -> Point2 {
+        Point2::new(self.su * si.uv.x + self.du, self.sv * si.uv.y + self.dv)
+    }
+}
+
+// Polar angles of the direction from the texture space origin to the point
+pub struct SphericalMapping2D {
+    world_to_texture: Transform,
+}
+
+impl SphericalMapping2D {
+    pub fn new(world_to_texture: Transform) -> SphericalMapping2D {
+        SphericalMapping2D { world_to_texture }
+    }
+}
+
+impl TextureMapping2D for SphericalMapping2D {
+    fn map(&self, si: &SurfaceInteraction) -> Point2 {
+        let v = (self.world_to_texture.transform_point(si.interaction.p)
+            - Point3::new(0.0, 0.0, 0.0))
+        .normalize();
+        Point2::new(
+            spherical_theta(v) * std::f32::consts::FRAC_1_PI,
+            spherical_phi(v) / (2.0 * PI),
+        )
+    }
+}
+
+// Angle around and height along the z axis of texture space
+pub struct CylindricalMapping2D {
+    world_to_texture: Transform,
+}
+
+impl CylindricalMapping2D {
+    pub fn new(world_to_texture: Transform) -> CylindricalMapping2D {
+        CylindricalMapping2D { world_to_texture }
+    }
+}
+
+impl TextureMapping2D for CylindricalMapping2D {
+    fn map(&self, si: &SurfaceInteraction) -> Point2 {
+        let p = self.world_to_texture.transform_point(si.interaction.p);
+        Point2::new((PI + p.y.atan2(p.x)) / (2.0 * PI), p.z)
+    }
+}
+
+// Projection of the point onto the plane spanned by vs and vt
+pub struct PlanarMapping2D {
+    vs: Vec3,
+    vt: Vec3,
+    ds: f32,
+    dt: f32,
+}
+
+impl PlanarMapping2D {
+    pub fn new(vs: Vec3, vt: Vec3, ds: f32, dt: f32) -> PlanarMapping2D {
+        PlanarMapping2D { vs, vt, ds, dt }
+    }
+}
+
+impl TextureMapping2D for PlanarMapping2D {
+    fn map(&self, si: &SurfaceInteraction) -> Point2 {
+        let v = si.interaction.p - Point3::new(0.0, 0.0, 0.0);
+        Point2::new(self.ds + dot(v, self.vs), self.dt + dot(v, self.vt))
+    }
+}
+
+// The "mapping" parameter of a 2D texture with its settings, the error names an unknown mapping
+pub fn create_mapping_2d(
+    texture_to_world: &Transform,
+    params: &ParamSet,
+) -> Result<Box<dyn TextureMapping2D>, String> {
+    let mapping = params.find_one_string("mapping", "uv");
+    match mapping.as_str() {
+        "uv" => Ok(Box::new(UVMapping2D::new(
+            params.find_one_float("uscale", 1.0),
+            params.find_one_float("vscale", 1.0),
+            params.find_one_float("udelta", 0.0),
+            params.find_one_float("vdelta", 0.0),
+        ))),
+        "spherical" => Ok(Box::new(SphericalMapping2D::new(
+            texture_to_world.inverse(),
+        ))),
+        "cylindrical" => Ok(Box::new(CylindricalMapping2D::new(
+            texture_to_world.inverse(),
+        ))),
+        "planar" => Ok(Box::new(PlanarMapping2D::new(
+            params.find_one_vector3("v1", vec3(1.0, 0.0, 0.0)),
+            params.find_one_vector3("v2", vec3(0.0, 1.0, 0.0)),
+            params.find_one_float("udelta", 0.0),
+            params.find_one_float("vdelta", 0.0),
+        ))),
+        _ => Err(format!("2D texture mapping \"{}\" unknown", mapping)),
+    }
+}
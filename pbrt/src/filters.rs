@@ -0,0 +1,19 @@
+use crate::math::*;
+
+mod box_filter;
+mod gaussian_filter;
+mod lanczos_filter;
+mod mitchell_filter;
+mod triangle_filter;
+
+pub use box_filter::BoxFilter;
+pub use gaussian_filter::GaussianFilter;
+pub use lanczos_filter::LanczosSincFilter;
+pub use mitchell_filter::MitchellFilter;
+pub use triangle_filter::TriangleFilter;
+
+// Pixel reconstruction filter, centered at the origin and zero outside of its radius
+pub trait Filter: Send + Sync {
+    fn radius(&self) -> Vec2;
+    fn evaluate(&self, p: Point2) -> f32;
+}
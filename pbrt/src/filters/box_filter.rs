@@ -0,0 +1,28 @@
+use super::*;
+use crate::core::ParamSet;
+
+pub struct BoxFilter {
+    radius: Vec2,
+}
+
+impl BoxFilter {
+    pub fn new(radius: Vec2) -> BoxFilter {
+        BoxFilter { radius }
+    }
+
+    pub fn create(params: &ParamSet) -> BoxFilter {
+        let x_width = params.find_one_float("xwidth", 0.5);
+        let y_width = params.find_one_float("ywidth", 0.5);
+        BoxFilter::new(Vec2::new(x_width, y_width))
+    }
+}
+
+impl Filter for BoxFilter {
+    fn radius(&self) -> Vec2 {
+        self.radius
+    }
+
+    fn evaluate(&self, _p: Point2) -> f32 {
+        1.0
+    }
+}
@@ -0,0 +1,42 @@
+use super::*;
+use crate::core::ParamSet;
+
+pub struct GaussianFilter {
+    radius: Vec2,
+    alpha: f32,
+    // Value of the gaussian at the radius, subtracted so the filter goes to zero at its edge
+    exp_x: f32,
+    exp_y: f32,
+}
+
+impl GaussianFilter {
+    pub fn new(radius: Vec2, alpha: f32) -> GaussianFilter {
+        GaussianFilter {
+            radius,
+            alpha,
+            exp_x: (-alpha * radius.x * radius.x).exp(),
+            exp_y: (-alpha * radius.y * radius.y).exp(),
+        }
+    }
+
+    pub fn create(params: &ParamSet) -> GaussianFilter {
+        let x_width = params.find_one_float("xwidth", 2.0);
+        let y_width = params.find_one_float("ywidth", 2.0);
+        let alpha = params.find_one_float("alpha", 2.0);
+        GaussianFilter::new(Vec2::new(x_width, y_width), alpha)
+    }
+
+    fn gaussian(&self, d: f32, exp_v: f32) -> f32 {
+        max(0.0, (-self.alpha * d * d).exp() - exp_v)
+    }
+}
+
+impl Filter for GaussianFilter {
+    fn radius(&self) -> Vec2 {
+        self.radius
+    }
+
+    fn evaluate(&self, p: Point2) -> f32 {
+        self.gaussian(p.x, self.exp_x) * self.gaussian(p.y, self.exp_y)
+    }
+}
@@ -0,0 +1,48 @@
+use super::*;
+use crate::core::ParamSet;
+use std::f32::consts::PI;
+
+pub struct LanczosSincFilter {
+    radius: Vec2,
+    tau: f32,
+}
+
+impl LanczosSincFilter {
+    pub fn new(radius: Vec2, tau: f32) -> LanczosSincFilter {
+        LanczosSincFilter { radius, tau }
+    }
+
+    pub fn create(params: &ParamSet) -> LanczosSincFilter {
+        let x_width = params.find_one_float("xwidth", 4.0);
+        let y_width = params.find_one_float("ywidth", 4.0);
+        let tau = params.find_one_float("tau", 3.0);
+        LanczosSincFilter::new(Vec2::new(x_width, y_width), tau)
+    }
+
+    fn windowed_sinc(&self, x: f32, radius: f32) -> f32 {
+        let x = x.abs();
+        if x > radius {
+            return 0.0;
+        }
+        sinc(x) * sinc(x / self.tau)
+    }
+}
+
+fn sinc(x: f32) -> f32 {
+    let x = x.abs();
+    if x < 1e-5 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+impl Filter for LanczosSincFilter {
+    fn radius(&self) -> Vec2 {
+        self.radius
+    }
+
+    fn evaluate(&self, p: Point2) -> f32 {
+        self.windowed_sinc(p.x, self.radius.x) * self.windowed_sinc(p.y, self.radius.y)
+    }
+}
@@ -0,0 +1,50 @@
+use super::*;
+use crate::core::ParamSet;
+
+pub struct MitchellFilter {
+    radius: Vec2,
+    b: f32,
+    c: f32,
+}
+
+impl MitchellFilter {
+    pub fn new(radius: Vec2, b: f32, c: f32) -> MitchellFilter {
+        MitchellFilter { radius, b, c }
+    }
+
+    pub fn create(params: &ParamSet) -> MitchellFilter {
+        let x_width = params.find_one_float("xwidth", 2.0);
+        let y_width = params.find_one_float("ywidth", 2.0);
+        let b = params.find_one_float("B", 1.0 / 3.0);
+        let c = params.find_one_float("C", 1.0 / 3.0);
+        MitchellFilter::new(Vec2::new(x_width, y_width), b, c)
+    }
+
+    // Defined over [-1, 1]
+    fn mitchell_1d(&self, x: f32) -> f32 {
+        let (b, c) = (self.b, self.c);
+        let x = (2.0 * x).abs();
+        if x > 1.0 {
+            ((-b - 6.0 * c) * x * x * x
+                + (6.0 * b + 30.0 * c) * x * x
+                + (-12.0 * b - 48.0 * c) * x
+                + (8.0 * b + 24.0 * c))
+                * (1.0 / 6.0)
+        } else {
+            ((12.0 - 9.0 * b - 6.0 * c) * x * x * x
+                + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+                + (6.0 - 2.0 * b))
+                * (1.0 / 6.0)
+        }
+    }
+}
+
+impl Filter for MitchellFilter {
+    fn radius(&self) -> Vec2 {
+        self.radius
+    }
+
+    fn evaluate(&self, p: Point2) -> f32 {
+        self.mitchell_1d(p.x / self.radius.x) * self.mitchell_1d(p.y / self.radius.y)
+    }
+}
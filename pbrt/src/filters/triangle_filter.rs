@@ -0,0 +1,28 @@
+use super::*;
+use crate::core::ParamSet;
+
+pub struct TriangleFilter {
+    radius: Vec2,
+}
+
+impl TriangleFilter {
+    pub fn new(radius: Vec2) -> TriangleFilter {
+        TriangleFilter { radius }
+    }
+
+    pub fn create(params: &ParamSet) -> TriangleFilter {
+        let x_width = params.find_one_float("xwidth", 2.0);
+        let y_width = params.find_one_float("ywidth", 2.0);
+        TriangleFilter::new(Vec2::new(x_width, y_width))
+    }
+}
+
+impl Filter for TriangleFilter {
+    fn radius(&self) -> Vec2 {
+        self.radius
+    }
+
+    fn evaluate(&self, p: Point2) -> f32 {
+        max(0.0, self.radius.x - p.x.abs()) * max(0.0, self.radius.y - p.y.abs())
+    }
+}
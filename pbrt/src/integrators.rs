@@ -1,11 +1,260 @@
-use crate::core::Scene;
+use crate::core::reflection::BxDFType;
+use crate::core::reflection::BSDF_TYPES::*;
+use crate::core::sampling::{power_heuristic, Distribution1D};
+use crate::core::{
+    Interaction, MediumInteraction, ParallelOptions, PhaseFunction, Scene, SurfaceInteraction,
+};
+use crate::lights::{is_delta_light, AreaLight, Light};
+use crate::math::*;
+use crate::ray::RayDifferential;
+use crate::samplers::Sampler;
+use crate::spectrum::Spectrum;
 
+mod directlighting_integrator;
+mod path_integrator;
 mod sample_integrator;
+mod volpath_integrator;
 mod whitted_integrator;
 
-pub use sample_integrator::SampleIntegrator;
+pub use directlighting_integrator::DirectLightingIntegrator;
+pub use path_integrator::PathIntegrator;
+pub use volpath_integrator::VolPathIntegrator;
 pub use whitted_integrator::WhittedIntegrator;
 
 pub trait Integrator {
-    fn render(&self, scene: &Scene) -> ();
+    fn render(&self, scene: &Scene, options: &ParallelOptions);
+}
+
+// Point light is scattered at, on a surface by its BSDF or inside a medium by its phase
+// function
+pub enum ScatteringInteraction<'b, 'a> {
+    Surface(&'b SurfaceInteraction<'a>),
+    Medium(&'b MediumInteraction),
+}
+
+impl ScatteringInteraction<'_, '_> {
+    pub fn interaction(&self) -> &Interaction {
+        match self {
+            ScatteringInteraction::Surface(isect) => &isect.interaction,
+            ScatteringInteraction::Medium(mi) => &mi.interaction,
+        }
+    }
+
+    // Scattered light from wi to wo, including the cosine factor on surfaces
+    fn f(&self, wo: &Vec3, wi: &Vec3, flags: BxDFType) -> Spectrum {
+        match self {
+            ScatteringInteraction::Surface(isect) => match &isect.bsdf {
+                Some(bsdf) => bsdf.f(wo, wi, flags) * dot(*wi, isect.shading.n).abs(),
+                None => Spectrum::new(),
+            },
+            ScatteringInteraction::Medium(mi) => Spectrum::from_value(mi.phase.p(wo, wi)),
+        }
+    }
+
+    fn pdf(&self, wo: &Vec3, wi: &Vec3, flags: BxDFType) -> f32 {
+        match self {
+            ScatteringInteraction::Surface(isect) => match &isect.bsdf {
+                Some(bsdf) => bsdf.pdf(wo, wi, flags),
+                None => 0.0,
+            },
+            ScatteringInteraction::Medium(mi) => mi.phase.p(wo, wi),
+        }
+    }
+
+    // Sampled wi with f, its pdf and whether a specular lobe was sampled
+    fn sample_f(&self, wo: &Vec3, u: &Point2, flags: BxDFType) -> (Spectrum, Vec3, f32, bool) {
+        match self {
+            ScatteringInteraction::Surface(isect) => match &isect.bsdf {
+                Some(bsdf) => {
+                    let (f, wi, pdf, sampled_type) = bsdf.sample_f(wo, u, flags);
+                    let f = f * dot(wi, isect.shading.n).abs();
+                    (f, wi, pdf, sampled_type.intersects(BSDF_SPECULAR))
+                }
+                None => (Spectrum::new(), Vec3::zero(), 0.0, false),
+            },
+            ScatteringInteraction::Medium(mi) => {
+                let (p, wi) = mi.phase.sample_p(wo, u);
+                (Spectrum::from_value(p), wi, p, false)
+            }
+        }
+    }
+}
+
+// Direct lighting at the point from every light, averaging n_light_samples[i] samples of
+// light i. They come from the sample arrays requested in preprocess, past the depth those
+// were requested for a single sample is taken instead
+pub fn uniform_sample_all_lights(
+    it: &ScatteringInteraction,
+    scene: &Scene,
+    sampler: &mut dyn Sampler,
+    n_light_samples: &[usize],
+    handle_media: bool,
+) -> Spectrum {
+    let mut L = Spectrum::new();
+    for (light, &n_samples) in scene.lights.iter().zip(n_light_samples) {
+        // Copied so the sampler can be used again while estimating
+        let u_light_array = sampler.get_2d_array(n_samples).map(<[Point2]>::to_vec);
+        let u_scattering_array = sampler.get_2d_array(n_samples).map(<[Point2]>::to_vec);
+        match (u_light_array, u_scattering_array) {
+            (Some(u_light_array), Some(u_scattering_array)) => {
+                let mut ld = Spectrum::new();
+                for (u_light, u_scattering) in u_light_array.iter().zip(&u_scattering_array) {
+                    ld += estimate_direct(
+                        it,
+                        u_scattering,
+                        &**light,
+                        u_light,
+                        scene,
+                        sampler,
+                        false,
+                        handle_media,
+                    );
+                }
+                L += ld / n_samples as f32;
+            }
+            _ => {
+                let u_light = sampler.get_2d();
+                let u_scattering = sampler.get_2d();
+                L += estimate_direct(
+                    it,
+                    &u_scattering,
+                    &**light,
+                    &u_light,
+                    scene,
+                    sampler,
+                    false,
+                    handle_media,
+                );
+            }
+        }
+    }
+    L
+}
+
+// Direct lighting at the point from a single light, picked with light_distribution or
+// uniformly without one, divided by the probability of picking it. With handle_media the
+// light is attenuated by the media it passes through instead of only checked for occluders
+pub fn uniform_sample_one_light(
+    it: &ScatteringInteraction,
+    scene: &Scene,
+    sampler: &mut dyn Sampler,
+    handle_media: bool,
+    light_distribution: Option<&Distribution1D>,
+) -> Spectrum {
+    let n_lights = scene.lights.len();
+    if n_lights == 0 {
+        return Spectrum::new();
+    }
+    let (light_num, light_pdf) = match light_distribution {
+        Some(distribution) => distribution.sample_discrete(sampler.get_1d()),
+        None => (
+            std::cmp::min((sampler.get_1d() * n_lights as f32) as usize, n_lights - 1),
+            1.0 / n_lights as f32,
+        ),
+    };
+    if light_pdf == 0.0 {
+        return Spectrum::new();
+    }
+    let light = &*scene.lights[light_num];
+    let u_light = sampler.get_2d();
+    let u_scattering = sampler.get_2d();
+    estimate_direct(
+        it,
+        &u_scattering,
+        light,
+        &u_light,
+        scene,
+        sampler,
+        false,
+        handle_media,
+    ) / light_pdf
+}
+
+// Direct lighting from one light, combining a light sample and a sample of the BSDF or
+// phase function with multiple importance sampling
+pub fn estimate_direct(
+    it: &ScatteringInteraction,
+    u_scattering: &Point2,
+    light: &dyn Light,
+    u_light: &Point2,
+    scene: &Scene,
+    sampler: &mut dyn Sampler,
+    specular: bool,
+    handle_media: bool,
+) -> Spectrum {
+    let bsdf_flags = if specular {
+        BxDFType::all()
+    } else {
+        BxDFType::all() & !BxDFType::from(BSDF_SPECULAR)
+    };
+    let interaction = it.interaction();
+    let wo = interaction.wo;
+    let mut ld = Spectrum::new();
+
+    // Sample the light
+    let (mut li, wi, light_pdf, visibility) = light.sample_light_incoming(interaction, u_light);
+    if light_pdf > 0.0 && !li.is_black() {
+        let f = it.f(&wo, &wi, bsdf_flags);
+        let scattering_pdf = it.pdf(&wo, &wi, bsdf_flags);
+        if !f.is_black() {
+            if handle_media {
+                li *= visibility.tr(scene, sampler);
+            } else if !visibility.unoccluded(scene) {
+                li = Spectrum::new();
+            }
+            if !li.is_black() {
+                if is_delta_light(light.flags()) {
+                    ld += f * li / light_pdf;
+                } else {
+                    let weight = power_heuristic(1, light_pdf, 1, scattering_pdf);
+                    ld += f * li * weight / light_pdf;
+                }
+            }
+        }
+    }
+
+    // Sample the BSDF or phase function, delta lights can't be hit this way
+    if !is_delta_light(light.flags()) {
+        let (f, wi, scattering_pdf, sampled_specular) = it.sample_f(&wo, u_scattering, bsdf_flags);
+        if !f.is_black() && scattering_pdf > 0.0 {
+            let mut weight = 1.0;
+            if !sampled_specular {
+                let light_pdf = light.pdf_light_incoming(interaction, &wi);
+                if light_pdf == 0.0 {
+                    return ld;
+                }
+                weight = power_heuristic(1, scattering_pdf, 1, light_pdf);
+            }
+
+            let ray = RayDifferential::from(interaction.spawn_ray(wi));
+            let (light_isect, tr) = if handle_media {
+                scene.intersect_tr(&ray.ray, sampler)
+            } else {
+                (scene.intersect(&ray.ray), Spectrum::from_value(1.0))
+            };
+            let li = match light_isect {
+                // Only the light being estimated counts, others get their own samples
+                Some(light_isect) => match light_isect.primitive.and_then(|p| p.area_light()) {
+                    Some(area_light) if is_same_light(area_light, light) => {
+                        light_isect.light_emission(&-wi)
+                    }
+                    _ => Spectrum::new(),
+                },
+                None => light.light_emission(&ray),
+            };
+            if !li.is_black() {
+                ld += f * li * tr * weight / scattering_pdf;
+            }
+        }
+    }
+    ld
+}
+
+// Area lights reach the scene's light list and the primitives as separate trait objects,
+// so only the data they point to can tell whether they are the same
+fn is_same_light(area_light: &dyn AreaLight, light: &dyn Light) -> bool {
+    std::ptr::eq(
+        area_light as *const dyn AreaLight as *const u8,
+        light as *const dyn Light as *const u8,
+    )
 }
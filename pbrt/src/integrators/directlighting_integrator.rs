@@ -0,0 +1,117 @@
+use super::sample_integrator::*;
+use super::{uniform_sample_all_lights, uniform_sample_one_light, ScatteringInteraction};
+use crate::cameras::Camera;
+use crate::core::*;
+use crate::ray::*;
+use crate::samplers::Sampler;
+use crate::spectrum::Spectrum;
+use std::sync::OnceLock;
+
+#[derive(Clone, Copy, PartialEq)]
+enum LightStrategy {
+    // Every light with its own number of samples
+    UniformSampleAll,
+    // One randomly picked light per intersection
+    UniformSampleOne,
+}
+
+// Direct lighting only, plus perfectly specular reflection and transmission
+pub struct DirectLightingIntegrator {
+    strategy: LightStrategy,
+    max_depth: i32,
+    // Samples taken for each light, rounded to what the sampler supports
+    n_light_samples: OnceLock<Vec<usize>>,
+}
+
+impl DirectLightingIntegrator {
+    pub fn create(
+        params: &ParamSet,
+        sampler: Box<dyn Sampler>,
+        camera: Box<dyn Camera>,
+    ) -> SampleIntegrator {
+        let strategy = match params.find_one_string("strategy", "all").as_str() {
+            "all" => LightStrategy::UniformSampleAll,
+            "one" => LightStrategy::UniformSampleOne,
+            name => {
+                eprintln!(
+                    "warning: strategy \"{}\" for direct lighting unknown, using \"all\"",
+                    name
+                );
+                LightStrategy::UniformSampleAll
+            }
+        };
+        let integrator = DirectLightingIntegrator {
+            strategy,
+            max_depth: params.find_one_int("maxdepth", 5),
+            n_light_samples: OnceLock::new(),
+        };
+        SampleIntegrator::new(sampler, camera, Box::new(integrator))
+    }
+}
+
+impl SampleIntegratorInterface for DirectLightingIntegrator {
+    fn preprocess(&self, scene: &Scene, sampler: &mut dyn Sampler) {
+        if self.strategy != LightStrategy::UniformSampleAll {
+            return;
+        }
+        let n_light_samples = self.n_light_samples.get_or_init(|| {
+            scene
+                .lights
+                .iter()
+                .map(|light| sampler.round_count(light.n_samples() as usize))
+                .collect()
+        });
+        // Light and BSDF samples for every light at every depth
+        for _ in 0..self.max_depth {
+            for &n_samples in n_light_samples {
+                sampler.request_2d_array(n_samples);
+                sampler.request_2d_array(n_samples);
+            }
+        }
+    }
+
+    fn light_incoming(
+        &self,
+        sample_integrator: &SampleIntegrator,
+        ray: &RayDifferential,
+        scene: &Scene,
+        sampler: &mut dyn Sampler,
+        // Memory Arena
+        depth: i32,
+    ) -> Spectrum {
+        let mut L = Spectrum::new();
+        let maybe_isect = scene.intersect(&ray.ray);
+        if maybe_isect.is_none() {
+            for light in scene.lights.iter() {
+                L += light.light_emission(ray);
+            }
+            return L;
+        }
+        let mut isect = maybe_isect.unwrap();
+        let wo = isect.interaction.wo;
+        isect.compute_scattering_functions(ray, false);
+        // Surfaces without a material only separate media, continue past them
+        if isect.bsdf.is_none() {
+            let ray = RayDifferential::from(isect.interaction.spawn_ray(ray.ray.d));
+            return self.light_incoming(sample_integrator, &ray, scene, sampler, depth);
+        }
+        L += isect.light_emission(&wo);
+
+        if !scene.lights.is_empty() {
+            let it = ScatteringInteraction::Surface(&isect);
+            L += match self.n_light_samples.get() {
+                Some(n_light_samples) => {
+                    uniform_sample_all_lights(&it, scene, sampler, n_light_samples, false)
+                }
+                None => uniform_sample_one_light(&it, scene, sampler, false, None),
+            };
+        }
+
+        if depth + 1 < self.max_depth {
+            L += sample_integrator.specular_reflect(ray, &isect, scene, sampler, depth);
+            L += sample_integrator.specular_transmit(ray, &isect, scene, sampler, depth);
+        }
+
+        L
+    }
+}
@@ -0,0 +1,247 @@
+use super::sample_integrator::*;
+use super::{uniform_sample_one_light, ScatteringInteraction};
+use crate::cameras::Camera;
+use crate::core::lightdistrib::{create_light_sample_distribution, LightDistribution};
+use crate::core::reflection::BxDFType;
+use crate::core::reflection::BSDF_TYPES::*;
+use crate::core::*;
+use crate::math::*;
+use crate::ray::*;
+use crate::samplers::Sampler;
+use crate::spectrum::Spectrum;
+use std::sync::OnceLock;
+
+// Unidirectional path tracer with next event estimation
+pub struct PathIntegrator {
+    max_depth: i32,
+    // Paths whose throughput falls below this are randomly terminated
+    rr_threshold: f32,
+    // Bounces before Russian roulette starts
+    rr_depth: i32,
+    light_sample_strategy: String,
+    // Built in preprocess once the scene's lights are known
+    light_distribution: OnceLock<Box<dyn LightDistribution>>,
+}
+
+impl PathIntegrator {
+    pub fn create(
+        params: &ParamSet,
+        sampler: Box<dyn Sampler>,
+        camera: Box<dyn Camera>,
+    ) -> SampleIntegrator {
+        let integrator = PathIntegrator {
+            max_depth: params.find_one_int("maxdepth", 5),
+            rr_threshold: params.find_one_float("rrthreshold", 1.0),
+            rr_depth: params.find_one_int("rrdepth", 3),
+            light_sample_strategy: params.find_one_string("lightsamplestrategy", "power"),
+            light_distribution: OnceLock::new(),
+        };
+        SampleIntegrator::new(sampler, camera, Box::new(integrator))
+    }
+}
+
+impl SampleIntegratorInterface for PathIntegrator {
+    fn preprocess(&self, scene: &Scene, _sampler: &mut dyn Sampler) {
+        self.light_distribution.get_or_init(|| {
+            create_light_sample_distribution(&self.light_sample_strategy, scene).unwrap_or_else(
+                |err| {
+                    eprintln!("warning: {}, using \"power\"", err);
+                    create_light_sample_distribution("power", scene).unwrap()
+                },
+            )
+        });
+    }
+
+    fn light_incoming(
+        &self,
+        _sample_integrator: &SampleIntegrator,
+        ray: &RayDifferential,
+        scene: &Scene,
+        sampler: &mut dyn Sampler,
+        // Memory Arena
+        _depth: i32,
+    ) -> Spectrum {
+        let mut L = Spectrum::new();
+        let mut beta = Spectrum::from_value(1.0);
+        let mut ray = ray.clone();
+        let mut specular_bounce = false;
+        // Radiance scale from refraction, which Russian roulette should not see
+        let mut eta_scale = 1.0;
+        let mut bounces = 0;
+        loop {
+            let maybe_isect = scene.intersect(&ray.ray);
+
+            // Emission is only added where light sampling at the previous vertex couldn't
+            // account for it
+            if bounces == 0 || specular_bounce {
+                match &maybe_isect {
+                    Some(isect) => L += beta * isect.light_emission(&-ray.ray.d),
+                    None => {
+                        for light in scene.lights.iter() {
+                            L += beta * light.light_emission(&ray);
+                        }
+                    }
+                }
+            }
+
+            let mut isect = match maybe_isect {
+                Some(isect) if bounces < self.max_depth => isect,
+                _ => break,
+            };
+            isect.compute_scattering_functions(&ray, true);
+            // Surfaces without a material only separate media, continue past them
+            let bsdf = match &isect.bsdf {
+                Some(bsdf) => bsdf,
+                None => {
+                    ray = RayDifferential::from(isect.interaction.spawn_ray(ray.ray.d));
+                    continue;
+                }
+            };
+
+            // Light sampling is pointless for perfectly specular surfaces
+            let non_specular = BxDFType::all() & !BxDFType::from(BSDF_SPECULAR);
+            if bsdf.num_components(non_specular) > 0 {
+                let distribution = self
+                    .light_distribution
+                    .get()
+                    .map(|distribution| distribution.lookup(&isect.interaction.p));
+                L += beta
+                    * uniform_sample_one_light(
+                        &ScatteringInteraction::Surface(&isect),
+                        scene,
+                        sampler,
+                        false,
+                        distribution,
+                    );
+            }
+
+            let wo = isect.interaction.wo;
+            let (f, wi, pdf, flags) = bsdf.sample_f(&wo, &sampler.get_2d(), BxDFType::all());
+            if f.is_black() || pdf == 0.0 {
+                break;
+            }
+            beta *= f * dot(wi, isect.shading.n).abs() / pdf;
+            specular_bounce = flags.intersects(BSDF_SPECULAR);
+            if flags.intersects(BSDF_SPECULAR) && flags.intersects(BSDF_TRANSMISSION) {
+                let eta = bsdf.eta;
+                eta_scale *= if dot(wo, isect.interaction.n) > 0.0 {
+                    eta * eta
+                } else {
+                    1.0 / (eta * eta)
+                };
+            }
+            ray = RayDifferential::from(isect.interaction.spawn_ray(wi));
+
+            // Terminate low contribution paths with probability q, the survivors carry
+            // their share
+            let rr_beta = beta * eta_scale;
+            if rr_beta.max_component_value() < self.rr_threshold && bounces > self.rr_depth {
+                let q = (1.0 - rr_beta.max_component_value()).max(0.05);
+                if sampler.get_1d() < q {
+                    break;
+                }
+                beta /= 1.0 - q;
+            }
+            bounces += 1;
+        }
+        L
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::api::SceneBuilder;
+    use crate::core::parser::parse_file;
+    use crate::core::ParallelOptions;
+    use crate::spectrum::{Spectrum, SpectrumType};
+
+    // Average of all pixels and channels of the scene rendered with the path integrator
+    fn render_mean(name: &str, integrator: &str, world: &str) -> f32 {
+        let dir = std::env::temp_dir().join(format!("garage_ray_{}_{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let scene = dir.join(format!("{}.pbrt", name));
+        let output = dir.join(format!("{}.exr", name));
+        std::fs::write(
+            &scene,
+            format!(
+                r#"LookAt 0 0 0  0 0 1  0 1 0
+                   Camera "perspective" "float fov" 30
+                   Sampler "sobol" "integer pixelsamples" 256
+                   PixelFilter "box"
+                   Film "image" "integer xresolution" 8 "integer yresolution" 8
+                   Integrator "path" {}
+                   WorldBegin
+                   {}
+                   WorldEnd"#,
+                integrator, world
+            ),
+        )
+        .unwrap();
+
+        let parallel = ParallelOptions {
+            quiet: true,
+            ..ParallelOptions::default()
+        };
+        let mut builder = SceneBuilder::new(Some(output.to_str().unwrap().to_string()), parallel);
+        parse_file(&scene, &mut builder).unwrap();
+        let image = garage_ray_imageio::load_image(&output).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let sum: f32 = image.pixels.iter().flatten().sum();
+        sum / (3 * image.pixels.len()) as f32
+    }
+
+    #[test]
+    fn furnace_converges_to_the_analytic_radiance() {
+        // Inside a closed sphere emitting L with albedo a every point sees
+        // L * (1 + a + a^2 + ...) = L / (1 - a). Light sampling, BSDF sampling and Russian
+        // roulette all have to be weighted right to get there
+        let mean = render_mean(
+            "furnace",
+            r#""integer maxdepth" 100 "integer rrdepth" 1"#,
+            r#"AttributeBegin
+                 ReverseOrientation
+                 Material "matte" "rgb Kd" [ .5 .5 .5 ]
+                 AreaLightSource "diffuse" "rgb L" [ 1 1 1 ]
+                 Shape "sphere" "float radius" 10
+               AttributeEnd"#,
+        );
+        // Computed from the spectra, grey RGB values aren't quite flat with sampled spectra
+        let emitted = Spectrum::from_rgb([1.0, 1.0, 1.0], SpectrumType::Reflectance);
+        let albedo = Spectrum::from_rgb([0.5, 0.5, 0.5], SpectrumType::Reflectance);
+        let expected = (emitted / (Spectrum::from_value(1.0) - albedo)).to_rgb();
+        let expected = expected.iter().sum::<f32>() / 3.0;
+        assert!(
+            (mean - expected).abs() < 0.01 * expected,
+            "furnace radiance {}, expected {}",
+            mean,
+            expected
+        );
+    }
+
+    #[test]
+    fn light_strategies_converge_to_the_same_image() {
+        // Lights of very different power, so the strategies pick them very differently
+        let world = r#"LightSource "point" "rgb I" [ 50 50 50 ] "point from" [ 2 2 3 ]
+                       LightSource "point" "rgb I" [ .5 .5 .5 ] "point from" [ -2 -1 3 ]
+                       AttributeBegin
+                         AreaLightSource "diffuse" "rgb L" [ 2 2 2 ]
+                         Translate 0 3 5
+                         Shape "sphere" "float radius" .5
+                       AttributeEnd
+                       Material "matte" "rgb Kd" [ .5 .5 .5 ]
+                       Translate 0 0 6
+                       Shape "sphere" "float radius" 2"#;
+        let uniform = render_mean(
+            "uniform",
+            r#""string lightsamplestrategy" "uniform""#,
+            world,
+        );
+        let power = render_mean("power", r#""string lightsamplestrategy" "power""#, world);
+        assert!(
+            (uniform - power).abs() < 0.02 * power,
+            "uniform {} and power {}",
+            uniform,
+            power
+        );
+    }
+}
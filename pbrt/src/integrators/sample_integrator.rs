@@ -8,16 +8,16 @@ use crate::samplers::Sampler;
 use crate::spectrum::Spectrum;
 
 pub struct SampleIntegrator {
-    sampler: Box<Sampler>,
-    camera: Box<Camera>,
-    implementor: Box<SampleIntegratorInterface>,
+    sampler: Box<dyn Sampler>,
+    camera: Box<dyn Camera>,
+    implementor: Box<dyn SampleIntegratorInterface>,
 }
 
 impl SampleIntegrator {
-    fn new(
-        sampler: Box<Sampler>,
-        camera: Box<Camera>,
-        implementor: Box<SampleIntegratorInterface>,
+    pub fn new(
+        sampler: Box<dyn Sampler>,
+        camera: Box<dyn Camera>,
+        implementor: Box<dyn SampleIntegratorInterface>,
     ) -> SampleIntegrator {
         SampleIntegrator {
             sampler,
@@ -28,24 +28,26 @@ impl SampleIntegrator {
 
     pub fn specular_reflect(
         &self,
-        ray: &RayDifferential,
+        _ray: &RayDifferential,
         isect: &SurfaceInteraction,
         scene: &Scene,
-        sampler: &mut Sampler,
+        sampler: &mut dyn Sampler,
         depth: i32,
     ) -> Spectrum {
+        let bsdf = match &isect.bsdf {
+            Some(bsdf) => bsdf,
+            None => return Spectrum::new(),
+        };
         let wo = isect.interaction.wo;
-        let (f, wi, pdf) =
-            isect
-                .bsdf
-                .sample_f(&wo, &sampler.get_2d(), BSDF_REFLECTION | BSDF_SPECULAR);
+        let (f, wi, pdf, _) =
+            bsdf.sample_f(&wo, &sampler.get_2d(), BSDF_REFLECTION | BSDF_SPECULAR);
         let ns = isect.shading.n;
         if pdf > 0.0 && !f.is_black() && (dot(wi, ns).abs() != 0.0) {
             // Compute ray differential rd for specular
-            // ray should be from upper line
+            let rd = RayDifferential::from(isect.interaction.spawn_ray(wi));
             f * self
                 .implementor
-                .light_incoming(self, ray, scene, sampler, depth + 1)
+                .light_incoming(self, &rd, scene, sampler, depth + 1)
                 * dot(wi, ns).abs()
                 / pdf
         } else {
@@ -55,24 +57,26 @@ impl SampleIntegrator {
 
     pub fn specular_transmit(
         &self,
-        ray: &RayDifferential,
+        _ray: &RayDifferential,
         isect: &SurfaceInteraction,
         scene: &Scene,
-        sampler: &mut Sampler,
+        sampler: &mut dyn Sampler,
         depth: i32,
     ) -> Spectrum {
+        let bsdf = match &isect.bsdf {
+            Some(bsdf) => bsdf,
+            None => return Spectrum::new(),
+        };
         let wo = isect.interaction.wo;
-        let (f, wi, pdf) =
-            isect
-                .bsdf
-                .sample_f(&wo, &sampler.get_2d(), BSDF_TRANSMISSION | BSDF_SPECULAR);
+        let (f, wi, pdf, _) =
+            bsdf.sample_f(&wo, &sampler.get_2d(), BSDF_TRANSMISSION | BSDF_SPECULAR);
         let ns = isect.shading.n;
         if pdf > 0.0 && !f.is_black() && (dot(wi, ns).abs() != 0.0) {
             // Compute ray differential rd for specular
-            // ray should be from upper line
+            let rd = RayDifferential::from(isect.interaction.spawn_ray(wi));
             f * self
                 .implementor
-                .light_incoming(self, ray, scene, sampler, depth + 1)
+                .light_incoming(self, &rd, scene, sampler, depth + 1)
                 * dot(wi, ns).abs()
                 / pdf
         } else {
@@ -81,22 +85,24 @@ impl SampleIntegrator {
     }
 }
 
-pub trait SampleIntegratorInterface {
-    fn preprocess(&self, scene: &Scene, sampler: &Sampler);
+pub trait SampleIntegratorInterface: Send + Sync {
+    // Sample arrays the integrator needs are requested here, before any tile starts
+    fn preprocess(&self, scene: &Scene, sampler: &mut dyn Sampler);
     fn light_incoming(
         &self,
         sample_integrator: &SampleIntegrator,
         ray: &RayDifferential,
         scene: &Scene,
-        sampler: &mut Sampler,
+        sampler: &mut dyn Sampler,
         // Memory Arena
         depth: i32,
     ) -> Spectrum;
 }
 
 impl Integrator for SampleIntegrator {
-    fn render(&self, scene: &Scene) -> () {
-        self.implementor.preprocess(scene, &*self.sampler);
+    fn render(&self, scene: &Scene, options: &ParallelOptions) {
+        let mut sampler = self.sampler.clone(0);
+        self.implementor.preprocess(scene, &mut *sampler);
 
         let sample_bounds = self.camera.film().get_sample_bounds();
         let sample_extent = sample_bounds.diagonal();
@@ -106,12 +112,16 @@ impl Integrator for SampleIntegrator {
             (sample_extent.y + tile_size - 1) / tile_size,
         );
 
-        parallel_for_2d(
+        let progress = ProgressReporter::new(
+            (num_tiles.x * num_tiles.y) as u64,
+            "Rendering",
+            options.quiet,
+        );
+        let finished = parallel_for_2d(
             &|tile| {
                 // Memory Arena
                 let seed = tile.y * num_tiles.x + tile.x;
-                // TODO: this is weird
-                let mut tile_sampler = self.sampler.clone();
+                let mut tile_sampler = sampler.clone(seed);
                 let x0 = sample_bounds.min.x + tile.x * tile_size;
                 let x1 = std::cmp::min(x0 + tile_size, sample_bounds.max.x);
                 let y0 = sample_bounds.min.y + tile.y * tile_size;
@@ -119,9 +129,10 @@ impl Integrator for SampleIntegrator {
                 let tile_bounds =
                     Bounds2Di::from_two_points(Point2i::new(x0, y0), Point2i::new(x1, y1));
 
-                let film_tile = self.camera.film().get_film_tile(tile_bounds);
-                for pixel in film_tile.iter() {
-                    for sample in tile_sampler.start_pixel(pixel) {
+                let mut film_tile = self.camera.film().get_film_tile(tile_bounds);
+                for pixel in tile_bounds.iter() {
+                    tile_sampler.start_pixel(pixel);
+                    loop {
                         let camera_sample = tile_sampler.get_camera_sample(pixel);
 
                         let (mut ray, ray_weight) =
@@ -141,16 +152,24 @@ impl Integrator for SampleIntegrator {
                             );
                         }
                         // Issue warning if unexpected
-                        // TODO: this should be camera_sample.pfilm
-                        film_tile.add_sample(&self.camera.film(), light, ray_weight);
+                        film_tile.add_sample(camera_sample.p_film, light, ray_weight);
+                        if !tile_sampler.start_next_sample() {
+                            break;
+                        }
                     }
                 }
 
                 self.camera.film().merge_film_tile(film_tile);
             },
             Point2i::from_vec(num_tiles),
+            options,
+            &progress,
         );
+        progress.done();
 
+        if !finished {
+            eprintln!("warning: rendering was cancelled, the image is incomplete");
+        }
         self.camera.film().write_image();
     }
 }
@@ -0,0 +1,184 @@
+use super::sample_integrator::*;
+use super::{uniform_sample_one_light, ScatteringInteraction};
+use crate::cameras::Camera;
+use crate::core::lightdistrib::{create_light_sample_distribution, LightDistribution};
+use crate::core::reflection::BxDFType;
+use crate::core::reflection::BSDF_TYPES::*;
+use crate::core::*;
+use crate::math::*;
+use crate::ray::*;
+use crate::samplers::Sampler;
+use crate::spectrum::Spectrum;
+use std::sync::OnceLock;
+
+// Path tracer that also follows light through participating media, sampling scattering
+// points along rays inside media and attenuating shadow rays by their transmittance
+pub struct VolPathIntegrator {
+    max_depth: i32,
+    // Paths whose throughput falls below this are randomly terminated
+    rr_threshold: f32,
+    // Bounces before Russian roulette starts
+    rr_depth: i32,
+    light_sample_strategy: String,
+    // Built in preprocess once the scene's lights are known
+    light_distribution: OnceLock<Box<dyn LightDistribution>>,
+}
+
+impl VolPathIntegrator {
+    pub fn create(
+        params: &ParamSet,
+        sampler: Box<dyn Sampler>,
+        camera: Box<dyn Camera>,
+    ) -> SampleIntegrator {
+        let integrator = VolPathIntegrator {
+            max_depth: params.find_one_int("maxdepth", 5),
+            rr_threshold: params.find_one_float("rrthreshold", 1.0),
+            rr_depth: params.find_one_int("rrdepth", 3),
+            light_sample_strategy: params.find_one_string("lightsamplestrategy", "power"),
+            light_distribution: OnceLock::new(),
+        };
+        SampleIntegrator::new(sampler, camera, Box::new(integrator))
+    }
+}
+
+impl SampleIntegratorInterface for VolPathIntegrator {
+    fn preprocess(&self, scene: &Scene, _sampler: &mut dyn Sampler) {
+        self.light_distribution.get_or_init(|| {
+            create_light_sample_distribution(&self.light_sample_strategy, scene).unwrap_or_else(
+                |err| {
+                    eprintln!("warning: {}, using \"power\"", err);
+                    create_light_sample_distribution("power", scene).unwrap()
+                },
+            )
+        });
+    }
+
+    fn light_incoming(
+        &self,
+        _sample_integrator: &SampleIntegrator,
+        ray: &RayDifferential,
+        scene: &Scene,
+        sampler: &mut dyn Sampler,
+        // Memory Arena
+        _depth: i32,
+    ) -> Spectrum {
+        let mut L = Spectrum::new();
+        let mut beta = Spectrum::from_value(1.0);
+        let mut ray = ray.clone();
+        let mut specular_bounce = false;
+        // Radiance scale from refraction, which Russian roulette should not see
+        let mut eta_scale = 1.0;
+        let mut bounces = 0;
+        loop {
+            // Also shortens the ray to the surface, so the medium is only sampled up to it
+            let maybe_isect = scene.intersect(&ray.ray);
+
+            let mut medium_interaction = None;
+            if let Some(medium) = &ray.ray.medium {
+                let (weight, mi) = medium.sample(&ray.ray, sampler);
+                beta *= weight;
+                medium_interaction = mi;
+            }
+            if beta.is_black() {
+                break;
+            }
+
+            if let Some(mi) = medium_interaction {
+                // Scattering inside the medium
+                if bounces >= self.max_depth {
+                    break;
+                }
+                let distribution = self
+                    .light_distribution
+                    .get()
+                    .map(|distribution| distribution.lookup(&mi.interaction.p));
+                L += beta
+                    * uniform_sample_one_light(
+                        &ScatteringInteraction::Medium(&mi),
+                        scene,
+                        sampler,
+                        true,
+                        distribution,
+                    );
+                // The phase function is its own pdf, so beta is unchanged
+                let (_, wi) = mi.phase.sample_p(&mi.interaction.wo, &sampler.get_2d());
+                ray = RayDifferential::from(mi.interaction.spawn_ray(wi));
+                specular_bounce = false;
+            } else {
+                // Emission is only added where light sampling at the previous vertex
+                // couldn't account for it
+                if bounces == 0 || specular_bounce {
+                    match &maybe_isect {
+                        Some(isect) => L += beta * isect.light_emission(&-ray.ray.d),
+                        None => {
+                            for light in scene.lights.iter() {
+                                L += beta * light.light_emission(&ray);
+                            }
+                        }
+                    }
+                }
+
+                let mut isect = match maybe_isect {
+                    Some(isect) if bounces < self.max_depth => isect,
+                    _ => break,
+                };
+                isect.compute_scattering_functions(&ray, true);
+                // Surfaces without a material only separate media, continue past them
+                let bsdf = match &isect.bsdf {
+                    Some(bsdf) => bsdf,
+                    None => {
+                        ray = RayDifferential::from(isect.interaction.spawn_ray(ray.ray.d));
+                        continue;
+                    }
+                };
+
+                // Light sampling is pointless for perfectly specular surfaces
+                let non_specular = BxDFType::all() & !BxDFType::from(BSDF_SPECULAR);
+                if bsdf.num_components(non_specular) > 0 {
+                    let distribution = self
+                        .light_distribution
+                        .get()
+                        .map(|distribution| distribution.lookup(&isect.interaction.p));
+                    L += beta
+                        * uniform_sample_one_light(
+                            &ScatteringInteraction::Surface(&isect),
+                            scene,
+                            sampler,
+                            true,
+                            distribution,
+                        );
+                }
+
+                let wo = isect.interaction.wo;
+                let (f, wi, pdf, flags) = bsdf.sample_f(&wo, &sampler.get_2d(), BxDFType::all());
+                if f.is_black() || pdf == 0.0 {
+                    break;
+                }
+                beta *= f * dot(wi, isect.shading.n).abs() / pdf;
+                specular_bounce = flags.intersects(BSDF_SPECULAR);
+                if flags.intersects(BSDF_SPECULAR) && flags.intersects(BSDF_TRANSMISSION) {
+                    let eta = bsdf.eta;
+                    eta_scale *= if dot(wo, isect.interaction.n) > 0.0 {
+                        eta * eta
+                    } else {
+                        1.0 / (eta * eta)
+                    };
+                }
+                ray = RayDifferential::from(isect.interaction.spawn_ray(wi));
+            }
+
+            // Terminate low contribution paths with probability q, the survivors carry
+            // their share
+            let rr_beta = beta * eta_scale;
+            if rr_beta.max_component_value() < self.rr_threshold && bounces > self.rr_depth {
+                let q = (1.0 - rr_beta.max_component_value()).max(0.05);
+                if sampler.get_1d() < q {
+                    break;
+                }
+                beta /= 1.0 - q;
+            }
+            bounces += 1;
+        }
+        L
+    }
+}
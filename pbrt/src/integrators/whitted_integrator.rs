@@ -2,7 +2,6 @@ use super::sample_integrator::*;
 use crate::cameras::Camera;
 use crate::core::reflection::BxDFType;
 use crate::core::*;
-use crate::integrators::Integrator;
 use crate::math::*;
 use crate::ray::*;
 use crate::samplers::Sampler;
@@ -12,15 +11,26 @@ pub struct WhittedIntegrator {
     max_depth: i32,
 }
 
+impl WhittedIntegrator {
+    pub fn create(
+        params: &ParamSet,
+        sampler: Box<dyn Sampler>,
+        camera: Box<dyn Camera>,
+    ) -> SampleIntegrator {
+        let max_depth = params.find_one_int("maxdepth", 5);
+        SampleIntegrator::new(sampler, camera, Box::new(WhittedIntegrator { max_depth }))
+    }
+}
+
 impl SampleIntegratorInterface for WhittedIntegrator {
-    fn preprocess(&self, scene: &Scene, sampler: &Sampler) {}
+    fn preprocess(&self, _scene: &Scene, _sampler: &mut dyn Sampler) {}
 
     fn light_incoming(
         &self,
         sample_integrator: &SampleIntegrator,
         ray: &RayDifferential,
         scene: &Scene,
-        sampler: &mut Sampler,
+        sampler: &mut dyn Sampler,
         // Memory Arena
         depth: i32,
     ) -> Spectrum {
@@ -32,19 +42,28 @@ impl SampleIntegratorInterface for WhittedIntegrator {
             }
             return L;
         }
-        let isect = maybe_isect.unwrap();
-        let n = isect.shading.n;
+        let mut isect = maybe_isect.unwrap();
         let wo = isect.interaction.wo;
-        isect.compute_scattering_functions(ray);
+        isect.compute_scattering_functions(ray, false);
+        let n = isect.shading.n;
+        // Surfaces without a material only separate media, continue past them
+        let bsdf = match &isect.bsdf {
+            Some(bsdf) => bsdf,
+            None => {
+                let ray = RayDifferential::from(isect.interaction.spawn_ray(ray.ray.d));
+                return self.light_incoming(sample_integrator, &ray, scene, sampler, depth);
+            }
+        };
         L += isect.light_emission(&wo);
 
         for light in scene.lights.iter() {
-            let (Li, wi, pdf, visibiliy) = light.sample_light_incoming(&isect, &sampler.get_2d());
+            let (Li, wi, pdf, visibiliy) =
+                light.sample_light_incoming(&isect.interaction, &sampler.get_2d());
             if Li.is_black() || pdf == 0.0 {
                 continue;
             }
 
-            let f = isect.bsdf.f(&wo, &wi, BxDFType::all());
+            let f = bsdf.f(&wo, &wi, BxDFType::all());
             if !f.is_black() && visibiliy.unoccluded(scene) {
                 L += f * Li * dot(wi, n).abs() / pdf;
             }
@@ -1,23 +1,101 @@
 use crate::core::*;
 use crate::math::*;
 use crate::ray::RayDifferential;
+use crate::samplers::Sampler;
 use crate::spectrum::Spectrum;
+use bitmask::bitmask;
 
-pub trait Light {
-    fn preprocess(&self) -> ();
-    fn light_emission(&self, ray: &RayDifferential) -> Spectrum;
-    // This should be Interaction and not surfaceinteraction
+mod diffuse;
+mod distant;
+mod infinite;
+mod point;
+mod spot;
+
+pub use diffuse::DiffuseAreaLight;
+pub use distant::DistantLight;
+pub use infinite::InfiniteAreaLight;
+pub use point::PointLight;
+pub use spot::SpotLight;
+
+bitmask! {
+    pub mask LightFlags: u32 where flags LightFlag {
+        // A single point, or a single direction, which can't be hit by chance
+        DeltaPosition = 1 << 0,
+        DeltaDirection = 1 << 1,
+        Area = 1 << 2,
+        Infinite = 1 << 3,
+    }
+}
+
+pub fn is_delta_light(flags: LightFlags) -> bool {
+    flags.intersects(LightFlag::DeltaPosition) || flags.intersects(LightFlag::DeltaDirection)
+}
+
+pub trait Light: Send + Sync {
+    fn flags(&self) -> LightFlags;
+    // Shadow samples the integrators should take for this light
+    fn n_samples(&self) -> i32 {
+        1
+    }
+    fn preprocess(&self, _scene: &Scene) {}
+    // Radiance carried by a ray that left the scene, only infinite lights have any
+    fn light_emission(&self, _ray: &RayDifferential) -> Spectrum {
+        Spectrum::new()
+    }
+    // Radiance arriving at reference from a point on the light picked by u, along with the
+    // direction towards the light, the pdf of that direction with respect to solid angle
+    // and the segment that has to be unoccluded
     fn sample_light_incoming(
         &self,
-        isect: &SurfaceInteraction,
+        reference: &Interaction,
         u: &Point2,
     ) -> (Spectrum, Vec3, f32, VisibiliyTester);
+    // Density sample_light_incoming would pick wi with, always 0 for delta lights
+    fn pdf_light_incoming(&self, reference: &Interaction, wi: &Vec3) -> f32;
+    // Total emitted power
+    fn power(&self) -> Spectrum;
+}
+
+// Light emitted from the surface of a shape
+pub trait AreaLight: Light {
+    // Radiance leaving the point of the light's surface in direction w
+    fn light_emitted(&self, interaction: &Interaction, w: &Vec3) -> Spectrum;
 }
 
-pub struct VisibiliyTester {}
+// Pair of points a light sample is only valid for if nothing lies between them
+pub struct VisibiliyTester {
+    pub p0: Interaction,
+    pub p1: Interaction,
+}
 
 impl VisibiliyTester {
+    pub fn new(p0: Interaction, p1: Interaction) -> VisibiliyTester {
+        VisibiliyTester { p0, p1 }
+    }
+
     pub fn unoccluded(&self, scene: &Scene) -> bool {
-        true
+        !scene.intersect_p(&self.p0.spawn_ray_to(&self.p1))
+    }
+
+    // Transmittance between the points through the media along the way, surfaces that only
+    // separate media are passed through and any other blocks the light
+    pub fn tr(&self, scene: &Scene, sampler: &mut dyn Sampler) -> Spectrum {
+        let mut ray = self.p0.spawn_ray_to(&self.p1);
+        let mut tr = Spectrum::from_value(1.0);
+        loop {
+            let isect = scene.intersect(&ray);
+            if let Some(isect) = &isect {
+                if isect.primitive.and_then(|p| p.material()).is_some() {
+                    return Spectrum::new();
+                }
+            }
+            if let Some(medium) = &ray.medium {
+                tr *= medium.tr(&ray, sampler);
+            }
+            match isect {
+                Some(isect) => ray = isect.interaction.spawn_ray_to(&self.p1),
+                None => return tr,
+            }
+        }
     }
 }
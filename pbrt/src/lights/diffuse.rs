@@ -0,0 +1,92 @@
+use super::*;
+use crate::shapes::Shape;
+use std::sync::Arc;
+
+// Shape emitting the same radiance from every point in every direction of its outside
+pub struct DiffuseAreaLight {
+    emitted: Spectrum,
+    shape: Arc<Shape>,
+    // Emit from the inside of the shape too
+    two_sided: bool,
+    n_samples: i32,
+    area: f32,
+}
+
+impl DiffuseAreaLight {
+    pub fn new(
+        emitted: Spectrum,
+        n_samples: i32,
+        shape: Arc<Shape>,
+        two_sided: bool,
+    ) -> DiffuseAreaLight {
+        DiffuseAreaLight {
+            emitted,
+            area: shape.area(),
+            shape,
+            two_sided,
+            n_samples: n_samples.max(1),
+        }
+    }
+
+    pub fn create(params: &ParamSet, shape: Arc<Shape>) -> DiffuseAreaLight {
+        let emitted = params.find_one_spectrum("L", Spectrum::from_value(1.0));
+        let scale = params.find_one_spectrum("scale", Spectrum::from_value(1.0));
+        let n_samples = params.find_one_int("nsamples", 1);
+        let two_sided = params.find_one_bool("twosided", false);
+        DiffuseAreaLight::new(emitted * scale, n_samples, shape, two_sided)
+    }
+}
+
+impl Light for DiffuseAreaLight {
+    fn flags(&self) -> LightFlags {
+        LightFlag::Area.into()
+    }
+
+    fn n_samples(&self) -> i32 {
+        self.n_samples
+    }
+
+    fn sample_light_incoming(
+        &self,
+        reference: &Interaction,
+        u: &Point2,
+    ) -> (Spectrum, Vec3, f32, VisibiliyTester) {
+        let (p_shape, pdf) = self.shape.sample_from(reference, u);
+        let to_light = p_shape.p - reference.p;
+        if pdf == 0.0 || to_light.magnitude2() == 0.0 {
+            return (
+                Spectrum::new(),
+                Vec3::zero(),
+                0.0,
+                VisibiliyTester::new(reference.clone(), p_shape),
+            );
+        }
+        let wi = to_light.normalize();
+        let radiance = self.light_emitted(&p_shape, &-wi);
+        (
+            radiance,
+            wi,
+            pdf,
+            VisibiliyTester::new(reference.clone(), p_shape),
+        )
+    }
+
+    fn pdf_light_incoming(&self, reference: &Interaction, wi: &Vec3) -> f32 {
+        self.shape.pdf_from(reference, wi)
+    }
+
+    fn power(&self) -> Spectrum {
+        let sides = if self.two_sided { 2.0 } else { 1.0 };
+        sides * self.emitted * self.area * std::f32::consts::PI
+    }
+}
+
+impl AreaLight for DiffuseAreaLight {
+    fn light_emitted(&self, interaction: &Interaction, w: &Vec3) -> Spectrum {
+        if self.two_sided || dot(interaction.n, *w) > 0.0 {
+            self.emitted
+        } else {
+            Spectrum::new()
+        }
+    }
+}
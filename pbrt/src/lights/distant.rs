@@ -0,0 +1,67 @@
+use super::*;
+use std::sync::OnceLock;
+
+// Light arriving from a single direction everywhere, like the sun
+pub struct DistantLight {
+    radiance: Spectrum,
+    // Direction towards the light
+    w_light: Vec3,
+    // Bounding sphere of the scene, known once the scene is built
+    world_sphere: OnceLock<(Point3, f32)>,
+}
+
+impl DistantLight {
+    pub fn new(light_to_world: &Transform, radiance: Spectrum, w: Vec3) -> DistantLight {
+        DistantLight {
+            radiance,
+            w_light: light_to_world.transform_vec(w).normalize(),
+            world_sphere: OnceLock::new(),
+        }
+    }
+
+    pub fn create(light_to_world: &Transform, params: &ParamSet) -> DistantLight {
+        let radiance = params.find_one_spectrum("L", Spectrum::from_value(1.0));
+        let scale = params.find_one_spectrum("scale", Spectrum::from_value(1.0));
+        let from = params.find_one_point3("from", Point3::new(0.0, 0.0, 0.0));
+        let to = params.find_one_point3("to", Point3::new(0.0, 0.0, 1.0));
+        DistantLight::new(light_to_world, radiance * scale, from - to)
+    }
+
+    fn world_radius(&self) -> f32 {
+        self.world_sphere.get().map_or(0.0, |(_, radius)| *radius)
+    }
+}
+
+impl Light for DistantLight {
+    fn flags(&self) -> LightFlags {
+        LightFlag::DeltaDirection.into()
+    }
+
+    fn preprocess(&self, scene: &Scene) {
+        self.world_sphere
+            .get_or_init(|| scene.world_bound().bounding_sphere());
+    }
+
+    fn sample_light_incoming(
+        &self,
+        reference: &Interaction,
+        _u: &Point2,
+    ) -> (Spectrum, Vec3, f32, VisibiliyTester) {
+        // Any point outside the scene in the direction of the light will do
+        let p_outside = reference.p + self.w_light * (2.0 * self.world_radius());
+        let visibility = VisibiliyTester::new(
+            reference.clone(),
+            Interaction::new(p_outside, Vec3::zero(), Vec3::zero(), reference.time),
+        );
+        (self.radiance, self.w_light, 1.0, visibility)
+    }
+
+    fn pdf_light_incoming(&self, _reference: &Interaction, _wi: &Vec3) -> f32 {
+        0.0
+    }
+
+    fn power(&self) -> Spectrum {
+        let radius = self.world_radius();
+        self.radiance * std::f32::consts::PI * radius * radius
+    }
+}
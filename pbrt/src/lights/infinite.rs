@@ -0,0 +1,162 @@
+use super::*;
+use crate::core::sampling::Distribution2D;
+use crate::spectrum::SpectrumType;
+use std::f32::consts::PI;
+use std::sync::OnceLock;
+
+// Light arriving from every direction from infinitely far away, given by an equirectangular
+// map with +z in light space at the top row. Sampled proportionally to the map's brightness
+pub struct InfiniteAreaLight {
+    light_to_world: Transform,
+    world_to_light: Transform,
+    l_map: Vec<Spectrum>,
+    resolution: Point2i,
+    distribution: Distribution2D,
+    n_samples: i32,
+    // Bounding sphere of the scene, known once the scene is built
+    world_sphere: OnceLock<(Point3, f32)>,
+}
+
+impl InfiniteAreaLight {
+    pub fn new(
+        light_to_world: &Transform,
+        l_map: Vec<Spectrum>,
+        resolution: Point2i,
+        n_samples: i32,
+    ) -> InfiniteAreaLight {
+        // Weigh every texel by the solid angle it covers, which shrinks towards the poles
+        let width = resolution.x as usize;
+        let func = l_map
+            .iter()
+            .enumerate()
+            .map(|(i, texel)| {
+                let sin_theta = (PI * ((i / width) as f32 + 0.5) / resolution.y as f32).sin();
+                texel.y().max(0.0) * sin_theta
+            })
+            .collect::<Vec<f32>>();
+        InfiniteAreaLight {
+            light_to_world: *light_to_world,
+            world_to_light: light_to_world.inverse(),
+            distribution: Distribution2D::new(&func, width, resolution.y as usize),
+            l_map,
+            resolution,
+            n_samples: n_samples.max(1),
+            world_sphere: OnceLock::new(),
+        }
+    }
+
+    // The RGB map and its resolution are read by the caller, without one the light is the
+    // constant radiance L
+    pub fn create(
+        light_to_world: &Transform,
+        params: &ParamSet,
+        map: Option<(Vec<[f32; 3]>, Point2i)>,
+    ) -> InfiniteAreaLight {
+        let radiance = params.find_one_spectrum("L", Spectrum::from_value(1.0));
+        let scale = params.find_one_spectrum("scale", Spectrum::from_value(1.0));
+        let n_samples = params.find_one_int("nsamples", 1);
+        let (l_map, resolution) = match map {
+            Some((rgb, resolution)) => (
+                rgb.iter()
+                    .map(|texel| {
+                        Spectrum::from_rgb(*texel, SpectrumType::Illuminant) * radiance * scale
+                    })
+                    .collect(),
+                resolution,
+            ),
+            None => (vec![radiance * scale], Point2i::new(1, 1)),
+        };
+        InfiniteAreaLight::new(light_to_world, l_map, resolution, n_samples)
+    }
+
+    fn lookup(&self, st: Point2) -> Spectrum {
+        let x = clamp(
+            (st.x * self.resolution.x as f32) as i32,
+            0,
+            self.resolution.x - 1,
+        );
+        let y = clamp(
+            (st.y * self.resolution.y as f32) as i32,
+            0,
+            self.resolution.y - 1,
+        );
+        self.l_map[(y * self.resolution.x + x) as usize]
+    }
+
+    // Map coordinates of a world space direction
+    fn direction_to_st(&self, w: &Vec3) -> Point2 {
+        let wi = self.world_to_light.transform_vec(*w).normalize();
+        Point2::new(spherical_phi(wi) / (2.0 * PI), spherical_theta(wi) / PI)
+    }
+
+    fn world_radius(&self) -> f32 {
+        self.world_sphere.get().map_or(0.0, |(_, radius)| *radius)
+    }
+}
+
+impl Light for InfiniteAreaLight {
+    fn flags(&self) -> LightFlags {
+        LightFlag::Infinite.into()
+    }
+
+    fn n_samples(&self) -> i32 {
+        self.n_samples
+    }
+
+    fn preprocess(&self, scene: &Scene) {
+        self.world_sphere
+            .get_or_init(|| scene.world_bound().bounding_sphere());
+    }
+
+    fn light_emission(&self, ray: &RayDifferential) -> Spectrum {
+        self.lookup(self.direction_to_st(&ray.ray.d))
+    }
+
+    fn sample_light_incoming(
+        &self,
+        reference: &Interaction,
+        u: &Point2,
+    ) -> (Spectrum, Vec3, f32, VisibiliyTester) {
+        let (st, map_pdf) = self.distribution.sample_continuous(u);
+        let theta = st.y * PI;
+        let phi = st.x * 2.0 * PI;
+        let (sin_theta, cos_theta) = theta.sin_cos();
+        let wi = self.light_to_world.transform_vec(Vec3::new(
+            sin_theta * phi.cos(),
+            sin_theta * phi.sin(),
+            cos_theta,
+        ));
+        // The map pdf is over [0, 1]^2, the mapping to the sphere stretches it by
+        // 2 pi^2 sin(theta)
+        let pdf = if map_pdf == 0.0 || sin_theta == 0.0 {
+            0.0
+        } else {
+            map_pdf / (2.0 * PI * PI * sin_theta)
+        };
+        let p_outside = reference.p + wi * (2.0 * self.world_radius());
+        let visibility = VisibiliyTester::new(
+            reference.clone(),
+            Interaction::new(p_outside, Vec3::zero(), Vec3::zero(), reference.time),
+        );
+        (self.lookup(st), wi, pdf, visibility)
+    }
+
+    fn pdf_light_incoming(&self, _reference: &Interaction, wi: &Vec3) -> f32 {
+        let st = self.direction_to_st(wi);
+        let sin_theta = (st.y * PI).sin();
+        if sin_theta == 0.0 {
+            return 0.0;
+        }
+        self.distribution.pdf(&st) / (2.0 * PI * PI * sin_theta)
+    }
+
+    fn power(&self) -> Spectrum {
+        let radius = self.world_radius();
+        let average = self
+            .l_map
+            .iter()
+            .fold(Spectrum::new(), |sum, texel| sum + *texel)
+            / self.l_map.len() as f32;
+        PI * radius * radius * average
+    }
+}
@@ -0,0 +1,52 @@
+use super::*;
+
+// Emits the same intensity in every direction from a single point
+pub struct PointLight {
+    p_light: Point3,
+    intensity: Spectrum,
+}
+
+impl PointLight {
+    pub fn new(light_to_world: &Transform, intensity: Spectrum) -> PointLight {
+        PointLight {
+            p_light: light_to_world.transform_point(Point3::new(0.0, 0.0, 0.0)),
+            intensity,
+        }
+    }
+
+    pub fn create(light_to_world: &Transform, params: &ParamSet) -> PointLight {
+        let intensity = params.find_one_spectrum("I", Spectrum::from_value(1.0));
+        let scale = params.find_one_spectrum("scale", Spectrum::from_value(1.0));
+        let from = params.find_one_point3("from", Point3::new(0.0, 0.0, 0.0));
+        let light_to_world = *light_to_world * translate(from.to_vec());
+        PointLight::new(&light_to_world, intensity * scale)
+    }
+}
+
+impl Light for PointLight {
+    fn flags(&self) -> LightFlags {
+        LightFlag::DeltaPosition.into()
+    }
+
+    fn sample_light_incoming(
+        &self,
+        reference: &Interaction,
+        _u: &Point2,
+    ) -> (Spectrum, Vec3, f32, VisibiliyTester) {
+        let wi = (self.p_light - reference.p).normalize();
+        let visibility = VisibiliyTester::new(
+            reference.clone(),
+            Interaction::new(self.p_light, Vec3::zero(), Vec3::zero(), reference.time),
+        );
+        let distance_squared = (self.p_light - reference.p).magnitude2();
+        (self.intensity / distance_squared, wi, 1.0, visibility)
+    }
+
+    fn pdf_light_incoming(&self, _reference: &Interaction, _wi: &Vec3) -> f32 {
+        0.0
+    }
+
+    fn power(&self) -> Spectrum {
+        4.0 * std::f32::consts::PI * self.intensity
+    }
+}
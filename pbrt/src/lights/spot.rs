@@ -0,0 +1,94 @@
+use super::*;
+
+// Point light restricted to a cone, with a smooth falloff towards its edge
+pub struct SpotLight {
+    p_light: Point3,
+    world_to_light: Transform,
+    intensity: Spectrum,
+    cos_total_width: f32,
+    cos_falloff_start: f32,
+}
+
+impl SpotLight {
+    // The cone points down +z in light space, the angles are in degrees
+    pub fn new(
+        light_to_world: &Transform,
+        intensity: Spectrum,
+        total_width: f32,
+        falloff_start: f32,
+    ) -> SpotLight {
+        SpotLight {
+            p_light: light_to_world.transform_point(Point3::new(0.0, 0.0, 0.0)),
+            world_to_light: light_to_world.inverse(),
+            intensity,
+            cos_total_width: total_width.to_radians().cos(),
+            cos_falloff_start: falloff_start.to_radians().cos(),
+        }
+    }
+
+    pub fn create(light_to_world: &Transform, params: &ParamSet) -> SpotLight {
+        let intensity = params.find_one_spectrum("I", Spectrum::from_value(1.0));
+        let scale = params.find_one_spectrum("scale", Spectrum::from_value(1.0));
+        let cone_angle = params.find_one_float("coneangle", 30.0);
+        let cone_delta = params.find_one_float("conedelta", 5.0);
+        let from = params.find_one_point3("from", Point3::new(0.0, 0.0, 0.0));
+        let to = params.find_one_point3("to", Point3::new(0.0, 0.0, 1.0));
+        let light_to_world = *light_to_world * translate(from.to_vec()) * frame_from_z(to - from);
+        SpotLight::new(
+            &light_to_world,
+            intensity * scale,
+            cone_angle,
+            cone_angle - cone_delta,
+        )
+    }
+
+    // Fraction of the intensity sent in world direction w
+    fn falloff(&self, w: &Vec3) -> f32 {
+        let cos_theta = self.world_to_light.transform_vec(*w).normalize().z;
+        if cos_theta < self.cos_total_width {
+            return 0.0;
+        }
+        if cos_theta >= self.cos_falloff_start {
+            return 1.0;
+        }
+        let delta =
+            (cos_theta - self.cos_total_width) / (self.cos_falloff_start - self.cos_total_width);
+        (delta * delta) * (delta * delta)
+    }
+}
+
+impl Light for SpotLight {
+    fn flags(&self) -> LightFlags {
+        LightFlag::DeltaPosition.into()
+    }
+
+    fn sample_light_incoming(
+        &self,
+        reference: &Interaction,
+        _u: &Point2,
+    ) -> (Spectrum, Vec3, f32, VisibiliyTester) {
+        let wi = (self.p_light - reference.p).normalize();
+        let visibility = VisibiliyTester::new(
+            reference.clone(),
+            Interaction::new(self.p_light, Vec3::zero(), Vec3::zero(), reference.time),
+        );
+        let distance_squared = (self.p_light - reference.p).magnitude2();
+        (
+            self.intensity * self.falloff(&-wi) / distance_squared,
+            wi,
+            1.0,
+            visibility,
+        )
+    }
+
+    fn pdf_light_incoming(&self, _reference: &Interaction, _wi: &Vec3) -> f32 {
+        0.0
+    }
+
+    fn power(&self) -> Spectrum {
+        self.intensity
+            * 2.0
+            * std::f32::consts::PI
+            * (1.0 - 0.5 * (self.cos_falloff_start + self.cos_total_width))
+    }
+}
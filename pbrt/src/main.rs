@@ -1,4 +1,11 @@
 #![allow(non_snake_case)]
+// bitmask! checks for a `std` feature this crate doesn't declare
+#![allow(unexpected_cfgs)]
+#![allow(
+    clippy::upper_case_acronyms,
+    clippy::too_many_arguments,
+    clippy::new_ret_no_self
+)]
 
 // List all external crate
 // extern crate cgmath;
@@ -7,18 +14,106 @@
 mod accelerators;
 mod cameras;
 mod core;
+mod filters;
 mod integrators;
 mod lights;
+mod materials;
 mod math;
+mod media;
 mod ray;
 mod samplers;
 mod shapes;
 mod spectrum;
+mod textures;
+
+use std::path::Path;
+use std::sync::atomic::Ordering;
+
+const USAGE: &str = "Usage: garage_ray_pbrt [OPTIONS] <FILE.pbrt>...
+
+Options:
+    --outfile <PATH>    Write the final image to PATH instead of the film filename
+    --nthreads <COUNT>  Number of render threads, 0 uses one per core (default: 0).
+                        1 renders on the main thread, which is easiest to debug
+    --tileorder <NAME>  Order tiles are rendered in, one of scanline, spiral or hilbert
+                        (default: spiral)
+    --quiet             Don't print the progress bar
+    --help              Print this message";
+
+struct Options {
+    files: Vec<String>,
+    outfile: Option<String>,
+    parallel: core::ParallelOptions,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        files: Vec::new(),
+        outfile: None,
+        parallel: core::ParallelOptions::default(),
+    };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--outfile" => {
+                let value = args.next().ok_or("missing value for '--outfile'")?;
+                options.outfile = Some(value);
+            }
+            "--nthreads" => {
+                let value = args.next().ok_or("missing value for '--nthreads'")?;
+                options.parallel.n_threads = value
+                    .parse()
+                    .map_err(|_| format!("invalid value '{}' for '--nthreads'", value))?;
+            }
+            "--tileorder" => {
+                let value = args.next().ok_or("missing value for '--tileorder'")?;
+                options.parallel.tile_order = value.parse()?;
+            }
+            "--quiet" => options.parallel.quiet = true,
+            "--help" => {
+                println!("{}", USAGE);
+                std::process::exit(0);
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown option '{}'", arg)),
+            _ => options.files.push(arg),
+        }
+    }
+
+    if options.files.is_empty() {
+        return Err(String::from("no scene file given"));
+    }
+    Ok(options)
+}
 
 fn main() {
     // Parse command lines
-    // Init function
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("error: {}\nRun with --help for usage", err);
+            std::process::exit(2);
+        }
+    };
+
+    // The first Ctrl-C stops starting new tiles so the partial image still gets written, a
+    // second one exits right away
+    let cancel = options.parallel.cancel.clone();
+    let handler = ctrlc::set_handler(move || {
+        if cancel.swap(true, Ordering::Relaxed) {
+            std::process::exit(130);
+        }
+    });
+    if let Err(err) = handler {
+        eprintln!("warning: unable to install the Ctrl-C handler: {}", err);
+    }
 
-    // Cleanup function
-    println!("Hello World PBRT");
+    // Every file is parsed as a continuation of the previous ones, same as in pbrt
+    let mut builder = core::api::SceneBuilder::new(options.outfile, options.parallel);
+    for file in &options.files {
+        if let Err(err) = core::parser::parse_file(Path::new(file), &mut builder) {
+            eprintln!("error: {}", err);
+            std::process::exit(1);
+        }
+    }
 }
@@ -0,0 +1,154 @@
+use crate::core::microfacet::{
+    BeckmannDistribution, MicrofacetDistribution, TrowbridgeReitzDistribution,
+};
+use crate::core::reflection::*;
+use crate::core::texture::Texture;
+use crate::core::*;
+use crate::math::*;
+use crate::spectrum::Spectrum;
+use std::sync::Arc;
+
+mod glass;
+mod matte;
+mod metal;
+mod mirror;
+mod plastic;
+mod substrate;
+
+pub use glass::GlassMaterial;
+pub use matte::MatteMaterial;
+pub use metal::MetalMaterial;
+pub use mirror::MirrorMaterial;
+pub use plastic::PlasticMaterial;
+pub use substrate::SubstrateMaterial;
+
+pub trait Material: Send + Sync {
+    // Sets si.bsdf to the scattering at the hit point. Integrators that can handle a BxDF
+    // combining specular reflection and transmission pass allow_multiple_lobes
+    fn compute_scattering_functions(&self, si: &mut SurfaceInteraction, allow_multiple_lobes: bool);
+}
+
+// Copy of si moved to p with the given uv and normal, for evaluating textures at a neighbour
+fn shifted_interaction<'a>(
+    si: &SurfaceInteraction<'a>,
+    p: Point3,
+    uv: Point2,
+    n: Normal3f,
+) -> SurfaceInteraction<'a> {
+    let mut interaction = si.interaction.clone();
+    interaction.p = p;
+    interaction.n = n;
+    SurfaceInteraction {
+        interaction,
+        uv,
+        dpdu: si.dpdu,
+        dpdv: si.dpdv,
+        dndu: si.dndu,
+        dndv: si.dndv,
+        shape: si.shape,
+        primitive: si.primitive,
+        shading: si.shading.clone(),
+        bsdf: None,
+    }
+}
+
+// Perturbs the shading frame as if the surface was displaced along its shading normal by d,
+// with the derivatives of d estimated by forward differences
+pub fn bump(d: &dyn Texture<f32>, si: &mut SurfaceInteraction) {
+    // Without ray differentials the difference can't follow the pixel footprint, pbrt uses the
+    // same fallback for rays without them
+    let du = 0.0005;
+    let dv = 0.0005;
+    let n = si.shading.dpdu.cross(si.shading.dpdv);
+
+    let shifted = shifted_interaction(
+        si,
+        si.interaction.p + du * si.shading.dpdu,
+        si.uv + Vec2::new(du, 0.0),
+        (n + du * si.dndu).normalize(),
+    );
+    let u_displace = d.evaluate(&shifted);
+
+    let shifted = shifted_interaction(
+        si,
+        si.interaction.p + dv * si.shading.dpdv,
+        si.uv + Vec2::new(0.0, dv),
+        (n + dv * si.dndv).normalize(),
+    );
+    let v_displace = d.evaluate(&shifted);
+
+    let displace = d.evaluate(si);
+
+    let dpdu =
+        si.shading.dpdu + (u_displace - displace) / du * si.shading.n + displace * si.shading.dndu;
+    let dpdv =
+        si.shading.dpdv + (v_displace - displace) / dv * si.shading.n + displace * si.shading.dndv;
+    let (dndu, dndv) = (si.shading.dndu, si.shading.dndv);
+    si.set_shading_geometry(dpdu, dpdv, dndu, dndv, false);
+}
+
+// Microfacet distribution of the glossy materials, picked with their "distribution" parameter
+#[derive(Clone, Copy)]
+pub enum MicrofacetModel {
+    TrowbridgeReitz,
+    Beckmann,
+}
+
+impl MicrofacetModel {
+    fn create(params: &TextureParams) -> MicrofacetModel {
+        match params
+            .params
+            .find_one_string("distribution", "trowbridgereitz")
+            .as_str()
+        {
+            "trowbridgereitz" => MicrofacetModel::TrowbridgeReitz,
+            "beckmann" => MicrofacetModel::Beckmann,
+            name => {
+                eprintln!(
+                    "warning: microfacet distribution \"{}\" unknown, using \"trowbridgereitz\"",
+                    name
+                );
+                MicrofacetModel::TrowbridgeReitz
+            }
+        }
+    }
+
+    // With remap the roughness values are in [0, 1] and mapped to alpha first
+    fn distribution(
+        self,
+        u_roughness: f32,
+        v_roughness: f32,
+        remap: bool,
+    ) -> Box<dyn MicrofacetDistribution> {
+        match self {
+            MicrofacetModel::TrowbridgeReitz => {
+                let alpha = |roughness| {
+                    if remap {
+                        TrowbridgeReitzDistribution::roughness_to_alpha(roughness)
+                    } else {
+                        roughness
+                    }
+                };
+                Box::new(TrowbridgeReitzDistribution::new(
+                    alpha(u_roughness),
+                    alpha(v_roughness),
+                    true,
+                ))
+            }
+            MicrofacetModel::Beckmann => {
+                let alpha = |roughness| {
+                    if remap {
+                        BeckmannDistribution::roughness_to_alpha(roughness)
+                    } else {
+                        roughness
+                    }
+                };
+                Box::new(BeckmannDistribution::new(
+                    alpha(u_roughness),
+                    alpha(v_roughness),
+                    true,
+                ))
+            }
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use super::*;
+
+// Dielectric that reflects and refracts, smooth unless a roughness is given
+pub struct GlassMaterial {
+    kr: Arc<dyn Texture<Spectrum>>,
+    kt: Arc<dyn Texture<Spectrum>>,
+    u_roughness: Arc<dyn Texture<f32>>,
+    v_roughness: Arc<dyn Texture<f32>>,
+    index: Arc<dyn Texture<f32>>,
+    bump_map: Option<Arc<dyn Texture<f32>>>,
+    remap_roughness: bool,
+    microfacet: MicrofacetModel,
+}
+
+impl GlassMaterial {
+    pub fn new(
+        kr: Arc<dyn Texture<Spectrum>>,
+        kt: Arc<dyn Texture<Spectrum>>,
+        u_roughness: Arc<dyn Texture<f32>>,
+        v_roughness: Arc<dyn Texture<f32>>,
+        index: Arc<dyn Texture<f32>>,
+        bump_map: Option<Arc<dyn Texture<f32>>>,
+        remap_roughness: bool,
+        microfacet: MicrofacetModel,
+    ) -> GlassMaterial {
+        GlassMaterial {
+            kr,
+            kt,
+            u_roughness,
+            v_roughness,
+            index,
+            bump_map,
+            remap_roughness,
+            microfacet,
+        }
+    }
+
+    pub fn create(params: &TextureParams) -> GlassMaterial {
+        // "eta" is the current name, "index" the one of older scenes
+        let index = params
+            .get_texture_or_none("eta")
+            .unwrap_or_else(|| params.get_texture("index", 1.5));
+        GlassMaterial::new(
+            params.get_texture("Kr", Spectrum::from_value(1.0)),
+            params.get_texture("Kt", Spectrum::from_value(1.0)),
+            params.get_texture("uroughness", 0.0),
+            params.get_texture("vroughness", 0.0),
+            index,
+            params.get_texture_or_none("bumpmap"),
+            params.params.find_one_bool("remaproughness", true),
+            MicrofacetModel::create(params),
+        )
+    }
+}
+
+impl Material for GlassMaterial {
+    fn compute_scattering_functions(
+        &self,
+        si: &mut SurfaceInteraction,
+        allow_multiple_lobes: bool,
+    ) {
+        if let Some(bump_map) = &self.bump_map {
+            bump(bump_map.as_ref(), si);
+        }
+
+        let eta = self.index.evaluate(si);
+        let u_rough = self.u_roughness.evaluate(si);
+        let v_rough = self.v_roughness.evaluate(si);
+        let r = self.kr.evaluate(si).clamp(0.0, f32::INFINITY);
+        let t = self.kt.evaluate(si).clamp(0.0, f32::INFINITY);
+
+        let mut bsdf = BSDF::new(si, eta);
+        let is_specular = u_rough == 0.0 && v_rough == 0.0;
+        if r.is_black() && t.is_black() {
+            // Nothing scatters
+        } else if is_specular && allow_multiple_lobes {
+            bsdf.add(Box::new(FresnelSpecular::new(r, t, 1.0, eta)));
+        } else {
+            let distribution = || {
+                self.microfacet
+                    .distribution(u_rough, v_rough, self.remap_roughness)
+            };
+            if !r.is_black() {
+                let fresnel = Box::new(FresnelDielectric::new(1.0, eta));
+                if is_specular {
+                    bsdf.add(Box::new(SpecularReflection::new(r, fresnel)));
+                } else {
+                    bsdf.add(Box::new(MicrofacetReflection::new(
+                        r,
+                        distribution(),
+                        fresnel,
+                    )));
+                }
+            }
+            if !t.is_black() {
+                if is_specular {
+                    bsdf.add(Box::new(SpecularTransmission::new(t, 1.0, eta)));
+                } else {
+                    bsdf.add(Box::new(MicrofacetTransmission::new(
+                        t,
+                        distribution(),
+                        1.0,
+                        eta,
+                    )));
+                }
+            }
+        }
+        si.bsdf = Some(bsdf);
+    }
+}
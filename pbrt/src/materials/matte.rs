@@ -0,0 +1,54 @@
+use super::*;
+
+// Diffuse surface, Lambertian unless the facet angle sigma is given
+pub struct MatteMaterial {
+    kd: Arc<dyn Texture<Spectrum>>,
+    sigma: Arc<dyn Texture<f32>>,
+    bump_map: Option<Arc<dyn Texture<f32>>>,
+}
+
+impl MatteMaterial {
+    pub fn new(
+        kd: Arc<dyn Texture<Spectrum>>,
+        sigma: Arc<dyn Texture<f32>>,
+        bump_map: Option<Arc<dyn Texture<f32>>>,
+    ) -> MatteMaterial {
+        MatteMaterial {
+            kd,
+            sigma,
+            bump_map,
+        }
+    }
+
+    pub fn create(params: &TextureParams) -> MatteMaterial {
+        MatteMaterial::new(
+            params.get_texture("Kd", Spectrum::from_value(0.5)),
+            params.get_texture("sigma", 0.0),
+            params.get_texture_or_none("bumpmap"),
+        )
+    }
+}
+
+impl Material for MatteMaterial {
+    fn compute_scattering_functions(
+        &self,
+        si: &mut SurfaceInteraction,
+        _allow_multiple_lobes: bool,
+    ) {
+        if let Some(bump_map) = &self.bump_map {
+            bump(bump_map.as_ref(), si);
+        }
+
+        let mut bsdf = BSDF::new(si, 1.0);
+        let r = self.kd.evaluate(si).clamp(0.0, f32::INFINITY);
+        let sigma = clamp(self.sigma.evaluate(si), 0.0, 90.0);
+        if !r.is_black() {
+            if sigma == 0.0 {
+                bsdf.add(Box::new(LambertianReflection::new(r)));
+            } else {
+                bsdf.add(Box::new(OrenNayar::new(r, sigma)));
+            }
+        }
+        si.bsdf = Some(bsdf);
+    }
+}
@@ -0,0 +1,93 @@
+use super::*;
+use crate::spectrum::SpectrumType;
+
+// Conductor with glossy reflection, given by its complex index of refraction eta + ik
+pub struct MetalMaterial {
+    eta: Arc<dyn Texture<Spectrum>>,
+    k: Arc<dyn Texture<Spectrum>>,
+    roughness: Arc<dyn Texture<f32>>,
+    // Anisotropic roughness, replacing roughness in their direction when given
+    u_roughness: Option<Arc<dyn Texture<f32>>>,
+    v_roughness: Option<Arc<dyn Texture<f32>>>,
+    bump_map: Option<Arc<dyn Texture<f32>>>,
+    remap_roughness: bool,
+    microfacet: MicrofacetModel,
+}
+
+// Copper, the measured eta and k reduced to RGB
+const COPPER_ETA: [f32; 3] = [0.200438, 0.924033, 1.10221];
+const COPPER_K: [f32; 3] = [3.91295, 2.45285, 2.14219];
+
+impl MetalMaterial {
+    pub fn new(
+        eta: Arc<dyn Texture<Spectrum>>,
+        k: Arc<dyn Texture<Spectrum>>,
+        roughness: Arc<dyn Texture<f32>>,
+        u_roughness: Option<Arc<dyn Texture<f32>>>,
+        v_roughness: Option<Arc<dyn Texture<f32>>>,
+        bump_map: Option<Arc<dyn Texture<f32>>>,
+        remap_roughness: bool,
+        microfacet: MicrofacetModel,
+    ) -> MetalMaterial {
+        MetalMaterial {
+            eta,
+            k,
+            roughness,
+            u_roughness,
+            v_roughness,
+            bump_map,
+            remap_roughness,
+            microfacet,
+        }
+    }
+
+    pub fn create(params: &TextureParams) -> MetalMaterial {
+        MetalMaterial::new(
+            params.get_texture(
+                "eta",
+                Spectrum::from_rgb(COPPER_ETA, SpectrumType::Reflectance),
+            ),
+            params.get_texture("k", Spectrum::from_rgb(COPPER_K, SpectrumType::Reflectance)),
+            params.get_texture("roughness", 0.01),
+            params.get_texture_or_none("uroughness"),
+            params.get_texture_or_none("vroughness"),
+            params.get_texture_or_none("bumpmap"),
+            params.params.find_one_bool("remaproughness", true),
+            MicrofacetModel::create(params),
+        )
+    }
+}
+
+impl Material for MetalMaterial {
+    fn compute_scattering_functions(
+        &self,
+        si: &mut SurfaceInteraction,
+        _allow_multiple_lobes: bool,
+    ) {
+        if let Some(bump_map) = &self.bump_map {
+            bump(bump_map.as_ref(), si);
+        }
+
+        let mut bsdf = BSDF::new(si, 1.0);
+        let roughness = |texture: &Option<Arc<dyn Texture<f32>>>| match texture {
+            Some(texture) => texture.evaluate(si),
+            None => self.roughness.evaluate(si),
+        };
+        let distribution = self.microfacet.distribution(
+            roughness(&self.u_roughness),
+            roughness(&self.v_roughness),
+            self.remap_roughness,
+        );
+        let fresnel = FresnelConductor::new(
+            Spectrum::from_value(1.0),
+            self.eta.evaluate(si),
+            self.k.evaluate(si),
+        );
+        bsdf.add(Box::new(MicrofacetReflection::new(
+            Spectrum::from_value(1.0),
+            distribution,
+            Box::new(fresnel),
+        )));
+        si.bsdf = Some(bsdf);
+    }
+}
@@ -0,0 +1,45 @@
+use super::*;
+
+// Perfect specular reflection
+pub struct MirrorMaterial {
+    kr: Arc<dyn Texture<Spectrum>>,
+    bump_map: Option<Arc<dyn Texture<f32>>>,
+}
+
+impl MirrorMaterial {
+    pub fn new(
+        kr: Arc<dyn Texture<Spectrum>>,
+        bump_map: Option<Arc<dyn Texture<f32>>>,
+    ) -> MirrorMaterial {
+        MirrorMaterial { kr, bump_map }
+    }
+
+    pub fn create(params: &TextureParams) -> MirrorMaterial {
+        MirrorMaterial::new(
+            params.get_texture("Kr", Spectrum::from_value(0.9)),
+            params.get_texture_or_none("bumpmap"),
+        )
+    }
+}
+
+impl Material for MirrorMaterial {
+    fn compute_scattering_functions(
+        &self,
+        si: &mut SurfaceInteraction,
+        _allow_multiple_lobes: bool,
+    ) {
+        if let Some(bump_map) = &self.bump_map {
+            bump(bump_map.as_ref(), si);
+        }
+
+        let mut bsdf = BSDF::new(si, 1.0);
+        let r = self.kr.evaluate(si).clamp(0.0, f32::INFINITY);
+        if !r.is_black() {
+            bsdf.add(Box::new(SpecularReflection::new(
+                r,
+                Box::new(FresnelNoOp {}),
+            )));
+        }
+        si.bsdf = Some(bsdf);
+    }
+}
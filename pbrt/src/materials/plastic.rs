@@ -0,0 +1,76 @@
+use super::*;
+
+// Diffuse base with glossy dielectric reflection on top
+pub struct PlasticMaterial {
+    kd: Arc<dyn Texture<Spectrum>>,
+    ks: Arc<dyn Texture<Spectrum>>,
+    roughness: Arc<dyn Texture<f32>>,
+    bump_map: Option<Arc<dyn Texture<f32>>>,
+    // Roughness is in [0, 1] and mapped to the microfacet alpha, instead of being alpha
+    remap_roughness: bool,
+    microfacet: MicrofacetModel,
+}
+
+impl PlasticMaterial {
+    pub fn new(
+        kd: Arc<dyn Texture<Spectrum>>,
+        ks: Arc<dyn Texture<Spectrum>>,
+        roughness: Arc<dyn Texture<f32>>,
+        bump_map: Option<Arc<dyn Texture<f32>>>,
+        remap_roughness: bool,
+        microfacet: MicrofacetModel,
+    ) -> PlasticMaterial {
+        PlasticMaterial {
+            kd,
+            ks,
+            roughness,
+            bump_map,
+            remap_roughness,
+            microfacet,
+        }
+    }
+
+    pub fn create(params: &TextureParams) -> PlasticMaterial {
+        PlasticMaterial::new(
+            params.get_texture("Kd", Spectrum::from_value(0.25)),
+            params.get_texture("Ks", Spectrum::from_value(0.25)),
+            params.get_texture("roughness", 0.1),
+            params.get_texture_or_none("bumpmap"),
+            params.params.find_one_bool("remaproughness", true),
+            MicrofacetModel::create(params),
+        )
+    }
+}
+
+impl Material for PlasticMaterial {
+    fn compute_scattering_functions(
+        &self,
+        si: &mut SurfaceInteraction,
+        _allow_multiple_lobes: bool,
+    ) {
+        if let Some(bump_map) = &self.bump_map {
+            bump(bump_map.as_ref(), si);
+        }
+
+        let mut bsdf = BSDF::new(si, 1.0);
+        let kd = self.kd.evaluate(si).clamp(0.0, f32::INFINITY);
+        if !kd.is_black() {
+            bsdf.add(Box::new(LambertianReflection::new(kd)));
+        }
+
+        let ks = self.ks.evaluate(si).clamp(0.0, f32::INFINITY);
+        if !ks.is_black() {
+            let fresnel = FresnelDielectric::new(1.5, 1.0);
+            let rough = self.roughness.evaluate(si);
+            let distribution = self
+                .microfacet
+                .distribution(rough, rough, self.remap_roughness);
+            bsdf.add(Box::new(MicrofacetReflection::new(
+                ks,
+                distribution,
+                Box::new(fresnel),
+            )));
+        }
+        si.bsdf = Some(bsdf);
+    }
+}
@@ -0,0 +1,71 @@
+use super::*;
+
+// Diffuse substrate under a glossy coating that reflects more at grazing angles
+pub struct SubstrateMaterial {
+    kd: Arc<dyn Texture<Spectrum>>,
+    ks: Arc<dyn Texture<Spectrum>>,
+    nu: Arc<dyn Texture<f32>>,
+    nv: Arc<dyn Texture<f32>>,
+    bump_map: Option<Arc<dyn Texture<f32>>>,
+    remap_roughness: bool,
+    microfacet: MicrofacetModel,
+}
+
+impl SubstrateMaterial {
+    pub fn new(
+        kd: Arc<dyn Texture<Spectrum>>,
+        ks: Arc<dyn Texture<Spectrum>>,
+        nu: Arc<dyn Texture<f32>>,
+        nv: Arc<dyn Texture<f32>>,
+        bump_map: Option<Arc<dyn Texture<f32>>>,
+        remap_roughness: bool,
+        microfacet: MicrofacetModel,
+    ) -> SubstrateMaterial {
+        SubstrateMaterial {
+            kd,
+            ks,
+            nu,
+            nv,
+            bump_map,
+            remap_roughness,
+            microfacet,
+        }
+    }
+
+    pub fn create(params: &TextureParams) -> SubstrateMaterial {
+        SubstrateMaterial::new(
+            params.get_texture("Kd", Spectrum::from_value(0.5)),
+            params.get_texture("Ks", Spectrum::from_value(0.5)),
+            params.get_texture("uroughness", 0.1),
+            params.get_texture("vroughness", 0.1),
+            params.get_texture_or_none("bumpmap"),
+            params.params.find_one_bool("remaproughness", true),
+            MicrofacetModel::create(params),
+        )
+    }
+}
+
+impl Material for SubstrateMaterial {
+    fn compute_scattering_functions(
+        &self,
+        si: &mut SurfaceInteraction,
+        _allow_multiple_lobes: bool,
+    ) {
+        if let Some(bump_map) = &self.bump_map {
+            bump(bump_map.as_ref(), si);
+        }
+
+        let mut bsdf = BSDF::new(si, 1.0);
+        let d = self.kd.evaluate(si).clamp(0.0, f32::INFINITY);
+        let s = self.ks.evaluate(si).clamp(0.0, f32::INFINITY);
+        if !d.is_black() || !s.is_black() {
+            let distribution = self.microfacet.distribution(
+                self.nu.evaluate(si),
+                self.nv.evaluate(si),
+                self.remap_roughness,
+            );
+            bsdf.add(Box::new(FresnelBlend::new(d, s, distribution)));
+        }
+        si.bsdf = Some(bsdf);
+    }
+}
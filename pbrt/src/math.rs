@@ -1,7 +1,7 @@
 // Matrix type is column major in cgmath, but row major in book
 // Most likely cgmath is implemented with right hand rule, but book is left handed
 
-//pub type Vec2 = cgmath::Vector2<f32>;
+pub type Vec2 = cgmath::Vector2<f32>;
 pub type Vec2i = cgmath::Vector2<i32>;
 pub type Vec3 = cgmath::Vector3<f32>;
 
@@ -24,7 +24,7 @@ pub use cgmath::{Deg, Rad};
 pub use num_traits::identities::Zero; // needed for vector is_zero method // needed for normalize
 
 use crate::core::{Interaction, Shading, SurfaceInteraction};
-use crate::ray::Ray;
+use crate::ray::{Ray, RayDifferential};
 
 pub fn min<T: BaseNum>(lhs: T, rhs: T) -> T {
     match lhs.partial_cmp(&rhs) {
@@ -59,6 +59,7 @@ pub fn lerp<T: BaseNum>(t: T, lhs: T, rhs: T) -> T {
 }
 
 // Bounds 2d implementation
+#[derive(Clone, Copy)]
 pub struct Bounds2D<T> {
     pub min: cgmath::Point2<T>,
     pub max: cgmath::Point2<T>,
@@ -69,57 +70,12 @@ impl<T: BaseNum> Bounds2D<T> {
         self.max - self.min
     }
 
-    pub fn from_point(p: cgmath::Point2<T>) -> Bounds2D<T> {
-        Bounds2D { min: p, max: p }
-    }
-
     pub fn from_two_points(p1: cgmath::Point2<T>, p2: cgmath::Point2<T>) -> Bounds2D<T> {
         Bounds2D {
             min: cgmath::Point2::new(min(p1.x, p2.x), min(p1.y, p2.y)),
             max: cgmath::Point2::new(max(p1.x, p2.x), max(p1.y, p2.y)),
         }
     }
-
-    pub fn maximum_extent(&self) -> i32 {
-        let d = self.diagonal();
-        if d.x > d.y {
-            0
-        } else {
-            1
-        }
-    }
-
-    pub fn lerp(&self, t: &cgmath::Point2<T>) -> cgmath::Point2<T> {
-        cgmath::Point2::new(
-            lerp(t.x, self.min.x, self.max.x),
-            lerp(t.y, self.min.y, self.max.y),
-        )
-    }
-
-    pub fn offset(&self, p: &cgmath::Point2<T>) -> Vector2<T> {
-        let mut o = p - self.min;
-        if self.max.x > self.min.x {
-            o.x /= self.max.x - self.min.x;
-        }
-        if self.max.y > self.min.y {
-            o.y /= self.max.y - self.min.y;
-        }
-        o
-    }
-}
-
-impl<T: BaseFloat> Bounds2D<T> {
-    pub fn bounding_sphere(&self) -> (cgmath::Point2<T>, T) {
-        let center = cgmath::Point2::<T>::from_vec(
-            (self.min.to_vec() + self.max.to_vec()) / T::from(2).unwrap(),
-        );
-        let radius = if inside_2d(&center, self) {
-            center.distance(self.max)
-        } else {
-            T::zero()
-        };
-        (center, radius)
-    }
 }
 
 impl<T> Index<i32> for Bounds2D<T> {
@@ -148,51 +104,58 @@ impl<T: Bounded> Default for Bounds2D<T> {
 // Iterator for integer points inside bounds
 pub type Bounds2Di = Bounds2D<i32>;
 
-// Bounds freeroam functions
-pub fn union_2d_with_point<T: BaseNum>(b: &Bounds2D<T>, p: &Vector2<T>) -> Bounds2D<T> {
-    Bounds2D {
-        min: cgmath::Point2::new(min(b.min.x, p.x), min(b.min.y, p.y)),
-        max: cgmath::Point2::new(max(b.max.x, p.x), max(b.max.y, p.y)),
-    }
-}
-
-pub fn union_bounds_2d<T: BaseNum>(b1: &Bounds2D<T>, b2: &Bounds2D<T>) -> Bounds2D<T> {
-    Bounds2D {
-        min: cgmath::Point2::new(min(b1.min.x, b2.min.x), min(b1.min.y, b2.max.x)),
-        max: cgmath::Point2::new(max(b1.max.x, b2.max.x), max(b1.max.y, b2.max.y)),
+impl Bounds2Di {
+    pub fn area(&self) -> i32 {
+        let d = self.diagonal();
+        if d.x <= 0 || d.y <= 0 {
+            0
+        } else {
+            d.x * d.y
+        }
     }
-}
 
-pub fn intersect_2d<T: BaseNum>(b1: &Bounds2D<T>, b2: &Bounds2D<T>) -> Bounds2D<T> {
-    Bounds2D {
-        min: cgmath::Point2::new(max(b1.min.x, b2.min.x), max(b1.min.y, b2.max.x)),
-        max: cgmath::Point2::new(min(b1.max.x, b2.max.x), min(b1.max.y, b2.max.y)),
+    // Visits the points row by row, max is exclusive
+    pub fn iter(&self) -> Bounds2DiIterator {
+        Bounds2DiIterator {
+            bounds: *self,
+            p: self.min,
+        }
     }
 }
 
-pub fn overlaps_2d<T: BaseNum>(b1: &Bounds2D<T>, b2: &Bounds2D<T>) -> bool {
-    let x = (b1.max.x >= b2.min.x) && (b1.min.x <= b2.max.x);
-    let y = (b1.max.y >= b2.min.y) && (b1.min.y <= b2.max.y);
-    x && y
+pub struct Bounds2DiIterator {
+    bounds: Bounds2Di,
+    p: Point2i,
 }
 
-pub fn inside_2d<T: BaseNum>(p: &cgmath::Point2<T>, b: &Bounds2D<T>) -> bool {
-    p.x >= b.min.x && p.x <= b.max.x && p.y >= b.min.y && p.y <= b.max.y
-}
+impl Iterator for Bounds2DiIterator {
+    type Item = Point2i;
 
-pub fn inside_exclusive_2d<T: BaseNum>(p: &cgmath::Point2<T>, b: &Bounds2D<T>) -> bool {
-    p.x >= b.min.x && p.x < b.max.x && p.y >= b.min.y && p.y < b.max.y
+    fn next(&mut self) -> Option<Point2i> {
+        if self.bounds.area() == 0 || self.p.y >= self.bounds.max.y {
+            return None;
+        }
+        let p = self.p;
+        self.p.x += 1;
+        if self.p.x == self.bounds.max.x {
+            self.p.x = self.bounds.min.x;
+            self.p.y += 1;
+        }
+        Some(p)
+    }
 }
+pub type Bounds2Df = Bounds2D<f32>;
 
-pub fn expand_2d<T: BaseNum, U: BaseNum>(b: &Bounds2D<T>, delta: U) -> Bounds2D<T> {
-    let delta_t = T::from(delta).unwrap();
+// Bounds freeroam functions
+pub fn intersect_2d<T: BaseNum>(b1: &Bounds2D<T>, b2: &Bounds2D<T>) -> Bounds2D<T> {
     Bounds2D {
-        min: b.min - vec2(delta_t, delta_t),
-        max: b.max + vec2(delta_t, delta_t),
+        min: cgmath::Point2::new(max(b1.min.x, b2.min.x), max(b1.min.y, b2.min.y)),
+        max: cgmath::Point2::new(min(b1.max.x, b2.max.x), min(b1.max.y, b2.max.y)),
     }
 }
 
 // Bounds 3d implementation
+#[derive(Clone, Copy)]
 pub struct Bounds3D<T> {
     pub min: cgmath::Point3<T>,
     pub max: cgmath::Point3<T>,
@@ -214,41 +177,22 @@ impl<T: BaseNum> Bounds3D<T> {
         }
     }
 
-    pub fn corner(&self, corner: i32) -> cgmath::Point3<T> {
-        cgmath::Point3::new(
-            self[corner & 1].x,
-            self[if (corner & 2) != 0 { 1 } else { 0 }].y,
-            self[if (corner & 4) != 0 { 1 } else { 0 }].z,
-        )
-    }
-
     pub fn surface_area(&self) -> T {
         let d = self.diagonal();
         T::from(2).unwrap() * (d.x * d.y + d.x * d.z + d.y * d.z)
     }
 
-    pub fn volume(&self) -> T {
-        let d = self.diagonal();
-        d.x * d.y * d.z
-    }
-
     pub fn maximum_extent(&self) -> i32 {
         let d = self.diagonal();
-        if d.x > d.y {
+        if d.x > d.y && d.x > d.z {
             0
-        } else {
+        } else if d.y > d.z {
             1
+        } else {
+            2
         }
     }
 
-    pub fn lerp(&self, t: &cgmath::Point3<T>) -> cgmath::Point3<T> {
-        cgmath::Point3::new(
-            lerp(t.x, self.min.x, self.max.x),
-            lerp(t.y, self.min.y, self.max.y),
-            lerp(t.z, self.min.z, self.max.z),
-        )
-    }
-
     pub fn offset(&self, p: &cgmath::Point3<T>) -> Vector3<T> {
         let mut o = p - self.min;
         if self.max.x > self.min.x {
@@ -257,6 +201,9 @@ impl<T: BaseNum> Bounds3D<T> {
         if self.max.y > self.min.y {
             o.y /= self.max.y - self.min.y;
         }
+        if self.max.z > self.min.z {
+            o.z /= self.max.z - self.min.z;
+        }
         o
     }
 }
@@ -285,16 +232,17 @@ impl Bounds3D<f32> {
             let mut t_far = (self.max[i] - ray.o[i]) * inv_ray_dir;
             if t_near > t_far {
                 std::mem::swap(&mut t_near, &mut t_far);
-                // Update t_far to ensure robust ray-bounds intersection
-                if t_near > t0 {
-                    t0 = t_near;
-                }
-                if t_far < t1 {
-                    t1 = t_far;
-                }
-                if t0 > t1 {
-                    return (false, 0.0, 0.0);
-                }
+            }
+            // Update t_far to ensure robust ray-bounds intersection
+            t_far *= 1.0 + 2.0 * gamma(3);
+            if t_near > t0 {
+                t0 = t_near;
+            }
+            if t_far < t1 {
+                t1 = t_far;
+            }
+            if t0 > t1 {
+                return (false, 0.0, 0.0);
             }
         }
         (true, t0, t1)
@@ -357,15 +305,13 @@ impl<T: Bounded> Default for Bounds3D<T> {
     }
 }
 
-// Iterator for integer points inside bounds
-pub type Bounds3Di = Bounds3D<i32>;
 pub type Bounds3Df = Bounds3D<f32>;
 
 // Bounds 3d freeroam functions
 pub fn union_3d_with_point<T: BaseNum>(b: &Bounds3D<T>, p: &cgmath::Point3<T>) -> Bounds3D<T> {
     Bounds3D {
         min: cgmath::Point3::new(min(b.min.x, p.x), min(b.min.y, p.y), min(b.min.z, p.z)),
-        max: cgmath::Point3::new(max(b.max.x, p.x), max(b.max.y, p.y), max(b.min.z, p.z)),
+        max: cgmath::Point3::new(max(b.max.x, p.x), max(b.max.y, p.y), max(b.max.z, p.z)),
     }
 }
 
@@ -373,39 +319,17 @@ pub fn union_bounds_3d<T: BaseNum>(b1: &Bounds3D<T>, b2: &Bounds3D<T>) -> Bounds
     Bounds3D {
         min: cgmath::Point3::new(
             min(b1.min.x, b2.min.x),
-            min(b1.min.y, b2.max.x),
-            min(b1.min.z, b2.max.z),
+            min(b1.min.y, b2.min.y),
+            min(b1.min.z, b2.min.z),
         ),
         max: cgmath::Point3::new(
             max(b1.max.x, b2.max.x),
             max(b1.max.y, b2.max.y),
-            max(b1.min.z, b2.max.z),
+            max(b1.max.z, b2.max.z),
         ),
     }
 }
 
-pub fn intersect_3d<T: BaseNum>(b1: &Bounds3D<T>, b2: &Bounds3D<T>) -> Bounds3D<T> {
-    Bounds3D {
-        min: cgmath::Point3::new(
-            max(b1.min.x, b2.min.x),
-            max(b1.min.y, b2.max.x),
-            max(b1.min.z, b2.max.z),
-        ),
-        max: cgmath::Point3::new(
-            min(b1.max.x, b2.max.x),
-            min(b1.max.y, b2.max.y),
-            min(b1.min.z, b2.max.z),
-        ),
-    }
-}
-
-pub fn overlaps_3d<T: BaseNum>(b1: &Bounds3D<T>, b2: &Bounds3D<T>) -> bool {
-    let x = (b1.max.x >= b2.min.x) && (b1.min.x <= b2.max.x);
-    let y = (b1.max.y >= b2.min.y) && (b1.min.y <= b2.max.y);
-    let z = (b1.max.z >= b2.min.z) && (b1.min.z <= b2.max.z);
-    x && y && z
-}
-
 pub fn inside_3d<T: BaseNum>(p: &cgmath::Point3<T>, b: &Bounds3D<T>) -> bool {
     p.x >= b.min.x
         && p.x <= b.max.x
@@ -415,35 +339,54 @@ pub fn inside_3d<T: BaseNum>(p: &cgmath::Point3<T>, b: &Bounds3D<T>) -> bool {
         && p.z <= b.max.z
 }
 
-pub fn inside_exclusive_3d<T: BaseNum>(p: &cgmath::Point3<T>, b: &Bounds3D<T>) -> bool {
-    p.x >= b.min.x
-        && p.x < b.max.x
-        && p.y >= b.min.y
-        && p.y < b.max.y
-        && p.z >= b.min.z
-        && p.z < b.max.z
-}
-
-pub fn expand_3d<T: BaseNum, U: BaseNum>(b: &Bounds3D<T>, delta: U) -> Bounds3D<T> {
-    let delta_t = T::from(delta).unwrap();
-    Bounds3D {
-        min: b.min - vec3(delta_t, delta_t, delta_t),
-        max: b.max + vec3(delta_t, delta_t, delta_t),
-    }
+// Bound on the relative error of n floating point operations
+pub fn gamma(n: i32) -> f32 {
+    let epsilon = f32::EPSILON * 0.5;
+    (n as f32 * epsilon) / (1.0 - n as f32 * epsilon)
 }
 
 // Freeroam functions
 pub fn coordinate_system(v1: Vec3) -> (Vec3, Vec3, Vec3) {
-    let v2;
-    if v1.x.abs() > v1.y.abs() {
-        v2 = vec3(-v1.z, 0.0, v1.x).normalize();
+    let v2 = if v1.x.abs() > v1.y.abs() {
+        vec3(-v1.z, 0.0, v1.x).normalize()
     } else {
-        v2 = vec3(0.0, v1.z, -v1.y).normalize();
-    }
+        vec3(0.0, v1.z, -v1.y).normalize()
+    };
 
     (v1, v2, v1.cross(v2))
 }
 
+// Direction with the given polar angle and azimuth phi around +z
+pub fn spherical_direction(sin_theta: f32, cos_theta: f32, phi: f32) -> Vec3 {
+    vec3(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta)
+}
+
+// Direction with the given polar angle and azimuth phi in the frame of x, y and z
+pub fn spherical_direction_in(
+    sin_theta: f32,
+    cos_theta: f32,
+    phi: f32,
+    x: Vec3,
+    y: Vec3,
+    z: Vec3,
+) -> Vec3 {
+    sin_theta * phi.cos() * x + sin_theta * phi.sin() * y + cos_theta * z
+}
+
+pub fn spherical_theta(v: Vec3) -> f32 {
+    clamp(v.z, -1.0, 1.0).acos()
+}
+
+// Azimuth in [0, 2pi)
+pub fn spherical_phi(v: Vec3) -> f32 {
+    let phi = v.y.atan2(v.x);
+    if phi < 0.0 {
+        phi + 2.0 * std::f32::consts::PI
+    } else {
+        phi
+    }
+}
+
 pub fn face_forward(n: Normal3f, v: Vec3) -> Normal3f {
     if dot(n, v) < 0.0 {
         -n
@@ -453,6 +396,7 @@ pub fn face_forward(n: Normal3f, v: Vec3) -> Normal3f {
 }
 
 // Transformations
+#[derive(Clone, Copy)]
 pub struct Transform {
     m: cgmath::Matrix4<f32>,
     m_inv: cgmath::Matrix4<f32>,
@@ -477,8 +421,14 @@ impl From<Matrix4<f32>> for Transform {
 }
 
 impl Transform {
-    pub fn new(m: Matrix4<f32>, m_inv: Matrix4<f32>) -> Transform {
-        Transform { m, m_inv }
+    // Matrices in scene files are given column by column, which matches cgmath's layout
+    pub fn from_columns(m: [f32; 16]) -> Option<Transform> {
+        let mat = Matrix4::new(
+            m[0], m[1], m[2], m[3], m[4], m[5], m[6], m[7], m[8], m[9], m[10], m[11], m[12], m[13],
+            m[14], m[15],
+        );
+        let m_inv = mat.invert()?;
+        Some(Transform { m: mat, m_inv })
     }
 
     pub fn inverse(&self) -> Transform {
@@ -488,22 +438,17 @@ impl Transform {
         }
     }
 
-    pub fn transpose(&self) -> Transform {
-        Transform {
-            m: self.m.transpose(),
-            m_inv: self.m_inv.transpose(),
-        }
-    }
-
-    pub fn has_scale() -> bool {
-        // TODO: implement me properly // page 88
-        false
-    }
-
     pub fn transform_point(&self, p: Point3) -> Point3 {
         self.m.transform_point(p)
     }
 
+    // Conservative bound on an absolute error vector after transformation
+    pub fn transform_error(&self, e: Vec3) -> Vec3 {
+        let m = &self.m;
+        let bound = |r: usize| m[0][r].abs() * e.x + m[1][r].abs() * e.y + m[2][r].abs() * e.z;
+        vec3(bound(0), bound(1), bound(2))
+    }
+
     pub fn transform_vec(&self, v: Vec3) -> Vec3 {
         self.m.transform_vector(v)
     }
@@ -516,12 +461,29 @@ impl Transform {
         )
     }
 
-    pub fn transform_ray<'a>(&self, r: &'a Ray) -> Ray<'a> {
+    pub fn transform_ray(&self, r: &Ray) -> Ray {
         let o = self.transform_point(r.o);
         let d = self.transform_vec(r.d);
         let t_max = std::cell::Cell::new(r.t_max.get());
         // Offset ray origin to edge of error bounds
-        Ray { o, d, t_max, ..*r }
+        Ray {
+            o,
+            d,
+            t_max,
+            time: r.time,
+            medium: r.medium.clone(),
+        }
+    }
+
+    pub fn transform_ray_differential(&self, r: &RayDifferential) -> RayDifferential {
+        RayDifferential {
+            ray: self.transform_ray(&r.ray),
+            hasDifferentials: r.hasDifferentials,
+            rxOrigin: self.transform_point(r.rxOrigin),
+            ryOrigin: self.transform_point(r.ryOrigin),
+            rxDirection: self.transform_vec(r.rxDirection),
+            ryDirection: self.transform_vec(r.ryDirection),
+        }
     }
 
     pub fn transform_bounds(&self, b: &Bounds3Df) -> Bounds3Df {
@@ -550,8 +512,7 @@ impl Transform {
             &ret,
             &self.transform_point(Point3::new(b.max.x, b.min.y, b.max.z)),
         );
-        let ret = union_3d_with_point(&ret, &self.transform_point(b.max));
-        ret
+        union_3d_with_point(&ret, &self.transform_point(b.max))
     }
 
     pub fn transform_surface_interaction<'a>(
@@ -561,9 +522,9 @@ impl Transform {
         // Transform p and pError
         let mut ret = SurfaceInteraction {
             interaction: Interaction {
-                p: si.interaction.p,
+                p: self.transform_point(si.interaction.p),
                 n: self.transform_normal(si.interaction.n).normalize(),
-                p_error: si.interaction.p_error,
+                p_error: self.transform_error(si.interaction.p_error),
                 wo: self.transform_vec(si.interaction.wo).normalize(),
                 ..si.interaction
             },
@@ -612,34 +573,61 @@ pub fn scale(x: f32, y: f32, z: f32) -> Transform {
     Transform { m, m_inv }
 }
 
-pub fn rotate_x(theta: f32) -> Transform {
-    let m = Matrix4::from_angle_x(Deg(theta));
-    let m_inv = m.transpose();
-    Transform { m, m_inv }
-}
-
-pub fn rotate_y(theta: f32) -> Transform {
-    let m = Matrix4::from_angle_y(Deg(theta));
-    let m_inv = m.transpose();
-    Transform { m, m_inv }
-}
-
-pub fn rotate_z(theta: f32) -> Transform {
-    let m = Matrix4::from_angle_z(Deg(theta));
-    let m_inv = m.transpose();
-    Transform { m, m_inv }
-}
-
 pub fn rotate_axis(theta: f32, axis: Vec3) -> Transform {
     let m = Matrix4::from_axis_angle(axis, Deg(theta));
     let m_inv = m.transpose();
     Transform { m, m_inv }
 }
 
-pub fn look_at(pos: Point3, look: Point3, up: Vec3) -> Transform {
-    let m = Matrix4::look_at(pos, look, up);
-    let m_inv = m.invert().unwrap();
-    Transform { m, m_inv }
+// Rotation taking z to the normalized dir, with the other two axes from coordinate_system
+pub fn frame_from_z(dir: Vec3) -> Transform {
+    let (z, x, y) = coordinate_system(dir.normalize());
+    let m = Matrix4::from_cols(
+        x.extend(0.0),
+        y.extend(0.0),
+        z.extend(0.0),
+        Vector4::unit_w(),
+    );
+    Transform {
+        m,
+        m_inv: m.transpose(),
+    }
+}
+
+// Follows the book convention of a left handed camera space looking down +z,
+// instead of cgmath's right handed look_at
+pub fn look_at(pos: Point3, look: Point3, up: Vec3) -> Option<Transform> {
+    let dir = (look - pos).normalize();
+    let right = up.normalize().cross(dir);
+    if right.magnitude() == 0.0 {
+        return None;
+    }
+    let right = right.normalize();
+    let new_up = dir.cross(right);
+    let camera_to_world = Matrix4::from_cols(
+        right.extend(0.0),
+        new_up.extend(0.0),
+        dir.extend(0.0),
+        pos.to_homogeneous(),
+    );
+    Some(Transform {
+        m: camera_to_world.invert().unwrap(),
+        m_inv: camera_to_world,
+    })
+}
+
+pub fn perspective(fov: f32, n: f32, f: f32) -> Transform {
+    // Written row by row as in the book, hence the transpose
+    #[rustfmt::skip]
+    let persp = Matrix4::new(
+        1.0, 0.0, 0.0, 0.0,
+        0.0, 1.0, 0.0, 0.0,
+        0.0, 0.0, f / (f - n), -f * n / (f - n),
+        0.0, 0.0, 1.0, 0.0,
+    )
+    .transpose();
+    let inv_tan_ang = 1.0 / (Rad::from(Deg(fov)).0 / 2.0).tan();
+    scale(inv_tan_ang, inv_tan_ang, 1.0) * Transform::from(persp)
 }
 
 pub fn quadratic<T: BaseFloat>(a: T, b: T, c: T) -> Option<(T, T)> {
@@ -662,3 +650,72 @@ pub fn quadratic<T: BaseFloat>(a: T, b: T, c: T) -> Option<(T, T)> {
         Some((T::from(t0).unwrap(), T::from(t1).unwrap()))
     }
 }
+
+// Smallest representable float greater than v
+pub fn next_float_up(v: f32) -> f32 {
+    if v.is_infinite() && v > 0.0 {
+        return v;
+    }
+    let v = if v == -0.0 { 0.0 } else { v };
+    let bits = v.to_bits();
+    let bits = if v >= 0.0 { bits + 1 } else { bits - 1 };
+    f32::from_bits(bits)
+}
+
+// Largest representable float less than v
+pub fn next_float_down(v: f32) -> f32 {
+    if v.is_infinite() && v < 0.0 {
+        return v;
+    }
+    let v = if v == 0.0 { -0.0 } else { v };
+    let bits = v.to_bits();
+    let bits = if v > 0.0 { bits - 1 } else { bits + 1 };
+    f32::from_bits(bits)
+}
+
+// Abramowitz and Stegun approximation of the error function
+pub fn erf(x: f32) -> f32 {
+    let a1 = 0.254_829_6;
+    let a2 = -0.284_496_74;
+    let a3 = 1.421_413_7;
+    let a4 = -1.453_152;
+    let a5 = 1.061_405_4;
+    let p = 0.327_591_1;
+
+    let sign = if x < 0.0 { -1.0 } else { 1.0 };
+    let x = x.abs();
+    let t = 1.0 / (1.0 + p * x);
+    let y = 1.0 - (((((a5 * t + a4) * t) + a3) * t + a2) * t + a1) * t * (-x * x).exp();
+    sign * y
+}
+
+// Giles' single precision approximation of the inverse error function
+pub fn erf_inv(x: f32) -> f32 {
+    let x = clamp(x, -0.99999, 0.99999);
+    let mut w = -((1.0 - x) * (1.0 + x)).ln();
+    let mut p;
+    if w < 5.0 {
+        w -= 2.5;
+        p = 2.810_226_4e-8;
+        p = 3.432_739_4e-7 + p * w;
+        p = -3.523_387_7e-6 + p * w;
+        p = -4.391_506_5e-6 + p * w;
+        p = 0.000_218_580_87 + p * w;
+        p = -0.001_253_725 + p * w;
+        p = -0.004_177_681_6 + p * w;
+        p = 0.246_640_73 + p * w;
+        p = 1.501_409_4 + p * w;
+    } else {
+        w = w.sqrt() - 3.0;
+        p = -0.000_200_214_26;
+        p = 0.000_100_950_56 + p * w;
+        p = 0.001_349_343_2 + p * w;
+        p = -0.003_673_428_4 + p * w;
+        p = 0.005_739_507_7 + p * w;
+        p = -0.007_622_461 + p * w;
+        p = 0.009_438_870_5 + p * w;
+        p = 1.001_674 + p * w;
+        p = 2.832_976_8 + p * w;
+    }
+    p * x
+}
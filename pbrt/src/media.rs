@@ -0,0 +1,25 @@
+use crate::core::*;
+use crate::math::*;
+use crate::ray::Ray;
+use crate::samplers::Sampler;
+use crate::spectrum::{Spectrum, SpectrumType};
+
+mod grid;
+mod homogeneous;
+
+pub use grid::GridDensityMedium;
+pub use homogeneous::HomogeneousMedium;
+
+// Absorption and scattering coefficients with the phase function asymmetry g, the defaults
+// are the ones of pbrt
+fn scattering_properties(params: &ParamSet) -> (Spectrum, Spectrum, f32) {
+    let sigma_a = Spectrum::from_rgb([0.0011, 0.0024, 0.014], SpectrumType::Reflectance);
+    let sigma_s = Spectrum::from_rgb([2.55, 3.21, 3.77], SpectrumType::Reflectance);
+    let scale = params.find_one_float("scale", 1.0);
+    let g = params.find_one_float("g", 0.0);
+    (
+        params.find_one_spectrum("sigma_a", sigma_a) * scale,
+        params.find_one_spectrum("sigma_s", sigma_s) * scale,
+        g,
+    )
+}
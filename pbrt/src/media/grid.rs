@@ -0,0 +1,201 @@
+use super::*;
+
+// Medium whose coefficients are scaled by a density given on a voxel grid filling the unit
+// cube of medium space. Values sit at the voxel centers and are trilinearly interpolated,
+// free paths are sampled with delta tracking and transmittance is estimated with ratio
+// tracking, so sigma_a + sigma_s has to be the same in every channel
+pub struct GridDensityMedium {
+    sigma_s: Spectrum,
+    sigma_t: f32,
+    g: f32,
+    nx: i32,
+    ny: i32,
+    nz: i32,
+    world_to_medium: Transform,
+    density: Vec<f32>,
+    inv_max_density: f32,
+}
+
+impl GridDensityMedium {
+    pub fn new(
+        sigma_a: Spectrum,
+        sigma_s: Spectrum,
+        g: f32,
+        nx: i32,
+        ny: i32,
+        nz: i32,
+        medium_to_world: &Transform,
+        density: Vec<f32>,
+    ) -> GridDensityMedium {
+        let max_density = density.iter().copied().fold(0.0, f32::max);
+        GridDensityMedium {
+            sigma_s,
+            sigma_t: (sigma_a + sigma_s)[0],
+            g,
+            nx,
+            ny,
+            nz,
+            world_to_medium: medium_to_world.inverse(),
+            density,
+            inv_max_density: 1.0 / max_density,
+        }
+    }
+
+    // The grid spans p0 to p1 of the medium's coordinate system
+    pub fn create(
+        params: &ParamSet,
+        medium_to_world: &Transform,
+    ) -> Result<GridDensityMedium, String> {
+        let (sigma_a, sigma_s, g) = scattering_properties(params);
+        // Grey RGB values don't upsample to exactly constant spectra, so only differences
+        // beyond rounding are reported. Like pbrt, the first channel is used either way
+        let sigma_t = sigma_a + sigma_s;
+        let spread = (sigma_t - Spectrum::from_value(sigma_t[0]))
+            .map(f32::abs)
+            .max_component_value();
+        if spread > 0.01 * sigma_t[0].abs() {
+            eprintln!(
+                "warning: heterogeneous medium needs the same sigma_a + sigma_s in every \
+                 channel, using {}",
+                sigma_t[0]
+            );
+        }
+        let nx = params.find_one_int("nx", 1);
+        let ny = params.find_one_int("ny", 1);
+        let nz = params.find_one_int("nz", 1);
+        let density = params.find_floats("density").unwrap_or(&[]).to_vec();
+        if nx < 1 || ny < 1 || nz < 1 || density.len() != (nx * ny * nz) as usize {
+            return Err(format!(
+                "heterogeneous medium has {} density values, expected nx * ny * nz = {}",
+                density.len(),
+                nx * ny * nz
+            ));
+        }
+        let p0 = params.find_one_point3("p0", Point3::new(0.0, 0.0, 0.0));
+        let p1 = params.find_one_point3("p1", Point3::new(1.0, 1.0, 1.0));
+        let data_to_medium = translate(p0.to_vec()) * scale(p1.x - p0.x, p1.y - p0.y, p1.z - p0.z);
+        Ok(GridDensityMedium::new(
+            sigma_a,
+            sigma_s,
+            g,
+            nx,
+            ny,
+            nz,
+            &(*medium_to_world * data_to_medium),
+            density,
+        ))
+    }
+
+    // Density at a voxel, zero outside the grid
+    fn d(&self, x: i32, y: i32, z: i32) -> f32 {
+        if x < 0 || x >= self.nx || y < 0 || y >= self.ny || z < 0 || z >= self.nz {
+            return 0.0;
+        }
+        self.density[((z * self.ny + y) * self.nx + x) as usize]
+    }
+
+    fn density_at(&self, p: Point3) -> f32 {
+        let samples = vec3(
+            p.x * self.nx as f32 - 0.5,
+            p.y * self.ny as f32 - 0.5,
+            p.z * self.nz as f32 - 0.5,
+        );
+        let (x, y, z) = (
+            samples.x.floor() as i32,
+            samples.y.floor() as i32,
+            samples.z.floor() as i32,
+        );
+        let d = vec3(
+            samples.x - x as f32,
+            samples.y - y as f32,
+            samples.z - z as f32,
+        );
+        let d00 = lerp(d.x, self.d(x, y, z), self.d(x + 1, y, z));
+        let d10 = lerp(d.x, self.d(x, y + 1, z), self.d(x + 1, y + 1, z));
+        let d01 = lerp(d.x, self.d(x, y, z + 1), self.d(x + 1, y, z + 1));
+        let d11 = lerp(d.x, self.d(x, y + 1, z + 1), self.d(x + 1, y + 1, z + 1));
+        let d0 = lerp(d.y, d00, d10);
+        let d1 = lerp(d.y, d01, d11);
+        lerp(d.z, d0, d1)
+    }
+
+    // The ray in medium space with t measured in world space distance, along with the
+    // part of it inside the grid
+    fn medium_ray(&self, ray: &Ray) -> Option<(Ray, f32, f32)> {
+        // Without any extinction there is nothing to collide with
+        if !(self.sigma_t > 0.0 && self.inv_max_density.is_finite()) {
+            return None;
+        }
+        let world_ray = Ray::new(ray.o, ray.d.normalize());
+        world_ray.t_max.set(ray.t_max.get() * ray.d.magnitude());
+        let medium_ray = self.world_to_medium.transform_ray(&world_ray);
+        let bounds =
+            Bounds3Df::from_two_points(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 1.0, 1.0));
+        match bounds.intersect_p(&medium_ray) {
+            (true, t_min, t_max) => Some((medium_ray, t_min, t_max)),
+            _ => None,
+        }
+    }
+
+    // Distance to the next tentative collision of delta and ratio tracking
+    fn step(&self, sampler: &mut dyn Sampler) -> f32 {
+        -(1.0 - sampler.get_1d()).ln() * self.inv_max_density / self.sigma_t
+    }
+}
+
+impl Medium for GridDensityMedium {
+    fn tr(&self, ray: &Ray, sampler: &mut dyn Sampler) -> Spectrum {
+        let (medium_ray, t_min, t_max) = match self.medium_ray(ray) {
+            Some(segment) => segment,
+            None => return Spectrum::from_value(1.0),
+        };
+        let mut tr = 1.0;
+        let mut t = t_min;
+        loop {
+            t += self.step(sampler);
+            if t >= t_max {
+                break;
+            }
+            let density = self.density_at(medium_ray.at(t));
+            tr *= 1.0 - (density * self.inv_max_density).max(0.0);
+            // Russian roulette once little light is left
+            if tr < 0.1 {
+                let q = (1.0 - tr).max(0.05);
+                if sampler.get_1d() < q {
+                    return Spectrum::new();
+                }
+                tr /= 1.0 - q;
+            }
+        }
+        Spectrum::from_value(tr)
+    }
+
+    fn sample(
+        &self,
+        ray: &Ray,
+        sampler: &mut dyn Sampler,
+    ) -> (Spectrum, Option<MediumInteraction>) {
+        let (medium_ray, t_min, t_max) = match self.medium_ray(ray) {
+            Some(segment) => segment,
+            None => return (Spectrum::from_value(1.0), None),
+        };
+        let mut t = t_min;
+        loop {
+            t += self.step(sampler);
+            if t >= t_max {
+                return (Spectrum::from_value(1.0), None);
+            }
+            // A real collision with probability density / max density, a null one otherwise
+            if self.density_at(medium_ray.at(t)) * self.inv_max_density > sampler.get_1d() {
+                let mi = MediumInteraction::new(
+                    ray.at(t / ray.d.magnitude()),
+                    -ray.d,
+                    ray.time,
+                    ray.medium.clone(),
+                    HenyeyGreenstein::new(self.g),
+                );
+                return (self.sigma_s / self.sigma_t, Some(mi));
+            }
+        }
+    }
+}
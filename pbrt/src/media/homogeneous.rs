@@ -0,0 +1,67 @@
+use super::*;
+
+// Medium with the same coefficients everywhere
+pub struct HomogeneousMedium {
+    sigma_s: Spectrum,
+    sigma_t: Spectrum,
+    g: f32,
+}
+
+impl HomogeneousMedium {
+    pub fn new(sigma_a: Spectrum, sigma_s: Spectrum, g: f32) -> HomogeneousMedium {
+        HomogeneousMedium {
+            sigma_s,
+            sigma_t: sigma_a + sigma_s,
+            g,
+        }
+    }
+
+    pub fn create(params: &ParamSet) -> HomogeneousMedium {
+        let (sigma_a, sigma_s, g) = scattering_properties(params);
+        HomogeneousMedium::new(sigma_a, sigma_s, g)
+    }
+}
+
+impl Medium for HomogeneousMedium {
+    fn tr(&self, ray: &Ray, _sampler: &mut dyn Sampler) -> Spectrum {
+        let distance = (ray.t_max.get() * ray.d.magnitude()).min(f32::MAX);
+        (-self.sigma_t * distance).exp()
+    }
+
+    fn sample(
+        &self,
+        ray: &Ray,
+        sampler: &mut dyn Sampler,
+    ) -> (Spectrum, Option<MediumInteraction>) {
+        // Distances are sampled with the extinction of a randomly picked channel, the
+        // weight divides by the average density of all channels
+        let n_channels = self.sigma_t.c.len();
+        let channel = ((sampler.get_1d() * n_channels as f32) as usize).min(n_channels - 1);
+        let distance = -(1.0 - sampler.get_1d()).ln() / self.sigma_t[channel];
+        let t = (distance / ray.d.magnitude()).min(ray.t_max.get());
+        let sampled_medium = t < ray.t_max.get();
+
+        let tr = (-self.sigma_t * (t * ray.d.magnitude()).min(f32::MAX)).exp();
+        let density = if sampled_medium {
+            self.sigma_t * tr
+        } else {
+            tr
+        };
+        let mut pdf = density.c.iter().sum::<f32>() / n_channels as f32;
+        if pdf == 0.0 {
+            pdf = 1.0;
+        }
+        if sampled_medium {
+            let mi = MediumInteraction::new(
+                ray.at(t),
+                -ray.d,
+                ray.time,
+                ray.medium.clone(),
+                HenyeyGreenstein::new(self.g),
+            );
+            (tr * self.sigma_s / pdf, Some(mi))
+        } else {
+            (tr / pdf, None)
+        }
+    }
+}
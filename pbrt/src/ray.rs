@@ -1,22 +1,24 @@
 use crate::core::Medium;
 use crate::math::*;
 use std::cell::Cell;
-use std::rc::Rc;
+use std::sync::Arc;
 
-pub struct Ray<'a> {
+#[derive(Clone)]
+pub struct Ray {
     pub o: Point3,
     pub d: Vec3,
     pub t_max: Cell<f32>, // This is mutable in original C++ code so we need Cell to mutate it
     pub time: f32,
-    pub medium: Option<&'a Medium>,
+    // Medium the origin of the ray is in, None for vacuum
+    pub medium: Option<Arc<dyn Medium>>,
 }
 
-impl<'a> Ray<'a> {
-    pub fn new(o: Point3, d: Vec3) -> Ray<'a> {
+impl Ray {
+    pub fn new(o: Point3, d: Vec3) -> Ray {
         Ray {
             o,
             d,
-            t_max: Cell::new(std::f32::INFINITY),
+            t_max: Cell::new(f32::INFINITY),
             time: 0.0,
             medium: None,
         }
@@ -27,8 +29,9 @@ impl<'a> Ray<'a> {
     }
 }
 
-pub struct RayDifferential<'a> {
-    pub ray: Ray<'a>,
+#[derive(Clone)]
+pub struct RayDifferential {
+    pub ray: Ray,
     pub hasDifferentials: bool,
     pub rxOrigin: Point3,
     pub ryOrigin: Point3,
@@ -36,16 +39,16 @@ pub struct RayDifferential<'a> {
     pub ryDirection: Vec3,
 }
 
-impl<'a> RayDifferential<'a> {
+impl RayDifferential {
     // TODO: maybe this is better to not modify self but return new self
-    pub fn scale_differentials(&mut self, scalar: f32) -> () {
+    pub fn scale_differentials(&mut self, scalar: f32) {
         self.rxOrigin = self.ray.o + (self.rxOrigin - self.ray.o) * scalar;
         self.ryOrigin = self.ray.o + (self.ryOrigin - self.ray.o) * scalar;
         self.rxDirection = self.ray.d + (self.rxDirection - self.ray.d) * scalar;
         self.ryDirection = self.ray.d + (self.ryDirection - self.ray.d) * scalar;
     }
 
-    pub fn new(o: Point3, d: Vec3) -> RayDifferential<'a> {
+    pub fn new(o: Point3, d: Vec3) -> RayDifferential {
         RayDifferential {
             ray: Ray::new(o, d),
             hasDifferentials: false,
@@ -57,7 +60,7 @@ impl<'a> RayDifferential<'a> {
     }
 }
 
-impl<'a> From<Ray<'a>> for RayDifferential<'a> {
+impl From<Ray> for RayDifferential {
     fn from(ray: Ray) -> RayDifferential {
         RayDifferential {
             ray,
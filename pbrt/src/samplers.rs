@@ -1,21 +1,102 @@
 use crate::cameras::CameraSample;
 use crate::math::*;
 
-pub trait Sampler {
-    fn clone(&self) -> Box<Sampler>;
+mod global_sampler;
+mod halton;
+mod pixel_sampler;
+mod sobol;
+mod stratified;
 
-    fn start_pixel(&self, pixel: Point2i) -> SamplerPixel;
-    fn get_camera_sample(&self, pixel: Point2i) -> CameraSample;
-    fn get_samples_per_pixel(&self) -> i32;
-    fn get_2d(&self) -> Point2;
+pub use global_sampler::{GlobalSampler, GlobalSamplerInterface};
+pub use halton::HaltonSampler;
+pub use pixel_sampler::PixelSampler;
+pub use sobol::SobolSampler;
+pub use stratified::StratifiedSampler;
+
+// Samples are taken pixel by pixel: start_pixel, then the dimensions of one sample through
+// get_1d and get_2d, then start_next_sample until it returns false
+pub trait Sampler: Send + Sync {
+    // Copy for a tile, seeded so every tile gets its own deterministic sequence
+    fn clone(&self, seed: i32) -> Box<dyn Sampler>;
+
+    fn get_samples_per_pixel(&self) -> i64;
+    fn start_pixel(&mut self, pixel: Point2i);
+    fn start_next_sample(&mut self) -> bool;
+
+    fn get_1d(&mut self) -> f32;
+    fn get_2d(&mut self) -> Point2;
+
+    // Arrays of n values for every sample, requested before rendering starts and handed
+    // out in request order. Only the direct lighting integrator uses them, for its light
+    // and BSDF samples, so there are no one-dimensional arrays
+    fn request_2d_array(&mut self, n: usize);
+    fn round_count(&self, n: usize) -> usize {
+        n
+    }
+    fn get_2d_array(&mut self, n: usize) -> Option<&[Point2]>;
+
+    fn get_camera_sample(&mut self, pixel: Point2i) -> CameraSample {
+        let offset = self.get_2d();
+        let p_film = Point2::new(pixel.x as f32 + offset.x, pixel.y as f32 + offset.y);
+        let time = self.get_1d();
+        let p_lens = self.get_2d();
+        CameraSample {
+            p_film,
+            p_lens,
+            time,
+        }
+    }
 }
 
-pub struct SamplerPixel();
+// Bookkeeping shared by all samplers: the current pixel and sample, and the sample arrays
+#[derive(Clone)]
+struct SamplerState {
+    samples_per_pixel: i64,
+    current_pixel: Point2i,
+    current_pixel_sample_index: i64,
+    samples_2d_array_sizes: Vec<usize>,
+    // Every array holds its values for all samples of the pixel
+    sample_array_2d: Vec<Vec<Point2>>,
+    array_2d_offset: usize,
+}
 
-impl Iterator for SamplerPixel {
-    type Item = ();
+impl SamplerState {
+    fn new(samples_per_pixel: i64) -> SamplerState {
+        SamplerState {
+            samples_per_pixel,
+            current_pixel: Point2i::new(0, 0),
+            current_pixel_sample_index: 0,
+            samples_2d_array_sizes: Vec::new(),
+            sample_array_2d: Vec::new(),
+            array_2d_offset: 0,
+        }
+    }
+
+    fn start_pixel(&mut self, pixel: Point2i) {
+        self.current_pixel = pixel;
+        self.current_pixel_sample_index = 0;
+        self.array_2d_offset = 0;
+    }
+
+    fn start_next_sample(&mut self) -> bool {
+        self.array_2d_offset = 0;
+        self.current_pixel_sample_index += 1;
+        self.current_pixel_sample_index < self.samples_per_pixel
+    }
+
+    fn request_2d_array(&mut self, n: usize) {
+        self.samples_2d_array_sizes.push(n);
+        self.sample_array_2d.push(vec![
+            Point2::new(0.0, 0.0);
+            n * self.samples_per_pixel as usize
+        ]);
+    }
 
-    fn next(&mut self) -> Option<()> {
-        None
+    fn get_2d_array(&mut self, n: usize) -> Option<&[Point2]> {
+        let array = self.sample_array_2d.get(self.array_2d_offset)?;
+        debug_assert_eq!(self.samples_2d_array_sizes[self.array_2d_offset], n);
+        self.array_2d_offset += 1;
+        let start = self.current_pixel_sample_index as usize * n;
+        Some(&array[start..start + n])
     }
 }
@@ -0,0 +1,128 @@
+use super::{Sampler, SamplerState};
+use crate::math::*;
+
+// Dimensions reserved for the camera sample, arrays start right after them
+const ARRAY_START_DIM: usize = 5;
+
+// A low discrepancy sequence spanning the whole image, mapped to pixels by the implementor
+pub trait GlobalSamplerInterface: Send + Sync {
+    fn clone(&self) -> Box<dyn GlobalSamplerInterface>;
+    // Index in the global sequence of the sample_num-th sample falling into pixel
+    fn get_index_for_sample(&mut self, sample_num: i64, pixel: Point2i) -> i64;
+    // Value of a dimension of the index-th sample. The first two dimensions are returned
+    // relative to pixel
+    fn sample_dimension(&self, index: i64, dimension: usize, pixel: Point2i) -> f32;
+}
+
+pub struct GlobalSampler {
+    state: SamplerState,
+    dimension: usize,
+    interval_sample_index: i64,
+    array_end_dim: usize,
+    implementor: Box<dyn GlobalSamplerInterface>,
+}
+
+impl GlobalSampler {
+    pub fn new(
+        samples_per_pixel: i64,
+        implementor: Box<dyn GlobalSamplerInterface>,
+    ) -> GlobalSampler {
+        GlobalSampler {
+            state: SamplerState::new(samples_per_pixel),
+            dimension: 0,
+            interval_sample_index: 0,
+            array_end_dim: ARRAY_START_DIM,
+            implementor,
+        }
+    }
+
+    fn index_for_sample(&mut self, sample_num: i64) -> i64 {
+        self.implementor
+            .get_index_for_sample(sample_num, self.state.current_pixel)
+    }
+
+    fn next_dimension(&mut self, count: usize) -> usize {
+        // Dimensions used by the arrays are skipped
+        if self.dimension >= ARRAY_START_DIM && self.dimension < self.array_end_dim {
+            self.dimension = self.array_end_dim;
+        }
+        let dimension = self.dimension;
+        self.dimension += count;
+        dimension
+    }
+}
+
+impl Sampler for GlobalSampler {
+    // The sequence is deterministic, so there is nothing to seed
+    fn clone(&self, _seed: i32) -> Box<dyn Sampler> {
+        Box::new(GlobalSampler {
+            state: self.state.clone(),
+            dimension: self.dimension,
+            interval_sample_index: self.interval_sample_index,
+            array_end_dim: self.array_end_dim,
+            implementor: self.implementor.clone(),
+        })
+    }
+
+    fn get_samples_per_pixel(&self) -> i64 {
+        self.state.samples_per_pixel
+    }
+
+    fn start_pixel(&mut self, pixel: Point2i) {
+        self.state.start_pixel(pixel);
+        self.dimension = 0;
+        self.interval_sample_index = self.index_for_sample(0);
+        self.array_end_dim = ARRAY_START_DIM + 2 * self.state.sample_array_2d.len();
+
+        // Arrays take the values of a single dimension over several samples
+        let samples_per_pixel = self.state.samples_per_pixel as usize;
+        let mut dimension = ARRAY_START_DIM;
+        for i in 0..self.state.sample_array_2d.len() {
+            let n_samples = self.state.samples_2d_array_sizes[i] * samples_per_pixel;
+            for j in 0..n_samples {
+                let index = self.index_for_sample(j as i64);
+                self.state.sample_array_2d[i][j] = Point2::new(
+                    self.implementor.sample_dimension(index, dimension, pixel),
+                    self.implementor
+                        .sample_dimension(index, dimension + 1, pixel),
+                );
+            }
+            dimension += 2;
+        }
+    }
+
+    fn start_next_sample(&mut self) -> bool {
+        self.dimension = 0;
+        self.interval_sample_index =
+            self.index_for_sample(self.state.current_pixel_sample_index + 1);
+        self.state.start_next_sample()
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        let dimension = self.next_dimension(1);
+        self.implementor.sample_dimension(
+            self.interval_sample_index,
+            dimension,
+            self.state.current_pixel,
+        )
+    }
+
+    fn get_2d(&mut self) -> Point2 {
+        let dimension = self.next_dimension(2);
+        let index = self.interval_sample_index;
+        let pixel = self.state.current_pixel;
+        Point2::new(
+            self.implementor.sample_dimension(index, dimension, pixel),
+            self.implementor
+                .sample_dimension(index, dimension + 1, pixel),
+        )
+    }
+
+    fn request_2d_array(&mut self, n: usize) {
+        self.state.request_2d_array(n)
+    }
+
+    fn get_2d_array(&mut self, n: usize) -> Option<&[Point2]> {
+        self.state.get_2d_array(n)
+    }
+}
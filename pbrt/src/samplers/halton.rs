@@ -0,0 +1,118 @@
+use super::{GlobalSampler, GlobalSamplerInterface};
+use crate::core::lowdiscrepancy::*;
+use crate::core::ParamSet;
+use crate::math::*;
+
+// The Halton sequence repeats over tiles of at most this many pixels on a side
+const MAX_RESOLUTION: i32 = 128;
+
+// Halton sequence with the first two dimensions scaled to cover the image, so each pixel
+// takes every sample_stride-th point. Dimensions past those are scrambled with random digit
+// permutations
+#[derive(Clone)]
+pub struct HaltonSampler {
+    base_scales: Point2i,
+    base_exponents: Point2i,
+    sample_stride: i64,
+    mult_inverse: [i64; 2],
+    pixel_for_offset: Point2i,
+    offset_for_current_pixel: i64,
+    sample_at_pixel_center: bool,
+}
+
+impl HaltonSampler {
+    pub fn new(
+        samples_per_pixel: i64,
+        sample_bounds: Bounds2Di,
+        sample_at_pixel_center: bool,
+    ) -> GlobalSampler {
+        // The first two dimensions are scaled by powers of two and three covering the
+        // image, up to MAX_RESOLUTION
+        let res = sample_bounds.max - sample_bounds.min;
+        let mut base_scales = Point2i::new(1, 1);
+        let mut base_exponents = Point2i::new(0, 0);
+        for i in 0..2 {
+            let base = if i == 0 { 2 } else { 3 };
+            while base_scales[i] < min(res[i], MAX_RESOLUTION) {
+                base_scales[i] *= base;
+                base_exponents[i] += 1;
+            }
+        }
+        let sample_stride = i64::from(base_scales.x) * i64::from(base_scales.y);
+        let mult_inverse = [
+            multiplicative_inverse(i64::from(base_scales.y), i64::from(base_scales.x)),
+            multiplicative_inverse(i64::from(base_scales.x), i64::from(base_scales.y)),
+        ];
+        GlobalSampler::new(
+            samples_per_pixel,
+            Box::new(HaltonSampler {
+                base_scales,
+                base_exponents,
+                sample_stride,
+                mult_inverse,
+                pixel_for_offset: Point2i::new(i32::MAX, i32::MAX),
+                offset_for_current_pixel: 0,
+                sample_at_pixel_center,
+            }),
+        )
+    }
+
+    pub fn create(params: &ParamSet, sample_bounds: Bounds2Di) -> GlobalSampler {
+        let samples_per_pixel = params.find_one_int("pixelsamples", 16).max(1);
+        let sample_at_pixel_center = params.find_one_bool("samplepixelcenter", false);
+        HaltonSampler::new(
+            i64::from(samples_per_pixel),
+            sample_bounds,
+            sample_at_pixel_center,
+        )
+    }
+}
+
+impl GlobalSamplerInterface for HaltonSampler {
+    fn clone(&self) -> Box<dyn GlobalSamplerInterface> {
+        Box::new(Clone::clone(self))
+    }
+
+    fn get_index_for_sample(&mut self, sample_num: i64, pixel: Point2i) -> i64 {
+        if pixel != self.pixel_for_offset {
+            // Find the first sample whose scaled first two dimensions land in the pixel
+            self.offset_for_current_pixel = 0;
+            if self.sample_stride > 1 {
+                let pm = Point2i::new(
+                    pixel.x.rem_euclid(MAX_RESOLUTION),
+                    pixel.y.rem_euclid(MAX_RESOLUTION),
+                );
+                for i in 0..2 {
+                    let base = if i == 0 { 2 } else { 3 };
+                    let dim_offset =
+                        inverse_radical_inverse(base, pm[i] as u64, self.base_exponents[i] as u32)
+                            as i64;
+                    self.offset_for_current_pixel += dim_offset
+                        * (self.sample_stride / i64::from(self.base_scales[i]))
+                        * self.mult_inverse[i];
+                }
+                self.offset_for_current_pixel %= self.sample_stride;
+            }
+            self.pixel_for_offset = pixel;
+        }
+        self.offset_for_current_pixel + sample_num * self.sample_stride
+    }
+
+    fn sample_dimension(&self, index: i64, dimension: usize, _pixel: Point2i) -> f32 {
+        if self.sample_at_pixel_center && dimension < 2 {
+            return 0.5;
+        }
+        // Dimensions past the prime table wrap around
+        let dimension = dimension % PRIME_TABLE_SIZE;
+        let index = index as u64;
+        match dimension {
+            0 => radical_inverse(0, index >> self.base_exponents.x),
+            1 => radical_inverse(1, index / self.base_scales.y as u64),
+            _ => scrambled_radical_inverse(
+                dimension,
+                index,
+                &radical_inverse_permutations()[permutation_offset(dimension)..],
+            ),
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use super::SamplerState;
+use crate::core::RNG;
+use crate::math::*;
+
+// Base of samplers that generate all samples of a pixel at once, for a fixed number of
+// dimensions. Dimensions past those come from the random number generator
+#[derive(Clone)]
+pub struct PixelSampler {
+    pub(super) state: SamplerState,
+    // Values of every sampled dimension for all samples of the pixel
+    pub(super) samples_1d: Vec<Vec<f32>>,
+    pub(super) samples_2d: Vec<Vec<Point2>>,
+    current_1d_dimension: usize,
+    current_2d_dimension: usize,
+    pub(super) rng: RNG,
+}
+
+impl PixelSampler {
+    pub fn new(samples_per_pixel: i64, n_sampled_dimensions: usize) -> PixelSampler {
+        let n = samples_per_pixel as usize;
+        PixelSampler {
+            state: SamplerState::new(samples_per_pixel),
+            samples_1d: vec![vec![0.0; n]; n_sampled_dimensions],
+            samples_2d: vec![vec![Point2::new(0.0, 0.0); n]; n_sampled_dimensions],
+            current_1d_dimension: 0,
+            current_2d_dimension: 0,
+            rng: RNG::default(),
+        }
+    }
+
+    pub fn reseed(&mut self, seed: i32) {
+        self.rng.set_sequence(seed as u64);
+    }
+
+    pub fn samples_per_pixel(&self) -> i64 {
+        self.state.samples_per_pixel
+    }
+
+    pub fn start_pixel(&mut self, pixel: Point2i) {
+        self.current_1d_dimension = 0;
+        self.current_2d_dimension = 0;
+        self.state.start_pixel(pixel);
+    }
+
+    pub fn start_next_sample(&mut self) -> bool {
+        self.current_1d_dimension = 0;
+        self.current_2d_dimension = 0;
+        self.state.start_next_sample()
+    }
+
+    pub fn get_1d(&mut self) -> f32 {
+        match self.samples_1d.get(self.current_1d_dimension) {
+            Some(samples) => {
+                self.current_1d_dimension += 1;
+                samples[self.state.current_pixel_sample_index as usize]
+            }
+            None => self.rng.uniform_float(),
+        }
+    }
+
+    pub fn get_2d(&mut self) -> Point2 {
+        match self.samples_2d.get(self.current_2d_dimension) {
+            Some(samples) => {
+                self.current_2d_dimension += 1;
+                samples[self.state.current_pixel_sample_index as usize]
+            }
+            None => Point2::new(self.rng.uniform_float(), self.rng.uniform_float()),
+        }
+    }
+
+    pub fn request_2d_array(&mut self, n: usize) {
+        self.state.request_2d_array(n);
+    }
+
+    pub fn get_2d_array(&mut self, n: usize) -> Option<&[Point2]> {
+        self.state.get_2d_array(n)
+    }
+}
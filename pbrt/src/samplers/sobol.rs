@@ -0,0 +1,70 @@
+use super::{GlobalSampler, GlobalSamplerInterface};
+use crate::core::lowdiscrepancy::*;
+use crate::core::{ParamSet, ONE_MINUS_EPSILON, RNG};
+use crate::math::*;
+
+// Sobol sequence over a power of two square covering the sample bounds. The sample count
+// per pixel is rounded up to a power of two as well. Only the first NUM_SOBOL_DIMENSIONS
+// dimensions are Sobol, the ones after them get independent uniform values
+#[derive(Clone)]
+pub struct SobolSampler {
+    sample_bounds: Bounds2Di,
+    resolution: i32,
+    indexer: SobolPixelIndexer,
+}
+
+impl SobolSampler {
+    pub fn new(samples_per_pixel: i64, sample_bounds: Bounds2Di) -> GlobalSampler {
+        let samples_per_pixel = if (samples_per_pixel as u64).is_power_of_two() {
+            samples_per_pixel
+        } else {
+            let rounded = (samples_per_pixel as u64).next_power_of_two() as i64;
+            eprintln!(
+                "warning: Sobol sampler rounds the sample count {} up to {}",
+                samples_per_pixel, rounded
+            );
+            rounded
+        };
+        let diagonal = sample_bounds.diagonal();
+        let resolution = (max(diagonal.x, diagonal.y).max(1) as u32).next_power_of_two();
+        let log2_resolution = resolution.trailing_zeros();
+        GlobalSampler::new(
+            samples_per_pixel,
+            Box::new(SobolSampler {
+                sample_bounds,
+                resolution: resolution as i32,
+                indexer: SobolPixelIndexer::new(log2_resolution),
+            }),
+        )
+    }
+
+    pub fn create(params: &ParamSet, sample_bounds: Bounds2Di) -> GlobalSampler {
+        let samples_per_pixel = params.find_one_int("pixelsamples", 16).max(1);
+        SobolSampler::new(i64::from(samples_per_pixel), sample_bounds)
+    }
+}
+
+impl GlobalSamplerInterface for SobolSampler {
+    fn clone(&self) -> Box<dyn GlobalSamplerInterface> {
+        Box::new(Clone::clone(self))
+    }
+
+    fn get_index_for_sample(&mut self, sample_num: i64, pixel: Point2i) -> i64 {
+        let p = Point2i::from_vec(pixel - self.sample_bounds.min);
+        self.indexer.index(sample_num, p)
+    }
+
+    fn sample_dimension(&self, index: i64, dimension: usize, pixel: Point2i) -> f32 {
+        if dimension >= NUM_SOBOL_DIMENSIONS {
+            // Reusing Sobol dimensions here would correlate them with the earlier ones
+            let sequence = (index as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15) ^ dimension as u64;
+            return RNG::new(sequence).uniform_float();
+        }
+        let v = sobol_sample(index, dimension);
+        if dimension < 2 {
+            let v = v * self.resolution as f32 + self.sample_bounds.min[dimension] as f32;
+            return clamp(v - pixel[dimension] as f32, 0.0, ONE_MINUS_EPSILON);
+        }
+        v
+    }
+}
@@ -0,0 +1,108 @@
+use super::{PixelSampler, Sampler};
+use crate::core::sampling::{latin_hypercube, shuffle, stratified_sample_1d, stratified_sample_2d};
+use crate::core::ParamSet;
+use crate::math::*;
+
+// Jittered samples on an x_pixel_samples by y_pixel_samples grid in every pixel, with the
+// dimensions randomly paired up
+#[derive(Clone)]
+pub struct StratifiedSampler {
+    pixel: PixelSampler,
+    x_pixel_samples: usize,
+    y_pixel_samples: usize,
+    jitter_samples: bool,
+}
+
+impl StratifiedSampler {
+    pub fn new(
+        x_pixel_samples: usize,
+        y_pixel_samples: usize,
+        jitter_samples: bool,
+        n_sampled_dimensions: usize,
+    ) -> StratifiedSampler {
+        StratifiedSampler {
+            pixel: PixelSampler::new(
+                (x_pixel_samples * y_pixel_samples) as i64,
+                n_sampled_dimensions,
+            ),
+            x_pixel_samples,
+            y_pixel_samples,
+            jitter_samples,
+        }
+    }
+
+    pub fn create(params: &ParamSet) -> StratifiedSampler {
+        let jitter = params.find_one_bool("jitter", true);
+        let x_samples = params.find_one_int("xsamples", 4).max(1) as usize;
+        let y_samples = params.find_one_int("ysamples", 4).max(1) as usize;
+        let sampled_dimensions = params.find_one_int("dimensions", 4).max(0) as usize;
+        StratifiedSampler::new(x_samples, y_samples, jitter, sampled_dimensions)
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn clone(&self, seed: i32) -> Box<dyn Sampler> {
+        let mut sampler = Clone::clone(self);
+        sampler.pixel.reseed(seed);
+        Box::new(sampler)
+    }
+
+    fn get_samples_per_pixel(&self) -> i64 {
+        self.pixel.samples_per_pixel()
+    }
+
+    fn start_pixel(&mut self, pixel: Point2i) {
+        let jitter = self.jitter_samples;
+        let sampler = &mut self.pixel;
+        for samples in sampler.samples_1d.iter_mut() {
+            stratified_sample_1d(samples, &mut sampler.rng, jitter);
+            shuffle(samples, 1, &mut sampler.rng);
+        }
+        for samples in sampler.samples_2d.iter_mut() {
+            stratified_sample_2d(
+                samples,
+                self.x_pixel_samples,
+                self.y_pixel_samples,
+                &mut sampler.rng,
+                jitter,
+            );
+            shuffle(samples, 1, &mut sampler.rng);
+        }
+
+        // Arrays are stratified within every sample
+        let state = &mut sampler.state;
+        for (array, &count) in state
+            .sample_array_2d
+            .iter_mut()
+            .zip(&state.samples_2d_array_sizes)
+        {
+            if count == 0 {
+                continue;
+            }
+            for samples in array.chunks_mut(count) {
+                latin_hypercube(samples, &mut sampler.rng);
+            }
+        }
+        sampler.start_pixel(pixel);
+    }
+
+    fn start_next_sample(&mut self) -> bool {
+        self.pixel.start_next_sample()
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        self.pixel.get_1d()
+    }
+
+    fn get_2d(&mut self) -> Point2 {
+        self.pixel.get_2d()
+    }
+
+    fn request_2d_array(&mut self, n: usize) {
+        self.pixel.request_2d_array(n)
+    }
+
+    fn get_2d_array(&mut self, n: usize) -> Option<&[Point2]> {
+        self.pixel.get_2d_array(n)
+    }
+}
@@ -2,11 +2,11 @@ mod sphere;
 
 pub use sphere::Sphere;
 
-use crate::core::SurfaceInteraction;
-use crate::math::{Bounds3Df, Transform};
+use crate::core::{Interaction, SurfaceInteraction};
+use crate::math::*;
 use crate::ray::Ray;
 
-pub trait ShapeInterface {
+pub trait ShapeInterface: Send + Sync {
     fn object_bound(&self) -> Bounds3Df;
     fn world_bound(&self, shape: &Shape) -> Bounds3Df {
         shape.object_to_world.transform_bounds(&self.object_bound())
@@ -23,6 +23,61 @@ pub trait ShapeInterface {
     }
 
     fn area(&self) -> f32;
+
+    // Point distributed uniformly over the surface, with its pdf with respect to area
+    fn sample(&self, shape: &Shape, u: &Point2) -> (Interaction, f32);
+
+    // Point on the surface as seen from reference, with its pdf with respect to solid angle
+    // at reference. Uniform over the area unless the shape can do better
+    fn sample_from(
+        &self,
+        shape: &Shape,
+        reference: &Interaction,
+        u: &Point2,
+    ) -> (Interaction, f32) {
+        area_sample_to_solid_angle(reference, self.sample(shape, u))
+    }
+
+    // Solid angle density of sample_from for direction wi
+    fn pdf_from(&self, shape: &Shape, reference: &Interaction, wi: &Vec3) -> f32 {
+        area_pdf_to_solid_angle(self, shape, reference, wi)
+    }
+}
+
+// Converts the pdf of an area sample to solid angle at reference
+fn area_sample_to_solid_angle(
+    reference: &Interaction,
+    (intr, pdf): (Interaction, f32),
+) -> (Interaction, f32) {
+    let wi = intr.p - reference.p;
+    if wi.magnitude2() == 0.0 {
+        return (intr, 0.0);
+    }
+    let wi = wi.normalize();
+    let pdf = pdf * (reference.p - intr.p).magnitude2() / dot(intr.n, -wi).abs();
+    (intr, if pdf.is_infinite() { 0.0 } else { pdf })
+}
+
+// Solid angle density of uniform area sampling, for the point of the shape wi points at
+fn area_pdf_to_solid_angle<S: ShapeInterface + ?Sized>(
+    shape_impl: &S,
+    shape: &Shape,
+    reference: &Interaction,
+    wi: &Vec3,
+) -> f32 {
+    let ray = reference.spawn_ray(*wi);
+    match shape_impl.intersect(shape, &ray, false) {
+        Some((_, isect)) => {
+            let pdf = (reference.p - isect.interaction.p).magnitude2()
+                / (dot(isect.interaction.n, -*wi).abs() * shape_impl.area());
+            if pdf.is_infinite() {
+                0.0
+            } else {
+                pdf
+            }
+        }
+        None => 0.0,
+    }
 }
 
 pub struct Shape {
@@ -49,23 +104,31 @@ impl Shape {
         }
     }
 
-    pub fn object_bound(&self) -> Bounds3Df {
-        self.shape_impl.object_bound()
-    }
-
     pub fn world_bound(&self) -> Bounds3Df {
         self.shape_impl.world_bound(self)
     }
 
-    fn intersect(&self, ray: &Ray, test_alpha_texture: bool) -> Option<(f32, SurfaceInteraction)> {
+    pub fn intersect(
+        &self,
+        ray: &Ray,
+        test_alpha_texture: bool,
+    ) -> Option<(f32, SurfaceInteraction<'_>)> {
         self.shape_impl.intersect(self, ray, test_alpha_texture)
     }
 
-    fn intersect_p(&self, ray: &Ray, test_alpha_texture: bool) -> bool {
+    pub fn intersect_p(&self, ray: &Ray, test_alpha_texture: bool) -> bool {
         self.shape_impl.intersect_p(self, ray, test_alpha_texture)
     }
 
-    fn area(&self) -> f32 {
+    pub fn area(&self) -> f32 {
         self.shape_impl.area()
     }
+
+    pub fn sample_from(&self, reference: &Interaction, u: &Point2) -> (Interaction, f32) {
+        self.shape_impl.sample_from(self, reference, u)
+    }
+
+    pub fn pdf_from(&self, reference: &Interaction, wi: &Vec3) -> f32 {
+        self.shape_impl.pdf_from(self, reference, wi)
+    }
 }
@@ -1,4 +1,6 @@
 use super::*;
+use crate::core::sampling::*;
+use crate::core::{offset_ray_origin, ParamSet};
 use crate::math::*;
 
 pub struct Sphere {
@@ -13,8 +15,8 @@ pub struct Sphere {
 impl ShapeInterface for Sphere {
     fn object_bound(&self) -> Bounds3Df {
         Bounds3Df {
-            min: Point3::new(-self.radius, self.radius, self.z_min),
-            max: Point3::new(-self.radius, self.radius, self.z_max),
+            min: Point3::new(-self.radius, -self.radius, self.z_min),
+            max: Point3::new(self.radius, self.radius, self.z_max),
         }
     }
 
@@ -22,7 +24,7 @@ impl ShapeInterface for Sphere {
         &self,
         shape: &'a Shape,
         r: &Ray,
-        test_alpha_texture: bool,
+        _test_alpha_texture: bool,
     ) -> Option<(f32, SurfaceInteraction<'a>)> {
         let ray = shape.world_to_object.transform_ray(r); // TODO: this should be with floating point errors
                                                           //TODO: use EFloat instead of floats (page 135)
@@ -55,6 +57,7 @@ impl ShapeInterface for Sphere {
             p_hit.x = 1e-5 * self.radius;
         }
         // Refine sphere intersection point 255
+        p_hit = p_hit * (self.radius / p_hit.to_vec().magnitude());
         let mut phi = p_hit.y.atan2(p_hit.x);
         if phi < 0.0 {
             phi += 2.0 * std::f32::consts::PI;
@@ -73,6 +76,7 @@ impl ShapeInterface for Sphere {
                 p_hit.x = 1e-5 * self.radius;
             }
             // Refine sphere intersection point 255
+            p_hit = p_hit * (self.radius / p_hit.to_vec().magnitude());
             phi = p_hit.y.atan2(p_hit.x);
             if phi < 0.0 {
                 phi += 2.0 * std::f32::consts::PI;
@@ -121,8 +125,7 @@ impl ShapeInterface for Sphere {
         let dndv =
             Normal3f::from((g * F - f * G) * inv_egf2 * dpdu + (f * F - g * E) * inv_egf2 * dpdv);
 
-        // TODO: calculate this
-        let p_error = Vec3::zero();
+        let p_error = gamma(5) * vec3(p_hit.x.abs(), p_hit.y.abs(), p_hit.z.abs());
         let isect =
             shape
                 .object_to_world
@@ -142,7 +145,101 @@ impl ShapeInterface for Sphere {
     }
 
     fn area(&self) -> f32 {
-        0.0
+        self.phi_max * self.radius * (self.z_max - self.z_min)
+    }
+
+    // Samples the whole sphere, ignoring z and phi limits as the book does
+    fn sample(&self, shape: &Shape, u: &Point2) -> (Interaction, f32) {
+        let p_obj = Point3::from_vec(self.radius * uniform_sample_sphere(*u));
+        let mut n = shape
+            .object_to_world
+            .transform_normal(p_obj.to_vec())
+            .normalize();
+        if shape.reverse_orientation {
+            n = -n;
+        }
+        // Reproject onto the surface to limit the error
+        let p_obj = p_obj * (self.radius / p_obj.to_vec().magnitude());
+        let p_obj_error = gamma(5) * vec3(p_obj.x.abs(), p_obj.y.abs(), p_obj.z.abs());
+        let p = shape.object_to_world.transform_point(p_obj);
+        let p_error = shape.object_to_world.transform_error(p_obj_error);
+        (Interaction::new(p, n, p_error, 0.0), 1.0 / self.area())
+    }
+
+    // Samples the cone of directions the sphere covers as seen from reference
+    fn sample_from(
+        &self,
+        shape: &Shape,
+        reference: &Interaction,
+        u: &Point2,
+    ) -> (Interaction, f32) {
+        let p_center = shape
+            .object_to_world
+            .transform_point(Point3::new(0.0, 0.0, 0.0));
+        let p_origin = offset_ray_origin(
+            reference.p,
+            reference.p_error,
+            reference.n,
+            p_center - reference.p,
+        );
+        if (p_origin - p_center).magnitude2() <= self.radius * self.radius {
+            // Inside the sphere every direction hits it, fall back to area sampling
+            return area_sample_to_solid_angle(reference, self.sample(shape, u));
+        }
+
+        let sin_theta_max = self.radius / (reference.p - p_center).magnitude();
+        let sin_theta_max2 = sin_theta_max * sin_theta_max;
+        let inv_sin_theta_max = 1.0 / sin_theta_max;
+        let cos_theta_max = (1.0 - sin_theta_max2).max(0.0).sqrt();
+
+        let mut cos_theta = (cos_theta_max - 1.0) * u.x + 1.0;
+        let mut sin_theta2 = 1.0 - cos_theta * cos_theta;
+        // Small cones lose too much precision in cos_theta, go through sin_theta instead
+        if sin_theta_max2 < 0.000_685_23 {
+            sin_theta2 = sin_theta_max2 * u.x;
+            cos_theta = (1.0 - sin_theta2).sqrt();
+        }
+
+        // Angle from the center of the sphere to the sampled point
+        let cos_alpha = sin_theta2 * inv_sin_theta_max
+            + cos_theta
+                * (1.0 - sin_theta2 * inv_sin_theta_max * inv_sin_theta_max)
+                    .max(0.0)
+                    .sqrt();
+        let sin_alpha = (1.0 - cos_alpha * cos_alpha).max(0.0).sqrt();
+        let phi = u.y * 2.0 * std::f32::consts::PI;
+
+        let (wc, wc_x, wc_y) = coordinate_system((p_center - reference.p).normalize());
+        let n_world = spherical_direction_in(sin_alpha, cos_alpha, phi, -wc_x, -wc_y, -wc);
+        let p_world = p_center + self.radius * n_world;
+        let p_error = gamma(5) * vec3(p_world.x.abs(), p_world.y.abs(), p_world.z.abs());
+        let n = if shape.reverse_orientation {
+            -n_world
+        } else {
+            n_world
+        };
+        (
+            Interaction::new(p_world, n, p_error, reference.time),
+            uniform_cone_pdf(cos_theta_max),
+        )
+    }
+
+    fn pdf_from(&self, shape: &Shape, reference: &Interaction, wi: &Vec3) -> f32 {
+        let p_center = shape
+            .object_to_world
+            .transform_point(Point3::new(0.0, 0.0, 0.0));
+        let p_origin = offset_ray_origin(
+            reference.p,
+            reference.p_error,
+            reference.n,
+            p_center - reference.p,
+        );
+        if (p_origin - p_center).magnitude2() <= self.radius * self.radius {
+            return area_pdf_to_solid_angle(self, shape, reference, wi);
+        }
+        let sin_theta_max2 = self.radius * self.radius / (reference.p - p_center).magnitude2();
+        let cos_theta_max = (1.0 - sin_theta_max2).max(0.0).sqrt();
+        uniform_cone_pdf(cos_theta_max)
     }
 }
 
@@ -166,20 +263,29 @@ impl Sphere {
                 z_max: clamp(max(z_min, z_max), -radius, radius),
                 theta_min: clamp(z_min / radius, -1.0, 1.0).acos(),
                 theta_max: clamp(z_max / radius, -1.0, 1.0).acos(),
-                phi_max: Rad(clamp(phi_max, 0.0, 360.0)).0,
+                phi_max: Rad::from(Deg(clamp(phi_max, 0.0, 360.0))).0,
             }),
         )
     }
 
-    pub fn unit() -> Shape {
+    pub fn create(
+        object_to_world: Transform,
+        world_to_object: Transform,
+        reverse_orientation: bool,
+        params: &ParamSet,
+    ) -> Shape {
+        let radius = params.find_one_float("radius", 1.0);
+        let z_min = params.find_one_float("zmin", -radius);
+        let z_max = params.find_one_float("zmax", radius);
+        let phi_max = params.find_one_float("phimax", 360.0);
         Sphere::new(
-            Transform::default(),
-            Transform::default(),
-            false,
-            1.0,
-            -1.0,
-            1.0,
-            360.0,
+            object_to_world,
+            world_to_object,
+            reverse_orientation,
+            radius,
+            z_min,
+            z_max,
+            phi_max,
         )
     }
 }
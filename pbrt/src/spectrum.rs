@@ -1,44 +1,334 @@
-use std::ops::AddAssign;
-use std::ops::Div;
-use std::ops::Mul;
+use std::ops::{
+    Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub, SubAssign,
+};
 
-pub struct Spectrum {}
+mod cie;
+mod rgb_spectrum;
+mod sampled_spectrum;
 
-impl Spectrum {
-    pub fn new() -> Spectrum {
-        Spectrum {}
+#[cfg(not(feature = "sampled_spectrum"))]
+pub use rgb_spectrum::RGBSpectrum;
+#[cfg(feature = "sampled_spectrum")]
+pub use sampled_spectrum::SampledSpectrum;
+
+// The renderer works with a single spectral representation, picked at compile time
+#[cfg(not(feature = "sampled_spectrum"))]
+pub type Spectrum = RGBSpectrum;
+#[cfg(feature = "sampled_spectrum")]
+pub type Spectrum = SampledSpectrum;
+
+// Upsampling RGB to a spectrum depends on whether the result scatters or emits light
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SpectrumType {
+    Reflectance,
+    Illuminant,
+}
+
+// Linear sRGB primaries with a D65 white point
+pub fn xyz_to_rgb(xyz: [f32; 3]) -> [f32; 3] {
+    [
+        3.240479 * xyz[0] - 1.53715 * xyz[1] - 0.498535 * xyz[2],
+        -0.969256 * xyz[0] + 1.875991 * xyz[1] + 0.041556 * xyz[2],
+        0.055648 * xyz[0] - 0.204043 * xyz[1] + 1.057311 * xyz[2],
+    ]
+}
+
+pub fn rgb_to_xyz(rgb: [f32; 3]) -> [f32; 3] {
+    [
+        0.412453 * rgb[0] + 0.357580 * rgb[1] + 0.180423 * rgb[2],
+        0.212671 * rgb[0] + 0.715160 * rgb[1] + 0.072169 * rgb[2],
+        0.019334 * rgb[0] + 0.119193 * rgb[1] + 0.950227 * rgb[2],
+    ]
+}
+
+// Emitted radiance of a black body at wavelength lambda in nm and temperature t in Kelvin
+pub fn blackbody(lambda: f32, t: f32) -> f32 {
+    if t <= 0.0 {
+        return 0.0;
+    }
+    let c = 299_792_458.0_f64;
+    let h = 6.626_069_57e-34_f64;
+    let kb = 1.380_648_8e-23_f64;
+    let l = lambda as f64 * 1e-9;
+    ((2.0 * h * c * c) / (l.powi(5) * (((h * c) / (l * kb * t as f64)).exp() - 1.0))) as f32
+}
+
+// Black body emission scaled so its peak is one
+pub fn blackbody_normalized(lambda: f32, t: f32) -> f32 {
+    // Wien's displacement law
+    let lambda_max = 2.897_772e-3 / t * 1e9;
+    blackbody(lambda, t) / blackbody(lambda_max, t)
+}
+
+pub fn spectrum_samples_sorted(lambda: &[f32]) -> bool {
+    lambda.windows(2).all(|pair| pair[0] <= pair[1])
+}
+
+pub fn sort_spectrum_samples(lambda: &mut Vec<f32>, values: &mut Vec<f32>) {
+    let mut samples = lambda
+        .iter()
+        .copied()
+        .zip(values.iter().copied())
+        .collect::<Vec<(f32, f32)>>();
+    samples.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    *lambda = samples.iter().map(|sample| sample.0).collect();
+    *values = samples.iter().map(|sample| sample.1).collect();
+}
+
+// Piecewise linear interpolation of sorted samples, constant past the end points
+pub fn interpolate_spectrum_samples(lambda: &[f32], values: &[f32], l: f32) -> f32 {
+    let n = lambda.len();
+    if l <= lambda[0] {
+        return values[0];
+    }
+    if l >= lambda[n - 1] {
+        return values[n - 1];
+    }
+    let i = lambda.partition_point(|sample| *sample <= l) - 1;
+    let t = (l - lambda[i]) / (lambda[i + 1] - lambda[i]);
+    values[i] * (1.0 - t) + values[i + 1] * t
+}
+
+// Average of the piecewise linear function through sorted samples over [lambda_start, lambda_end]
+pub fn average_spectrum_samples(
+    lambda: &[f32],
+    values: &[f32],
+    lambda_start: f32,
+    lambda_end: f32,
+) -> f32 {
+    let n = lambda.len();
+    // Handle cases with out-of-bounds range or single sample only
+    if lambda_end <= lambda[0] {
+        return values[0];
+    }
+    if lambda_start >= lambda[n - 1] {
+        return values[n - 1];
+    }
+    if n == 1 {
+        return values[0];
+    }
+
+    // Add contributions of constant segments before/after samples
+    let mut sum = 0.0;
+    if lambda_start < lambda[0] {
+        sum += values[0] * (lambda[0] - lambda_start);
+    }
+    if lambda_end > lambda[n - 1] {
+        sum += values[n - 1] * (lambda_end - lambda[n - 1]);
+    }
+
+    // Loop over wavelength sample segments and add contributions
+    let mut i = 0;
+    while lambda_start > lambda[i + 1] {
+        i += 1;
+    }
+    let interp = |w: f32, i: usize| {
+        let t = (w - lambda[i]) / (lambda[i + 1] - lambda[i]);
+        values[i] * (1.0 - t) + values[i + 1] * t
+    };
+    while i + 1 < n && lambda_end >= lambda[i] {
+        let segment_start = lambda_start.max(lambda[i]);
+        let segment_end = lambda_end.min(lambda[i + 1]);
+        sum += 0.5
+            * (interp(segment_start, i) + interp(segment_end, i))
+            * (segment_end - segment_start);
+        i += 1;
+    }
+    sum / (lambda_end - lambda_start)
+}
+
+// Fixed number of spectral coefficients, shared by the RGB and sampled representations
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CoefficientSpectrum<const N: usize> {
+    pub c: [f32; N],
+}
+
+impl<const N: usize> CoefficientSpectrum<N> {
+    // Black
+    pub fn new() -> Self {
+        Self::from_value(0.0)
+    }
+
+    pub fn from_value(v: f32) -> Self {
+        CoefficientSpectrum { c: [v; N] }
     }
 
     pub fn is_black(&self) -> bool {
-        false
+        self.c.iter().all(|c| *c == 0.0)
+    }
+
+    pub fn has_nans(&self) -> bool {
+        self.c.iter().any(|c| c.is_nan())
+    }
+
+    pub fn max_component_value(&self) -> f32 {
+        self.c.iter().copied().fold(f32::MIN, f32::max)
+    }
+
+    pub fn map(&self, f: impl Fn(f32) -> f32) -> Self {
+        let mut ret = *self;
+        for c in ret.c.iter_mut() {
+            *c = f(*c);
+        }
+        ret
+    }
+
+    pub fn sqrt(&self) -> Self {
+        self.map(f32::sqrt)
+    }
+
+    pub fn exp(&self) -> Self {
+        self.map(f32::exp)
+    }
+
+    pub fn pow(&self, e: f32) -> Self {
+        self.map(|c| c.powf(e))
+    }
+
+    pub fn clamp(&self, low: f32, high: f32) -> Self {
+        self.map(|c| c.max(low).min(high))
+    }
+
+    pub fn lerp(t: f32, s1: Self, s2: Self) -> Self {
+        s1 * (1.0 - t) + s2 * t
     }
 }
 
-impl AddAssign for Spectrum {
-    fn add_assign(&mut self, other: Spectrum) {
-        *self = Spectrum {};
+impl<const N: usize> Default for CoefficientSpectrum<N> {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
-impl Mul for Spectrum {
+impl<const N: usize> Index<usize> for CoefficientSpectrum<N> {
+    type Output = f32;
+
+    fn index(&self, index: usize) -> &f32 {
+        &self.c[index]
+    }
+}
+
+impl<const N: usize> IndexMut<usize> for CoefficientSpectrum<N> {
+    fn index_mut(&mut self, index: usize) -> &mut f32 {
+        &mut self.c[index]
+    }
+}
+
+impl<const N: usize> AddAssign for CoefficientSpectrum<N> {
+    fn add_assign(&mut self, other: Self) {
+        for (c, o) in self.c.iter_mut().zip(other.c.iter()) {
+            *c += o;
+        }
+    }
+}
+
+impl<const N: usize> SubAssign for CoefficientSpectrum<N> {
+    fn sub_assign(&mut self, other: Self) {
+        for (c, o) in self.c.iter_mut().zip(other.c.iter()) {
+            *c -= o;
+        }
+    }
+}
+
+impl<const N: usize> MulAssign for CoefficientSpectrum<N> {
+    fn mul_assign(&mut self, other: Self) {
+        for (c, o) in self.c.iter_mut().zip(other.c.iter()) {
+            *c *= o;
+        }
+    }
+}
+
+impl<const N: usize> DivAssign for CoefficientSpectrum<N> {
+    fn div_assign(&mut self, other: Self) {
+        for (c, o) in self.c.iter_mut().zip(other.c.iter()) {
+            *c /= o;
+        }
+    }
+}
+
+impl<const N: usize> MulAssign<f32> for CoefficientSpectrum<N> {
+    fn mul_assign(&mut self, rhs: f32) {
+        for c in self.c.iter_mut() {
+            *c *= rhs;
+        }
+    }
+}
+
+impl<const N: usize> DivAssign<f32> for CoefficientSpectrum<N> {
+    fn div_assign(&mut self, rhs: f32) {
+        let inv = 1.0 / rhs;
+        for c in self.c.iter_mut() {
+            *c *= inv;
+        }
+    }
+}
+
+impl<const N: usize> Add for CoefficientSpectrum<N> {
+    type Output = Self;
+
+    fn add(mut self, rhs: Self) -> Self {
+        self += rhs;
+        self
+    }
+}
+
+impl<const N: usize> Sub for CoefficientSpectrum<N> {
+    type Output = Self;
+
+    fn sub(mut self, rhs: Self) -> Self {
+        self -= rhs;
+        self
+    }
+}
+
+impl<const N: usize> Mul for CoefficientSpectrum<N> {
+    type Output = Self;
+
+    fn mul(mut self, rhs: Self) -> Self {
+        self *= rhs;
+        self
+    }
+}
+
+impl<const N: usize> Div for CoefficientSpectrum<N> {
     type Output = Self;
 
-    fn mul(self, rhs: Self) -> Spectrum {
+    fn div(mut self, rhs: Self) -> Self {
+        self /= rhs;
         self
     }
 }
 
-impl Mul<f32> for Spectrum {
+impl<const N: usize> Mul<f32> for CoefficientSpectrum<N> {
     type Output = Self;
-    fn mul(self, rhs: f32) -> Spectrum {
+
+    fn mul(mut self, rhs: f32) -> Self {
+        self *= rhs;
         self
     }
 }
 
-impl Div<f32> for Spectrum {
+impl<const N: usize> Mul<CoefficientSpectrum<N>> for f32 {
+    type Output = CoefficientSpectrum<N>;
+
+    fn mul(self, rhs: CoefficientSpectrum<N>) -> CoefficientSpectrum<N> {
+        rhs * self
+    }
+}
+
+impl<const N: usize> Div<f32> for CoefficientSpectrum<N> {
     type Output = Self;
 
-    fn div(self, rhs: f32) -> Spectrum {
+    fn div(mut self, rhs: f32) -> Self {
+        self /= rhs;
         self
     }
 }
+
+impl<const N: usize> Neg for CoefficientSpectrum<N> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        self.map(|c| -c)
+    }
+}
@@ -0,0 +1,67 @@
+// Color matching data used to convert between spectra and tristimulus values
+
+pub const CIE_LAMBDA_START: f32 = 360.0;
+pub const CIE_LAMBDA_END: f32 = 830.0;
+pub const CIE_Y_INTEGRAL: f32 = 106.856_895;
+
+// Piecewise gaussian with different widths left and right of the mean
+fn g(lambda: f32, mu: f32, sigma_left: f32, sigma_right: f32) -> f32 {
+    let t = (lambda - mu) / if lambda < mu { sigma_left } else { sigma_right };
+    (-0.5 * t * t).exp()
+}
+
+// CIE 1931 2 degree observer, using the multi-lobe fit from
+// "Simple Analytic Approximations to the CIE XYZ Color Matching Functions" by Wyman et al.
+pub fn cie_x(lambda: f32) -> f32 {
+    1.056 * g(lambda, 599.8, 37.9, 31.0) + 0.362 * g(lambda, 442.0, 16.0, 26.7)
+        - 0.065 * g(lambda, 501.1, 20.4, 26.2)
+}
+
+pub fn cie_y(lambda: f32) -> f32 {
+    0.821 * g(lambda, 568.8, 46.9, 40.5) + 0.286 * g(lambda, 530.9, 16.3, 31.1)
+}
+
+pub fn cie_z(lambda: f32) -> f32 {
+    1.217 * g(lambda, 437.0, 11.8, 36.0) + 0.681 * g(lambda, 459.0, 26.0, 13.8)
+}
+
+// Basis spectra for RGB to spectrum upsampling, from "An RGB to Spectrum Conversion for
+// Reflectances" by Smits. Ten equal bins covering 380-720nm, given at the bin centers
+pub const RGB_TO_SPECT_LAMBDA_START: f32 = 380.0;
+pub const RGB_TO_SPECT_LAMBDA_END: f32 = 720.0;
+pub const RGB_TO_SPECT_SAMPLES: usize = 10;
+
+pub const RGB_TO_SPECT_WHITE: [f32; RGB_TO_SPECT_SAMPLES] = [
+    1.0000, 1.0000, 0.9999, 0.9993, 0.9992, 0.9998, 1.0000, 1.0000, 1.0000, 1.0000,
+];
+pub const RGB_TO_SPECT_CYAN: [f32; RGB_TO_SPECT_SAMPLES] = [
+    0.9710, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0000, 0.0000, 0.0000,
+];
+pub const RGB_TO_SPECT_MAGENTA: [f32; RGB_TO_SPECT_SAMPLES] = [
+    1.0000, 1.0000, 0.9685, 0.2229, 0.0000, 0.0458, 0.8369, 1.0000, 1.0000, 0.9959,
+];
+pub const RGB_TO_SPECT_YELLOW: [f32; RGB_TO_SPECT_SAMPLES] = [
+    0.0001, 0.0000, 0.1088, 0.6651, 1.0000, 1.0000, 0.9996, 0.9586, 0.9685, 0.9840,
+];
+pub const RGB_TO_SPECT_RED: [f32; RGB_TO_SPECT_SAMPLES] = [
+    0.1012, 0.0515, 0.0000, 0.0000, 0.0000, 0.0000, 0.8325, 1.0149, 1.0149, 1.0149,
+];
+pub const RGB_TO_SPECT_GREEN: [f32; RGB_TO_SPECT_SAMPLES] = [
+    0.0000, 0.0000, 0.0273, 0.7937, 1.0000, 0.9418, 0.1719, 0.0000, 0.0000, 0.0025,
+];
+pub const RGB_TO_SPECT_BLUE: [f32; RGB_TO_SPECT_SAMPLES] = [
+    1.0000, 1.0000, 0.8916, 0.3323, 0.0000, 0.0000, 0.0003, 0.0369, 0.0483, 0.0496,
+];
+
+pub fn rgb_to_spect_lambda() -> [f32; RGB_TO_SPECT_SAMPLES] {
+    let mut lambda = [0.0; RGB_TO_SPECT_SAMPLES];
+    let width = (RGB_TO_SPECT_LAMBDA_END - RGB_TO_SPECT_LAMBDA_START) / RGB_TO_SPECT_SAMPLES as f32;
+    for (i, l) in lambda.iter_mut().enumerate() {
+        *l = RGB_TO_SPECT_LAMBDA_START + (i as f32 + 0.5) * width;
+    }
+    lambda
+}
+
+// Illuminants are upsampled relative to a white that matches the sRGB D65 white point,
+// approximated by a black body of the same correlated color temperature
+pub const ILLUMINANT_WHITE_TEMPERATURE: f32 = 6504.0;
@@ -0,0 +1,53 @@
+use super::cie::*;
+use super::*;
+
+// Linear sRGB coefficients
+pub type RGBSpectrum = CoefficientSpectrum<3>;
+
+impl RGBSpectrum {
+    pub fn from_rgb(rgb: [f32; 3], _spectrum_type: SpectrumType) -> RGBSpectrum {
+        CoefficientSpectrum { c: rgb }
+    }
+
+    pub fn from_xyz(xyz: [f32; 3], _spectrum_type: SpectrumType) -> RGBSpectrum {
+        CoefficientSpectrum { c: xyz_to_rgb(xyz) }
+    }
+
+    // Values at the given wavelengths in nm, which don't need to be sorted
+    pub fn from_sampled(lambda: &[f32], values: &[f32]) -> RGBSpectrum {
+        if !spectrum_samples_sorted(lambda) {
+            let mut lambda = lambda.to_vec();
+            let mut values = values.to_vec();
+            sort_spectrum_samples(&mut lambda, &mut values);
+            return RGBSpectrum::from_sampled(&lambda, &values);
+        }
+
+        // Integrate against the matching functions in 1nm steps
+        let mut xyz = [0.0; 3];
+        let mut l = CIE_LAMBDA_START;
+        while l <= CIE_LAMBDA_END {
+            let value = interpolate_spectrum_samples(lambda, values, l);
+            xyz[0] += value * cie_x(l);
+            xyz[1] += value * cie_y(l);
+            xyz[2] += value * cie_z(l);
+            l += 1.0;
+        }
+        for c in xyz.iter_mut() {
+            *c /= CIE_Y_INTEGRAL;
+        }
+        RGBSpectrum::from_xyz(xyz, SpectrumType::Reflectance)
+    }
+
+    pub fn to_rgb(self) -> [f32; 3] {
+        self.c
+    }
+
+    pub fn to_xyz(self) -> [f32; 3] {
+        rgb_to_xyz(self.c)
+    }
+
+    // Luminance
+    pub fn y(&self) -> f32 {
+        0.212671 * self.c[0] + 0.715160 * self.c[1] + 0.072169 * self.c[2]
+    }
+}
@@ -0,0 +1,164 @@
+use super::cie::*;
+use super::*;
+use std::sync::OnceLock;
+
+pub const SAMPLED_LAMBDA_START: f32 = 400.0;
+pub const SAMPLED_LAMBDA_END: f32 = 700.0;
+pub const N_SPECTRAL_SAMPLES: usize = 60;
+
+// Equal width bins between SAMPLED_LAMBDA_START and SAMPLED_LAMBDA_END
+pub type SampledSpectrum = CoefficientSpectrum<N_SPECTRAL_SAMPLES>;
+
+// Matching functions and upsampling basis resampled to the spectrum bins
+struct SampledTables {
+    x: SampledSpectrum,
+    y: SampledSpectrum,
+    z: SampledSpectrum,
+    // White, cyan, magenta, yellow, red, green and blue
+    reflectance: [SampledSpectrum; 7],
+    illuminant: [SampledSpectrum; 7],
+}
+
+const WHITE: usize = 0;
+const CYAN: usize = 1;
+const MAGENTA: usize = 2;
+const YELLOW: usize = 3;
+const RED: usize = 4;
+const GREEN: usize = 5;
+const BLUE: usize = 6;
+
+fn bin_range(i: usize) -> (f32, f32) {
+    let width = (SAMPLED_LAMBDA_END - SAMPLED_LAMBDA_START) / N_SPECTRAL_SAMPLES as f32;
+    let start = SAMPLED_LAMBDA_START + i as f32 * width;
+    (start, start + width)
+}
+
+// Average of a continuous function over every bin
+fn from_function(f: impl Fn(f32) -> f32) -> SampledSpectrum {
+    const STEPS: usize = 16;
+    let mut s = SampledSpectrum::new();
+    for i in 0..N_SPECTRAL_SAMPLES {
+        let (l0, l1) = bin_range(i);
+        let sum: f32 = (0..STEPS)
+            .map(|step| f(l0 + (step as f32 + 0.5) * (l1 - l0) / STEPS as f32))
+            .sum();
+        s[i] = sum / STEPS as f32;
+    }
+    s
+}
+
+fn tables() -> &'static SampledTables {
+    static TABLES: OnceLock<SampledTables> = OnceLock::new();
+    TABLES.get_or_init(|| {
+        let lambda = rgb_to_spect_lambda();
+        let basis = [
+            RGB_TO_SPECT_WHITE,
+            RGB_TO_SPECT_CYAN,
+            RGB_TO_SPECT_MAGENTA,
+            RGB_TO_SPECT_YELLOW,
+            RGB_TO_SPECT_RED,
+            RGB_TO_SPECT_GREEN,
+            RGB_TO_SPECT_BLUE,
+        ];
+        let reflectance = basis.map(|values| SampledSpectrum::from_sampled(&lambda, &values));
+
+        // Tint the basis with the white illuminant, normalized to unit luminance
+        let white = from_function(|l| blackbody(l, ILLUMINANT_WHITE_TEMPERATURE));
+        let y = from_function(cie_y);
+        let white_y = (0..N_SPECTRAL_SAMPLES)
+            .map(|i| white[i] * y[i])
+            .sum::<f32>()
+            * (SAMPLED_LAMBDA_END - SAMPLED_LAMBDA_START)
+            / (CIE_Y_INTEGRAL * N_SPECTRAL_SAMPLES as f32);
+        let white = white / white_y;
+        let illuminant = reflectance.map(|s| s * white);
+
+        SampledTables {
+            x: from_function(cie_x),
+            y,
+            z: from_function(cie_z),
+            reflectance,
+            illuminant,
+        }
+    })
+}
+
+impl SampledSpectrum {
+    // Values at the given wavelengths in nm, which don't need to be sorted
+    pub fn from_sampled(lambda: &[f32], values: &[f32]) -> SampledSpectrum {
+        if !spectrum_samples_sorted(lambda) {
+            let mut lambda = lambda.to_vec();
+            let mut values = values.to_vec();
+            sort_spectrum_samples(&mut lambda, &mut values);
+            return SampledSpectrum::from_sampled(&lambda, &values);
+        }
+        let mut s = SampledSpectrum::new();
+        for i in 0..N_SPECTRAL_SAMPLES {
+            let (l0, l1) = bin_range(i);
+            s[i] = average_spectrum_samples(lambda, values, l0, l1);
+        }
+        s
+    }
+
+    // Smits' method, the result is the smallest amount of white plus the basis spectra
+    // needed to make up the remaining primaries
+    pub fn from_rgb(rgb: [f32; 3], spectrum_type: SpectrumType) -> SampledSpectrum {
+        let basis = match spectrum_type {
+            SpectrumType::Reflectance => &tables().reflectance,
+            SpectrumType::Illuminant => &tables().illuminant,
+        };
+        let [r, g, b] = rgb;
+        let result = if r <= g && r <= b {
+            // Compute spectrum with red as minimum
+            basis[WHITE] * r
+                + if g <= b {
+                    basis[CYAN] * (g - r) + basis[BLUE] * (b - g)
+                } else {
+                    basis[CYAN] * (b - r) + basis[GREEN] * (g - b)
+                }
+        } else if g <= r && g <= b {
+            // Compute spectrum with green as minimum
+            basis[WHITE] * g
+                + if r <= b {
+                    basis[MAGENTA] * (r - g) + basis[BLUE] * (b - r)
+                } else {
+                    basis[MAGENTA] * (b - g) + basis[RED] * (r - b)
+                }
+        } else {
+            // Compute spectrum with blue as minimum
+            basis[WHITE] * b
+                + if r <= g {
+                    basis[YELLOW] * (r - b) + basis[GREEN] * (g - r)
+                } else {
+                    basis[YELLOW] * (g - b) + basis[RED] * (r - g)
+                }
+        };
+        result.clamp(0.0, f32::INFINITY)
+    }
+
+    pub fn from_xyz(xyz: [f32; 3], spectrum_type: SpectrumType) -> SampledSpectrum {
+        SampledSpectrum::from_rgb(xyz_to_rgb(xyz), spectrum_type)
+    }
+
+    pub fn to_xyz(self) -> [f32; 3] {
+        let tables = tables();
+        let mut xyz = [0.0; 3];
+        for i in 0..N_SPECTRAL_SAMPLES {
+            xyz[0] += tables.x[i] * self[i];
+            xyz[1] += tables.y[i] * self[i];
+            xyz[2] += tables.z[i] * self[i];
+        }
+        let scale = (SAMPLED_LAMBDA_END - SAMPLED_LAMBDA_START)
+            / (CIE_Y_INTEGRAL * N_SPECTRAL_SAMPLES as f32);
+        xyz.map(|c| c * scale)
+    }
+
+    pub fn to_rgb(self) -> [f32; 3] {
+        xyz_to_rgb(self.to_xyz())
+    }
+
+    // Luminance
+    pub fn y(&self) -> f32 {
+        self.to_xyz()[1]
+    }
+}
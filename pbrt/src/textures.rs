@@ -0,0 +1,16 @@
+use crate::core::texture::*;
+use crate::core::*;
+use crate::math::*;
+use std::sync::Arc;
+
+mod checkerboard;
+mod constant;
+mod imagemap;
+mod mix;
+mod scale;
+
+pub use checkerboard::CheckerboardTexture;
+pub use constant::ConstantTexture;
+pub use imagemap::ImageTexture;
+pub use mix::MixTexture;
+pub use scale::ScaleTexture;
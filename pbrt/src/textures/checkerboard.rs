@@ -0,0 +1,45 @@
+use super::*;
+
+// Alternates between two textures on the unit squares of (s, t) space
+pub struct CheckerboardTexture<T> {
+    mapping: Box<dyn TextureMapping2D>,
+    tex1: Arc<dyn Texture<T>>,
+    tex2: Arc<dyn Texture<T>>,
+}
+
+impl<T: TextureValue> CheckerboardTexture<T> {
+    pub fn new(
+        mapping: Box<dyn TextureMapping2D>,
+        tex1: Arc<dyn Texture<T>>,
+        tex2: Arc<dyn Texture<T>>,
+    ) -> CheckerboardTexture<T> {
+        CheckerboardTexture {
+            mapping,
+            tex1,
+            tex2,
+        }
+    }
+
+    pub fn create(
+        mapping: Box<dyn TextureMapping2D>,
+        params: &TextureParams,
+    ) -> CheckerboardTexture<T> {
+        CheckerboardTexture::new(
+            mapping,
+            params.get_texture("tex1", T::from_value(1.0)),
+            params.get_texture("tex2", T::from_value(0.0)),
+        )
+    }
+}
+
+impl<T: TextureValue> Texture<T> for CheckerboardTexture<T> {
+    fn evaluate(&self, si: &SurfaceInteraction) -> T {
+        // Point sampled, without ray differentials there is no footprint to filter over
+        let st = self.mapping.map(si);
+        if (st.x.floor() as i32 + st.y.floor() as i32) % 2 == 0 {
+            self.tex1.evaluate(si)
+        } else {
+            self.tex2.evaluate(si)
+        }
+    }
+}
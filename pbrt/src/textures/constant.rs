@@ -0,0 +1,18 @@
+use super::*;
+
+// The same value everywhere
+pub struct ConstantTexture<T> {
+    value: T,
+}
+
+impl<T: TextureValue> ConstantTexture<T> {
+    pub fn new(value: T) -> ConstantTexture<T> {
+        ConstantTexture { value }
+    }
+}
+
+impl<T: TextureValue> Texture<T> for ConstantTexture<T> {
+    fn evaluate(&self, _si: &SurfaceInteraction) -> T {
+        self.value
+    }
+}
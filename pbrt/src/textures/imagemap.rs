@@ -0,0 +1,119 @@
+use super::*;
+
+// What lookups outside of [0, 1]^2 return
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ImageWrap {
+    Repeat,
+    Black,
+    Clamp,
+}
+
+impl std::str::FromStr for ImageWrap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ImageWrap, String> {
+        match s {
+            "repeat" => Ok(ImageWrap::Repeat),
+            "black" => Ok(ImageWrap::Black),
+            "clamp" => Ok(ImageWrap::Clamp),
+            _ => Err(format!("wrap mode \"{}\" unknown", s)),
+        }
+    }
+}
+
+// Bilinearly interpolated image, with t = 0 at the top row
+pub struct ImageTexture<T> {
+    mapping: Box<dyn TextureMapping2D>,
+    texels: Vec<T>,
+    resolution: Point2i,
+    wrap: ImageWrap,
+}
+
+impl<T: TextureValue> ImageTexture<T> {
+    pub fn new(
+        mapping: Box<dyn TextureMapping2D>,
+        texels: Vec<T>,
+        resolution: Point2i,
+        wrap: ImageWrap,
+    ) -> ImageTexture<T> {
+        ImageTexture {
+            mapping,
+            texels,
+            resolution,
+            wrap,
+        }
+    }
+
+    // The RGB image and its resolution are read by the caller, without one the texture is
+    // the constant one
+    pub fn create(
+        mapping: Box<dyn TextureMapping2D>,
+        params: &TextureParams,
+        image: Option<(Vec<[f32; 3]>, Point2i)>,
+    ) -> ImageTexture<T> {
+        let scale = params.params.find_one_float("scale", 1.0);
+        let invert = params.params.find_one_bool("invert", false);
+        let wrap = params
+            .params
+            .find_one_string("wrap", "repeat")
+            .parse()
+            .unwrap_or_else(|err| {
+                params.warning(&format!("{}, using \"repeat\"", err));
+                ImageWrap::Repeat
+            });
+        let (rgb, resolution) = image.unwrap_or_else(|| (vec![[1.0; 3]], Point2i::new(1, 1)));
+        let texels = rgb
+            .iter()
+            .map(|texel| {
+                let texel = texel.map(|c| {
+                    let c = c * scale;
+                    if invert {
+                        (1.0 - c).max(0.0)
+                    } else {
+                        c
+                    }
+                });
+                T::from_rgb(texel)
+            })
+            .collect();
+        ImageTexture::new(mapping, texels, resolution, wrap)
+    }
+
+    fn texel(&self, x: i32, y: i32) -> Option<T> {
+        let (width, height) = (self.resolution.x, self.resolution.y);
+        let (x, y) = match self.wrap {
+            ImageWrap::Repeat => (x.rem_euclid(width), y.rem_euclid(height)),
+            ImageWrap::Clamp => (clamp(x, 0, width - 1), clamp(y, 0, height - 1)),
+            ImageWrap::Black => {
+                if x < 0 || x >= width || y < 0 || y >= height {
+                    return None;
+                }
+                (x, y)
+            }
+        };
+        Some(self.texels[(y * width + x) as usize])
+    }
+}
+
+impl<T: TextureValue> Texture<T> for ImageTexture<T> {
+    fn evaluate(&self, si: &SurfaceInteraction) -> T {
+        let st = self.mapping.map(si);
+        let x = st.x * self.resolution.x as f32 - 0.5;
+        let y = st.y * self.resolution.y as f32 - 0.5;
+        let (x0, y0) = (x.floor() as i32, y.floor() as i32);
+        let (dx, dy) = (x - x0 as f32, y - y0 as f32);
+        let weights = [
+            (x0, y0, (1.0 - dx) * (1.0 - dy)),
+            (x0 + 1, y0, dx * (1.0 - dy)),
+            (x0, y0 + 1, (1.0 - dx) * dy),
+            (x0 + 1, y0 + 1, dx * dy),
+        ];
+        let mut result = T::from_value(0.0);
+        for (x, y, weight) in weights.iter() {
+            if let Some(texel) = self.texel(*x, *y) {
+                result = result + texel * *weight;
+            }
+        }
+        result
+    }
+}
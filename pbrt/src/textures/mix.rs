@@ -0,0 +1,33 @@
+use super::*;
+
+// Linear interpolation between two textures by a float texture
+pub struct MixTexture<T> {
+    tex1: Arc<dyn Texture<T>>,
+    tex2: Arc<dyn Texture<T>>,
+    amount: Arc<dyn Texture<f32>>,
+}
+
+impl<T: TextureValue> MixTexture<T> {
+    pub fn new(
+        tex1: Arc<dyn Texture<T>>,
+        tex2: Arc<dyn Texture<T>>,
+        amount: Arc<dyn Texture<f32>>,
+    ) -> MixTexture<T> {
+        MixTexture { tex1, tex2, amount }
+    }
+
+    pub fn create(params: &TextureParams) -> MixTexture<T> {
+        MixTexture::new(
+            params.get_texture("tex1", T::from_value(0.0)),
+            params.get_texture("tex2", T::from_value(1.0)),
+            params.get_texture("amount", 0.5),
+        )
+    }
+}
+
+impl<T: TextureValue> Texture<T> for MixTexture<T> {
+    fn evaluate(&self, si: &SurfaceInteraction) -> T {
+        let amount = self.amount.evaluate(si);
+        self.tex1.evaluate(si) * (1.0 - amount) + self.tex2.evaluate(si) * amount
+    }
+}
@@ -0,0 +1,26 @@
+use super::*;
+
+// Product of two textures
+pub struct ScaleTexture<T> {
+    tex1: Arc<dyn Texture<T>>,
+    tex2: Arc<dyn Texture<T>>,
+}
+
+impl<T: TextureValue> ScaleTexture<T> {
+    pub fn new(tex1: Arc<dyn Texture<T>>, tex2: Arc<dyn Texture<T>>) -> ScaleTexture<T> {
+        ScaleTexture { tex1, tex2 }
+    }
+
+    pub fn create(params: &TextureParams) -> ScaleTexture<T> {
+        ScaleTexture::new(
+            params.get_texture("tex1", T::from_value(1.0)),
+            params.get_texture("tex2", T::from_value(1.0)),
+        )
+    }
+}
+
+impl<T: TextureValue> Texture<T> for ScaleTexture<T> {
+    fn evaluate(&self, si: &SurfaceInteraction) -> T {
+        self.tex1.evaluate(si) * self.tex2.evaluate(si)
+    }
+}
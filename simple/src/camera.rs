@@ -1,6 +1,6 @@
 use crate::math::*;
-use crate::random::*;
 use crate::ray::*;
+use crate::sampler::Sampler;
 
 pub struct Camera {
     origin: Vec3,
@@ -15,13 +15,13 @@ pub struct Camera {
     time1: f32,
 }
 
-fn random_in_unit_disk() -> Vec3 {
-    loop {
-        let p = 2.0 * vec3(random_float(), random_float(), 0.0) - vec3(1.0, 1.0, 0.0);
-        if dot(p, p) < 1.0 {
-            break p;
-        }
-    }
+// Uniform on the unit disk. Maps a 2D sample directly instead of rejecting points outside
+// the disk, so every ray uses the same sample dimensions
+fn random_in_unit_disk(sampler: &mut Sampler) -> Vec3 {
+    let (u, v) = sampler.get_2d();
+    let r = u.sqrt();
+    let phi = 2.0 * std::f32::consts::PI * v;
+    vec3(r * phi.cos(), r * phi.sin(), 0.0)
 }
 
 impl Camera {
@@ -60,10 +60,12 @@ impl Camera {
         }
     }
 
-    pub fn get_ray(&self, s: f32, t: f32) -> Ray {
-        let rd = self.lens_radius * random_in_unit_disk();
+    pub fn get_ray(&self, s: f32, t: f32, sampler: &mut Sampler) -> Ray {
+        // Drawn even for a pinhole camera or a still scene, so the dimensions used by the
+        // path don't depend on the camera
+        let rd = self.lens_radius * random_in_unit_disk(sampler);
         let offset = self.u * rd.x + self.v * rd.y;
-        let time = self.time0 + random_float() * (self.time1 - self.time0);
+        let time = self.time0 + sampler.get_1d() * (self.time1 - self.time0);
         Ray {
             origin: self.origin + offset,
             direction: self.lower_left_corner + s * self.horizontal + t * self.vertical
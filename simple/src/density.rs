@@ -0,0 +1,16 @@
+use crate::math::*;
+
+pub mod grid_density;
+pub mod noise_density;
+
+pub use grid_density::GridDensity;
+pub use noise_density::NoiseDensity;
+
+// Scalar field scaling the coefficients of a heterogeneous medium, looked up in the space of
+// its boundary
+pub trait DensityField: Send + Sync {
+    fn density(&self, p: &Vec3) -> f32;
+
+    // Upper bound of the density anywhere, collisions are sampled with this
+    fn max_density(&self) -> f32;
+}
@@ -0,0 +1,156 @@
+use crate::density::DensityField;
+use crate::math::*;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// Voxel grid spanning the box from min to max, with x varying fastest in values. Each
+// value sits at the center of its voxel and the density is trilinearly interpolated in
+// between, it is zero outside the box
+pub struct GridDensity {
+    resolution: [usize; 3],
+    values: Vec<f32>,
+    min: Vec3,
+    max: Vec3,
+    max_density: f32,
+}
+
+impl GridDensity {
+    pub fn new(resolution: [usize; 3], values: Vec<f32>, min: Vec3, max: Vec3) -> Self {
+        assert_eq!(
+            values.len(),
+            resolution[0] * resolution[1] * resolution[2],
+            "grid values don't match its resolution"
+        );
+        let max_density = values.iter().cloned().fold(0.0, f32::max);
+        GridDensity {
+            resolution,
+            values,
+            min,
+            max,
+            max_density,
+        }
+    }
+
+    // Reads a binary Mitsuba .vol file of float32 data, channels are averaged
+    pub fn load(path: &Path) -> io::Result<GridDensity> {
+        GridDensity::from_vol(&fs::read(path)?)
+    }
+
+    fn from_vol(data: &[u8]) -> io::Result<GridDensity> {
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+        if data.len() < 48 || &data[0..3] != b"VOL" || data[3] != 3 {
+            return Err(invalid("not a version 3 .vol file"));
+        }
+        let int = |i: usize| i32::from_le_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]);
+        let float = |i: usize| f32::from_bits(int(i) as u32);
+        if int(4) != 1 {
+            return Err(invalid("only float32 .vol data is supported"));
+        }
+        if [8, 12, 16, 20].iter().any(|&i| int(i) <= 0) {
+            return Err(invalid(
+                ".vol resolution and channel count have to be positive",
+            ));
+        }
+        let resolution = [int(8) as usize, int(12) as usize, int(16) as usize];
+        let channels = int(20) as usize;
+        let size = resolution
+            .iter()
+            .try_fold(4 * channels, |size, &n| size.checked_mul(n))
+            .and_then(|size| size.checked_add(48));
+        if size.is_none_or(|size| data.len() < size) {
+            return Err(invalid("truncated .vol data"));
+        }
+        let count = resolution[0] * resolution[1] * resolution[2];
+        let min = vec3(float(24), float(28), float(32));
+        let max = vec3(float(36), float(40), float(44));
+        let values = (0..count)
+            .map(|i| {
+                let start = 48 + 4 * i * channels;
+                (0..channels).map(|c| float(start + 4 * c)).sum::<f32>() / channels as f32
+            })
+            .collect();
+        Ok(GridDensity::new(resolution, values, min, max))
+    }
+
+    fn value(&self, x: usize, y: usize, z: usize) -> f32 {
+        self.values[(z * self.resolution[1] + y) * self.resolution[0] + x]
+    }
+}
+
+impl DensityField for GridDensity {
+    fn density(&self, p: &Vec3) -> f32 {
+        let mut index = [0; 2 * 3];
+        let mut weight = [0.0; 3];
+        for axis in 0..3 {
+            let extent = self.max[axis] - self.min[axis];
+            let u = (p[axis] - self.min[axis]) / extent;
+            if !(0.0..=1.0).contains(&u) {
+                return 0.0;
+            }
+            // Continuous voxel coordinate with the values at the voxel centers
+            let last = self.resolution[axis] - 1;
+            let coordinate = (u * self.resolution[axis] as f32 - 0.5).max(0.0);
+            let lower = (coordinate as usize).min(last);
+            index[2 * axis] = lower;
+            index[2 * axis + 1] = (lower + 1).min(last);
+            weight[axis] = (coordinate - lower as f32).min(1.0);
+        }
+        let mut density = 0.0;
+        for corner in 0..8 {
+            let mut w = 1.0;
+            let mut at = [0; 3];
+            for axis in 0..3 {
+                let upper = (corner >> axis) & 1;
+                at[axis] = index[2 * axis + upper];
+                w *= if upper == 1 {
+                    weight[axis]
+                } else {
+                    1.0 - weight[axis]
+                };
+            }
+            density += w * self.value(at[0], at[1], at[2]);
+        }
+        density
+    }
+
+    fn max_density(&self) -> f32 {
+        self.max_density
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vol(resolution: [i32; 3], channels: i32, values: &[f32]) -> Vec<u8> {
+        let mut data = b"VOL\x03".to_vec();
+        for int in [1, resolution[0], resolution[1], resolution[2], channels].iter() {
+            data.extend_from_slice(&int.to_le_bytes());
+        }
+        for float in [0.0f32, 0.0, 0.0, 1.0, 1.0, 1.0].iter().chain(values) {
+            data.extend_from_slice(&float.to_le_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn vol_data_is_read_and_channels_are_averaged() {
+        let grid = GridDensity::from_vol(&vol([2, 1, 1], 2, &[1.0, 3.0, 4.0, 4.0])).unwrap();
+        assert_eq!(grid.resolution, [2, 1, 1]);
+        assert_eq!(grid.values, [2.0, 4.0]);
+        assert_eq!(grid.max_density(), 4.0);
+    }
+
+    #[test]
+    fn malformed_headers_are_rejected() {
+        let error = |data: &[u8]| GridDensity::from_vol(data).err().unwrap().to_string();
+        assert!(error(&vol([2, 2, 2], 1, &[0.5; 7])).contains("truncated"));
+        assert!(error(&vol([2, 2, 2], 1, &[0.5; 8])[..40]).contains("not a version 3"));
+        assert!(error(&vol([0, 2, 2], 1, &[])).contains("positive"));
+        assert!(error(&vol([2, -1, 2], 1, &[0.5; 8])).contains("positive"));
+        assert!(error(&vol([2, 2, 2], 0, &[0.5; 8])).contains("positive"));
+        let huge = i32::MAX;
+        assert!(error(&vol([huge, huge, huge], huge, &[0.5; 8])).contains("truncated"));
+    }
+}
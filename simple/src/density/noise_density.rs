@@ -0,0 +1,28 @@
+use crate::density::DensityField;
+use crate::math::*;
+use crate::texture::{NoiseTexture, Texture};
+
+// Wispy procedural density from the same turbulence as the marble noise texture
+pub struct NoiseDensity {
+    noise: NoiseTexture,
+    density: f32,
+}
+
+impl NoiseDensity {
+    pub fn new(scale: f32, density: f32) -> Self {
+        NoiseDensity {
+            noise: NoiseTexture { scale },
+            density,
+        }
+    }
+}
+
+impl DensityField for NoiseDensity {
+    fn density(&self, p: &Vec3) -> f32 {
+        self.density * self.noise.value(0.0, 0.0, p).x
+    }
+
+    fn max_density(&self) -> f32 {
+        self.density
+    }
+}
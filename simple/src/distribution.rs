@@ -0,0 +1,157 @@
+// Piecewise constant distributions for importance sampling tabulated functions, see
+// section 13.3.1 and 13.6.7 of Physically Based Rendering
+
+pub struct Distribution1D {
+    func: Vec<f32>,
+    cdf: Vec<f32>,
+    func_int: f32,
+}
+
+impl Distribution1D {
+    pub fn new(func: Vec<f32>) -> Distribution1D {
+        let n = func.len();
+        let mut cdf = vec![0.0; n + 1];
+        for i in 1..=n {
+            cdf[i] = cdf[i - 1] + func[i - 1] / n as f32;
+        }
+        let func_int = cdf[n];
+        for (i, value) in cdf.iter_mut().enumerate().skip(1) {
+            // An all zero function is sampled uniformly
+            *value = if func_int == 0.0 {
+                i as f32 / n as f32
+            } else {
+                *value / func_int
+            };
+        }
+        Distribution1D {
+            func,
+            cdf,
+            func_int,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.func.len()
+    }
+
+    // Value in [0, 1) distributed proportionally to the function, its pdf and the index
+    // of the segment it falls in
+    pub fn sample_continuous(&self, u: f32) -> (f32, f32, usize) {
+        let offset =
+            (self.cdf.partition_point(|value| *value <= u).max(1) - 1).min(self.count() - 1);
+        let mut du = u - self.cdf[offset];
+        let width = self.cdf[offset + 1] - self.cdf[offset];
+        if width > 0.0 {
+            du /= width;
+        }
+        let pdf = if self.func_int > 0.0 {
+            self.func[offset] / self.func_int
+        } else {
+            0.0
+        };
+        ((offset as f32 + du) / self.count() as f32, pdf, offset)
+    }
+}
+
+// Distribution over [0, 1)^2 of a function tabulated on a grid of nu by nv cells, stored
+// row by row
+pub struct Distribution2D {
+    conditional: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    pub fn new(func: &[f32], nu: usize, nv: usize) -> Distribution2D {
+        let conditional = func
+            .chunks(nu)
+            .take(nv)
+            .map(|row| Distribution1D::new(row.to_vec()))
+            .collect::<Vec<_>>();
+        let marginal = Distribution1D::new(conditional.iter().map(|row| row.func_int).collect());
+        Distribution2D {
+            conditional,
+            marginal,
+        }
+    }
+
+    // Point distributed proportionally to the function and its pdf
+    pub fn sample_continuous(&self, u: (f32, f32)) -> ((f32, f32), f32) {
+        let (v, pdf_v, row) = self.marginal.sample_continuous(u.1);
+        let (u, pdf_u, _) = self.conditional[row].sample_continuous(u.0);
+        ((u, v), pdf_u * pdf_v)
+    }
+
+    pub fn pdf(&self, u: f32, v: f32) -> f32 {
+        let row = &self.conditional
+            [((v * self.marginal.count() as f32) as usize).min(self.marginal.count() - 1)];
+        let column = ((u * row.count() as f32) as usize).min(row.count() - 1);
+        if self.marginal.func_int == 0.0 {
+            0.0
+        } else {
+            row.func[column] / self.marginal.func_int
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NU: usize = 8;
+    const NV: usize = 6;
+
+    // Grid with some empty cells
+    fn func() -> Vec<f32> {
+        (0..NU * NV)
+            .map(|i| ((i % NU * 7 + i / NU * 3) % 5) as f32)
+            .collect()
+    }
+
+    #[test]
+    fn discrete_segments_follow_the_function() {
+        let func = vec![0.0, 1.0, 3.0, 0.0, 2.0];
+        let distribution = Distribution1D::new(func.clone());
+        let total: f32 = func.iter().sum();
+        let n = 1000;
+        let mut counts = vec![0; func.len()];
+        for i in 0..n {
+            let (x, pdf, offset) = distribution.sample_continuous((i as f32 + 0.5) / n as f32);
+            assert_eq!(pdf, func[offset] * func.len() as f32 / total);
+            assert_eq!((x * func.len() as f32) as usize, offset);
+            counts[offset] += 1;
+        }
+        for (count, value) in counts.iter().zip(&func) {
+            assert!((*count as f32 / n as f32 - value / total).abs() < 0.002);
+        }
+    }
+
+    #[test]
+    fn sample_pdf_matches_pdf() {
+        let func = func();
+        let distribution = Distribution2D::new(&func, NU, NV);
+        let total: f32 = func.iter().sum();
+        let n = 256;
+        let mut counts = [0; NU * NV];
+        for i in 0..n * n {
+            let u = ((i % n) as f32 + 0.5) / n as f32;
+            let v = ((i / n) as f32 + 0.5) / n as f32;
+            let ((x, y), pdf) = distribution.sample_continuous((u, v));
+            assert!(pdf > 0.0);
+            assert!((pdf - distribution.pdf(x, y)).abs() <= 1e-5 * pdf);
+            counts[(y * NV as f32) as usize * NU + (x * NU as f32) as usize] += 1;
+        }
+        // Cells are hit in proportion to the function
+        for (count, value) in counts.iter().zip(&func) {
+            assert!((*count as f32 / (n * n) as f32 - value / total).abs() < 0.005);
+        }
+        // The pdf integrates to one
+        let integral: f32 = (0..NU * NV)
+            .map(|i| {
+                let x = ((i % NU) as f32 + 0.5) / NU as f32;
+                let y = ((i / NU) as f32 + 0.5) / NV as f32;
+                distribution.pdf(x, y) / (NU * NV) as f32
+            })
+            .sum();
+        assert!((integral - 1.0).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,144 @@
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::distribution::Distribution2D;
+use crate::hitable::{HitRecord, Hitable, AABB};
+use crate::math::*;
+use crate::random::random_on_unit_sphere;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+
+use std::f32::consts::PI;
+
+// Radiance arriving from infinitely far away, seen by every ray that leaves the scene. As a
+// light shape it importance samples the directions it is brightest in
+#[derive(Clone)]
+pub enum Environment {
+    Constant(Vec3),
+    Map(Arc<EnvironmentMap>),
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Environment::Constant(Vec3::zero())
+    }
+}
+
+impl Environment {
+    pub fn is_black(&self) -> bool {
+        match self {
+            Environment::Constant(color) => *color == Vec3::zero(),
+            Environment::Map(_) => false,
+        }
+    }
+
+    pub fn radiance(&self, direction: &Vec3) -> Vec3 {
+        match self {
+            Environment::Constant(color) => *color,
+            Environment::Map(map) => map.radiance(direction),
+        }
+    }
+}
+
+impl Hitable for Environment {
+    fn hit(&self, _ray: &Ray, _t_min: f32, _t_max: f32) -> Option<HitRecord<'_>> {
+        None
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
+        None
+    }
+
+    fn pdf_value(&self, _o: &Vec3, v: &Vec3) -> f32 {
+        match self {
+            Environment::Constant(_) => 1.0 / (4.0 * PI),
+            Environment::Map(map) => map.pdf(v),
+        }
+    }
+
+    fn random(&self, _o: &Vec3, sampler: &mut Sampler) -> Vec3 {
+        match self {
+            Environment::Constant(_) => random_on_unit_sphere(sampler),
+            Environment::Map(map) => map.sample(sampler),
+        }
+    }
+}
+
+// Equirectangular image with +y at the top row and -z in the middle of the image
+pub struct EnvironmentMap {
+    width: usize,
+    height: usize,
+    pixels: Vec<Vec3>,
+    // Over the image, proportional to luminance and the solid angle of each pixel
+    distribution: Distribution2D,
+}
+
+impl EnvironmentMap {
+    pub fn new(width: usize, height: usize, pixels: Vec<Vec3>) -> EnvironmentMap {
+        let func = pixels
+            .iter()
+            .enumerate()
+            .map(|(i, pixel)| {
+                let sin_theta = (PI * ((i / width) as f32 + 0.5) / height as f32).sin();
+                luminance(pixel).max(0.0) * sin_theta
+            })
+            .collect::<Vec<_>>();
+        EnvironmentMap {
+            width,
+            height,
+            distribution: Distribution2D::new(&func, width, height),
+            pixels,
+        }
+    }
+
+    // Reads a .hdr or .exr image, scaling its pixels by intensity
+    pub fn load(path: &Path, intensity: f32) -> io::Result<EnvironmentMap> {
+        let image = garage_ray_imageio::load_image(path)?;
+        let pixels = image
+            .pixels
+            .iter()
+            .map(|[r, g, b]| intensity * vec3(*r, *g, *b))
+            .collect();
+        Ok(EnvironmentMap::new(image.width, image.height, pixels))
+    }
+
+    pub fn radiance(&self, direction: &Vec3) -> Vec3 {
+        let (u, v) = direction_to_uv(direction);
+        let x = ((u * self.width as f32) as usize).min(self.width - 1);
+        let y = ((v * self.height as f32) as usize).min(self.height - 1);
+        self.pixels[y * self.width + x]
+    }
+
+    // Pdf with respect to solid angle
+    pub fn pdf(&self, direction: &Vec3) -> f32 {
+        let (u, v) = direction_to_uv(direction);
+        let sin_theta = (PI * v).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        self.distribution.pdf(u, v) / (2.0 * PI * PI * sin_theta)
+    }
+
+    pub fn sample(&self, sampler: &mut Sampler) -> Vec3 {
+        let ((u, v), _) = self.distribution.sample_continuous(sampler.get_2d());
+        uv_to_direction(u, v)
+    }
+}
+
+fn luminance(color: &Vec3) -> f32 {
+    0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z
+}
+
+fn direction_to_uv(direction: &Vec3) -> (f32, f32) {
+    let d = direction.normalize();
+    let phi = d.x.atan2(-d.z);
+    let theta = d.y.clamp(-1.0, 1.0).acos();
+    (0.5 + phi / (2.0 * PI), theta / PI)
+}
+
+fn uv_to_direction(u: f32, v: f32) -> Vec3 {
+    let phi = 2.0 * PI * (u - 0.5);
+    let (sin_theta, cos_theta) = (PI * v).sin_cos();
+    vec3(sin_theta * phi.sin(), cos_theta, -sin_theta * phi.cos())
+}
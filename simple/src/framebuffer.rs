@@ -0,0 +1,119 @@
+use crate::math::*;
+use crate::tonemap::ToneMapping;
+use garage_ray_imageio::ExrLayer;
+pub use garage_ray_imageio::ExrPixelType;
+use std::path::Path;
+
+// Linear radiance of a rendered image, row by row starting at the top
+#[derive(Clone)]
+pub struct FrameBuffer {
+    pub width: u32,
+    pub height: u32,
+    pixels: Vec<Vec3>,
+}
+
+impl FrameBuffer {
+    pub fn new(width: u32, height: u32) -> FrameBuffer {
+        FrameBuffer {
+            width,
+            height,
+            pixels: vec![Vec3::zero(); (width * height) as usize],
+        }
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> Vec3 {
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, color: Vec3) {
+        self.pixels[(y * self.width + x) as usize] = color;
+    }
+
+    pub fn pixels(&self) -> &[Vec3] {
+        &self.pixels
+    }
+
+    pub fn pixels_mut(&mut self) -> &mut [Vec3] {
+        &mut self.pixels
+    }
+
+    pub fn rows(&self) -> std::slice::Chunks<'_, Vec3> {
+        self.pixels.chunks(self.width as usize)
+    }
+
+    // Displayable 8 bit sRGB image
+    pub fn tone_map(&self, tone_mapping: &ToneMapping) -> image::RgbImage {
+        let mut image = image::RgbImage::new(self.width, self.height);
+        for (pixel, color) in image.pixels_mut().zip(self.pixels.iter()) {
+            *pixel = image::Rgb(tone_mapping.map(*color));
+        }
+        image
+    }
+
+    fn to_rgb(&self) -> Vec<[f32; 3]> {
+        self.pixels.iter().map(|color| (*color).into()).collect()
+    }
+
+    // Linear OpenEXR image. Extra layers of the same size, like albedo or normals, are
+    // written next to the main RGB channels under their names
+    pub fn save_exr(
+        &self,
+        path: &Path,
+        pixel_type: ExrPixelType,
+        extra_layers: &[(&str, &FrameBuffer)],
+    ) -> Result<(), String> {
+        let mut rgb = vec![self.to_rgb()];
+        for (name, layer) in extra_layers {
+            if layer.width != self.width || layer.height != self.height {
+                return Err(format!("layer '{}' doesn't match the image size", name));
+            }
+            rgb.push(layer.to_rgb());
+        }
+        let layers = std::iter::once("")
+            .chain(extra_layers.iter().map(|(name, _)| *name))
+            .zip(&rgb)
+            .map(|(name, pixels)| ExrLayer { name, pixels })
+            .collect::<Vec<ExrLayer>>();
+        garage_ray_imageio::save_exr(
+            path,
+            self.width as usize,
+            self.height as usize,
+            pixel_type,
+            &layers,
+        )
+        .map_err(|err| format!("failed to write '{}': {}", path.display(), err))
+    }
+
+    // Linear Radiance RGBE image
+    pub fn save_hdr(&self, path: &Path) -> Result<(), String> {
+        garage_ray_imageio::save_hdr(
+            path,
+            self.width as usize,
+            self.height as usize,
+            &self.to_rgb(),
+        )
+        .map_err(|err| format!("failed to write '{}': {}", path.display(), err))
+    }
+
+    // Picks the format from the extension. EXR and HDR files keep the linear values, other
+    // formats get the tone mapped image
+    pub fn save(
+        &self,
+        path: &Path,
+        tone_mapping: &ToneMapping,
+        exr_pixel_type: ExrPixelType,
+    ) -> Result<(), String> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("exr") => self.save_exr(path, exr_pixel_type, &[]),
+            Some("hdr") => self.save_hdr(path),
+            _ => self
+                .tone_map(tone_mapping)
+                .save(path)
+                .map_err(|err| format!("failed to write '{}': {}", path.display(), err)),
+        }
+    }
+}
@@ -2,26 +2,32 @@ mod box_hitable;
 mod bvh;
 mod constant_medium;
 mod flip_normals;
+mod heterogeneous_medium;
 pub mod hitable_list;
+mod medium;
 mod rect;
 mod sphere;
 mod transformations;
+mod triangle_mesh;
 
 pub use box_hitable::BoxHitable;
 pub use constant_medium::ConstantMedium;
 pub use flip_normals::FlipNormals;
+pub use heterogeneous_medium::HeterogeneousMedium;
 pub use rect::XYRect;
 pub use rect::XZRect;
 pub use rect::YZRect;
 pub use sphere::MovingSphere;
 pub use sphere::Sphere;
 pub use transformations::{RotateY, Translate};
+pub use triangle_mesh::{MeshData, Triangle, TriangleMesh};
 
 pub use bvh::BVHNode;
 
 use crate::material::Material;
 use crate::math::{vec3, Vec3};
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 
 #[derive(Clone, Copy)]
 pub struct HitRecord<'a> {
@@ -46,13 +52,13 @@ impl Default for HitRecord<'_> {
     }
 }
 
-pub trait Hitable: Sync {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord>;
+pub trait Hitable: Send + Sync {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>>;
     fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB>;
     fn pdf_value(&self, _o: &Vec3, _v: &Vec3) -> f32 {
         0.0
     }
-    fn random(&self, _o: &Vec3) -> Vec3 {
+    fn random(&self, _o: &Vec3, _sampler: &mut Sampler) -> Vec3 {
         vec3(1.0, 0.0, 0.0)
     }
 }
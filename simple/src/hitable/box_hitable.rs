@@ -10,7 +10,7 @@ pub struct BoxHitable {
 }
 
 impl Hitable for BoxHitable {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         self.list.hit(ray, t_min, t_max)
     }
 
@@ -74,7 +74,7 @@ impl BoxHitable {
                     z0: p0.z,
                     z1: p1.z,
                     k: p0.x,
-                    material: material,
+                    material,
                 }))),
             ],
         }
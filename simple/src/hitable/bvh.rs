@@ -1,78 +1,230 @@
 use crate::hitable::{surrounding_box, HitRecord, Hitable, AABB};
-use crate::random::random_int;
+use crate::math::*;
 use crate::ray::Ray;
 
-pub struct BVHNode {
-    left: Box<dyn Hitable>,
-    right: Box<dyn Hitable>,
+// Candidate split planes tested per axis
+const BUCKET_COUNT: usize = 12;
+const MAX_LEAF_SIZE: usize = 4;
+
+struct BuildEntry {
+    index: usize,
+    aabb: AABB,
+    centroid: Vec3,
+}
+
+// Nodes are stored depth first, so the first child of an interior node directly follows it
+struct LinearNode {
     aabb: AABB,
+    // First hitable for leaves, second child for interior nodes
+    offset: usize,
+    // Zero for interior nodes
+    count: usize,
+    axis: usize,
+}
+
+pub struct BVHNode {
+    hitables: Vec<Box<dyn Hitable>>,
+    nodes: Vec<LinearNode>,
+    // Hitables without a bounding box are tested against every ray
+    unbounded: Vec<Box<dyn Hitable>>,
 }
 
 impl Hitable for BVHNode {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
-        if self.aabb.hit(ray, t_min, t_max) {
-            match (
-                self.left.hit(ray, t_min, t_max),
-                self.right.hit(ray, t_min, t_max),
-            ) {
-                (Some(left_record), Some(right_record)) => {
-                    if left_record.t < right_record.t {
-                        Some(left_record)
-                    } else {
-                        Some(right_record)
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let mut hit = self.unbounded.hit(ray, t_min, t_max);
+        let mut closest_so_far = hit.map_or(t_max, |record| record.t);
+        if self.nodes.is_empty() {
+            return hit;
+        }
+
+        let mut to_visit = Vec::with_capacity(64);
+        to_visit.push(0);
+        while let Some(index) = to_visit.pop() {
+            let node = &self.nodes[index];
+            // Nodes beyond the closest hit found so far are skipped
+            if !node.aabb.hit(ray, t_min, closest_so_far) {
+                continue;
+            }
+            if node.count > 0 {
+                for hitable in &self.hitables[node.offset..node.offset + node.count] {
+                    if let Some(record) = hitable.hit(ray, t_min, closest_so_far) {
+                        closest_so_far = record.t;
+                        hit = Some(record);
                     }
                 }
-                (Some(left_record), None) => Some(left_record),
-                (None, Some(right_record)) => Some(right_record),
-                (None, None) => None,
+            } else if ray.direction[node.axis] < 0.0 {
+                // Visit the nearer child first
+                to_visit.push(index + 1);
+                to_visit.push(node.offset);
+            } else {
+                to_visit.push(node.offset);
+                to_visit.push(index + 1);
             }
-        } else {
-            None
         }
+        hit
     }
 
     fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
-        Some(self.aabb)
+        if !self.unbounded.is_empty() {
+            return None;
+        }
+        self.nodes.first().map(|root| root.aabb)
     }
 }
 
-fn box_compare(left: &dyn Hitable, right: &dyn Hitable, i: usize) -> std::cmp::Ordering {
-    if let (Some(left_box), Some(right_box)) =
-        (left.bounding_box(0.0, 0.0), right.bounding_box(0.0, 0.0))
-    {
-        left_box.min[i].partial_cmp(&right_box.min[i]).unwrap()
+fn centroid(aabb: &AABB) -> Vec3 {
+    (aabb.min + aabb.max) * 0.5
+}
+
+fn surface_area(aabb: &AABB) -> f32 {
+    let d = aabb.max - aabb.min;
+    2.0 * (d.x * d.y + d.x * d.z + d.y * d.z)
+}
+
+fn union_all<'a>(boxes: impl Iterator<Item = &'a AABB>) -> Option<AABB> {
+    boxes.fold(None, |acc, aabb| match acc {
+        Some(acc) => Some(surrounding_box(acc, *aabb)),
+        None => Some(*aabb),
+    })
+}
+
+impl BVHNode {
+    pub fn build(list: Vec<Box<dyn Hitable>>, t0: f32, t1: f32) -> BVHNode {
+        let mut entries = Vec::with_capacity(list.len());
+        let mut bounded = Vec::with_capacity(list.len());
+        let mut unbounded = Vec::new();
+        for hitable in list {
+            match hitable.bounding_box(t0, t1) {
+                Some(aabb) => {
+                    entries.push(BuildEntry {
+                        index: bounded.len(),
+                        aabb,
+                        centroid: centroid(&aabb),
+                    });
+                    bounded.push(Some(hitable));
+                }
+                None => unbounded.push(hitable),
+            }
+        }
+
+        let mut nodes = Vec::new();
+        if !entries.is_empty() {
+            build_recursive(&mut entries, 0, &mut nodes);
+        }
+
+        // Leaves reference ranges of hitables in the order the build left the entries in
+        let hitables = entries
+            .iter()
+            .map(|entry| bounded[entry.index].take().unwrap())
+            .collect();
+        BVHNode {
+            hitables,
+            nodes,
+            unbounded,
+        }
+    }
+}
+
+// Builds the subtree over entries, which start at offset in the final hitable order.
+// Returns the index of the subtree root in nodes
+fn build_recursive(
+    entries: &mut [BuildEntry],
+    offset: usize,
+    nodes: &mut Vec<LinearNode>,
+) -> usize {
+    let aabb = union_all(entries.iter().map(|entry| &entry.aabb)).unwrap();
+    let node_index = nodes.len();
+    nodes.push(LinearNode {
+        aabb,
+        offset,
+        count: entries.len(),
+        axis: 0,
+    });
+
+    let split = if entries.len() > 1 {
+        find_split(entries, &aabb)
     } else {
-        panic!("This should not happen");
+        None
+    };
+    if let Some((axis, mid)) = split {
+        build_recursive(&mut entries[..mid], offset, nodes);
+        let second_child = build_recursive(&mut entries[mid..], offset + mid, nodes);
+        let node = &mut nodes[node_index];
+        node.offset = second_child;
+        node.count = 0;
+        node.axis = axis;
     }
+    node_index
 }
 
-impl BVHNode {
-    pub fn build(mut list: Vec<Box<dyn Hitable>>, t0: f32, t1: f32) -> BVHNode {
-        let axis = random_int(0, 3);
-        list.sort_unstable_by(|a, b| box_compare(&**a, &**b, axis));
+// Picks the axis and bucket boundary with the lowest surface area heuristic cost and
+// partitions entries around it. Returns None when a leaf is cheaper
+fn find_split(entries: &mut [BuildEntry], aabb: &AABB) -> Option<(usize, usize)> {
+    let centroid_bounds = entries.iter().skip(1).fold(
+        AABB {
+            min: entries[0].centroid,
+            max: entries[0].centroid,
+        },
+        |acc, entry| {
+            surrounding_box(
+                acc,
+                AABB {
+                    min: entry.centroid,
+                    max: entry.centroid,
+                },
+            )
+        },
+    );
+    let extent = centroid_bounds.max - centroid_bounds.min;
+    let bucket_index = |entry: &BuildEntry, axis: usize| {
+        let b = ((entry.centroid[axis] - centroid_bounds.min[axis]) / extent[axis]
+            * BUCKET_COUNT as f32) as usize;
+        b.min(BUCKET_COUNT - 1)
+    };
 
-        let n = list.len();
-        if n == 1 {
-            panic!("should not happen");
+    let mut best: Option<(f32, usize, usize)> = None;
+    for axis in 0..3 {
+        if extent[axis] <= 0.0 {
+            continue;
+        }
+        let mut counts = [0; BUCKET_COUNT];
+        let mut bounds: [Option<AABB>; BUCKET_COUNT] = [None; BUCKET_COUNT];
+        for entry in entries.iter() {
+            let b = bucket_index(entry, axis);
+            counts[b] += 1;
+            bounds[b] = union_all(bounds[b].iter().chain(std::iter::once(&entry.aabb)));
         }
-        let (left, right): (Box<dyn Hitable>, Box<dyn Hitable>) = match n {
-            2 => (list.pop().unwrap(), list.pop().unwrap()),
-            3 => (list.pop().unwrap(), Box::new(BVHNode::build(list, t0, t1))),
-            _ => (
-                Box::new(BVHNode::build(list.drain(..n / 2).collect(), t0, t1)),
-                Box::new(BVHNode::build(list, t0, t1)),
-            ),
-        };
-        if let (Some(left_box), Some(right_box)) =
-            (left.bounding_box(t0, t1), right.bounding_box(t0, t1))
-        {
-            BVHNode {
-                left,
-                right,
-                aabb: surrounding_box(left_box, right_box),
+        for split in 1..BUCKET_COUNT {
+            let count0: usize = counts[..split].iter().sum();
+            let count1: usize = counts[split..].iter().sum();
+            let (b0, b1) = match (
+                union_all(bounds[..split].iter().flatten()),
+                union_all(bounds[split..].iter().flatten()),
+            ) {
+                (Some(b0), Some(b1)) => (b0, b1),
+                _ => continue,
+            };
+            let cost = 1.0
+                + (count0 as f32 * surface_area(&b0) + count1 as f32 * surface_area(&b1))
+                    / surface_area(aabb);
+            if best.is_none_or(|(best_cost, _, _)| cost < best_cost) {
+                best = Some((cost, axis, split));
             }
-        } else {
-            panic!("no bounding box in BVHNode::build");
         }
     }
+
+    // All centroids coincide, there is nothing to split on
+    let (cost, axis, split) = best?;
+    if entries.len() <= MAX_LEAF_SIZE && cost >= entries.len() as f32 {
+        return None;
+    }
+
+    let mut mid = 0;
+    for i in 0..entries.len() {
+        if bucket_index(&entries[i], axis) < split {
+            entries.swap(mid, i);
+            mid += 1;
+        }
+    }
+    Some((axis, mid))
 }
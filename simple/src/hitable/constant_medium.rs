@@ -1,49 +1,48 @@
+use crate::hitable::medium::{first_collision, free_path_rng, inside_segments, medium_salt};
 use crate::hitable::{HitRecord, Hitable, AABB};
 use crate::material::*;
 use crate::math::*;
-use crate::random::*;
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 use crate::texture::*;
 
+// Homogeneous medium filling a closed boundary. Collisions are sampled with the largest
+// extinction of the three channels, in the channels with less extinction part of the
+// collisions are null collisions that leave the light unchanged
 pub struct ConstantMedium {
     boundary: Box<dyn Hitable>,
-    density: f32,
+    majorant: f32,
+    collision: Collision,
+    salt: u32,
+}
+
+// What happens at a sampled collision: scattering by the phase function, whose albedo is
+// scaled to the majorant, or passing through with the null collision share
+#[derive(Clone)]
+struct Collision {
     phase_function: Box<dyn Material>,
+    null_weight: Vec3,
+}
+
+impl Material for Collision {
+    fn scatter(&self, ray: &Ray, rec: &HitRecord, sampler: &mut Sampler) -> Option<ScatterResult> {
+        self.phase_function.scatter(ray, rec, sampler)
+    }
+
+    fn scattering_pdf(&self, ray: &Ray, rec: &HitRecord, scattered: &Ray) -> f32 {
+        self.phase_function.scattering_pdf(ray, rec, scattered)
+    }
+
+    fn pass_through(&self, _rec: &HitRecord) -> Vec3 {
+        self.null_weight
+    }
 }
 
 impl Hitable for ConstantMedium {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
-        //let mut db = random_float() < 0.00001;
-        if let Some(mut rec1) = self.boundary.hit(ray, -std::f32::MAX, std::f32::MAX) {
-            if let Some(mut rec2) = self.boundary.hit(ray, rec1.t + 0.0001, std::f32::MAX) {
-                if rec1.t < t_min {
-                    rec1.t = t_min;
-                }
-                if rec2.t > t_max {
-                    rec2.t = t_max;
-                }
-                if rec1.t >= rec2.t {
-                    return None;
-                }
-                if rec1.t < 0.0 {
-                    rec1.t = 0.0;
-                }
-                let distance_inside_boundary = (rec2.t - rec1.t) * ray.direction.magnitude();
-                let hit_distance = -(1.0 / self.density) * random_float().ln();
-                if hit_distance < distance_inside_boundary {
-                    let t = rec1.t + hit_distance / ray.direction.magnitude();
-                    return Some(HitRecord {
-                        t,
-                        p: ray.point_at_parameter(t),
-                        normal: vec3(1.0, 0.0, 0.0), //arbitrary
-                        material: Some(&*self.phase_function),
-                        u: 0.0,
-                        v: 0.0,
-                    });
-                }
-            }
-        }
-        None
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let segments = inside_segments(&*self.boundary, ray, t_min, t_max);
+        let mut rng = free_path_rng(ray, self.salt);
+        first_collision(&segments, ray, self.majorant, &mut rng, &self.collision)
     }
 
     fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB> {
@@ -52,12 +51,44 @@ impl Hitable for ConstantMedium {
 }
 
 impl ConstantMedium {
-    pub fn new(boundary: Box<dyn Hitable>, density: f32, texture: Box<dyn Texture>) -> Self {
+    // Medium that absorbs and scatters the same amount in every channel, the albedo of the
+    // phase function gives the part of the extinction that is scattering
+    pub fn new(
+        boundary: Box<dyn Hitable>,
+        density: f32,
+        phase_function: Box<dyn Material>,
+    ) -> Self {
+        ConstantMedium {
+            salt: medium_salt(&*boundary, density),
+            boundary,
+            majorant: density,
+            collision: Collision {
+                phase_function,
+                null_weight: Vec3::zero(),
+            },
+        }
+    }
+
+    // Medium with separate absorption and scattering coefficients per channel, so light is
+    // tinted by what it passes through as well as by what scatters it
+    pub fn with_coefficients(
+        boundary: Box<dyn Hitable>,
+        sigma_a: Vec3,
+        sigma_s: Vec3,
+        g: f32,
+    ) -> Self {
+        let sigma_t = sigma_a + sigma_s;
+        let majorant = sigma_t.x.max(sigma_t.y).max(sigma_t.z);
+        // Without extinction no collision is ever sampled
+        let scale = if majorant > 0.0 { 1.0 / majorant } else { 0.0 };
         ConstantMedium {
+            salt: medium_salt(&*boundary, majorant),
             boundary,
-            density,
-            //phase_function: Box::new(Isotropic(texture)),
-            phase_function: Box::new(Lambertian { albedo: texture }),
+            majorant,
+            collision: Collision {
+                phase_function: phase_function(Box::new(ConstantTexture(sigma_s * scale)), g),
+                null_weight: vec3(1.0, 1.0, 1.0) - sigma_t * scale,
+            },
         }
     }
 }
@@ -4,7 +4,7 @@ use crate::ray::Ray;
 pub struct FlipNormals(pub Box<dyn Hitable>);
 
 impl Hitable for FlipNormals {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         let rec = self.0.hit(ray, t_min, t_max)?;
         Some(HitRecord {
             normal: -rec.normal,
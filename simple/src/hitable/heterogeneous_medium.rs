@@ -0,0 +1,158 @@
+use crate::density::DensityField;
+use crate::hitable::medium::{first_collision, free_path_rng, inside_segments, medium_salt};
+use crate::hitable::{HitRecord, Hitable, AABB};
+use crate::material::*;
+use crate::math::*;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::texture::Texture;
+use std::sync::Arc;
+
+// Medium whose coefficients are scaled by a density field. Tentative collisions are
+// sampled with the largest extinction the medium can have, at each one the local extinction
+// decides how much scatters and how much is a null collision. Following the null collisions
+// in the renderer gives delta tracking for free paths and ratio tracking for transmittance
+pub struct HeterogeneousMedium {
+    boundary: Box<dyn Hitable>,
+    density: Arc<dyn DensityField>,
+    majorant: f32,
+    collision: Collision,
+    salt: u32,
+}
+
+// Coefficients at a collision, relative to the majorant. Transforms around the medium
+// only move p, so the density is looked up in the medium's own space when the collision is
+// found and handed over in u
+#[derive(Clone)]
+struct LocalCoefficient {
+    coefficient: Vec3,
+}
+
+impl Texture for LocalCoefficient {
+    fn value(&self, density: f32, _v: f32, _p: &Vec3) -> Vec3 {
+        density * self.coefficient
+    }
+}
+
+#[derive(Clone)]
+struct Collision {
+    phase_function: Box<dyn Material>,
+    extinction: LocalCoefficient,
+}
+
+impl Material for Collision {
+    fn scatter(&self, ray: &Ray, rec: &HitRecord, sampler: &mut Sampler) -> Option<ScatterResult> {
+        self.phase_function.scatter(ray, rec, sampler)
+    }
+
+    fn scattering_pdf(&self, ray: &Ray, rec: &HitRecord, scattered: &Ray) -> f32 {
+        self.phase_function.scattering_pdf(ray, rec, scattered)
+    }
+
+    fn pass_through(&self, rec: &HitRecord) -> Vec3 {
+        vec3(1.0, 1.0, 1.0) - self.extinction.value(rec.u, rec.v, &rec.p)
+    }
+}
+
+impl Hitable for HeterogeneousMedium {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let segments = inside_segments(&*self.boundary, ray, t_min, t_max);
+        let mut rng = free_path_rng(ray, self.salt);
+        first_collision(&segments, ray, self.majorant, &mut rng, &self.collision).map(|rec| {
+            HitRecord {
+                u: self.density.density(&rec.p),
+                ..rec
+            }
+        })
+    }
+
+    fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB> {
+        self.boundary.bounding_box(t0, t1)
+    }
+}
+
+impl HeterogeneousMedium {
+    // The density field is in the same space as the boundary, so it moves along when the
+    // medium is transformed
+    pub fn new(
+        boundary: Box<dyn Hitable>,
+        density: Arc<dyn DensityField>,
+        sigma_a: Vec3,
+        sigma_s: Vec3,
+        g: f32,
+    ) -> Self {
+        let sigma_t = sigma_a + sigma_s;
+        let majorant = density.max_density() * sigma_t.x.max(sigma_t.y).max(sigma_t.z);
+        // Without extinction no collision is ever sampled
+        let scale = if majorant > 0.0 { 1.0 / majorant } else { 0.0 };
+        let scattering = LocalCoefficient {
+            coefficient: sigma_s * scale,
+        };
+        HeterogeneousMedium {
+            salt: medium_salt(&*boundary, majorant),
+            boundary,
+            density,
+            majorant,
+            collision: Collision {
+                phase_function: phase_function(Box::new(scattering), g),
+                extinction: LocalCoefficient {
+                    coefficient: sigma_t * scale,
+                },
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::density::GridDensity;
+    use crate::hitable::{Sphere, Translate};
+    use crate::texture::ConstantTexture;
+
+    #[test]
+    fn density_moves_with_the_medium() {
+        let boundary = Box::new(Sphere {
+            center: vec3(0.0, 0.0, 0.0),
+            radius: 1.0,
+            material: Box::new(Lambertian {
+                albedo: Box::new(ConstantTexture(vec3(0.5, 0.5, 0.5))),
+            }),
+        });
+        let density = Arc::new(GridDensity::new(
+            [2, 1, 1],
+            vec![0.25, 1.0],
+            vec3(-1.0, -1.0, -1.0),
+            vec3(1.0, 1.0, 1.0),
+        ));
+        let half = vec3(0.5, 0.5, 0.5);
+        let medium = HeterogeneousMedium::new(boundary, density.clone(), half, half, 0.0);
+        let offset = vec3(10.0, 0.0, 0.0);
+        let moved = Translate {
+            offset,
+            hitable: Box::new(medium),
+        };
+
+        let mut hits = 0;
+        for i in 0..64 {
+            let ray = Ray {
+                origin: offset
+                    + vec3(
+                        -2.0,
+                        (i % 8) as f32 * 0.1 - 0.35,
+                        (i / 8) as f32 * 0.1 - 0.35,
+                    ),
+                direction: vec3(1.0, 0.0, 0.0),
+                time: 0.0,
+            };
+            if let Some(rec) = moved.hit(&ray, 0.001, f32::MAX) {
+                // With a majorant of 1 the null collision weight is 1 - density
+                let expected = 1.0 - density.density(&(rec.p - offset));
+                let pass_through = rec.material.unwrap().pass_through(&rec);
+                assert!((pass_through.x - expected).abs() < 1e-5, "{:?}", rec.p);
+                hits += 1;
+            }
+        }
+        assert!(hits > 32);
+    }
+}
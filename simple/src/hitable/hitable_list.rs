@@ -1,10 +1,10 @@
 use crate::hitable::{surrounding_box, HitRecord, Hitable, AABB};
 use crate::math::*;
-use crate::random::*;
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 
 impl Hitable for Vec<Box<dyn Hitable>> {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         let mut hit: Option<HitRecord> = None;
         let mut closest_so_far = t_max;
         for hitable in self {
@@ -18,7 +18,7 @@ impl Hitable for Vec<Box<dyn Hitable>> {
     }
 
     fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB> {
-        if self.len() < 1 {
+        if self.is_empty() {
             return None;
         }
         let mut result = self.first().unwrap().bounding_box(t0, t1)?;
@@ -31,13 +31,17 @@ impl Hitable for Vec<Box<dyn Hitable>> {
 
     fn pdf_value(&self, o: &Vec3, v: &Vec3) -> f32 {
         let weight = 1.0 / self.len() as f32;
-        self.into_iter()
+        self.iter()
             .map(|hitable| weight * hitable.pdf_value(o, v))
             .sum()
     }
 
-    fn random(&self, o: &Vec3) -> Vec3 {
-        let index = (random_float() * self.len() as f32) as usize;
-        self[index].random(o)
+    fn random(&self, o: &Vec3, sampler: &mut Sampler) -> Vec3 {
+        // Without shapes there is nothing to aim at, pdf_value is 0 for every direction
+        if self.is_empty() {
+            return vec3(1.0, 0.0, 0.0);
+        }
+        let index = (sampler.get_1d() * self.len() as f32) as usize;
+        self[index].random(o, sampler)
     }
 }
@@ -0,0 +1,200 @@
+use crate::hitable::{HitRecord, Hitable};
+use crate::material::Material;
+use crate::math::*;
+use crate::random::Rng;
+use crate::ray::Ray;
+use smallvec::SmallVec;
+
+// Boundary crossings closer together than this are taken to be the same crossing
+const CROSSING_EPSILON: f32 = 0.0001;
+
+// Most rays cross a boundary only a few times, so the segments are kept on the stack
+pub(super) type Segments = SmallVec<[(f32, f32); 4]>;
+
+// Parts of the ray between t_min and t_max inside a closed boundary with outward facing
+// normals, in order. Crossings are told apart by the side they are hit from, so the
+// boundary may be non-convex or made of nested shells. How deep inside the ray starts
+// depends on all the crossings ahead of it, so the boundary is followed past t_max but only
+// the crossings before t_max are kept
+pub(super) fn inside_segments(
+    boundary: &dyn Hitable,
+    ray: &Ray,
+    t_min: f32,
+    t_max: f32,
+) -> Segments {
+    // Crossings between t_min and t_max, 1 for entering and -1 for leaving
+    let mut crossings: SmallVec<[(f32, i32); 8]> = SmallVec::new();
+    let mut depth = 0;
+    let mut lowest = 0;
+    let mut t = t_min;
+    while let Some(rec) = boundary.hit(ray, t, f32::MAX) {
+        let step = if dot(rec.normal, ray.direction) < 0.0 {
+            1
+        } else {
+            -1
+        };
+        if rec.t < t_max {
+            crossings.push((rec.t, step));
+        }
+        depth += step;
+        lowest = lowest.min(depth);
+        t = rec.t + CROSSING_EPSILON;
+    }
+
+    // Leaving more often than entering means the ray starts inside
+    let mut depth = -lowest;
+
+    let mut segments = Segments::new();
+    let mut start = t_min;
+    for (t, step) in crossings {
+        let was_inside = depth > 0;
+        depth += step;
+        if !was_inside && depth > 0 {
+            start = t;
+        } else if was_inside && depth == 0 && start < t {
+            segments.push((start, t));
+        }
+    }
+    if depth > 0 && start < t_max {
+        segments.push((start, t_max));
+    }
+    segments
+}
+
+// Hit tests can't be handed a generator, so free paths are drawn from a generator seeded
+// from the ray itself to keep renders reproducible. The medium's salt keeps the free paths
+// of different media along the same ray independent
+pub(super) fn free_path_rng(ray: &Ray, salt: u32) -> Rng {
+    Rng::from_hash(&[
+        ray.origin.x.to_bits(),
+        ray.origin.y.to_bits(),
+        ray.origin.z.to_bits(),
+        ray.direction.x.to_bits(),
+        ray.direction.y.to_bits(),
+        ray.direction.z.to_bits(),
+        salt,
+    ])
+}
+
+// Salt for free_path_rng that only depends on where the medium is and how dense it is
+pub(super) fn medium_salt(boundary: &dyn Hitable, majorant: f32) -> u32 {
+    let mut values = vec![majorant.to_bits()];
+    if let Some(bbox) = boundary.bounding_box(0.0, 1.0) {
+        for i in 0..3 {
+            values.push(bbox.min[i].to_bits());
+            values.push(bbox.max[i].to_bits());
+        }
+    }
+    Rng::from_hash(&values).next_u32()
+}
+
+// First collision in the segments for collisions happening at rate majorant per unit of
+// length, by exponentially distributed free paths
+pub(super) fn first_collision<'a>(
+    segments: &[(f32, f32)],
+    ray: &Ray,
+    majorant: f32,
+    rng: &mut Rng,
+    material: &'a dyn Material,
+) -> Option<HitRecord<'a>> {
+    let speed = ray.direction.magnitude();
+    for (t0, t1) in segments {
+        let free_path = -(1.0 / majorant) * (1.0 - rng.float()).ln();
+        let t = t0 + free_path / speed;
+        if t < *t1 {
+            return Some(HitRecord {
+                t,
+                p: ray.point_at_parameter(t),
+                normal: vec3(1.0, 0.0, 0.0), //arbitrary
+                material: Some(material),
+                u: 0.0,
+                v: 0.0,
+            });
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hitable::Sphere;
+    use crate::material::Lambertian;
+    use crate::texture::ConstantTexture;
+
+    fn sphere(radius: f32) -> Box<dyn Hitable> {
+        Box::new(Sphere {
+            center: vec3(0.0, 0.0, 0.0),
+            radius,
+            material: Box::new(Lambertian {
+                albedo: Box::new(ConstantTexture(vec3(0.5, 0.5, 0.5))),
+            }),
+        })
+    }
+
+    fn ray_from(x: f32) -> Ray {
+        Ray {
+            origin: vec3(x, 0.0, 0.0),
+            direction: vec3(1.0, 0.0, 0.0),
+            time: 0.0,
+        }
+    }
+
+    fn assert_segments(segments: &[(f32, f32)], expected: &[(f32, f32)]) {
+        assert_eq!(segments.len(), expected.len(), "{:?}", segments);
+        for (a, b) in segments.iter().zip(expected) {
+            assert!(
+                (a.0 - b.0).abs() < 1e-3 && (a.1 - b.1).abs() < 1e-3,
+                "{:?}",
+                segments
+            );
+        }
+    }
+
+    #[test]
+    fn segments_are_clipped_to_the_ray_range() {
+        let boundary = sphere(1.0);
+        let ray = ray_from(-5.0);
+        assert_segments(
+            &inside_segments(&*boundary, &ray, 0.001, f32::MAX),
+            &[(4.0, 6.0)],
+        );
+        assert_segments(
+            &inside_segments(&*boundary, &ray, 0.001, 5.0),
+            &[(4.0, 5.0)],
+        );
+        assert_segments(&inside_segments(&*boundary, &ray, 0.001, 3.0), &[]);
+    }
+
+    #[test]
+    fn rays_starting_inside_nested_shells_stay_inside() {
+        let boundary: Box<dyn Hitable> = Box::new(vec![sphere(1.0), sphere(2.0)]);
+        let ray = ray_from(0.0);
+        assert_segments(
+            &inside_segments(&*boundary, &ray, 0.001, f32::MAX),
+            &[(0.001, 2.0)],
+        );
+        assert_segments(
+            &inside_segments(&*boundary, &ray, 0.001, 1.5),
+            &[(0.001, 1.5)],
+        );
+    }
+
+    #[test]
+    fn rays_starting_between_nested_shells_are_inside_before_the_inner_one() {
+        let boundary: Box<dyn Hitable> = Box::new(vec![sphere(1.0), sphere(2.0)]);
+        let ray = ray_from(-1.5);
+        assert_segments(
+            &inside_segments(&*boundary, &ray, 0.001, 0.3),
+            &[(0.001, 0.3)],
+        );
+        assert_segments(
+            &inside_segments(&*boundary, &ray, 0.001, 3.0),
+            &[(0.001, 3.0)],
+        );
+        assert_segments(
+            &inside_segments(&*boundary, &ray, 0.001, f32::MAX),
+            &[(0.001, 3.5)],
+        );
+    }
+}
@@ -1,8 +1,8 @@
 use crate::hitable::{HitRecord, Hitable, AABB};
 use crate::material::Material;
 use crate::math::*;
-use crate::random::random_float;
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 
 pub struct XYRect {
     pub x0: f32,
@@ -14,7 +14,7 @@ pub struct XYRect {
 }
 
 impl Hitable for XYRect {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         let t = (self.k - ray.origin.z) / ray.direction.z;
         if t < t_min || t > t_max {
             return None;
@@ -52,7 +52,7 @@ pub struct XZRect {
 }
 
 impl Hitable for XZRect {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         let t = (self.k - ray.origin.y) / ray.direction.y;
         if t < t_min || t > t_max {
             return None;
@@ -87,7 +87,7 @@ impl Hitable for XZRect {
                 time: 0.0,
             },
             0.001,
-            std::f32::MAX,
+            f32::MAX,
         ) {
             let area = (self.x1 - self.x0) * (self.z1 - self.z0);
             let distance_squared = rec.t * rec.t * v.magnitude2();
@@ -98,11 +98,12 @@ impl Hitable for XZRect {
         }
     }
 
-    fn random(&self, o: &Vec3) -> Vec3 {
+    fn random(&self, o: &Vec3, sampler: &mut Sampler) -> Vec3 {
+        let (u, v) = sampler.get_2d();
         let random_point = vec3(
-            self.x0 + random_float() * (self.x1 - self.x0),
+            self.x0 + u * (self.x1 - self.x0),
             self.k,
-            self.z0 + random_float() * (self.z1 - self.z0),
+            self.z0 + v * (self.z1 - self.z0),
         );
         random_point - o
     }
@@ -118,7 +119,7 @@ pub struct YZRect {
 }
 
 impl Hitable for YZRect {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         let t = (self.k - ray.origin.x) / ray.direction.x;
         if t < t_min || t > t_max {
             return None;
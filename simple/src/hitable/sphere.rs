@@ -2,8 +2,9 @@ use crate::hitable::{surrounding_box, HitRecord, Hitable, AABB};
 use crate::material::Material;
 use crate::math::*;
 use crate::onb::*;
-use crate::random::*;
+use crate::random::random_on_unit_sphere;
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 
 #[derive(Clone)]
 pub struct Sphere {
@@ -22,7 +23,7 @@ fn get_sphere_uv(p: Vec3) -> (f32, f32) {
 }
 
 impl Hitable for Sphere {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         let oc = ray.origin - self.center;
         let a = dot(ray.direction, ray.direction);
         let b = dot(oc, ray.direction);
@@ -67,34 +68,44 @@ impl Hitable for Sphere {
     }
 
     fn pdf_value(&self, o: &Vec3, v: &Vec3) -> f32 {
-        if let Some(_) = self.hit(
-            &Ray {
-                origin: *o,
-                direction: *v,
-                time: 0.0,
-            },
-            0.001,
-            std::f32::MAX,
-        ) {
-            let cos_theta_max =
-                (1.0 - self.radius * self.radius / (self.center - o).magnitude2()).sqrt();
+        // From inside, like from a medium within the sphere, every direction reaches it and
+        // they are sampled uniformly
+        let distance_squared = (self.center - o).magnitude2();
+        if distance_squared <= self.radius * self.radius {
+            return 1.0 / (4.0 * std::f32::consts::PI);
+        }
+        if self
+            .hit(
+                &Ray {
+                    origin: *o,
+                    direction: *v,
+                    time: 0.0,
+                },
+                0.001,
+                f32::MAX,
+            )
+            .is_some()
+        {
+            let cos_theta_max = (1.0 - self.radius * self.radius / distance_squared).sqrt();
             let solid_angle = 2.0 * std::f32::consts::PI * (1.0 - cos_theta_max);
             1.0 / solid_angle
         } else {
             0.0
         }
     }
-    fn random(&self, o: &Vec3) -> Vec3 {
+    fn random(&self, o: &Vec3, sampler: &mut Sampler) -> Vec3 {
         let direction = self.center - o;
         let distance_squared = direction.magnitude2();
+        if distance_squared <= self.radius * self.radius {
+            return random_on_unit_sphere(sampler);
+        }
         let uvw = ONB::build_from_w(&direction);
-        uvw.local_vec(&random_to_sphere(self.radius, distance_squared))
+        uvw.local_vec(&random_to_sphere(self.radius, distance_squared, sampler))
     }
 }
 
-fn random_to_sphere(radius: f32, distance_squared: f32) -> Vec3 {
-    let r1 = random_float();
-    let r2 = random_float();
+fn random_to_sphere(radius: f32, distance_squared: f32, sampler: &mut Sampler) -> Vec3 {
+    let (r1, r2) = sampler.get_2d();
     let z = 1.0 + r2 * ((1.0 - radius * radius / distance_squared).sqrt() - 1.0);
     let phi = 2.0 * std::f32::consts::PI * r1;
     let x = phi.cos() * (1.0 - z * z).sqrt();
@@ -119,7 +130,7 @@ impl MovingSphere {
 }
 
 impl Hitable for MovingSphere {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         let oc = ray.origin - self.center(ray.time);
         let a = dot(ray.direction, ray.direction);
         let b = dot(oc, ray.direction);
@@ -15,30 +15,24 @@ pub struct RotateY {
 }
 
 impl Hitable for Translate {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         let moved_ray = Ray {
             origin: ray.origin - self.offset,
             ..*ray
         };
-        if let Some(rec) = self.hitable.hit(&moved_ray, t_min, t_max) {
-            Some(HitRecord {
+        self.hitable
+            .hit(&moved_ray, t_min, t_max)
+            .map(|rec| HitRecord {
                 p: rec.p + self.offset,
                 ..rec
             })
-        } else {
-            None
-        }
     }
 
     fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB> {
-        if let Some(aabb) = self.hitable.bounding_box(t0, t1) {
-            Some(AABB {
-                min: aabb.min + self.offset,
-                max: aabb.max + self.offset,
-            })
-        } else {
-            None
-        }
+        self.hitable.bounding_box(t0, t1).map(|aabb| AABB {
+            min: aabb.min + self.offset,
+            max: aabb.max + self.offset,
+        })
     }
 }
 
@@ -48,8 +42,8 @@ impl RotateY {
         let cos_theta = radians.cos();
         let sin_theta = radians.sin();
         if let Some(bbox) = hitable.bounding_box(0.0, 1.0) {
-            let mut min = vec3(std::f32::MAX, std::f32::MAX, std::f32::MAX);
-            let mut max = vec3(-std::f32::MAX, -std::f32::MAX, -std::f32::MAX);
+            let mut min = vec3(f32::MAX, f32::MAX, f32::MAX);
+            let mut max = vec3(-f32::MAX, -f32::MAX, -f32::MAX);
 
             for i in 0..2 {
                 for j in 0..2 {
@@ -89,7 +83,7 @@ impl RotateY {
 }
 
 impl Hitable for RotateY {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         let origin = vec3(
             self.cos_theta * ray.origin.x - self.sin_theta * ray.origin.z,
             ray.origin.y,
@@ -105,8 +99,9 @@ impl Hitable for RotateY {
             direction,
             ..*ray
         };
-        if let Some(rec) = self.hitable.hit(&rotated_ray, t_min, t_max) {
-            Some(HitRecord {
+        self.hitable
+            .hit(&rotated_ray, t_min, t_max)
+            .map(|rec| HitRecord {
                 p: vec3(
                     self.cos_theta * rec.p.x + self.sin_theta * rec.p.z,
                     rec.p.y,
@@ -119,9 +114,6 @@ impl Hitable for RotateY {
                 ),
                 ..rec
             })
-        } else {
-            None
-        }
     }
 
     fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
@@ -0,0 +1,211 @@
+use std::sync::Arc;
+
+use crate::hitable::{BVHNode, HitRecord, Hitable, AABB};
+use crate::material::Material;
+use crate::math::*;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+
+// Vertex buffers shared by all triangles of a mesh. Normals and uvs are either empty or
+// have an entry per position
+pub struct MeshData {
+    pub positions: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<(f32, f32)>,
+    pub indices: Vec<[usize; 3]>,
+    // Material of each triangle, as an index into materials
+    pub material_indices: Vec<usize>,
+    pub materials: Vec<Box<dyn Material>>,
+}
+
+pub struct Triangle {
+    mesh: Arc<MeshData>,
+    index: usize,
+}
+
+impl Triangle {
+    pub fn new(mesh: Arc<MeshData>, index: usize) -> Triangle {
+        Triangle { mesh, index }
+    }
+
+    fn vertices(&self) -> (Vec3, Vec3, Vec3) {
+        let [i0, i1, i2] = self.mesh.indices[self.index];
+        let positions = &self.mesh.positions;
+        (positions[i0], positions[i1], positions[i2])
+    }
+
+    fn area(&self) -> f32 {
+        let (p0, p1, p2) = self.vertices();
+        0.5 * (p1 - p0).cross(p2 - p0).magnitude()
+    }
+
+    // Uniformly distributed point on the triangle
+    fn sample(&self, sampler: &mut Sampler) -> Vec3 {
+        let (p0, p1, p2) = self.vertices();
+        let (u, v) = sampler.get_2d();
+        let su = u.sqrt();
+        let b0 = 1.0 - su;
+        let b1 = v * su;
+        b0 * p0 + b1 * p1 + (1.0 - b0 - b1) * p2
+    }
+}
+
+impl Hitable for Triangle {
+    // Möller–Trumbore ray triangle intersection
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let (p0, p1, p2) = self.vertices();
+        let e1 = p1 - p0;
+        let e2 = p2 - p0;
+        let pvec = ray.direction.cross(e2);
+        let det = dot(e1, pvec);
+        if det.abs() < 1e-12 {
+            return None;
+        }
+        let inv_det = 1.0 / det;
+        let tvec = ray.origin - p0;
+        let b1 = dot(tvec, pvec) * inv_det;
+        if !(0.0..=1.0).contains(&b1) {
+            return None;
+        }
+        let qvec = tvec.cross(e1);
+        let b2 = dot(ray.direction, qvec) * inv_det;
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return None;
+        }
+        let t = dot(e2, qvec) * inv_det;
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        let b0 = 1.0 - b1 - b2;
+        let [i0, i1, i2] = self.mesh.indices[self.index];
+        let normal = if self.mesh.normals.is_empty() {
+            e1.cross(e2).normalize()
+        } else {
+            let normals = &self.mesh.normals;
+            (b0 * normals[i0] + b1 * normals[i1] + b2 * normals[i2]).normalize()
+        };
+        let (u, v) = if self.mesh.uvs.is_empty() {
+            (b1, b2)
+        } else {
+            let uvs = &self.mesh.uvs;
+            (
+                b0 * uvs[i0].0 + b1 * uvs[i1].0 + b2 * uvs[i2].0,
+                b0 * uvs[i0].1 + b1 * uvs[i1].1 + b2 * uvs[i2].1,
+            )
+        };
+        let material = &self.mesh.materials[self.mesh.material_indices[self.index]];
+        Some(HitRecord {
+            t,
+            p: ray.point_at_parameter(t),
+            normal,
+            u,
+            v,
+            material: Some(&**material),
+        })
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
+        let (p0, p1, p2) = self.vertices();
+        // Pad axis aligned triangles so the box doesn't have zero width
+        Some(AABB {
+            min: vec3(
+                p0.x.min(p1.x).min(p2.x) - 0.0001,
+                p0.y.min(p1.y).min(p2.y) - 0.0001,
+                p0.z.min(p1.z).min(p2.z) - 0.0001,
+            ),
+            max: vec3(
+                p0.x.max(p1.x).max(p2.x) + 0.0001,
+                p0.y.max(p1.y).max(p2.y) + 0.0001,
+                p0.z.max(p1.z).max(p2.z) + 0.0001,
+            ),
+        })
+    }
+
+    fn pdf_value(&self, o: &Vec3, v: &Vec3) -> f32 {
+        area_pdf(self, self.area(), o, v)
+    }
+
+    fn random(&self, o: &Vec3, sampler: &mut Sampler) -> Vec3 {
+        self.sample(sampler) - o
+    }
+}
+
+// Solid angle density of uniformly sampling the area of hitable, seen from o along v
+fn area_pdf(hitable: &dyn Hitable, area: f32, o: &Vec3, v: &Vec3) -> f32 {
+    let ray = Ray {
+        origin: *o,
+        direction: *v,
+        time: 0.0,
+    };
+    if let Some(rec) = hitable.hit(&ray, 0.001, f32::MAX) {
+        let distance_squared = rec.t * rec.t * v.magnitude2();
+        let cosine = (dot(*v, rec.normal) / v.magnitude()).abs();
+        distance_squared / (cosine * area)
+    } else {
+        0.0
+    }
+}
+
+// Triangles of a mesh in their own hierarchy. Light sampling picks triangles proportionally
+// to their area, so the mesh is sampled uniformly by area as a whole
+pub struct TriangleMesh {
+    bvh: BVHNode,
+    triangles: Vec<Triangle>,
+    // Running sum of the triangle areas
+    cumulative_area: Vec<f32>,
+}
+
+impl TriangleMesh {
+    pub fn new(mesh: MeshData) -> TriangleMesh {
+        let mesh = Arc::new(mesh);
+        let triangles = (0..mesh.indices.len())
+            .map(|index| Triangle::new(mesh.clone(), index))
+            .collect::<Vec<Triangle>>();
+        let cumulative_area = triangles
+            .iter()
+            .scan(0.0, |sum, triangle| {
+                *sum += triangle.area();
+                Some(*sum)
+            })
+            .collect();
+        let hitables = (0..mesh.indices.len())
+            .map(|index| Box::new(Triangle::new(mesh.clone(), index)) as Box<dyn Hitable>)
+            .collect();
+        TriangleMesh {
+            bvh: BVHNode::build(hitables, 0.0, 1.0),
+            triangles,
+            cumulative_area,
+        }
+    }
+
+    fn total_area(&self) -> f32 {
+        self.cumulative_area.last().copied().unwrap_or(0.0)
+    }
+}
+
+impl Hitable for TriangleMesh {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        self.bvh.hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB> {
+        self.bvh.bounding_box(t0, t1)
+    }
+
+    fn pdf_value(&self, o: &Vec3, v: &Vec3) -> f32 {
+        area_pdf(self, self.total_area(), o, v)
+    }
+
+    fn random(&self, o: &Vec3, sampler: &mut Sampler) -> Vec3 {
+        if self.triangles.is_empty() {
+            return vec3(1.0, 0.0, 0.0);
+        }
+        let target = sampler.get_1d() * self.total_area();
+        let index = self
+            .cumulative_area
+            .partition_point(|area| *area <= target)
+            .min(self.triangles.len() - 1);
+        self.triangles[index].sample(sampler) - o
+    }
+}
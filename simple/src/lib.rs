@@ -1,4 +1,10 @@
+#![allow(clippy::upper_case_acronyms, clippy::too_many_arguments)]
+
 mod camera;
+mod density;
+mod distribution;
+mod environment;
+mod framebuffer;
 mod hitable;
 mod material;
 mod math;
@@ -6,7 +12,11 @@ mod onb;
 mod pdf;
 mod random;
 mod ray;
+mod renderer;
+mod sampler;
+mod scene;
 mod texture;
+mod tonemap;
 
 #[macro_use]
 extern crate lazy_static;
@@ -14,590 +24,253 @@ extern crate lazy_static;
 pub extern crate image;
 
 pub use camera::*;
+pub use environment::*;
+pub use framebuffer::*;
 pub use hitable::*;
 use material::*;
 pub use math::*;
-use pdf::*;
 use ray::*;
-use texture::*;
-
-use rand::distributions::Distribution;
+pub use renderer::*;
+pub use sampler::*;
+pub use scene::*;
+pub use tonemap::*;
 
-#[cfg(feature = "parallel")]
-use rayon::prelude::*;
+// Weight of a sample from the strategy with density f_pdf when one with density g_pdf could
+// also have produced it
+fn power_heuristic(f_pdf: f32, g_pdf: f32) -> f32 {
+    if f_pdf.is_infinite() {
+        return 1.0;
+    }
+    (f_pdf * f_pdf) / (f_pdf * f_pdf + g_pdf * g_pdf)
+}
 
-fn color(ray: &Ray, world: &dyn Hitable, light_shape: &dyn Hitable, depth: i32) -> Vec3 {
-    if let Some(rec) = world.hit(ray, 0.001, std::f32::MAX) {
-        let emitted = rec
-            .material
-            .unwrap()
-            .emitted(ray, &rec, rec.u, rec.v, &rec.p);
-        if depth < 50 {
-            match rec.material.unwrap().scatter(ray, &rec) {
-                Some(ScatterResult {
-                    attenuation,
-                    specular_ray,
-                    pdf,
-                }) => {
-                    if let Some(reflected_ray) = specular_ray {
-                        attenuation.mul_element_wise(color(
-                            &reflected_ray,
-                            world,
-                            light_shape,
-                            depth + 1,
-                        ))
-                    } else {
-                        let p_light = HitablePDF {
-                            hitable: light_shape,
-                            o: rec.p,
-                        };
-                        let p = Mixture {
-                            p: [&p_light, &*pdf.unwrap()],
-                        };
-                        let scattered = Ray {
-                            origin: rec.p,
-                            direction: p.generate(),
-                            ..*ray
-                        };
-                        let pdf_val = p.value(&scattered.direction);
-                        emitted
-                            + (attenuation
-                                * rec.material.unwrap().scattering_pdf(ray, &rec, &scattered))
-                            .mul_element_wise(color(&scattered, world, light_shape, depth + 1))
-                                / pdf_val
-                    }
-                }
-                _ => emitted,
-            }
-        } else {
-            emitted
+// Radiance found along the ray, which is the emission of whatever the world hits first or
+// the environment when it hits nothing. Null collisions in media let a share of it through
+fn emission_along(ray: &Ray, world: &dyn Hitable, environment: &Environment) -> Vec3 {
+    let mut transmittance = vec3(1.0, 1.0, 1.0);
+    let mut ray = Ray { ..*ray };
+    loop {
+        let rec = match world.hit(&ray, 0.001, f32::MAX) {
+            Some(rec) => rec,
+            None => return transmittance.mul_element_wise(environment.radiance(&ray.direction)),
+        };
+        let material = rec.material.unwrap();
+        let pass_through = material.pass_through(&rec);
+        if pass_through == Vec3::zero() {
+            let emitted = material.emitted(&ray, &rec, rec.u, rec.v, &rec.p);
+            return transmittance.mul_element_wise(emitted);
         }
-    } else {
-        Vec3::zero()
+        transmittance = transmittance.mul_element_wise(pass_through);
+        ray = Ray {
+            origin: rec.p,
+            ..ray
+        };
     }
 }
 
-//#[allow(dead_code)]
-//fn random_scene() -> Vec<Box<dyn Hitable>> {
-// let n = 500;
-// let mut list: Vec<Box<dyn Hitable>> = Vec::with_capacity(n + 1);
-// list.push(Box::new(Sphere {
-//     center: vec3(0.0, -1000.0, 0.0),
-//     radius: 1000.0,
-//     material: Box::new(Lambertian {
-//         albedo: Box::new(CheckerTexture {
-//             even: Box::new(ConstantTexture(vec3(0.2, 0.3, 0.1))),
-//             odd: Box::new(ConstantTexture(vec3(0.9, 0.9, 0.9))),
-//         }),
-//     }),
-// }));
-// for a in -11..11 {
-//     for b in -11..11 {
-//         let choose_mat = random_float();
-//         let center = vec3(
-//             a as f32 + 0.9 * random_float(),
-//             0.2,
-//             b as f32 + 0.9 * random_float(),
-//         );
-//         if (center - vec3(4.0, 0.2, 0.0)).magnitude() > 0.9 {
-//             match choose_mat {
-//                 x if x < 0.8 => list.push(Box::new(MovingSphere {
-//                     center0: center,
-//                     center1: center + vec3(0.0, 0.5 * random_float(), 0.0),
-//                     time0: 0.0,
-//                     time1: 1.0,
-//                     radius: 0.2,
-//                     material: Box::new(Lambertian {
-//                         albedo: Box::new(ConstantTexture(vec3(
-//                             random_float() * random_float(),
-//                             random_float() * random_float(),
-//                             random_float() * random_float(),
-//                         ))),
-//                     }),
-//                 })),
-//                 x if x < 0.95 => list.push(Box::new(Sphere {
-//                     center,
-//                     radius: 0.2,
-//                     material: Box::new(Metal {
-//                         albedo: vec3(
-//                             0.5 * (1.0 + random_float()),
-//                             0.5 * (1.0 + random_float()),
-//                             0.5 * (1.0 + random_float()),
-//                         ),
-//                         fuzz: 0.5 * random_float(),
-//                     }),
-//                 })),
-//                 _ => list.push(Box::new(Sphere {
-//                     center,
-//                     radius: 0.2,
-//                     material: Box::new(Dielectric { ref_index: 1.5 }),
-//                 })),
-//             }
-//         }
-//     }
-// }
+// Bounces before paths start being terminated by Russian roulette
+const RUSSIAN_ROULETTE_DEPTH: i32 = 3;
 
-// list.push(Box::new(Sphere {
-//     center: vec3(0.0, 1.0, 0.0),
-//     radius: 1.0,
-//     material: Box::new(Dielectric { ref_index: 1.5 }),
-// }));
-// list.push(Box::new(Sphere {
-//     center: vec3(-4.0, 1.0, 0.0),
-//     radius: 1.0,
-//     material: Box::new(Lambertian {
-//         albedo: Box::new(ConstantTexture(vec3(0.4, 0.2, 0.1))),
-//     }),
-// }));
-// list.push(Box::new(Sphere {
-//     center: vec3(4.0, 1.0, 0.0),
-//     radius: 1.0,
-//     material: Box::new(Metal {
-//         albedo: vec3(0.7, 0.6, 0.5),
-//         fuzz: 0.0,
-//     }),
-// }));
-// list
-//}
+// Path traced radiance arriving along ray. At every diffuse vertex one of the light shapes
+// is sampled with a shadow ray, and the direction sampled from the material continues the
+// path. Emission reached by either is weighted with the power heuristic
+fn color(
+    ray: &Ray,
+    world: &dyn Hitable,
+    light_shape: &dyn Hitable,
+    environment: &Environment,
+    max_depth: i32,
+    sampler: &mut Sampler,
+) -> Vec3 {
+    let mut radiance = Vec3::zero();
+    let mut throughput = vec3(1.0, 1.0, 1.0);
+    let mut ray = Ray { ..*ray };
+    // Density of the material sample that produced ray, None when nothing else could
+    // have, like for camera rays and specular bounces
+    let mut scattering_pdf: Option<f32> = None;
+    // Where that sample was taken, null collisions move the ray but not this
+    let mut scattering_origin = ray.origin;
+    let mut depth = 0;
+    loop {
+        let rec = match world.hit(&ray, 0.001, f32::MAX) {
+            Some(rec) => rec,
+            None => {
+                let weight = match scattering_pdf {
+                    Some(pdf) => power_heuristic(
+                        pdf,
+                        light_shape.pdf_value(&scattering_origin, &ray.direction),
+                    ),
+                    None => 1.0,
+                };
+                radiance +=
+                    weight * throughput.mul_element_wise(environment.radiance(&ray.direction));
+                break;
+            }
+        };
+        let material = rec.material.unwrap();
 
-#[allow(dead_code)]
-fn two_perlin_spheres() -> Vec<Box<dyn Hitable>> {
-    let img = image::open("untitled.png").unwrap();
-    let image_texture = Box::new(ImageTexture::new(img));
-    let noise = Box::new(NoiseTexture { scale: 4.0 });
-    let list: Vec<Box<dyn Hitable>> = vec![
-        Box::new(Sphere {
-            center: vec3(0.0, -1000.0, 0.0),
-            radius: 1000.0,
-            material: Box::new(Lambertian { albedo: noise }),
-        }),
-        Box::new(Sphere {
-            center: vec3(0.0, 2.0, 0.0),
-            radius: 2.0,
-            material: Box::new(Lambertian {
-                albedo: image_texture,
-            }),
-        }),
-    ];
-    list
-}
+        let emitted = material.emitted(&ray, &rec, rec.u, rec.v, &rec.p);
+        if emitted != Vec3::zero() {
+            let weight = match scattering_pdf {
+                Some(pdf) => power_heuristic(
+                    pdf,
+                    light_shape.pdf_value(&scattering_origin, &ray.direction),
+                ),
+                None => 1.0,
+            };
+            radiance += weight * throughput.mul_element_wise(emitted);
+        }
 
-#[allow(dead_code)]
-fn simple_light() -> Vec<Box<dyn Hitable>> {
-    let noise = Box::new(NoiseTexture { scale: 4.0 });
-    let list: Vec<Box<dyn Hitable>> = vec![
-        Box::new(Sphere {
-            center: vec3(0.0, -1000.0, 0.0),
-            radius: 1000.0,
-            material: Box::new(Lambertian {
-                albedo: noise.clone(),
-            }),
-        }),
-        Box::new(Sphere {
-            center: vec3(0.0, 2.0, 0.0),
-            radius: 2.0,
-            material: Box::new(Lambertian { albedo: noise }),
-        }),
-        Box::new(Sphere {
-            center: vec3(0.0, 7.0, 0.0),
-            radius: 2.0,
-            material: Box::new(DiffuseLight {
-                emit: Box::new(ConstantTexture(vec3(4.0, 4.0, 4.0))),
-            }),
-        }),
-        Box::new(XYRect {
-            x0: 3.0,
-            x1: 5.0,
-            y0: 1.0,
-            y1: 3.0,
-            k: -2.0,
-            material: Box::new(DiffuseLight {
-                emit: Box::new(ConstantTexture(vec3(4.0, 4.0, 4.0))),
-            }),
-        }),
-    ];
-    list
-}
+        // Null collisions are taken with the average share of the light they let through,
+        // they don't count as a bounce
+        let pass_through = material.pass_through(&rec);
+        if pass_through != Vec3::zero() {
+            let null_probability = (pass_through.x + pass_through.y + pass_through.z) / 3.0;
+            if sampler.get_1d() < null_probability {
+                throughput = throughput.mul_element_wise(pass_through) / null_probability;
+                ray = Ray {
+                    origin: rec.p,
+                    ..ray
+                };
+                continue;
+            }
+            throughput /= 1.0 - null_probability;
+        }
 
-#[allow(dead_code)]
-pub fn cornel_box() -> (Vec<Box<dyn Hitable>>, Vec<Box<dyn Hitable>>) {
-    let red = Box::new(Lambertian {
-        albedo: Box::new(ConstantTexture(vec3(0.65, 0.05, 0.05))),
-    });
-    let white = Box::new(Lambertian {
-        albedo: Box::new(ConstantTexture(vec3(0.73, 0.73, 0.73))),
-    });
-    let green = Box::new(Lambertian {
-        albedo: Box::new(ConstantTexture(vec3(0.12, 0.45, 0.15))),
-    });
-    let light = Box::new(DiffuseLight {
-        emit: Box::new(ConstantTexture(vec3(15.0, 15.0, 15.0))),
-    });
-    let light_shape_1 = Box::new(XZRect {
-        x0: 213.0,
-        x1: 343.0,
-        z0: 227.0,
-        z1: 332.0,
-        k: 554.0,
-        material: light.clone(),
-    });
-    let light_shape_2 = Box::new(Sphere {
-        center: vec3(190.0, 90.0, 190.0),
-        radius: 90.0,
-        material: light.clone(),
-    });
-    let light_shape: Vec<Box<dyn Hitable>> = vec![light_shape_1, light_shape_2];
+        if depth >= max_depth {
+            break;
+        }
+        let ScatterResult {
+            attenuation,
+            specular_ray,
+            pdf,
+        } = match material.scatter(&ray, &rec, sampler) {
+            Some(result) => result,
+            None => break,
+        };
 
-    let aluminum = Box::new(Metal {
-        albedo: vec3(0.8, 0.85, 0.88),
-        fuzz: 0.0,
-    });
-    let list: Vec<Box<dyn Hitable>> = vec![
-        Box::new(FlipNormals(Box::new(YZRect {
-            y0: 0.0,
-            y1: 555.0,
-            z0: 0.0,
-            z1: 555.0,
-            k: 555.0,
-            material: green,
-        }))),
-        Box::new(YZRect {
-            y0: 0.0,
-            y1: 555.0,
-            z0: 0.0,
-            z1: 555.0,
-            k: 0.0,
-            material: red,
-        }),
-        Box::new(FlipNormals(Box::new(XZRect {
-            x0: 213.0,
-            x1: 343.0,
-            z0: 227.0,
-            z1: 332.0,
-            k: 554.0,
-            material: light,
-        }))),
-        Box::new(FlipNormals(Box::new(XZRect {
-            x0: 0.0,
-            x1: 555.0,
-            z0: 0.0,
-            z1: 555.0,
-            k: 555.0,
-            material: white.clone(),
-        }))),
-        Box::new(XZRect {
-            x0: 0.0,
-            x1: 555.0,
-            z0: 0.0,
-            z1: 555.0,
-            k: 0.0,
-            material: white.clone(),
-        }),
-        Box::new(FlipNormals(Box::new(XYRect {
-            x0: 0.0,
-            x1: 555.0,
-            y0: 0.0,
-            y1: 555.0,
-            k: 555.0,
-            material: white.clone(),
-        }))),
-        // Box::new(Translate {
-        //     offset: vec3(130.0, 0.0, 65.0),
-        //     hitable: Box::new(RotateY::new(
-        //         Box::new(BoxHitable::new(
-        //             &vec3(0.0, 0.0, 0.0),
-        //             &vec3(165.0, 165.0, 165.0),
-        //             white.clone(),
-        //         )),
-        //         -18.0,
-        //     )),
-        // }),
-        Box::new(Sphere {
-            center: vec3(190.0, 90.0, 190.0),
-            radius: 90.0,
-            material: Box::new(Dielectric { ref_index: 1.5 }),
-        }),
-        Box::new(Translate {
-            offset: vec3(265.0, 0.0, 295.0),
-            hitable: Box::new(RotateY::new(
-                Box::new(BoxHitable::new(
-                    &vec3(0.0, 0.0, 0.0),
-                    &vec3(165.0, 330.0, 165.0),
-                    white,
-                )),
-                15.0,
-            )),
-        }),
-    ];
-    (list, light_shape)
-}
+        if let Some(specular_ray) = specular_ray {
+            throughput = throughput.mul_element_wise(attenuation);
+            ray = specular_ray;
+            scattering_pdf = None;
+        } else {
+            let pdf = pdf.unwrap();
 
-#[allow(dead_code)]
-fn cornel_smoke() -> Vec<Box<dyn Hitable>> {
-    let red = Box::new(Lambertian {
-        albedo: Box::new(ConstantTexture(vec3(0.65, 0.05, 0.05))),
-    });
-    let white = Box::new(Lambertian {
-        albedo: Box::new(ConstantTexture(vec3(0.73, 0.73, 0.73))),
-    });
-    let green = Box::new(Lambertian {
-        albedo: Box::new(ConstantTexture(vec3(0.12, 0.45, 0.15))),
-    });
-    let light = Box::new(DiffuseLight {
-        emit: Box::new(ConstantTexture(vec3(7.0, 7.0, 7.0))),
-    });
+            // Next event estimation towards the light shapes
+            let light_direction = light_shape.random(&rec.p, sampler);
+            let light_pdf = light_shape.pdf_value(&rec.p, &light_direction);
+            if light_pdf > 0.0 {
+                let shadow_ray = Ray {
+                    origin: rec.p,
+                    direction: light_direction,
+                    ..ray
+                };
+                let f = attenuation * material.scattering_pdf(&ray, &rec, &shadow_ray);
+                if f != Vec3::zero() {
+                    let weight = power_heuristic(light_pdf, pdf.value(&light_direction));
+                    let light = emission_along(&shadow_ray, world, environment);
+                    radiance +=
+                        weight / light_pdf * throughput.mul_element_wise(f).mul_element_wise(light);
+                }
+            }
 
-    let box1 = Box::new(Translate {
-        offset: vec3(130.0, 0.0, 65.0),
-        hitable: Box::new(RotateY::new(
-            Box::new(BoxHitable::new(
-                &vec3(0.0, 0.0, 0.0),
-                &vec3(165.0, 165.0, 165.0),
-                white.clone(),
-            )),
-            -18.0,
-        )),
-    });
-    let box2 = Box::new(Translate {
-        offset: vec3(265.0, 0.0, 295.0),
-        hitable: Box::new(RotateY::new(
-            Box::new(BoxHitable::new(
-                &vec3(0.0, 0.0, 0.0),
-                &vec3(165.0, 330.0, 165.0),
-                white.clone(),
-            )),
-            15.0,
-        )),
-    });
+            let scattered = Ray {
+                origin: rec.p,
+                direction: pdf.generate(sampler),
+                ..ray
+            };
+            let pdf_val = pdf.value(&scattered.direction);
+            // Directions the material can't produce carry no estimate
+            if pdf_val <= 0.0 {
+                break;
+            }
+            let f = attenuation * material.scattering_pdf(&ray, &rec, &scattered);
+            throughput = throughput.mul_element_wise(f) / pdf_val;
+            ray = scattered;
+            scattering_pdf = Some(pdf_val);
+            scattering_origin = rec.p;
+        }
 
-    let list: Vec<Box<dyn Hitable>> = vec![
-        Box::new(FlipNormals(Box::new(YZRect {
-            y0: 0.0,
-            y1: 555.0,
-            z0: 0.0,
-            z1: 555.0,
-            k: 555.0,
-            material: green,
-        }))),
-        Box::new(YZRect {
-            y0: 0.0,
-            y1: 555.0,
-            z0: 0.0,
-            z1: 555.0,
-            k: 0.0,
-            material: red,
-        }),
-        Box::new(XZRect {
-            x0: 113.0,
-            x1: 443.0,
-            z0: 127.0,
-            z1: 432.0,
-            k: 554.0,
-            material: light,
-        }),
-        Box::new(FlipNormals(Box::new(XZRect {
-            x0: 0.0,
-            x1: 555.0,
-            z0: 0.0,
-            z1: 555.0,
-            k: 555.0,
-            material: white.clone(),
-        }))),
-        Box::new(XZRect {
-            x0: 0.0,
-            x1: 555.0,
-            z0: 0.0,
-            z1: 555.0,
-            k: 0.0,
-            material: white.clone(),
-        }),
-        Box::new(FlipNormals(Box::new(XYRect {
-            x0: 0.0,
-            x1: 555.0,
-            y0: 0.0,
-            y1: 555.0,
-            k: 555.0,
-            material: white.clone(),
-        }))),
-        Box::new(ConstantMedium::new(
-            box1,
-            0.01,
-            Box::new(ConstantTexture(vec3(1.0, 1.0, 1.0))),
-        )),
-        Box::new(ConstantMedium::new(
-            box2,
-            0.01,
-            Box::new(ConstantTexture(vec3(0.0, 0.0, 0.0))),
-        )),
-    ];
-    list
+        // Paths that carry little light are stopped with probability q, the survivors
+        // make up for them
+        depth += 1;
+        if depth > RUSSIAN_ROULETTE_DEPTH {
+            let max_throughput = throughput.x.max(throughput.y).max(throughput.z);
+            let q = (1.0 - max_throughput).max(0.05);
+            if sampler.get_1d() < q {
+                break;
+            }
+            throughput /= 1.0 - q;
+        }
+        if throughput == Vec3::zero() {
+            break;
+        }
+    }
+    radiance
 }
 
-// pub fn final_scene() -> Vec<Box<dyn Hitable>> {
-//     let white = Box::new(Lambertian {
-//         albedo: Box::new(ConstantTexture(vec3(0.73, 0.73, 0.73))),
-//     });
-//     let ground = Box::new(Lambertian {
-//         albedo: Box::new(ConstantTexture(vec3(0.48, 0.83, 0.53))),
-//     });
-
-//     let mut boxlist: Vec<Box<dyn Hitable>> = vec![];
-//     for i in 0..20 {
-//         for j in 0..20 {
-//             let w = 100.0;
-//             let x0 = -1000.0 + i as f32 * w;
-//             let z0 = -1000.0 + j as f32 * w;
-//             let y0 = 0.0;
-//             let x1 = x0 + w;
-//             let y1 = 100.0 * (random_float() + 0.01);
-//             let z1 = z0 + w;
-//             boxlist.push(Box::new(BoxHitable::new(
-//                 &vec3(x0, y0, z0),
-//                 &vec3(x1, y1, z1),
-//                 ground.clone(),
-//             )));
-//         }
-//     }
-//     let center = vec3(400.0, 400.0, 200.0);
-//     let boundary = Sphere {
-//         center: vec3(360.0, 150.0, 145.0),
-//         radius: 70.0,
-//         material: Box::new(Dielectric { ref_index: 1.5 }),
-//     };
-//     let boundary2 = Sphere {
-//         center: vec3(0.0, 0.0, 0.0),
-//         radius: 5000.0,
-//         material: Box::new(Dielectric { ref_index: 1.5 }),
-//     };
-//     let img = image::open("earthmap.jpg").unwrap();
-//     let image_texture = Box::new(ImageTexture::new(img));
-//     let emat = Box::new(Lambertian {
-//         albedo: image_texture,
-//     });
-//     let mut boxlist2: Vec<Box<dyn Hitable>> = vec![];
-//     for _j in 0..1000 {
-//         boxlist2.push(Box::new(Sphere {
-//             center: vec3(
-//                 165.0 * random_float(),
-//                 165.0 * random_float(),
-//                 165.0 * random_float(),
-//             ),
-//             radius: 10.0,
-//             material: white.clone(),
-//         }))
-//     }
-
-//     let list: Vec<Box<dyn Hitable>> = vec![
-//         Box::new(BVHNode::build(boxlist, 0.0, 1.0)),
-//         Box::new(XZRect {
-//             x0: 123.0,
-//             x1: 423.0,
-//             z0: 147.0,
-//             z1: 412.0,
-//             k: 554.0,
-//             material: Box::new(DiffuseLight {
-//                 emit: Box::new(ConstantTexture(vec3(7.0, 7.0, 7.0))),
-//             }),
-//         }),
-//         Box::new(MovingSphere {
-//             center0: center,
-//             center1: center + vec3(30.0, 0.0, 0.0),
-//             radius: 50.0,
-//             time0: 0.0,
-//             time1: 1.0,
-//             material: Box::new(Lambertian {
-//                 albedo: Box::new(ConstantTexture(vec3(0.7, 0.3, 0.1))),
-//             }),
-//         }),
-//         Box::new(Sphere {
-//             center: vec3(260.0, 150.0, 45.0),
-//             radius: 50.0,
-//             material: Box::new(Dielectric { ref_index: 1.5 }),
-//         }),
-//         Box::new(Sphere {
-//             center: vec3(0.0, 150.0, 145.0),
-//             radius: 50.0,
-//             material: Box::new(Metal {
-//                 albedo: vec3(0.8, 0.8, 0.9),
-//                 fuzz: 10.0,
-//             }),
-//         }),
-//         Box::new(boundary.clone()),
-//         Box::new(ConstantMedium::new(
-//             Box::new(boundary),
-//             0.2,
-//             Box::new(ConstantTexture(vec3(0.2, 0.4, 0.9))),
-//         )),
-//         Box::new(ConstantMedium::new(
-//             Box::new(boundary2),
-//             0.0001,
-//             Box::new(ConstantTexture(vec3(1.0, 1.0, 1.0))),
-//         )),
-//         Box::new(Sphere {
-//             center: vec3(400.0, 200.0, 400.0),
-//             radius: 100.0,
-//             material: emat,
-//         }),
-//         Box::new(Sphere {
-//             center: vec3(220.0, 280.0, 300.0),
-//             radius: 80.0,
-//             material: Box::new(Lambertian {
-//                 albedo: Box::new(NoiseTexture { scale: 0.1 }),
-//             }),
-//         }),
-//         Box::new(Translate {
-//             offset: vec3(-100.0, 270.0, 395.0),
-//             hitable: Box::new(RotateY::new(
-//                 Box::new(BVHNode::build(boxlist2, 0.0, 1.0)),
-//                 15.0,
-//             )),
-//         }),
-//     ];
-//     list
-// }
-
-pub fn evaluate_pixel(
+// Sum of the radiance of samples camera rays through pixel (x, y), starting at sample index
+// first_sample so samples can be accumulated over several passes. The result only depends
+// on the sampler settings and the sample indices
+pub fn sample_pixel(
     x: u32,
     y: u32,
     width: u32,
     height: u32,
+    settings: &SamplerSettings,
+    first_sample: i32,
     samples: i32,
+    max_depth: i32,
     world: &dyn Hitable,
     light_shape: &dyn Hitable,
+    environment: &Environment,
     camera: &Camera,
-) -> (u8, u8, u8) {
-    #[cfg(feature = "parallel")]
-    let samples_vector: Vec<i32> = (0..samples).collect();
-    #[cfg(feature = "parallel")]
-    let samples_itr = samples_vector.par_iter();
-
-    #[cfg(not(feature = "parallel"))]
-    let samples_itr = 0..samples;
-
-    let mut color: Vec3 = samples_itr
-        .map(|_| {
-            let mut rng = rand::thread_rng();
-            let uniform_distribution = rand::distributions::Uniform::new(0.0, 1.0);
-            let u = (x as f32 + uniform_distribution.sample(&mut rng)) / width as f32;
-            let v =
-                ((height - y - 1) as f32 + uniform_distribution.sample(&mut rng)) / height as f32;
-            let ray = camera.get_ray(u, v);
-            color(&ray, world, light_shape, 0)
+) -> Vec3 {
+    (first_sample..first_sample + samples)
+        .map(|index| {
+            let mut sampler = Sampler::new(settings, x, y, index as u32);
+            let (jitter_x, jitter_y) = sampler.get_2d();
+            let u = (x as f32 + jitter_x) / width as f32;
+            let v = ((height - y - 1) as f32 + jitter_y) / height as f32;
+            let ray = camera.get_ray(u, v, &mut sampler);
+            color(
+                &ray,
+                world,
+                light_shape,
+                environment,
+                max_depth,
+                &mut sampler,
+            )
         })
         .sum::<Vec3>()
-        / samples as f32;
+}
 
-    if color.x > 1.0 {
-        color.x = 1.0;
-    }
-    if color.y > 1.0 {
-        color.y = 1.0;
-    }
-    if color.z > 1.0 {
-        color.z = 1.0;
-    }
-    let ir = (255.99 * color.x.sqrt()) as u8;
-    let ig = (255.99 * color.y.sqrt()) as u8;
-    let ib = (255.99 * color.z.sqrt()) as u8;
-    (ir, ig, ib)
+// Average linear radiance of all settings.samples_per_pixel samples of the pixel, tone
+// mapping is left to the caller
+pub fn evaluate_pixel(
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    settings: &SamplerSettings,
+    max_depth: i32,
+    world: &dyn Hitable,
+    light_shape: &dyn Hitable,
+    environment: &Environment,
+    camera: &Camera,
+) -> Vec3 {
+    sample_pixel(
+        x,
+        y,
+        width,
+        height,
+        settings,
+        0,
+        settings.samples_per_pixel,
+        max_depth,
+        world,
+        light_shape,
+        environment,
+        camera,
+    ) / settings.samples_per_pixel as f32
 }
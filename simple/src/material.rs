@@ -1,12 +1,14 @@
 mod dielectric;
 mod diffuse_light;
-//mod isotropic;
+mod henyey_greenstein;
+mod isotropic;
 mod lambertian;
 mod metal;
 
 pub use dielectric::Dielectric;
 pub use diffuse_light::DiffuseLight;
-//pub use isotropic::Isotropic;
+pub use henyey_greenstein::HenyeyGreenstein;
+pub use isotropic::Isotropic;
 pub use lambertian::Lambertian;
 pub use metal::Metal;
 
@@ -14,6 +16,8 @@ use crate::hitable::HitRecord;
 use crate::math::*;
 use crate::pdf::PDF;
 use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::texture::Texture;
 
 pub struct ScatterResult {
     pub attenuation: Vec3,
@@ -22,17 +26,31 @@ pub struct ScatterResult {
 }
 
 pub trait Material: MaterialClone {
-    fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Option<ScatterResult>;
+    fn scatter(&self, ray: &Ray, rec: &HitRecord, sampler: &mut Sampler) -> Option<ScatterResult>;
     fn scattering_pdf(&self, _ray: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f32 {
         0.0
     }
     fn emitted(&self, _ray: &Ray, _rec: &HitRecord, _u: f32, _v: f32, _p: &Vec3) -> Vec3 {
         Vec3::zero()
     }
+    // Share of the light that continues unchanged through the hit, per channel. Media use it
+    // for null collisions, surfaces block everything
+    fn pass_through(&self, _rec: &HitRecord) -> Vec3 {
+        Vec3::zero()
+    }
+}
+
+// Henyey-Greenstein phase function, or the cheaper isotropic one when g is 0
+pub fn phase_function(albedo: Box<dyn Texture>, g: f32) -> Box<dyn Material> {
+    if g == 0.0 {
+        Box::new(Isotropic(albedo))
+    } else {
+        Box::new(HenyeyGreenstein { albedo, g })
+    }
 }
 
 // Box cloning implementation
-pub trait MaterialClone: Sync {
+pub trait MaterialClone: Send + Sync {
     fn box_clone(&self) -> Box<dyn Material>;
 }
 
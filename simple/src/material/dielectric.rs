@@ -2,8 +2,8 @@ use crate::hitable::HitRecord;
 use crate::material::metal::reflect;
 use crate::material::{Material, ScatterResult};
 use crate::math::*;
-use crate::random::random_float;
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 
 #[derive(Clone)]
 pub struct Dielectric {
@@ -28,7 +28,7 @@ fn schlick(cosine: f32, ref_index: f32) -> f32 {
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Option<ScatterResult> {
+    fn scatter(&self, ray: &Ray, rec: &HitRecord, sampler: &mut Sampler) -> Option<ScatterResult> {
         let reflected = reflect(&ray.direction, &rec.normal);
         let (outward_normal, ni_over_nt, cosine) = if dot(ray.direction, rec.normal) > 0.0 {
             (
@@ -51,7 +51,7 @@ impl Material for Dielectric {
             1.0
         };
 
-        if random_float() < reflect_probability {
+        if sampler.get_1d() < reflect_probability {
             Some(ScatterResult {
                 attenuation: vec3(1.0, 1.0, 1.0),
                 specular_ray: Some(Ray {
@@ -2,6 +2,7 @@ use crate::hitable::HitRecord;
 use crate::material::{Material, ScatterResult};
 use crate::math::*;
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 use crate::texture::Texture;
 
 #[derive(Clone)]
@@ -10,7 +11,12 @@ pub struct DiffuseLight {
 }
 
 impl Material for DiffuseLight {
-    fn scatter(&self, _ray: &Ray, _rec: &HitRecord) -> Option<ScatterResult> {
+    fn scatter(
+        &self,
+        _ray: &Ray,
+        _rec: &HitRecord,
+        _sampler: &mut Sampler,
+    ) -> Option<ScatterResult> {
         None
     }
     fn emitted(&self, ray: &Ray, rec: &HitRecord, u: f32, v: f32, p: &Vec3) -> Vec3 {
@@ -0,0 +1,30 @@
+use crate::hitable::HitRecord;
+use crate::material::{Material, ScatterResult};
+use crate::math::*;
+use crate::pdf::{phase_hg, HenyeyGreensteinPDF};
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::texture::Texture;
+
+// Phase function of a medium that prefers scattering forward for positive g and backward
+// for negative g, g is in (-1, 1)
+#[derive(Clone)]
+pub struct HenyeyGreenstein {
+    pub albedo: Box<dyn Texture>,
+    pub g: f32,
+}
+
+impl Material for HenyeyGreenstein {
+    fn scatter(&self, ray: &Ray, rec: &HitRecord, _sampler: &mut Sampler) -> Option<ScatterResult> {
+        Some(ScatterResult {
+            attenuation: self.albedo.value(rec.u, rec.v, &rec.p),
+            pdf: Some(Box::new(HenyeyGreensteinPDF::new(&ray.direction, self.g))),
+            specular_ray: None,
+        })
+    }
+
+    fn scattering_pdf(&self, ray: &Ray, _rec: &HitRecord, scattered: &Ray) -> f32 {
+        let cos_theta = dot(ray.direction.normalize(), scattered.direction.normalize());
+        phase_hg(cos_theta, self.g)
+    }
+}
@@ -1,21 +1,29 @@
-use crate::material::*;
-use crate::random::random_in_unit_sphere;
+use crate::hitable::HitRecord;
+use crate::material::{Material, ScatterResult};
+use crate::pdf::UniformSphere;
 use crate::ray::Ray;
-use crate::texture::*;
+use crate::sampler::Sampler;
+use crate::texture::Texture;
 
+// Phase function of a medium that scatters equally in all directions
 #[derive(Clone)]
 pub struct Isotropic(pub Box<dyn Texture>);
 
 impl Material for Isotropic {
-    fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Option<ScatterResult> {
+    fn scatter(
+        &self,
+        _ray: &Ray,
+        rec: &HitRecord,
+        _sampler: &mut Sampler,
+    ) -> Option<ScatterResult> {
         Some(ScatterResult {
-            albedo: self.0.value(rec.u, rec.v, &rec.p),
-            scattered_ray: Ray {
-                origin: rec.p,
-                direction: random_in_unit_sphere(),
-                ..*ray
-            },
-            pdf: 0.0,
+            attenuation: self.0.value(rec.u, rec.v, &rec.p),
+            pdf: Some(Box::new(UniformSphere)),
+            specular_ray: None,
         })
     }
+
+    fn scattering_pdf(&self, _ray: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f32 {
+        1.0 / (4.0 * std::f32::consts::PI)
+    }
 }
@@ -3,6 +3,7 @@ use crate::material::{Material, ScatterResult};
 use crate::math::*;
 use crate::pdf::*;
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 use crate::texture::Texture;
 
 #[derive(Clone)]
@@ -11,7 +12,12 @@ pub struct Lambertian {
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, _ray: &Ray, rec: &HitRecord) -> Option<ScatterResult> {
+    fn scatter(
+        &self,
+        _ray: &Ray,
+        rec: &HitRecord,
+        _sampler: &mut Sampler,
+    ) -> Option<ScatterResult> {
         Some(ScatterResult {
             attenuation: self.albedo.value(rec.u, rec.v, &rec.p),
             pdf: Some(Box::new(Cosine::new(&rec.normal))),
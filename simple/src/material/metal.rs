@@ -3,6 +3,7 @@ use crate::material::{Material, ScatterResult};
 use crate::math::*;
 use crate::random::random_in_unit_sphere;
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 
 #[derive(Clone)]
 pub struct Metal {
@@ -15,14 +16,14 @@ pub fn reflect(v: &Vec3, n: &Vec3) -> Vec3 {
 }
 
 impl Material for Metal {
-    fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Option<ScatterResult> {
+    fn scatter(&self, ray: &Ray, rec: &HitRecord, sampler: &mut Sampler) -> Option<ScatterResult> {
         let reflected = reflect(&ray.direction.normalize(), &rec.normal);
         Some(ScatterResult {
             attenuation: self.albedo,
             pdf: None,
             specular_ray: Some(Ray {
                 origin: rec.p,
-                direction: reflected + self.fuzz * random_in_unit_sphere(),
+                direction: reflected + self.fuzz * random_in_unit_sphere(sampler),
                 ..*ray
             }),
         })
@@ -1,14 +1,15 @@
 use crate::math::Vec3;
+use crate::sampler::Sampler;
 
 mod cosine;
-mod hitable_pdf;
-mod mixture;
+mod henyey_greenstein;
+mod uniform_sphere;
 
 pub use cosine::Cosine;
-pub use hitable_pdf::HitablePDF;
-pub use mixture::Mixture;
+pub use henyey_greenstein::{phase_hg, HenyeyGreensteinPDF};
+pub use uniform_sphere::UniformSphere;
 
 pub trait PDF {
     fn value(&self, direction: &Vec3) -> f32;
-    fn generate(&self) -> Vec3;
+    fn generate(&self, sampler: &mut Sampler) -> Vec3;
 }
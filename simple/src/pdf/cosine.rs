@@ -2,6 +2,7 @@ use crate::math::*;
 use crate::onb::ONB;
 use crate::pdf::PDF;
 use crate::random::random_cosine_direction;
+use crate::sampler::Sampler;
 
 pub struct Cosine {
     uvw: ONB,
@@ -25,7 +26,7 @@ impl PDF for Cosine {
         }
     }
 
-    fn generate(&self) -> Vec3 {
-        self.uvw.local_vec(&random_cosine_direction())
+    fn generate(&self, sampler: &mut Sampler) -> Vec3 {
+        self.uvw.local_vec(&random_cosine_direction(sampler))
     }
 }
@@ -0,0 +1,52 @@
+use crate::math::*;
+use crate::onb::ONB;
+use crate::pdf::PDF;
+use crate::sampler::Sampler;
+use std::f32::consts::PI;
+
+// Henyey-Greenstein phase function for the cosine of the angle between the incoming and the
+// scattered direction. Positive g scatters forward, negative g backward and 0 is isotropic
+pub fn phase_hg(cos_theta: f32, g: f32) -> f32 {
+    let denom = 1.0 + g * g - 2.0 * g * cos_theta;
+    (1.0 - g * g) / (4.0 * PI * denom * denom.max(0.0).sqrt())
+}
+
+// Directions distributed like the Henyey-Greenstein phase function around the incoming
+// direction
+pub struct HenyeyGreensteinPDF {
+    uvw: ONB,
+    g: f32,
+}
+
+impl HenyeyGreensteinPDF {
+    pub fn new(incoming: &Vec3, g: f32) -> Self {
+        HenyeyGreensteinPDF {
+            uvw: ONB::build_from_w(incoming),
+            g,
+        }
+    }
+}
+
+impl PDF for HenyeyGreensteinPDF {
+    fn value(&self, direction: &Vec3) -> f32 {
+        phase_hg(dot(direction.normalize(), self.uvw.w), self.g)
+    }
+
+    fn generate(&self, sampler: &mut Sampler) -> Vec3 {
+        let (u, v) = sampler.get_2d();
+        let g = self.g;
+        let cos_theta = if g.abs() < 1e-3 {
+            1.0 - 2.0 * u
+        } else {
+            let square = (1.0 - g * g) / (1.0 - g + 2.0 * g * u);
+            (1.0 + g * g - square * square) / (2.0 * g)
+        };
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * v;
+        self.uvw.local_vec(&vec3(
+            sin_theta * phi.cos(),
+            sin_theta * phi.sin(),
+            cos_theta,
+        ))
+    }
+}
@@ -0,0 +1,17 @@
+use crate::math::*;
+use crate::pdf::PDF;
+use crate::random::random_on_unit_sphere;
+use crate::sampler::Sampler;
+
+// Every direction is equally likely
+pub struct UniformSphere;
+
+impl PDF for UniformSphere {
+    fn value(&self, _direction: &Vec3) -> f32 {
+        1.0 / (4.0 * std::f32::consts::PI)
+    }
+
+    fn generate(&self, sampler: &mut Sampler) -> Vec3 {
+        random_on_unit_sphere(sampler)
+    }
+}
@@ -1,36 +1,84 @@
 use crate::math::*;
-use rand::Rng;
-
-pub fn random_in_unit_sphere() -> Vec3 {
-    let mut rng = rand::thread_rng();
-    loop {
-        let p =
-            2.0 * vec3(
-                rng.gen_range(0.0_f32, 1.0),
-                rng.gen_range(0.0_f32, 1.0),
-                rng.gen_range(0.0_f32, 1.0),
-            ) - vec3(1.0, 1.0, 1.0);
-        if dot(p, p) < 1.0 {
-            break p;
-        }
+use crate::sampler::Sampler;
+
+// PCG32 generator. Every camera sample gets its own generator seeded from the render seed,
+// the pixel and the sample index, so results don't depend on the order samples are taken in
+pub struct Rng {
+    state: u64,
+    increment: u64,
+}
+
+const MULTIPLIER: u64 = 6_364_136_223_846_793_005;
+
+// SplitMix64 finalizer, scrambles all input bits into all output bits
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        let mut rng = Rng {
+            state: 0,
+            increment: 1_442_695_040_888_963_407,
+        };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(mix(seed));
+        rng.next_u32();
+        rng
+    }
+
+    pub fn for_sample(seed: u64, x: u32, y: u32, sample: u32) -> Rng {
+        let pixel = u64::from(x) | (u64::from(y) << 32);
+        Rng::new(mix(mix(seed) ^ pixel) ^ u64::from(sample))
+    }
+
+    // Generator whose sequence is determined by the given values alone
+    pub fn from_hash(values: &[u32]) -> Rng {
+        Rng::new(
+            values
+                .iter()
+                .fold(0, |hash, value| mix(hash ^ u64::from(*value))),
+        )
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old_state = self.state;
+        self.state = old_state
+            .wrapping_mul(MULTIPLIER)
+            .wrapping_add(self.increment);
+        let xor_shifted = (((old_state >> 18) ^ old_state) >> 27) as u32;
+        let rotation = (old_state >> 59) as u32;
+        xor_shifted.rotate_right(rotation)
+    }
+
+    // Uniform in [0, 1)
+    pub fn float(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 * (1.0 / (1 << 24) as f32)
     }
 }
 
-pub fn random_cosine_direction() -> Vec3 {
-    let (r1, r2) = (random_float(), random_float());
-    let z = (1.0 - r2).sqrt();
-    let phi = 2.0 * std::f32::consts::PI * r1;
-    let x = phi.cos() * 2.0 * r2.sqrt();
-    let y = phi.sin() * 2.0 * r2.sqrt();
-    vec3(x, y, z)
+// Uniform over the directions, a point on the unit sphere
+pub fn random_on_unit_sphere(sampler: &mut Sampler) -> Vec3 {
+    let (u, v) = sampler.get_2d();
+    let z = 1.0 - 2.0 * u;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * std::f32::consts::PI * v;
+    vec3(r * phi.cos(), r * phi.sin(), z)
 }
 
-pub fn random_float() -> f32 {
-    let mut rng = rand::thread_rng();
-    rng.gen_range(0.0_f32, 1.0)
+// Uniform in the unit ball, a direction and a cube root distributed radius
+pub fn random_in_unit_sphere(sampler: &mut Sampler) -> Vec3 {
+    let direction = random_on_unit_sphere(sampler);
+    sampler.get_1d().cbrt() * direction
 }
 
-pub fn random_int(start: usize, end: usize) -> usize {
-    let mut rng = rand::thread_rng();
-    rng.gen_range(start, end)
+pub fn random_cosine_direction(sampler: &mut Sampler) -> Vec3 {
+    let (r1, r2) = sampler.get_2d();
+    let z = (1.0 - r2).sqrt();
+    let phi = 2.0 * std::f32::consts::PI * r1;
+    let x = phi.cos() * r2.sqrt();
+    let y = phi.sin() * r2.sqrt();
+    vec3(x, y, z)
 }
@@ -0,0 +1,105 @@
+use crate::camera::Camera;
+use crate::environment::Environment;
+use crate::hitable::Hitable;
+use crate::math::*;
+use crate::sample_pixel;
+use crate::sampler::SamplerSettings;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+// Tiles are the unit of work handed to the render threads
+pub const TILE_SIZE: u32 = 32;
+
+// Rectangle of the image, in pixels from the top left corner
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Tile {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Tile {
+    // Pixel coordinates in row order
+    pub fn pixels(self) -> impl Iterator<Item = (u32, u32)> {
+        (self.y..self.y + self.height)
+            .flat_map(move |y| (self.x..self.x + self.width).map(move |x| (x, y)))
+    }
+}
+
+// All tiles covering the image, row by row. Tiles on the right and bottom edges are cut to
+// the image size
+pub fn image_tiles(width: u32, height: u32) -> Vec<Tile> {
+    let mut tiles = Vec::new();
+    for y in (0..height).step_by(TILE_SIZE as usize) {
+        for x in (0..width).step_by(TILE_SIZE as usize) {
+            tiles.push(Tile {
+                x,
+                y,
+                width: TILE_SIZE.min(width - x),
+                height: TILE_SIZE.min(height - y),
+            });
+        }
+    }
+    tiles
+}
+
+// Takes samples camera rays through every pixel starting at sample index first_sample, a
+// tile at a time. With the parallel feature tiles are spread over the rayon workers. Every
+// finished tile is passed to on_tile, on the thread that rendered it, with the sum of the
+// samples of its pixels in row order. Returning false from on_tile stops the render, tiles
+// that already started are finished but not reported. Returns whether all tiles were
+// reported
+pub fn render_tiles(
+    width: u32,
+    height: u32,
+    settings: &SamplerSettings,
+    first_sample: i32,
+    samples: i32,
+    max_depth: i32,
+    world: &dyn Hitable,
+    light_shape: &dyn Hitable,
+    environment: &Environment,
+    camera: &Camera,
+    on_tile: &(dyn Fn(Tile, Vec<Vec3>) -> bool + Sync),
+) -> bool {
+    let stopped = AtomicBool::new(false);
+    let render_tile = |tile: &Tile| {
+        if stopped.load(Ordering::Relaxed) {
+            return;
+        }
+        let sums = tile
+            .pixels()
+            .map(|(x, y)| {
+                sample_pixel(
+                    x,
+                    y,
+                    width,
+                    height,
+                    settings,
+                    first_sample,
+                    samples,
+                    max_depth,
+                    world,
+                    light_shape,
+                    environment,
+                    camera,
+                )
+            })
+            .collect();
+        if stopped.load(Ordering::Relaxed) || !on_tile(*tile, sums) {
+            stopped.store(true, Ordering::Relaxed);
+        }
+    };
+
+    let tiles = image_tiles(width, height);
+    // One tile per task, rayon's work stealing evens out tiles of different cost
+    #[cfg(feature = "parallel")]
+    tiles.par_iter().with_max_len(1).for_each(render_tile);
+    #[cfg(not(feature = "parallel"))]
+    tiles.iter().for_each(render_tile);
+
+    !stopped.load(Ordering::Relaxed)
+}
@@ -0,0 +1,394 @@
+mod blue_noise;
+
+use crate::random::Rng;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SamplePattern {
+    // Independent uniform numbers
+    Random,
+    // Jittered strata, shuffled independently for every dimension
+    Stratified,
+    // Kensler's correlated multi-jittered patterns, stratified in 2D and in both 1D projections
+    MultiJittered,
+    // Sobol (0, 2) sequence with hash based Owen scrambling, shuffled for every dimension
+    Sobol,
+    // The same Sobol points in every pixel, offset by a blue noise mask, so the remaining
+    // error is spread out as high frequency noise
+    BlueNoise,
+}
+
+impl SamplePattern {
+    pub const ALL: [SamplePattern; 5] = [
+        SamplePattern::Random,
+        SamplePattern::Stratified,
+        SamplePattern::MultiJittered,
+        SamplePattern::Sobol,
+        SamplePattern::BlueNoise,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SamplePattern::Random => "random",
+            SamplePattern::Stratified => "stratified",
+            SamplePattern::MultiJittered => "cmj",
+            SamplePattern::Sobol => "sobol",
+            SamplePattern::BlueNoise => "bluenoise",
+        }
+    }
+}
+
+impl std::str::FromStr for SamplePattern {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<SamplePattern, String> {
+        SamplePattern::ALL
+            .iter()
+            .copied()
+            .find(|pattern| pattern.name() == name)
+            .ok_or_else(|| {
+                format!(
+                    "unknown sample pattern '{}', expected one of: {}",
+                    name,
+                    SamplePattern::ALL
+                        .iter()
+                        .map(|pattern| pattern.name())
+                        .collect::<Vec<&str>>()
+                        .join(", ")
+                )
+            })
+    }
+}
+
+// How the samples of a render are generated
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SamplerSettings {
+    pub pattern: SamplePattern,
+    pub seed: u64,
+    // Stratified patterns spread this many samples over each pixel
+    pub samples_per_pixel: i32,
+}
+
+// Numbers for one sample of one pixel. Every get_1d or get_2d call is a new dimension:
+// the pixel position, lens position and time come first, then whatever the path needs at
+// every bounce
+pub struct Sampler {
+    pattern: SamplePattern,
+    // Seeds the scrambling of every dimension, per pixel except for blue noise
+    seed: u32,
+    pixel: (u32, u32),
+    index: u32,
+    count: u32,
+    dimension: u32,
+    rng: Rng,
+}
+
+impl Sampler {
+    pub fn new(settings: &SamplerSettings, x: u32, y: u32, index: u32) -> Sampler {
+        let render_seed = hash(settings.seed as u32 ^ hash((settings.seed >> 32) as u32));
+        let seed = match settings.pattern {
+            SamplePattern::BlueNoise => render_seed,
+            _ => hash(render_seed ^ hash(x ^ hash(y))),
+        };
+        Sampler {
+            pattern: settings.pattern,
+            seed,
+            pixel: (x, y),
+            index,
+            count: settings.samples_per_pixel.max(1) as u32,
+            dimension: 0,
+            rng: Rng::for_sample(settings.seed, x, y, index),
+        }
+    }
+
+    fn next_dimension_seed(&mut self) -> u32 {
+        let seed = hash_combine(self.seed, self.dimension);
+        self.dimension += 1;
+        seed
+    }
+
+    // Uniform in [0, 1)
+    pub fn get_1d(&mut self) -> f32 {
+        let seed = self.next_dimension_seed();
+        // Samples past the planned count can't be stratified
+        let pattern = match self.pattern {
+            SamplePattern::Stratified | SamplePattern::MultiJittered
+                if self.index >= self.count =>
+            {
+                SamplePattern::Random
+            }
+            pattern => pattern,
+        };
+        match pattern {
+            SamplePattern::Random => self.rng.float(),
+            SamplePattern::Stratified | SamplePattern::MultiJittered => {
+                let stratum = permute(self.index, self.count, seed);
+                (stratum as f32 + hash_float(self.index, hash(seed))) / self.count as f32
+            }
+            SamplePattern::Sobol => {
+                let index = nested_uniform_scramble(self.index, hash(seed));
+                to_float(nested_uniform_scramble(index.reverse_bits(), seed))
+            }
+            SamplePattern::BlueNoise => {
+                let index = nested_uniform_scramble(self.index, hash(seed));
+                let value = to_float(nested_uniform_scramble(index.reverse_bits(), seed));
+                wrap(value + blue_noise::offset(self.pixel, seed))
+            }
+        }
+        .min(ONE_MINUS_EPSILON)
+    }
+
+    // Uniform in [0, 1)^2
+    pub fn get_2d(&mut self) -> (f32, f32) {
+        let seed = self.next_dimension_seed();
+        let pattern = match self.pattern {
+            SamplePattern::Stratified | SamplePattern::MultiJittered
+                if self.index >= self.count =>
+            {
+                SamplePattern::Random
+            }
+            pattern => pattern,
+        };
+        let (x, y) = match pattern {
+            SamplePattern::Random => (self.rng.float(), self.rng.float()),
+            SamplePattern::Stratified => {
+                // When count doesn't fill the grid the cells left out are picked at random
+                // for every pixel and dimension, otherwise the same top row cells would
+                // always stay empty
+                let (m, n) = grid_size(self.count);
+                let stratum = permute(self.index, m * n, seed);
+                (
+                    ((stratum % m) as f32 + hash_float(self.index, hash(seed ^ 1))) / m as f32,
+                    ((stratum / m) as f32 + hash_float(self.index, hash(seed ^ 2))) / n as f32,
+                )
+            }
+            SamplePattern::MultiJittered => cmj(self.index, self.count, seed),
+            SamplePattern::Sobol => sobol_owen_2d(self.index, seed),
+            SamplePattern::BlueNoise => {
+                let (x, y) = sobol_owen_2d(self.index, seed);
+                (
+                    wrap(x + blue_noise::offset(self.pixel, seed)),
+                    wrap(y + blue_noise::offset(self.pixel, hash(seed))),
+                )
+            }
+        };
+        (x.min(ONE_MINUS_EPSILON), y.min(ONE_MINUS_EPSILON))
+    }
+}
+
+const ONE_MINUS_EPSILON: f32 = 1.0 - f32::EPSILON / 2.0;
+
+fn to_float(bits: u32) -> f32 {
+    (bits >> 8) as f32 * (1.0 / (1 << 24) as f32)
+}
+
+fn wrap(value: f32) -> f32 {
+    if value >= 1.0 {
+        value - 1.0
+    } else {
+        value
+    }
+}
+
+// Chris Wellons' lowbias32 integer hash
+fn hash(mut x: u32) -> u32 {
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7feb_352d);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846c_a68b);
+    x ^ (x >> 16)
+}
+
+fn hash_combine(seed: u32, value: u32) -> u32 {
+    seed ^ (hash(value)
+        .wrapping_add(0x9e37_79b9)
+        .wrapping_add(seed << 6)
+        .wrapping_add(seed >> 2))
+}
+
+fn hash_float(i: u32, seed: u32) -> f32 {
+    to_float(hash(i ^ seed))
+}
+
+// Most square m x n grid with at least count cells
+fn grid_size(count: u32) -> (u32, u32) {
+    let m = ((count as f32).sqrt() as u32).max(1);
+    (m, count.div_ceil(m))
+}
+
+// Kensler's hash based permutation of [0, length), index i of the permutation picked by seed
+fn permute(mut i: u32, length: u32, seed: u32) -> u32 {
+    let p = seed;
+    let mut w = length - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    // Cycle walking, the hash is a permutation of [0, w] so values outside length are
+    // hashed again until they land inside
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170_893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929_eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935_fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dc_b303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e50_1cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860_a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < length {
+            break;
+        }
+    }
+    (i.wrapping_add(p)) % length
+}
+
+// Correlated multi-jittered sample index of count, from Kensler's paper. Like the stratified
+// pattern it draws count of the m x n cells
+fn cmj(index: u32, count: u32, seed: u32) -> (f32, f32) {
+    let (m, n) = grid_size(count);
+    let s = permute(index, m * n, seed.wrapping_mul(0x5163_3e2d));
+    let sx = permute(s % m, m, seed.wrapping_mul(0x68bc_21eb));
+    let sy = permute(s / m, n, seed.wrapping_mul(0x02e5_be93));
+    let jx = hash_float(s, seed.wrapping_mul(0x967a_889b));
+    let jy = hash_float(s, seed.wrapping_mul(0x368c_c8b7));
+    (
+        ((s % m) as f32 + (sy as f32 + jx) / n as f32) / m as f32,
+        ((s / m) as f32 + (sx as f32 + jy) / m as f32) / n as f32,
+    )
+}
+
+// Owen scrambling in reversed bit order, every bit is flipped based on the bits above it.
+// Burley's improved Laine-Karras hash
+fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50_b47c);
+    x ^= x.wrapping_mul(0xb82f_1e52);
+    x ^= x.wrapping_mul(0xc7af_e638);
+    x ^= x.wrapping_mul(0x8d22_f6e6);
+    x
+}
+
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
+
+// The first two Sobol dimensions: van der Corput and the one for the polynomial x + 1
+fn sobol_2d(index: u32) -> (u32, u32) {
+    let mut y = 0;
+    let mut v = 1 << 31;
+    let mut i = index;
+    while i != 0 {
+        if i & 1 != 0 {
+            y ^= v;
+        }
+        i >>= 1;
+        v ^= v >> 1;
+    }
+    (index.reverse_bits(), y)
+}
+
+// Shuffling the index decorrelates dimensions that all use the same two Sobol dimensions
+fn sobol_owen_2d(index: u32, seed: u32) -> (f32, f32) {
+    let index = nested_uniform_scramble(index, hash(seed));
+    let (x, y) = sobol_2d(index);
+    (
+        to_float(nested_uniform_scramble(x, hash_combine(seed, 0))),
+        to_float(nested_uniform_scramble(y, hash_combine(seed, 1))),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Mean of both coordinates and the share of samples in the top row of the grid, over
+    // all samples of many pixels
+    fn marginals(pattern: SamplePattern, count: u32) -> (f32, f32, f32) {
+        let settings = SamplerSettings {
+            pattern,
+            seed: 7,
+            samples_per_pixel: count as i32,
+        };
+        let (_, n) = grid_size(count);
+        let pixels = 200_000 / count;
+        let (mut sum_x, mut sum_y, mut top_row) = (0.0f64, 0.0f64, 0);
+        for pixel in 0..pixels {
+            for index in 0..count {
+                let mut sampler = Sampler::new(&settings, pixel % 64, pixel / 64, index);
+                let (x, y) = sampler.get_2d();
+                sum_x += x as f64;
+                sum_y += y as f64;
+                if y >= (n - 1) as f32 / n as f32 {
+                    top_row += 1;
+                }
+            }
+        }
+        let total = (pixels * count) as f64;
+        (
+            (sum_x / total) as f32,
+            (sum_y / total) as f32,
+            (top_row as f64 / total) as f32,
+        )
+    }
+
+    #[test]
+    fn stratified_patterns_are_unbiased_for_non_square_counts() {
+        for &pattern in &[SamplePattern::Stratified, SamplePattern::MultiJittered] {
+            for &count in &[10, 1000] {
+                let (_, n) = grid_size(count);
+                let (x, y, top_row) = marginals(pattern, count);
+                assert!(
+                    (x - 0.5).abs() < 0.005,
+                    "{:?} {}: E[x] = {}",
+                    pattern,
+                    count,
+                    x
+                );
+                assert!(
+                    (y - 0.5).abs() < 0.005,
+                    "{:?} {}: E[y] = {}",
+                    pattern,
+                    count,
+                    y
+                );
+                assert!(
+                    (top_row - 1.0 / n as f32).abs() < 0.003,
+                    "{:?} {}: top row mass {}",
+                    pattern,
+                    count,
+                    top_row
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn samples_stay_in_unit_square() {
+        for &pattern in &SamplePattern::ALL {
+            let settings = SamplerSettings {
+                pattern,
+                seed: 3,
+                samples_per_pixel: 16,
+            };
+            for index in 0..32 {
+                let mut sampler = Sampler::new(&settings, 5, 9, index);
+                for _ in 0..8 {
+                    let u = sampler.get_1d();
+                    let (x, y) = sampler.get_2d();
+                    assert!((0.0..1.0).contains(&u));
+                    assert!((0.0..1.0).contains(&x) && (0.0..1.0).contains(&y));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use crate::random::Rng;
+
+const SIZE: usize = 64;
+const SIGMA: f32 = 1.5;
+
+// Toroidal offset into the blue noise mask for a pixel, different for every seed
+pub fn offset(pixel: (u32, u32), seed: u32) -> f32 {
+    let x = (pixel.0 as usize + (seed & 0xffff) as usize) % SIZE;
+    let y = (pixel.1 as usize + (seed >> 16) as usize) % SIZE;
+    BLUE_NOISE_MASK[y * SIZE + x]
+}
+
+lazy_static! {
+    static ref BLUE_NOISE_MASK: Vec<f32> = void_and_cluster(0);
+}
+
+// Gaussian falloff with the toroidal distance between two pixels
+fn energy_kernel() -> Vec<f32> {
+    let mut kernel = vec![0.0; SIZE * SIZE];
+    for dy in 0..SIZE {
+        for dx in 0..SIZE {
+            let wx = dx.min(SIZE - dx) as f32;
+            let wy = dy.min(SIZE - dy) as f32;
+            kernel[dy * SIZE + dx] = (-(wx * wx + wy * wy) / (2.0 * SIGMA * SIGMA)).exp();
+        }
+    }
+    kernel
+}
+
+struct Pattern {
+    ones: Vec<bool>,
+    // Kernel summed over all ones, for every pixel
+    energy: Vec<f32>,
+}
+
+impl Pattern {
+    fn toggle(&mut self, pixel: usize, kernel: &[f32]) {
+        self.ones[pixel] = !self.ones[pixel];
+        let sign = if self.ones[pixel] { 1.0 } else { -1.0 };
+        let (px, py) = (pixel % SIZE, pixel / SIZE);
+        for y in 0..SIZE {
+            let dy = (y + SIZE - py) % SIZE;
+            for x in 0..SIZE {
+                let dx = (x + SIZE - px) % SIZE;
+                self.energy[y * SIZE + x] += sign * kernel[dy * SIZE + dx];
+            }
+        }
+    }
+
+    // The one with the most ones around it
+    fn tightest_cluster(&self) -> usize {
+        (0..SIZE * SIZE)
+            .filter(|pixel| self.ones[*pixel])
+            .max_by(|a, b| self.energy[*a].partial_cmp(&self.energy[*b]).unwrap())
+            .unwrap()
+    }
+
+    // The zero with the fewest ones around it
+    fn largest_void(&self) -> usize {
+        (0..SIZE * SIZE)
+            .filter(|pixel| !self.ones[*pixel])
+            .min_by(|a, b| self.energy[*a].partial_cmp(&self.energy[*b]).unwrap())
+            .unwrap()
+    }
+}
+
+// Ulichney's void and cluster method. Returns a threshold in (0, 1) for every pixel of a
+// tileable SIZE x SIZE mask
+fn void_and_cluster(seed: u64) -> Vec<f32> {
+    let kernel = energy_kernel();
+    let count = SIZE * SIZE;
+    let mut pattern = Pattern {
+        ones: vec![false; count],
+        energy: vec![0.0; count],
+    };
+
+    // Random initial pattern, relaxed until moving the tightest cluster to the largest
+    // void doesn't change anything
+    let initial_ones = count / 10;
+    let mut rng = Rng::new(seed);
+    let mut placed = 0;
+    while placed < initial_ones {
+        let pixel = (rng.next_u32() as usize) % count;
+        if !pattern.ones[pixel] {
+            pattern.toggle(pixel, &kernel);
+            placed += 1;
+        }
+    }
+    for _ in 0..count {
+        let cluster = pattern.tightest_cluster();
+        pattern.toggle(cluster, &kernel);
+        let void = pattern.largest_void();
+        pattern.toggle(void, &kernel);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut ranks = vec![0; count];
+    // Ranks below the initial pattern, removing its tightest clusters first
+    let mut removing = Pattern {
+        ones: pattern.ones.clone(),
+        energy: pattern.energy.clone(),
+    };
+    for rank in (0..initial_ones).rev() {
+        let cluster = removing.tightest_cluster();
+        removing.toggle(cluster, &kernel);
+        ranks[cluster] = rank;
+    }
+    // Ranks above it, filling the largest voids. Past half full this is the same as
+    // removing the tightest clusters of zeros
+    for rank in initial_ones..count {
+        let void = pattern.largest_void();
+        pattern.toggle(void, &kernel);
+        ranks[void] = rank;
+    }
+
+    ranks
+        .iter()
+        .map(|rank| (*rank as f32 + 0.5) / count as f32)
+        .collect()
+}
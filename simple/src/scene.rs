@@ -0,0 +1,20 @@
+mod builtin;
+mod loader;
+mod obj;
+
+pub use builtin::{builtin_scene, BUILTIN_SCENES};
+pub use loader::{load_scene, parse_scene, SceneError};
+pub use obj::load_obj;
+
+use crate::camera::Camera;
+use crate::environment::Environment;
+use crate::hitable::Hitable;
+
+pub struct Scene {
+    pub world: Vec<Box<dyn Hitable>>,
+    // Shapes towards which scattered rays are importance sampled
+    pub lights: Vec<Box<dyn Hitable>>,
+    // Seen by rays leaving the scene, the loader also adds it to lights unless it is black
+    pub environment: Environment,
+    pub camera: Camera,
+}
@@ -0,0 +1,564 @@
+use crate::camera::Camera;
+use crate::environment::Environment;
+use crate::hitable::*;
+use crate::material::*;
+use crate::math::*;
+use crate::scene::Scene;
+use crate::texture::*;
+
+pub const BUILTIN_SCENES: [&str; 4] = [
+    "cornel_box",
+    "cornel_smoke",
+    "two_perlin_spheres",
+    "simple_light",
+];
+
+// Builds one of BUILTIN_SCENES, scenes that use files fail when the file is missing
+pub fn builtin_scene(name: &str, aspect: f32) -> Result<Scene, String> {
+    match name {
+        "cornel_box" => Ok(cornel_box(aspect)),
+        "cornel_smoke" => Ok(cornel_smoke(aspect)),
+        "two_perlin_spheres" => two_perlin_spheres(aspect),
+        "simple_light" => Ok(simple_light(aspect)),
+        _ => Err(format!(
+            "unknown built-in scene '{}', expected one of: {}",
+            name,
+            BUILTIN_SCENES.join(", ")
+        )),
+    }
+}
+
+fn cornel_camera(aspect: f32) -> Camera {
+    Camera::new(
+        vec3(278.0, 278.0, -800.0),
+        vec3(278.0, 278.0, 0.0),
+        vec3(0.0, 1.0, 0.0),
+        40.0,
+        aspect,
+        0.0,
+        10.0,
+        0.0,
+        1.0,
+    )
+}
+
+//#[allow(dead_code)]
+//fn random_scene() -> Vec<Box<dyn Hitable>> {
+// let n = 500;
+// let mut list: Vec<Box<dyn Hitable>> = Vec::with_capacity(n + 1);
+// list.push(Box::new(Sphere {
+//     center: vec3(0.0, -1000.0, 0.0),
+//     radius: 1000.0,
+//     material: Box::new(Lambertian {
+//         albedo: Box::new(CheckerTexture {
+//             even: Box::new(ConstantTexture(vec3(0.2, 0.3, 0.1))),
+//             odd: Box::new(ConstantTexture(vec3(0.9, 0.9, 0.9))),
+//         }),
+//     }),
+// }));
+// for a in -11..11 {
+//     for b in -11..11 {
+//         let choose_mat = random_float();
+//         let center = vec3(
+//             a as f32 + 0.9 * random_float(),
+//             0.2,
+//             b as f32 + 0.9 * random_float(),
+//         );
+//         if (center - vec3(4.0, 0.2, 0.0)).magnitude() > 0.9 {
+//             match choose_mat {
+//                 x if x < 0.8 => list.push(Box::new(MovingSphere {
+//                     center0: center,
+//                     center1: center + vec3(0.0, 0.5 * random_float(), 0.0),
+//                     time0: 0.0,
+//                     time1: 1.0,
+//                     radius: 0.2,
+//                     material: Box::new(Lambertian {
+//                         albedo: Box::new(ConstantTexture(vec3(
+//                             random_float() * random_float(),
+//                             random_float() * random_float(),
+//                             random_float() * random_float(),
+//                         ))),
+//                     }),
+//                 })),
+//                 x if x < 0.95 => list.push(Box::new(Sphere {
+//                     center,
+//                     radius: 0.2,
+//                     material: Box::new(Metal {
+//                         albedo: vec3(
+//                             0.5 * (1.0 + random_float()),
+//                             0.5 * (1.0 + random_float()),
+//                             0.5 * (1.0 + random_float()),
+//                         ),
+//                         fuzz: 0.5 * random_float(),
+//                     }),
+//                 })),
+//                 _ => list.push(Box::new(Sphere {
+//                     center,
+//                     radius: 0.2,
+//                     material: Box::new(Dielectric { ref_index: 1.5 }),
+//                 })),
+//             }
+//         }
+//     }
+// }
+
+// list.push(Box::new(Sphere {
+//     center: vec3(0.0, 1.0, 0.0),
+//     radius: 1.0,
+//     material: Box::new(Dielectric { ref_index: 1.5 }),
+// }));
+// list.push(Box::new(Sphere {
+//     center: vec3(-4.0, 1.0, 0.0),
+//     radius: 1.0,
+//     material: Box::new(Lambertian {
+//         albedo: Box::new(ConstantTexture(vec3(0.4, 0.2, 0.1))),
+//     }),
+// }));
+// list.push(Box::new(Sphere {
+//     center: vec3(4.0, 1.0, 0.0),
+//     radius: 1.0,
+//     material: Box::new(Metal {
+//         albedo: vec3(0.7, 0.6, 0.5),
+//         fuzz: 0.0,
+//     }),
+// }));
+// list
+//}
+
+// Needs an untitled.png image in the working directory for the texture of the upper sphere
+fn two_perlin_spheres(aspect: f32) -> Result<Scene, String> {
+    let img = image::open("untitled.png")
+        .map_err(|err| format!("failed to load image 'untitled.png': {}", err))?;
+    let image_texture = Box::new(ImageTexture::new(img));
+    let noise = Box::new(NoiseTexture { scale: 4.0 });
+    let list: Vec<Box<dyn Hitable>> = vec![
+        Box::new(Sphere {
+            center: vec3(0.0, -1000.0, 0.0),
+            radius: 1000.0,
+            material: Box::new(Lambertian { albedo: noise }),
+        }),
+        Box::new(Sphere {
+            center: vec3(0.0, 2.0, 0.0),
+            radius: 2.0,
+            material: Box::new(Lambertian {
+                albedo: image_texture,
+            }),
+        }),
+    ];
+    Ok(Scene {
+        world: list,
+        lights: vec![],
+        environment: Environment::default(),
+        camera: Camera::new(
+            vec3(13.0, 2.0, 3.0),
+            vec3(0.0, 0.0, 0.0),
+            vec3(0.0, 1.0, 0.0),
+            20.0,
+            aspect,
+            0.0,
+            10.0,
+            0.0,
+            1.0,
+        ),
+    })
+}
+
+fn simple_light(aspect: f32) -> Scene {
+    let noise = Box::new(NoiseTexture { scale: 4.0 });
+    let light = Box::new(DiffuseLight {
+        emit: Box::new(ConstantTexture(vec3(4.0, 4.0, 4.0))),
+    });
+    let light_shape = Sphere {
+        center: vec3(0.0, 7.0, 0.0),
+        radius: 2.0,
+        material: light.clone(),
+    };
+    let list: Vec<Box<dyn Hitable>> = vec![
+        Box::new(Sphere {
+            center: vec3(0.0, -1000.0, 0.0),
+            radius: 1000.0,
+            material: Box::new(Lambertian {
+                albedo: noise.clone(),
+            }),
+        }),
+        Box::new(Sphere {
+            center: vec3(0.0, 2.0, 0.0),
+            radius: 2.0,
+            material: Box::new(Lambertian { albedo: noise }),
+        }),
+        Box::new(light_shape.clone()),
+        Box::new(XYRect {
+            x0: 3.0,
+            x1: 5.0,
+            y0: 1.0,
+            y1: 3.0,
+            k: -2.0,
+            material: light,
+        }),
+    ];
+    Scene {
+        world: list,
+        lights: vec![Box::new(light_shape)],
+        environment: Environment::default(),
+        camera: Camera::new(
+            vec3(26.0, 3.0, 6.0),
+            vec3(0.0, 2.0, 0.0),
+            vec3(0.0, 1.0, 0.0),
+            20.0,
+            aspect,
+            0.0,
+            10.0,
+            0.0,
+            1.0,
+        ),
+    }
+}
+
+fn cornel_box(aspect: f32) -> Scene {
+    let red = Box::new(Lambertian {
+        albedo: Box::new(ConstantTexture(vec3(0.65, 0.05, 0.05))),
+    });
+    let white = Box::new(Lambertian {
+        albedo: Box::new(ConstantTexture(vec3(0.73, 0.73, 0.73))),
+    });
+    let green = Box::new(Lambertian {
+        albedo: Box::new(ConstantTexture(vec3(0.12, 0.45, 0.15))),
+    });
+    let light = Box::new(DiffuseLight {
+        emit: Box::new(ConstantTexture(vec3(15.0, 15.0, 15.0))),
+    });
+    let light_shape_1 = Box::new(XZRect {
+        x0: 213.0,
+        x1: 343.0,
+        z0: 227.0,
+        z1: 332.0,
+        k: 554.0,
+        material: light.clone(),
+    });
+    let light_shape_2 = Box::new(Sphere {
+        center: vec3(190.0, 90.0, 190.0),
+        radius: 90.0,
+        material: light.clone(),
+    });
+    let light_shape: Vec<Box<dyn Hitable>> = vec![light_shape_1, light_shape_2];
+
+    let list: Vec<Box<dyn Hitable>> = vec![
+        Box::new(FlipNormals(Box::new(YZRect {
+            y0: 0.0,
+            y1: 555.0,
+            z0: 0.0,
+            z1: 555.0,
+            k: 555.0,
+            material: green,
+        }))),
+        Box::new(YZRect {
+            y0: 0.0,
+            y1: 555.0,
+            z0: 0.0,
+            z1: 555.0,
+            k: 0.0,
+            material: red,
+        }),
+        Box::new(FlipNormals(Box::new(XZRect {
+            x0: 213.0,
+            x1: 343.0,
+            z0: 227.0,
+            z1: 332.0,
+            k: 554.0,
+            material: light,
+        }))),
+        Box::new(FlipNormals(Box::new(XZRect {
+            x0: 0.0,
+            x1: 555.0,
+            z0: 0.0,
+            z1: 555.0,
+            k: 555.0,
+            material: white.clone(),
+        }))),
+        Box::new(XZRect {
+            x0: 0.0,
+            x1: 555.0,
+            z0: 0.0,
+            z1: 555.0,
+            k: 0.0,
+            material: white.clone(),
+        }),
+        Box::new(FlipNormals(Box::new(XYRect {
+            x0: 0.0,
+            x1: 555.0,
+            y0: 0.0,
+            y1: 555.0,
+            k: 555.0,
+            material: white.clone(),
+        }))),
+        // Box::new(Translate {
+        //     offset: vec3(130.0, 0.0, 65.0),
+        //     hitable: Box::new(RotateY::new(
+        //         Box::new(BoxHitable::new(
+        //             &vec3(0.0, 0.0, 0.0),
+        //             &vec3(165.0, 165.0, 165.0),
+        //             white.clone(),
+        //         )),
+        //         -18.0,
+        //     )),
+        // }),
+        Box::new(Sphere {
+            center: vec3(190.0, 90.0, 190.0),
+            radius: 90.0,
+            material: Box::new(Dielectric { ref_index: 1.5 }),
+        }),
+        Box::new(Translate {
+            offset: vec3(265.0, 0.0, 295.0),
+            hitable: Box::new(RotateY::new(
+                Box::new(BoxHitable::new(
+                    &vec3(0.0, 0.0, 0.0),
+                    &vec3(165.0, 330.0, 165.0),
+                    white,
+                )),
+                15.0,
+            )),
+        }),
+    ];
+    Scene {
+        world: list,
+        lights: light_shape,
+        environment: Environment::default(),
+        camera: cornel_camera(aspect),
+    }
+}
+
+fn cornel_smoke(aspect: f32) -> Scene {
+    let red = Box::new(Lambertian {
+        albedo: Box::new(ConstantTexture(vec3(0.65, 0.05, 0.05))),
+    });
+    let white = Box::new(Lambertian {
+        albedo: Box::new(ConstantTexture(vec3(0.73, 0.73, 0.73))),
+    });
+    let green = Box::new(Lambertian {
+        albedo: Box::new(ConstantTexture(vec3(0.12, 0.45, 0.15))),
+    });
+    let light = Box::new(DiffuseLight {
+        emit: Box::new(ConstantTexture(vec3(7.0, 7.0, 7.0))),
+    });
+
+    let box1 = Box::new(Translate {
+        offset: vec3(130.0, 0.0, 65.0),
+        hitable: Box::new(RotateY::new(
+            Box::new(BoxHitable::new(
+                &vec3(0.0, 0.0, 0.0),
+                &vec3(165.0, 165.0, 165.0),
+                white.clone(),
+            )),
+            -18.0,
+        )),
+    });
+    let box2 = Box::new(Translate {
+        offset: vec3(265.0, 0.0, 295.0),
+        hitable: Box::new(RotateY::new(
+            Box::new(BoxHitable::new(
+                &vec3(0.0, 0.0, 0.0),
+                &vec3(165.0, 330.0, 165.0),
+                white.clone(),
+            )),
+            15.0,
+        )),
+    });
+
+    let list: Vec<Box<dyn Hitable>> = vec![
+        Box::new(FlipNormals(Box::new(YZRect {
+            y0: 0.0,
+            y1: 555.0,
+            z0: 0.0,
+            z1: 555.0,
+            k: 555.0,
+            material: green,
+        }))),
+        Box::new(YZRect {
+            y0: 0.0,
+            y1: 555.0,
+            z0: 0.0,
+            z1: 555.0,
+            k: 0.0,
+            material: red,
+        }),
+        Box::new(FlipNormals(Box::new(XZRect {
+            x0: 113.0,
+            x1: 443.0,
+            z0: 127.0,
+            z1: 432.0,
+            k: 554.0,
+            material: light.clone(),
+        }))),
+        Box::new(FlipNormals(Box::new(XZRect {
+            x0: 0.0,
+            x1: 555.0,
+            z0: 0.0,
+            z1: 555.0,
+            k: 555.0,
+            material: white.clone(),
+        }))),
+        Box::new(XZRect {
+            x0: 0.0,
+            x1: 555.0,
+            z0: 0.0,
+            z1: 555.0,
+            k: 0.0,
+            material: white.clone(),
+        }),
+        Box::new(FlipNormals(Box::new(XYRect {
+            x0: 0.0,
+            x1: 555.0,
+            y0: 0.0,
+            y1: 555.0,
+            k: 555.0,
+            material: white.clone(),
+        }))),
+        Box::new(ConstantMedium::new(
+            box1,
+            0.01,
+            Box::new(Isotropic(Box::new(ConstantTexture(vec3(1.0, 1.0, 1.0))))),
+        )),
+        Box::new(ConstantMedium::new(
+            box2,
+            0.01,
+            Box::new(Isotropic(Box::new(ConstantTexture(vec3(0.0, 0.0, 0.0))))),
+        )),
+    ];
+    let light_shape: Vec<Box<dyn Hitable>> = vec![Box::new(XZRect {
+        x0: 113.0,
+        x1: 443.0,
+        z0: 127.0,
+        z1: 432.0,
+        k: 554.0,
+        material: light,
+    })];
+    Scene {
+        world: list,
+        lights: light_shape,
+        environment: Environment::default(),
+        camera: cornel_camera(aspect),
+    }
+}
+
+// pub fn final_scene() -> Vec<Box<dyn Hitable>> {
+//     let white = Box::new(Lambertian {
+//         albedo: Box::new(ConstantTexture(vec3(0.73, 0.73, 0.73))),
+//     });
+//     let ground = Box::new(Lambertian {
+//         albedo: Box::new(ConstantTexture(vec3(0.48, 0.83, 0.53))),
+//     });
+
+//     let mut boxlist: Vec<Box<dyn Hitable>> = vec![];
+//     for i in 0..20 {
+//         for j in 0..20 {
+//             let w = 100.0;
+//             let x0 = -1000.0 + i as f32 * w;
+//             let z0 = -1000.0 + j as f32 * w;
+//             let y0 = 0.0;
+//             let x1 = x0 + w;
+//             let y1 = 100.0 * (random_float() + 0.01);
+//             let z1 = z0 + w;
+//             boxlist.push(Box::new(BoxHitable::new(
+//                 &vec3(x0, y0, z0),
+//                 &vec3(x1, y1, z1),
+//                 ground.clone(),
+//             )));
+//         }
+//     }
+//     let center = vec3(400.0, 400.0, 200.0);
+//     let boundary = Sphere {
+//         center: vec3(360.0, 150.0, 145.0),
+//         radius: 70.0,
+//         material: Box::new(Dielectric { ref_index: 1.5 }),
+//     };
+//     let boundary2 = Sphere {
+//         center: vec3(0.0, 0.0, 0.0),
+//         radius: 5000.0,
+//         material: Box::new(Dielectric { ref_index: 1.5 }),
+//     };
+//     let img = image::open("earthmap.jpg").unwrap();
+//     let image_texture = Box::new(ImageTexture::new(img));
+//     let emat = Box::new(Lambertian {
+//         albedo: image_texture,
+//     });
+//     let mut boxlist2: Vec<Box<dyn Hitable>> = vec![];
+//     for _j in 0..1000 {
+//         boxlist2.push(Box::new(Sphere {
+//             center: vec3(
+//                 165.0 * random_float(),
+//                 165.0 * random_float(),
+//                 165.0 * random_float(),
+//             ),
+//             radius: 10.0,
+//             material: white.clone(),
+//         }))
+//     }
+
+//     let list: Vec<Box<dyn Hitable>> = vec![
+//         Box::new(BVHNode::build(boxlist, 0.0, 1.0)),
+//         Box::new(XZRect {
+//             x0: 123.0,
+//             x1: 423.0,
+//             z0: 147.0,
+//             z1: 412.0,
+//             k: 554.0,
+//             material: Box::new(DiffuseLight {
+//                 emit: Box::new(ConstantTexture(vec3(7.0, 7.0, 7.0))),
+//             }),
+//         }),
+//         Box::new(MovingSphere {
+//             center0: center,
+//             center1: center + vec3(30.0, 0.0, 0.0),
+//             radius: 50.0,
+//             time0: 0.0,
+//             time1: 1.0,
+//             material: Box::new(Lambertian {
+//                 albedo: Box::new(ConstantTexture(vec3(0.7, 0.3, 0.1))),
+//             }),
+//         }),
+//         Box::new(Sphere {
+//             center: vec3(260.0, 150.0, 45.0),
+//             radius: 50.0,
+//             material: Box::new(Dielectric { ref_index: 1.5 }),
+//         }),
+//         Box::new(Sphere {
+//             center: vec3(0.0, 150.0, 145.0),
+//             radius: 50.0,
+//             material: Box::new(Metal {
+//                 albedo: vec3(0.8, 0.8, 0.9),
+//                 fuzz: 10.0,
+//             }),
+//         }),
+//         Box::new(boundary.clone()),
+//         Box::new(ConstantMedium::new(
+//             Box::new(boundary),
+//             0.2,
+//             Box::new(ConstantTexture(vec3(0.2, 0.4, 0.9))),
+//         )),
+//         Box::new(ConstantMedium::new(
+//             Box::new(boundary2),
+//             0.0001,
+//             Box::new(ConstantTexture(vec3(1.0, 1.0, 1.0))),
+//         )),
+//         Box::new(Sphere {
+//             center: vec3(400.0, 200.0, 400.0),
+//             radius: 100.0,
+//             material: emat,
+//         }),
+//         Box::new(Sphere {
+//             center: vec3(220.0, 280.0, 300.0),
+//             radius: 80.0,
+//             material: Box::new(Lambertian {
+//                 albedo: Box::new(NoiseTexture { scale: 0.1 }),
+//             }),
+//         }),
+//         Box::new(Translate {
+//             offset: vec3(-100.0, 270.0, 395.0),
+//             hitable: Box::new(RotateY::new(
+//                 Box::new(BVHNode::build(boxlist2, 0.0, 1.0)),
+//                 15.0,
+//             )),
+//         }),
+//     ];
+//     list
+// }
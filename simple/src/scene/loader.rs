@@ -0,0 +1,712 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use toml::Spanned;
+
+use crate::camera::Camera;
+use crate::density::*;
+use crate::environment::{Environment, EnvironmentMap};
+use crate::hitable::*;
+use crate::material::*;
+use crate::math::*;
+use crate::scene::{load_obj, Scene};
+use crate::texture::*;
+
+// Scene files are TOML. Textures and materials are named tables which are referenced by
+// name, objects and light sampling shapes are arrays of tables:
+//
+// [camera]
+// look_from = [278.0, 278.0, -800.0]
+// look_at = [278.0, 278.0, 0.0]
+// vertical_fov = 40.0
+//
+// [materials.white]
+// type = "lambertian"
+// albedo = [0.73, 0.73, 0.73] # a constant color or a name from [textures]
+//
+// [[objects]]
+// type = "flip_normals"
+// hitable = { type = "xy_rect", x0 = 0.0, x1 = 555.0, y0 = 0.0, y1 = 555.0, k = 555.0, material = "white" }
+//
+// [[objects]]
+// type = "mesh"
+// path = "bunny.obj" # materials come from the OBJ's MTL files unless material is given
+//
+// [[objects]]
+// type = "constant_medium" # density = 0.01 with albedo = [r, g, b] works as well
+// boundary = { type = "sphere", center = [278.0, 278.0, 278.0], radius = 100.0, material = "white" }
+// sigma_a = [0.001, 0.004, 0.008]
+// sigma_s = [0.01, 0.01, 0.01]
+// g = 0.5 # Henyey-Greenstein asymmetry, 0 scatters equally in all directions
+//
+// [[objects]]
+// type = "heterogeneous_medium" # sigma_a and sigma_s are scaled by the density at each point
+// boundary = { type = "box", min = [0.0, 0.0, 0.0], max = [100.0, 100.0, 100.0], material = "white" }
+// density = { type = "grid", resolution = [2, 1, 1], values = [0.0, 1.0] } # spans the boundary unless min and max are given
+// # density = { type = "voxel_file", path = "smoke.vol" } or { type = "noise", scale = 0.05, density = 1.0 }
+// sigma_a = [0.01, 0.01, 0.01]
+// sigma_s = [0.05, 0.05, 0.05]
+//
+// [environment]
+// map = "sky.hdr" # an equirectangular .hdr or .exr image, or a constant color = [r, g, b]
+// intensity = 1.0
+
+#[derive(Debug)]
+pub struct SceneError {
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl std::fmt::Display for SceneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneDescription {
+    camera: CameraDescription,
+    #[serde(default)]
+    textures: HashMap<String, Spanned<toml::Value>>,
+    #[serde(default)]
+    materials: HashMap<String, Spanned<toml::Value>>,
+    #[serde(default)]
+    objects: Vec<Spanned<toml::Value>>,
+    #[serde(default)]
+    lights: Vec<Spanned<toml::Value>>,
+    #[serde(default)]
+    environment: Option<Spanned<toml::Value>>,
+}
+
+fn default_up() -> [f32; 3] {
+    [0.0, 1.0, 0.0]
+}
+
+fn default_focus_dist() -> f32 {
+    10.0
+}
+
+fn default_time1() -> f32 {
+    1.0
+}
+
+fn default_intensity() -> f32 {
+    1.0
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraDescription {
+    look_from: [f32; 3],
+    look_at: [f32; 3],
+    #[serde(default = "default_up")]
+    up: [f32; 3],
+    vertical_fov: f32,
+    #[serde(default)]
+    aperture: f32,
+    #[serde(default = "default_focus_dist")]
+    focus_dist: f32,
+    #[serde(default)]
+    time0: f32,
+    #[serde(default = "default_time1")]
+    time1: f32,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EnvironmentDescription {
+    #[serde(default)]
+    color: Option<[f32; 3]>,
+    #[serde(default)]
+    map: Option<String>,
+    #[serde(default = "default_intensity")]
+    intensity: f32,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TextureRef {
+    Color([f32; 3]),
+    Named(String),
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum TextureDescription {
+    Constant { color: [f32; 3] },
+    Checker { odd: TextureRef, even: TextureRef },
+    Image { path: String },
+    Noise { scale: f32 },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum DensityDescription {
+    Grid {
+        resolution: [usize; 3],
+        values: Vec<f32>,
+        #[serde(default)]
+        min: Option<[f32; 3]>,
+        #[serde(default)]
+        max: Option<[f32; 3]>,
+    },
+    // Mitsuba .vol file, which has its own bounds
+    VoxelFile {
+        path: String,
+    },
+    Noise {
+        scale: f32,
+        #[serde(default = "default_intensity")]
+        density: f32,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum MaterialDescription {
+    Lambertian {
+        albedo: TextureRef,
+    },
+    Metal {
+        albedo: [f32; 3],
+        #[serde(default)]
+        fuzz: f32,
+    },
+    Dielectric {
+        ref_index: f32,
+    },
+    DiffuseLight {
+        emit: TextureRef,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum HitableDescription {
+    Sphere {
+        center: [f32; 3],
+        radius: f32,
+        material: String,
+    },
+    MovingSphere {
+        center0: [f32; 3],
+        center1: [f32; 3],
+        #[serde(default)]
+        time0: f32,
+        #[serde(default = "default_time1")]
+        time1: f32,
+        radius: f32,
+        material: String,
+    },
+    #[serde(rename = "xy_rect")]
+    XYRect {
+        x0: f32,
+        x1: f32,
+        y0: f32,
+        y1: f32,
+        k: f32,
+        material: String,
+    },
+    #[serde(rename = "xz_rect")]
+    XZRect {
+        x0: f32,
+        x1: f32,
+        z0: f32,
+        z1: f32,
+        k: f32,
+        material: String,
+    },
+    #[serde(rename = "yz_rect")]
+    YZRect {
+        y0: f32,
+        y1: f32,
+        z0: f32,
+        z1: f32,
+        k: f32,
+        material: String,
+    },
+    #[serde(rename = "box")]
+    BoxHitable {
+        min: [f32; 3],
+        max: [f32; 3],
+        material: String,
+    },
+    // Either a density with the albedo of the scattering, or colored sigma_a and sigma_s
+    ConstantMedium {
+        boundary: Box<HitableDescription>,
+        #[serde(default)]
+        density: Option<f32>,
+        #[serde(default)]
+        albedo: Option<TextureRef>,
+        #[serde(default)]
+        sigma_a: Option<[f32; 3]>,
+        #[serde(default)]
+        sigma_s: Option<[f32; 3]>,
+        // Henyey-Greenstein asymmetry, 0 scatters isotropically
+        #[serde(default)]
+        g: f32,
+    },
+    HeterogeneousMedium {
+        boundary: Box<HitableDescription>,
+        density: DensityDescription,
+        sigma_a: [f32; 3],
+        sigma_s: [f32; 3],
+        #[serde(default)]
+        g: f32,
+    },
+    Translate {
+        offset: [f32; 3],
+        hitable: Box<HitableDescription>,
+    },
+    RotateY {
+        angle: f32,
+        hitable: Box<HitableDescription>,
+    },
+    FlipNormals {
+        hitable: Box<HitableDescription>,
+    },
+    Mesh {
+        path: String,
+        #[serde(default)]
+        material: Option<String>,
+    },
+}
+
+fn to_vec3(v: [f32; 3]) -> Vec3 {
+    vec3(v[0], v[1], v[2])
+}
+
+struct SceneBuilder<'a> {
+    source: &'a str,
+    base_dir: &'a Path,
+    texture_descriptions: &'a HashMap<String, Spanned<toml::Value>>,
+    material_descriptions: &'a HashMap<String, Spanned<toml::Value>>,
+    textures: HashMap<String, Box<dyn Texture>>,
+    materials: HashMap<String, Box<dyn Material>>,
+    // Names of textures currently being built, used to detect reference cycles
+    texture_stack: Vec<String>,
+}
+
+impl SceneBuilder<'_> {
+    fn error(&self, span: &Range<usize>, message: String) -> SceneError {
+        SceneError {
+            line: Some(line_at(self.source, span.start)),
+            message,
+        }
+    }
+
+    fn decode<T: DeserializeOwned>(&self, value: &Spanned<toml::Value>) -> Result<T, SceneError> {
+        value
+            .get_ref()
+            .clone()
+            .try_into()
+            .map_err(|err: toml::de::Error| self.error(&value.span(), err.message().to_string()))
+    }
+
+    fn texture(
+        &mut self,
+        reference: &TextureRef,
+        span: &Range<usize>,
+    ) -> Result<Box<dyn Texture>, SceneError> {
+        match reference {
+            TextureRef::Color(color) => Ok(Box::new(ConstantTexture(to_vec3(*color)))),
+            TextureRef::Named(name) => self.named_texture(name, span),
+        }
+    }
+
+    fn named_texture(
+        &mut self,
+        name: &str,
+        span: &Range<usize>,
+    ) -> Result<Box<dyn Texture>, SceneError> {
+        if let Some(texture) = self.textures.get(name) {
+            return Ok(texture.clone());
+        }
+        let value = match self.texture_descriptions.get(name) {
+            Some(value) => value,
+            None => return Err(self.error(span, format!("unknown texture '{}'", name))),
+        };
+        if self.texture_stack.iter().any(|n| n == name) {
+            return Err(self.error(
+                &value.span(),
+                format!("texture '{}' references itself", name),
+            ));
+        }
+        self.texture_stack.push(name.to_string());
+
+        let span = value.span();
+        let texture: Box<dyn Texture> = match self.decode(value)? {
+            TextureDescription::Constant { color } => Box::new(ConstantTexture(to_vec3(color))),
+            TextureDescription::Checker { odd, even } => Box::new(CheckerTexture {
+                odd: self.texture(&odd, &span)?,
+                even: self.texture(&even, &span)?,
+            }),
+            TextureDescription::Image { path } => match image::open(self.base_dir.join(&path)) {
+                Ok(img) => Box::new(ImageTexture::new(img)),
+                Err(err) => {
+                    return Err(
+                        self.error(&span, format!("failed to load image '{}': {}", path, err))
+                    )
+                }
+            },
+            TextureDescription::Noise { scale } => Box::new(NoiseTexture { scale }),
+        };
+
+        self.texture_stack.pop();
+        self.textures.insert(name.to_string(), texture.clone());
+        Ok(texture)
+    }
+
+    fn material(
+        &mut self,
+        name: &str,
+        span: &Range<usize>,
+    ) -> Result<Box<dyn Material>, SceneError> {
+        if let Some(material) = self.materials.get(name) {
+            return Ok(material.clone());
+        }
+        let value = match self.material_descriptions.get(name) {
+            Some(value) => value,
+            None => return Err(self.error(span, format!("unknown material '{}'", name))),
+        };
+
+        let span = value.span();
+        let material: Box<dyn Material> = match self.decode(value)? {
+            MaterialDescription::Lambertian { albedo } => Box::new(Lambertian {
+                albedo: self.texture(&albedo, &span)?,
+            }),
+            MaterialDescription::Metal { albedo, fuzz } => Box::new(Metal {
+                albedo: to_vec3(albedo),
+                fuzz,
+            }),
+            MaterialDescription::Dielectric { ref_index } => Box::new(Dielectric { ref_index }),
+            MaterialDescription::DiffuseLight { emit } => Box::new(DiffuseLight {
+                emit: self.texture(&emit, &span)?,
+            }),
+        };
+
+        self.materials.insert(name.to_string(), material.clone());
+        Ok(material)
+    }
+
+    fn density(
+        &self,
+        description: DensityDescription,
+        boundary: &dyn Hitable,
+        span: &Range<usize>,
+    ) -> Result<Arc<dyn DensityField>, SceneError> {
+        match description {
+            DensityDescription::Grid {
+                resolution,
+                values,
+                min,
+                max,
+            } => {
+                if resolution.contains(&0)
+                    || values.len() != resolution[0] * resolution[1] * resolution[2]
+                {
+                    return Err(self.error(
+                        span,
+                        format!(
+                            "density grid of resolution {:?} needs {} values, got {}",
+                            resolution,
+                            resolution[0] * resolution[1] * resolution[2],
+                            values.len()
+                        ),
+                    ));
+                }
+                let bbox = boundary.bounding_box(0.0, 1.0);
+                let (min, max) = match (min, max, bbox) {
+                    (Some(min), Some(max), _) => (to_vec3(min), to_vec3(max)),
+                    (None, None, Some(bbox)) => (bbox.min, bbox.max),
+                    _ => {
+                        return Err(self.error(
+                            span,
+                            "density grid needs both min and max, or a bounded boundary"
+                                .to_string(),
+                        ))
+                    }
+                };
+                Ok(Arc::new(GridDensity::new(resolution, values, min, max)))
+            }
+            DensityDescription::VoxelFile { path } => {
+                match GridDensity::load(&self.base_dir.join(&path)) {
+                    Ok(grid) => Ok(Arc::new(grid)),
+                    Err(err) => Err(self.error(
+                        span,
+                        format!("failed to load voxel file '{}': {}", path, err),
+                    )),
+                }
+            }
+            DensityDescription::Noise { scale, density } => {
+                Ok(Arc::new(NoiseDensity::new(scale, density)))
+            }
+        }
+    }
+
+    fn hitable(
+        &mut self,
+        description: HitableDescription,
+        span: &Range<usize>,
+    ) -> Result<Box<dyn Hitable>, SceneError> {
+        Ok(match description {
+            HitableDescription::Sphere {
+                center,
+                radius,
+                material,
+            } => Box::new(Sphere {
+                center: to_vec3(center),
+                radius,
+                material: self.material(&material, span)?,
+            }),
+            HitableDescription::MovingSphere {
+                center0,
+                center1,
+                time0,
+                time1,
+                radius,
+                material,
+            } => Box::new(MovingSphere {
+                center0: to_vec3(center0),
+                center1: to_vec3(center1),
+                time0,
+                time1,
+                radius,
+                material: self.material(&material, span)?,
+            }),
+            HitableDescription::XYRect {
+                x0,
+                x1,
+                y0,
+                y1,
+                k,
+                material,
+            } => Box::new(XYRect {
+                x0,
+                x1,
+                y0,
+                y1,
+                k,
+                material: self.material(&material, span)?,
+            }),
+            HitableDescription::XZRect {
+                x0,
+                x1,
+                z0,
+                z1,
+                k,
+                material,
+            } => Box::new(XZRect {
+                x0,
+                x1,
+                z0,
+                z1,
+                k,
+                material: self.material(&material, span)?,
+            }),
+            HitableDescription::YZRect {
+                y0,
+                y1,
+                z0,
+                z1,
+                k,
+                material,
+            } => Box::new(YZRect {
+                y0,
+                y1,
+                z0,
+                z1,
+                k,
+                material: self.material(&material, span)?,
+            }),
+            HitableDescription::BoxHitable { min, max, material } => Box::new(BoxHitable::new(
+                &to_vec3(min),
+                &to_vec3(max),
+                self.material(&material, span)?,
+            )),
+            HitableDescription::ConstantMedium {
+                boundary,
+                density,
+                albedo,
+                sigma_a,
+                sigma_s,
+                g,
+            } => {
+                if g <= -1.0 || g >= 1.0 {
+                    return Err(self.error(span, format!("g must be in (-1, 1), got {}", g)));
+                }
+                let boundary = self.hitable(*boundary, span)?;
+                match (density, albedo, sigma_a, sigma_s) {
+                    (Some(density), Some(albedo), None, None) => Box::new(ConstantMedium::new(
+                        boundary,
+                        density,
+                        phase_function(self.texture(&albedo, span)?, g),
+                    )),
+                    (None, None, Some(sigma_a), Some(sigma_s)) => {
+                        Box::new(ConstantMedium::with_coefficients(
+                            boundary,
+                            to_vec3(sigma_a),
+                            to_vec3(sigma_s),
+                            g,
+                        ))
+                    }
+                    _ => {
+                        return Err(self.error(
+                            span,
+                            "constant_medium needs either density and albedo or sigma_a and \
+                             sigma_s"
+                                .to_string(),
+                        ))
+                    }
+                }
+            }
+            HitableDescription::HeterogeneousMedium {
+                boundary,
+                density,
+                sigma_a,
+                sigma_s,
+                g,
+            } => {
+                if g <= -1.0 || g >= 1.0 {
+                    return Err(self.error(span, format!("g must be in (-1, 1), got {}", g)));
+                }
+                let boundary = self.hitable(*boundary, span)?;
+                let density = self.density(density, &*boundary, span)?;
+                Box::new(HeterogeneousMedium::new(
+                    boundary,
+                    density,
+                    to_vec3(sigma_a),
+                    to_vec3(sigma_s),
+                    g,
+                ))
+            }
+            HitableDescription::Translate { offset, hitable } => Box::new(Translate {
+                offset: to_vec3(offset),
+                hitable: self.hitable(*hitable, span)?,
+            }),
+            HitableDescription::RotateY { angle, hitable } => {
+                Box::new(RotateY::new(self.hitable(*hitable, span)?, angle))
+            }
+            HitableDescription::FlipNormals { hitable } => {
+                Box::new(FlipNormals(self.hitable(*hitable, span)?))
+            }
+            HitableDescription::Mesh { path, material } => {
+                let material = match material {
+                    Some(material) => Some(self.material(&material, span)?),
+                    None => None,
+                };
+                let mesh = load_obj(&self.base_dir.join(&path), material)
+                    .map_err(|err| self.error(span, format!("failed to load mesh: {}", err)))?;
+                Box::new(TriangleMesh::new(mesh))
+            }
+        })
+    }
+
+    fn environment(&self, value: &Spanned<toml::Value>) -> Result<Environment, SceneError> {
+        let span = value.span();
+        let description: EnvironmentDescription = self.decode(value)?;
+        match (description.color, description.map) {
+            (Some(color), None) => Ok(Environment::Constant(
+                description.intensity * to_vec3(color),
+            )),
+            (None, Some(path)) => {
+                match EnvironmentMap::load(&self.base_dir.join(&path), description.intensity) {
+                    Ok(map) => Ok(Environment::Map(Arc::new(map))),
+                    Err(err) => Err(self.error(
+                        &span,
+                        format!("failed to load environment map '{}': {}", path, err),
+                    )),
+                }
+            }
+            _ => Err(self.error(
+                &span,
+                "environment needs either a color or a map".to_string(),
+            )),
+        }
+    }
+
+    fn hitables(
+        &mut self,
+        values: &[Spanned<toml::Value>],
+    ) -> Result<Vec<Box<dyn Hitable>>, SceneError> {
+        values
+            .iter()
+            .map(|value| {
+                let description = self.decode(value)?;
+                self.hitable(description, &value.span())
+            })
+            .collect()
+    }
+}
+
+fn line_at(source: &str, offset: usize) -> usize {
+    source[..offset.min(source.len())].matches('\n').count() + 1
+}
+
+// Image textures and meshes are resolved relative to base_dir
+pub fn parse_scene(source: &str, base_dir: &Path, aspect: f32) -> Result<Scene, SceneError> {
+    let description: SceneDescription = toml::from_str(source).map_err(|err| SceneError {
+        line: err.span().map(|span| line_at(source, span.start)),
+        message: err.message().to_string(),
+    })?;
+
+    let mut builder = SceneBuilder {
+        source,
+        base_dir,
+        texture_descriptions: &description.textures,
+        material_descriptions: &description.materials,
+        textures: HashMap::new(),
+        materials: HashMap::new(),
+        texture_stack: Vec::new(),
+    };
+    let world = builder.hitables(&description.objects)?;
+    let mut lights = builder.hitables(&description.lights)?;
+    let environment = match &description.environment {
+        Some(value) => builder.environment(value)?,
+        None => Environment::default(),
+    };
+    if !environment.is_black() {
+        lights.push(Box::new(environment.clone()));
+    }
+
+    let camera = &description.camera;
+    Ok(Scene {
+        world,
+        lights,
+        environment,
+        camera: Camera::new(
+            to_vec3(camera.look_from),
+            to_vec3(camera.look_at),
+            to_vec3(camera.up),
+            camera.vertical_fov,
+            aspect,
+            camera.aperture,
+            camera.focus_dist,
+            camera.time0,
+            camera.time1,
+        ),
+    })
+}
+
+pub fn load_scene<P: AsRef<Path>>(path: P, aspect: f32) -> Result<Scene, SceneError> {
+    let path = path.as_ref();
+    let source = std::fs::read_to_string(path).map_err(|err| SceneError {
+        line: None,
+        message: format!("failed to read '{}': {}", path.display(), err),
+    })?;
+    parse_scene(
+        &source,
+        path.parent().unwrap_or_else(|| Path::new("")),
+        aspect,
+    )
+}
@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::hitable::MeshData;
+use crate::material::*;
+use crate::math::*;
+use crate::texture::*;
+
+// Wavefront OBJ meshes. Polygons are triangulated as fans and materials come from the
+// MTL libraries the file references, mapped to the closest material we have:
+// an emissive Ke makes a DiffuseLight, illum 4, 6, 7 or a transparent d/Tr a Dielectric,
+// illum 3 or 5 a Metal and everything else a Lambertian with Kd or map_Kd as albedo.
+// Faces without a material, or every face when override_material is given, use
+// override_material or a grey Lambertian
+pub fn load_obj(
+    path: &Path,
+    override_material: Option<Box<dyn Material>>,
+) -> Result<MeshData, String> {
+    let source = std::fs::read_to_string(path)
+        .map_err(|err| format!("failed to read '{}': {}", path.display(), err))?;
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut uvs = Vec::new();
+
+    let mut mesh = MeshData {
+        positions: Vec::new(),
+        normals: Vec::new(),
+        uvs: Vec::new(),
+        indices: Vec::new(),
+        material_indices: Vec::new(),
+        materials: vec![override_material.clone().unwrap_or_else(|| {
+            Box::new(Lambertian {
+                albedo: Box::new(ConstantTexture(vec3(0.5, 0.5, 0.5))),
+            })
+        })],
+    };
+    // Vertices are unique combinations of position, uv and normal indices
+    let mut vertices: HashMap<(usize, Option<usize>, Option<usize>), usize> = HashMap::new();
+    let mut library: HashMap<String, Box<dyn Material>> = HashMap::new();
+    // Materials already added to the mesh, by name
+    let mut material_slots: HashMap<String, usize> = HashMap::new();
+    let mut current_material = 0;
+    let mut has_uvs = true;
+    let mut has_normals = true;
+
+    for (line_index, line) in source.lines().enumerate() {
+        let error = |message: String| format!("{}:{}: {}", path.display(), line_index + 1, message);
+        let line = line.split('#').next().unwrap();
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let arguments = tokens.collect::<Vec<&str>>();
+        match keyword {
+            "v" => positions.push(parse_vec3(&arguments).map_err(error)?),
+            "vn" => normals.push(parse_vec3(&arguments).map_err(error)?),
+            "vt" => {
+                let uv = parse_floats(&arguments, 2).map_err(error)?;
+                uvs.push((uv[0], uv[1]));
+            }
+            "f" => {
+                if arguments.len() < 3 {
+                    return Err(error(String::from("faces need at least three vertices")));
+                }
+                let mut face = Vec::with_capacity(arguments.len());
+                for argument in &arguments {
+                    let (p, t, n) =
+                        parse_face_vertex(argument, positions.len(), uvs.len(), normals.len())
+                            .map_err(error)?;
+                    has_uvs &= t.is_some();
+                    has_normals &= n.is_some();
+                    let next_index = vertices.len();
+                    let index = *vertices.entry((p, t, n)).or_insert_with(|| {
+                        mesh.positions.push(positions[p]);
+                        mesh.uvs.push(t.map_or((0.0, 0.0), |t| uvs[t]));
+                        mesh.normals.push(n.map_or(Vec3::zero(), |n| normals[n]));
+                        next_index
+                    });
+                    face.push(index);
+                }
+                for i in 1..face.len() - 1 {
+                    mesh.indices.push([face[0], face[i], face[i + 1]]);
+                    mesh.material_indices.push(current_material);
+                }
+            }
+            "mtllib" => {
+                if override_material.is_some() {
+                    continue;
+                }
+                for file in &arguments {
+                    library.extend(load_mtl(&base_dir.join(file))?);
+                }
+            }
+            "usemtl" => {
+                if override_material.is_some() {
+                    continue;
+                }
+                let name = arguments.join(" ");
+                current_material = match material_slots.get(&name) {
+                    Some(slot) => *slot,
+                    None => {
+                        let material = match library.get(&name) {
+                            Some(material) => material.clone(),
+                            None => return Err(error(format!("unknown material '{}'", name))),
+                        };
+                        mesh.materials.push(material);
+                        material_slots.insert(name, mesh.materials.len() - 1);
+                        mesh.materials.len() - 1
+                    }
+                };
+            }
+            // Groups, smoothing groups, lines and the like don't matter for rendering
+            _ => {}
+        }
+    }
+
+    // Attributes are only used when every vertex has them
+    if !has_uvs {
+        mesh.uvs.clear();
+    }
+    if !has_normals {
+        mesh.normals.clear();
+    }
+    Ok(mesh)
+}
+
+fn parse_floats(arguments: &[&str], count: usize) -> Result<Vec<f32>, String> {
+    if arguments.len() < count {
+        return Err(format!(
+            "expected {} numbers, found {}",
+            count,
+            arguments.len()
+        ));
+    }
+    arguments[..count]
+        .iter()
+        .map(|argument| {
+            argument
+                .parse::<f32>()
+                .map_err(|_| format!("'{}' is not a number", argument))
+        })
+        .collect()
+}
+
+fn parse_vec3(arguments: &[&str]) -> Result<Vec3, String> {
+    let v = parse_floats(arguments, 3)?;
+    Ok(vec3(v[0], v[1], v[2]))
+}
+
+// OBJ indices start at 1, negative ones count back from the last element read so far
+fn parse_index(token: &str, count: usize) -> Result<usize, String> {
+    let index = token
+        .parse::<i64>()
+        .map_err(|_| format!("'{}' is not an index", token))?;
+    let resolved = if index < 0 {
+        count as i64 + index
+    } else {
+        index - 1
+    };
+    if resolved < 0 || resolved >= count as i64 {
+        return Err(format!("index {} is out of range", index));
+    }
+    Ok(resolved as usize)
+}
+
+// Face vertices are v, v/vt, v//vn or v/vt/vn
+fn parse_face_vertex(
+    token: &str,
+    position_count: usize,
+    uv_count: usize,
+    normal_count: usize,
+) -> Result<(usize, Option<usize>, Option<usize>), String> {
+    let mut parts = token.split('/');
+    let p = parse_index(parts.next().unwrap(), position_count)?;
+    let t = match parts.next() {
+        Some(part) if !part.is_empty() => Some(parse_index(part, uv_count)?),
+        _ => None,
+    };
+    let n = match parts.next() {
+        Some(part) if !part.is_empty() => Some(parse_index(part, normal_count)?),
+        _ => None,
+    };
+    Ok((p, t, n))
+}
+
+#[derive(Default)]
+struct MtlDescription {
+    diffuse: Option<Vec3>,
+    diffuse_map: Option<String>,
+    specular: Option<Vec3>,
+    emission: Option<Vec3>,
+    shininess: Option<f32>,
+    ref_index: Option<f32>,
+    dissolve: Option<f32>,
+    illum: Option<i32>,
+}
+
+impl MtlDescription {
+    fn build(&self, base_dir: &Path) -> Result<Box<dyn Material>, String> {
+        if let Some(emission) = self.emission.filter(|e| *e != Vec3::zero()) {
+            return Ok(Box::new(DiffuseLight {
+                emit: Box::new(ConstantTexture(emission)),
+            }));
+        }
+        if matches!(self.illum, Some(4) | Some(6) | Some(7))
+            || self.dissolve.is_some_and(|d| d < 1.0)
+        {
+            return Ok(Box::new(Dielectric {
+                ref_index: self.ref_index.unwrap_or(1.5),
+            }));
+        }
+        if matches!(self.illum, Some(3) | Some(5)) {
+            // Blinn-Phong exponent to a roughness
+            let fuzz = self
+                .shininess
+                .map_or(0.0, |ns| (2.0 / (ns + 2.0)).sqrt().min(1.0));
+            return Ok(Box::new(Metal {
+                albedo: self.specular.unwrap_or_else(|| vec3(1.0, 1.0, 1.0)),
+                fuzz,
+            }));
+        }
+        let albedo: Box<dyn Texture> = match &self.diffuse_map {
+            Some(map) => match image::open(base_dir.join(map)) {
+                Ok(img) => Box::new(ImageTexture::new(img)),
+                Err(err) => return Err(format!("failed to load image '{}': {}", map, err)),
+            },
+            None => Box::new(ConstantTexture(
+                self.diffuse.unwrap_or_else(|| vec3(0.5, 0.5, 0.5)),
+            )),
+        };
+        Ok(Box::new(Lambertian { albedo }))
+    }
+}
+
+fn load_mtl(path: &Path) -> Result<HashMap<String, Box<dyn Material>>, String> {
+    let source = std::fs::read_to_string(path)
+        .map_err(|err| format!("failed to read '{}': {}", path.display(), err))?;
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut descriptions: Vec<(String, MtlDescription)> = Vec::new();
+    for (line_index, line) in source.lines().enumerate() {
+        let error = |message: String| format!("{}:{}: {}", path.display(), line_index + 1, message);
+        let line = line.split('#').next().unwrap();
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let arguments = tokens.collect::<Vec<&str>>();
+        if keyword == "newmtl" {
+            descriptions.push((arguments.join(" "), MtlDescription::default()));
+            continue;
+        }
+        let description = match descriptions.last_mut() {
+            Some((_, description)) => description,
+            None => return Err(error(format!("'{}' before any newmtl", keyword))),
+        };
+        match keyword {
+            "Kd" => description.diffuse = Some(parse_vec3(&arguments).map_err(error)?),
+            "Ks" => description.specular = Some(parse_vec3(&arguments).map_err(error)?),
+            "Ke" => description.emission = Some(parse_vec3(&arguments).map_err(error)?),
+            "Ns" => description.shininess = Some(parse_floats(&arguments, 1).map_err(error)?[0]),
+            "Ni" => description.ref_index = Some(parse_floats(&arguments, 1).map_err(error)?[0]),
+            "d" => description.dissolve = Some(parse_floats(&arguments, 1).map_err(error)?[0]),
+            "Tr" => {
+                description.dissolve = Some(1.0 - parse_floats(&arguments, 1).map_err(error)?[0])
+            }
+            "illum" => {
+                description.illum = Some(
+                    arguments
+                        .first()
+                        .and_then(|illum| illum.parse().ok())
+                        .ok_or_else(|| error(String::from("expected an illumination model")))?,
+                )
+            }
+            // The file name is the last argument, after any texture options
+            "map_Kd" => description.diffuse_map = arguments.last().map(|map| map.to_string()),
+            // Statements for features we can't render are skipped
+            _ => {}
+        }
+    }
+
+    descriptions
+        .iter()
+        .map(|(name, description)| Ok((name.clone(), description.build(base_dir)?)))
+        .collect()
+}
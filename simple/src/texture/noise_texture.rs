@@ -1,6 +1,6 @@
 use crate::math::Vec3;
 use crate::math::*;
-use crate::random::random_float;
+use crate::random::Rng;
 use crate::texture::Texture;
 
 #[derive(Clone)]
@@ -29,6 +29,7 @@ fn turb(mut p: Vec3, depth: i32) -> f32 {
     accum.abs()
 }
 
+#[allow(clippy::needless_range_loop)]
 fn trilinear_intepolation(c: &[[[Vec3; 2]; 2]; 2], u: f32, v: f32, w: f32) -> f32 {
     let uu = u * u * (3.0 - 2.0 * u);
     let vv = v * v * (3.0 - 2.0 * v);
@@ -71,38 +72,41 @@ fn perlin_noise(p: &Vec3) -> f32 {
 
 type NoiseData = [usize; 256];
 
-fn permute(p: &mut NoiseData) {
+fn permute(p: &mut NoiseData, rng: &mut Rng) {
     for i in (0..p.len()).rev() {
-        let target = (random_float() * (i + 1) as f32) as usize;
+        let target = (rng.float() * (i + 1) as f32) as usize;
         p.swap(i, target);
     }
 }
 
-fn perlin_generate_permutation() -> NoiseData {
+fn perlin_generate_permutation(seed: u64) -> NoiseData {
+    let mut rng = Rng::new(seed);
     let mut result: NoiseData = [0; 256];
-    for i in 0..256 {
-        result[i] = i;
+    for (i, value) in result.iter_mut().enumerate() {
+        *value = i;
     }
-    permute(&mut result);
+    permute(&mut result, &mut rng);
     result
 }
 
-fn perlin_generate() -> [Vec3; 256] {
+fn perlin_generate(seed: u64) -> [Vec3; 256] {
+    let mut rng = Rng::new(seed);
     let mut result: [Vec3; 256] = [Vec3::zero(); 256];
-    for i in 0..256 {
-        result[i] = vec3(
-            -1.0 + 2.0 * random_float(),
-            -1.0 + 2.0 * random_float(),
-            -1.0 + 2.0 * random_float(),
+    for value in result.iter_mut() {
+        *value = vec3(
+            -1.0 + 2.0 * rng.float(),
+            -1.0 + 2.0 * rng.float(),
+            -1.0 + 2.0 * rng.float(),
         )
         .normalize();
     }
     result
 }
 
+// Fixed seeds so the noise is the same in every render
 lazy_static! {
-    static ref PERLIN_PERMUTATION_X: NoiseData = perlin_generate_permutation();
-    static ref PERLIN_PERMUTATION_Y: NoiseData = perlin_generate_permutation();
-    static ref PERLIN_PERMUTATION_Z: NoiseData = perlin_generate_permutation();
-    static ref PERLIN_RANDOM_FLOAT: [Vec3; 256] = perlin_generate();
+    static ref PERLIN_PERMUTATION_X: NoiseData = perlin_generate_permutation(1);
+    static ref PERLIN_PERMUTATION_Y: NoiseData = perlin_generate_permutation(2);
+    static ref PERLIN_PERMUTATION_Z: NoiseData = perlin_generate_permutation(3);
+    static ref PERLIN_RANDOM_FLOAT: [Vec3; 256] = perlin_generate(4);
 }
@@ -0,0 +1,96 @@
+use crate::math::*;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ToneMapper {
+    // Clip everything above 1.0
+    Clamp,
+    Reinhard,
+    // Narkowicz's fit of the ACES filmic curve
+    Aces,
+}
+
+impl ToneMapper {
+    pub const ALL: [ToneMapper; 3] = [ToneMapper::Clamp, ToneMapper::Reinhard, ToneMapper::Aces];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ToneMapper::Clamp => "clamp",
+            ToneMapper::Reinhard => "reinhard",
+            ToneMapper::Aces => "aces",
+        }
+    }
+
+    // Maps scene referred radiance to [0, 1]
+    fn apply(self, x: f32) -> f32 {
+        let mapped = match self {
+            ToneMapper::Clamp => x,
+            ToneMapper::Reinhard => x / (1.0 + x),
+            ToneMapper::Aces => (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14),
+        };
+        mapped.clamp(0.0, 1.0)
+    }
+}
+
+impl std::str::FromStr for ToneMapper {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<ToneMapper, String> {
+        ToneMapper::ALL
+            .iter()
+            .copied()
+            .find(|tone_mapper| tone_mapper.name() == name)
+            .ok_or_else(|| {
+                format!(
+                    "unknown tone mapper '{}', expected one of: {}",
+                    name,
+                    ToneMapper::ALL
+                        .iter()
+                        .map(|tone_mapper| tone_mapper.name())
+                        .collect::<Vec<&str>>()
+                        .join(", ")
+                )
+            })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ToneMapping {
+    pub tone_mapper: ToneMapper,
+    // In stops, every stop doubles the brightness
+    pub exposure: f32,
+}
+
+impl Default for ToneMapping {
+    fn default() -> Self {
+        ToneMapping {
+            tone_mapper: ToneMapper::Clamp,
+            exposure: 0.0,
+        }
+    }
+}
+
+impl ToneMapping {
+    // Linear radiance to 8 bit sRGB
+    pub fn map(&self, color: Vec3) -> [u8; 3] {
+        let scale = 2.0_f32.powf(self.exposure);
+        let channel = |c: f32| {
+            // NaNs and negative values from broken samples show up as black, huge values are
+            // capped so the operators don't overflow
+            let c = if c.is_nan() {
+                0.0
+            } else {
+                (c * scale).clamp(0.0, 1.0e6)
+            };
+            (255.0 * linear_to_srgb(self.tone_mapper.apply(c)) + 0.5) as u8
+        };
+        [channel(color.x), channel(color.y), channel(color.z)]
+    }
+}
+
+pub fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        12.92 * value
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
@@ -0,0 +1,108 @@
+// Renders only depend on the sampler settings, so the same settings have to give the same
+// image bit for bit, whether the tiles are rendered in order or spread over the rayon
+// workers of the parallel feature
+use garage_ray_simple::*;
+use std::sync::Mutex;
+
+const WIDTH: u32 = 48;
+const HEIGHT: u32 = 40;
+const SAMPLES: i32 = 4;
+const MAX_DEPTH: i32 = 8;
+
+fn settings(pattern: SamplePattern, seed: u64) -> SamplerSettings {
+    SamplerSettings {
+        pattern,
+        seed,
+        samples_per_pixel: SAMPLES,
+    }
+}
+
+// Sums of the samples of every pixel through render_tiles, in row order
+fn render(scene: &Scene, world: &dyn Hitable, settings: &SamplerSettings) -> Vec<Vec3> {
+    let frame = Mutex::new(vec![Vec3::zero(); (WIDTH * HEIGHT) as usize]);
+    let finished = render_tiles(
+        WIDTH,
+        HEIGHT,
+        settings,
+        0,
+        SAMPLES,
+        MAX_DEPTH,
+        world,
+        &scene.lights,
+        &scene.environment,
+        &scene.camera,
+        &|tile, sums| {
+            let mut frame = frame.lock().unwrap();
+            for ((x, y), sum) in tile.pixels().zip(sums) {
+                frame[(y * WIDTH + x) as usize] = sum;
+            }
+            true
+        },
+    );
+    assert!(finished);
+    frame.into_inner().unwrap()
+}
+
+// The same pixels one at a time on this thread
+fn render_in_order(scene: &Scene, world: &dyn Hitable, settings: &SamplerSettings) -> Vec<Vec3> {
+    (0..HEIGHT)
+        .flat_map(|y| (0..WIDTH).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            sample_pixel(
+                x,
+                y,
+                WIDTH,
+                HEIGHT,
+                settings,
+                0,
+                SAMPLES,
+                MAX_DEPTH,
+                world,
+                &scene.lights,
+                &scene.environment,
+                &scene.camera,
+            )
+        })
+        .collect()
+}
+
+fn bits(frame: &[Vec3]) -> Vec<[u32; 3]> {
+    frame
+        .iter()
+        .map(|c| [c.x.to_bits(), c.y.to_bits(), c.z.to_bits()])
+        .collect()
+}
+
+fn load(name: &str) -> (Scene, BVHNode) {
+    let mut scene = builtin_scene(name, WIDTH as f32 / HEIGHT as f32).unwrap();
+    let world = BVHNode::build(std::mem::take(&mut scene.world), 0.0, 1.0);
+    (scene, world)
+}
+
+#[test]
+fn renders_are_reproducible() {
+    for name in &["cornel_box", "cornel_smoke"] {
+        let (scene, world) = load(name);
+        for &pattern in &SamplePattern::ALL {
+            let settings = settings(pattern, 11);
+            let first = bits(&render(&scene, &world, &settings));
+            let second = bits(&render(&scene, &world, &settings));
+            assert!(first == second, "{} with {} differs", name, pattern.name());
+            let in_order = bits(&render_in_order(&scene, &world, &settings));
+            assert!(
+                first == in_order,
+                "{} with {} depends on the tile order",
+                name,
+                pattern.name()
+            );
+        }
+    }
+}
+
+#[test]
+fn seed_changes_the_render() {
+    let (scene, world) = load("cornel_box");
+    let first = bits(&render(&scene, &world, &settings(SamplePattern::Sobol, 1)));
+    let second = bits(&render(&scene, &world, &settings(SamplePattern::Sobol, 2)));
+    assert!(first != second);
+}
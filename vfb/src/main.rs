@@ -1,63 +1,277 @@
 use garage_ray_simple::*;
 
-struct PixelRow {
-    y: u32,
-    data: Vec<(u8, u8, u8)>,
+use std::sync::mpsc::{Receiver, Sender, TryRecvError};
+use std::sync::Mutex;
+
+const WIDTH: u32 = 800;
+const HEIGHT: u32 = 800;
+// Samples per pixel added by every pass over the image
+const SAMPLES_PER_PASS: i32 = 2;
+const MAX_SAMPLES: i32 = 1000;
+const MAX_DEPTH: i32 = 50;
+// Restarting a render with the same seed gives the same image
+const SEED: u64 = 0;
+
+#[derive(Clone, Copy, PartialEq)]
+enum RenderCommand {
+    Start,
+    Pause,
+    Stop,
+    Restart,
+    // Starts over with another sample pattern
+    SetSamplePattern(SamplePattern),
 }
 
-fn render_image(sender: std::sync::mpsc::Sender<PixelRow>) {
-    let now = std::time::Instant::now();
-    let width = 800;
-    let height = 800;
-    let samples = 1000;
-
-    //let world = random_scene();
-    //let world = two_perlin_spheres();
-    //let world = simple_light();
-    let (world, light_shape) = cornel_box();
-    //let world = cornel_smoke();
-    //let world = final_scene();
-    let accelerated_world = BVHNode::build(world, 0.0, 1.0);
-    //let accelerated_world = world;
-
-    //let look_from = vec3(478.0, 278.0, -600.0);
-    let look_from = vec3(278.0, 278.0, -800.0);
-    let look_at = vec3(278.0, 278.0, 0.0);
-    let camera = Camera::new(
-        look_from,
-        look_at,
-        vec3(0.0, 1.0, 0.0),
-        40.0,
-        width as f32 / height as f32,
-        0.0,
-        10.0,
-        0.0,
-        1.0,
-    );
+#[derive(Clone, Copy, PartialEq)]
+enum RenderState {
+    Rendering,
+    Paused,
+    Stopped,
+}
+
+enum RenderUpdate {
+    // Running average of a tile in linear radiance, including the pass in progress
+    Tile { tile: Tile, pixels: Vec<Vec3> },
+    // Every pixel has this many samples now
+    Pass { samples: i32 },
+    // Whole image without the samples of an interrupted pass
+    Frame { samples: i32, frame: FrameBuffer },
+    Finished,
+}
 
-    for y in 0..800 {
-        let mut row_data = Vec::with_capacity(800);
-        for x in 0..800 {
-            let (ir, ig, ib) = evaluate_pixel(
-                x,
-                y,
-                width,
-                height,
-                samples,
-                &accelerated_world,
-                &light_shape,
-                &camera,
-            );
-            //*pixel = image::Rgb([ir, ig, ib]);
-            row_data.push((ir, ig, ib));
+fn load_scene_from_args(aspect: f32) -> Scene {
+    // The first argument is either the name of a built-in scene or a path to a scene file
+    let name = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| String::from("cornel_box"));
+    let scene = if BUILTIN_SCENES.contains(&name.as_str()) {
+        builtin_scene(&name, aspect)
+    } else {
+        load_scene(&name, aspect).map_err(|err| err.to_string())
+    };
+    match scene {
+        Ok(scene) => scene,
+        Err(err) => {
+            eprintln!("Failed to load scene '{}': {}", name, err);
+            eprintln!("Built-in scenes are: {}", BUILTIN_SCENES.join(", "));
+            std::process::exit(1);
         }
-        if let Err(_) = sender.send(PixelRow { y, data: row_data }) {
-            println!("Render interrupted at {} seconds", now.elapsed().as_secs());
-            return;
+    }
+}
+
+// Reasons for abandoning a pass before it is complete
+enum Interrupt {
+    Stop,
+    Restart,
+    SetSamplePattern(SamplePattern),
+    Quit,
+}
+
+// Handles the commands sent since the last check. Pausing blocks until rendering is
+// resumed or the pass is interrupted
+fn check_commands(commands: &Receiver<RenderCommand>) -> Option<Interrupt> {
+    let mut paused = false;
+    loop {
+        let command = if paused {
+            commands.recv().map_err(|_| TryRecvError::Disconnected)
+        } else {
+            commands.try_recv()
+        };
+        match command {
+            Ok(RenderCommand::Start) => paused = false,
+            Ok(RenderCommand::Pause) => paused = true,
+            Ok(RenderCommand::Stop) => return Some(Interrupt::Stop),
+            Ok(RenderCommand::Restart) => return Some(Interrupt::Restart),
+            Ok(RenderCommand::SetSamplePattern(pattern)) => {
+                return Some(Interrupt::SetSamplePattern(pattern))
+            }
+            Err(TryRecvError::Empty) => return None,
+            Err(TryRecvError::Disconnected) => return Some(Interrupt::Quit),
+        }
+    }
+}
+
+struct ProgressiveRender {
+    world: BVHNode,
+    lights: Vec<Box<dyn Hitable>>,
+    environment: Environment,
+    camera: Camera,
+    sampler_settings: SamplerSettings,
+    // Sum of all samples taken so far for every pixel
+    accumulation: Vec<Vec3>,
+    samples: i32,
+}
+
+impl ProgressiveRender {
+    fn new(scene: Scene) -> ProgressiveRender {
+        ProgressiveRender {
+            world: BVHNode::build(scene.world, 0.0, 1.0),
+            lights: scene.lights,
+            environment: scene.environment,
+            camera: scene.camera,
+            // Stratified patterns are spread over all samples the render will take
+            sampler_settings: SamplerSettings {
+                pattern: SamplePattern::Sobol,
+                seed: SEED,
+                samples_per_pixel: MAX_SAMPLES,
+            },
+            accumulation: vec![Vec3::zero(); (WIDTH * HEIGHT) as usize],
+            samples: 0,
+        }
+    }
+
+    fn reset(&mut self) {
+        for color in self.accumulation.iter_mut() {
+            *color = Vec3::zero();
+        }
+        self.samples = 0;
+    }
+
+    // Adds SAMPLES_PER_PASS samples to every pixel, sending tiles to the viewer as they
+    // finish. Commands are checked after every tile. The accumulation only changes once the
+    // whole pass is done, so an interrupted pass leaves no trace
+    fn render_pass(
+        &mut self,
+        commands: &Mutex<Receiver<RenderCommand>>,
+        updates: &Sender<RenderUpdate>,
+    ) -> Result<(), Interrupt> {
+        let pass = Mutex::new(vec![Vec3::zero(); self.accumulation.len()]);
+        let interrupt = Mutex::new(None);
+        let accumulation = &self.accumulation;
+        let inv_samples = 1.0 / (self.samples + SAMPLES_PER_PASS) as f32;
+        let finished = render_tiles(
+            WIDTH,
+            HEIGHT,
+            &self.sampler_settings,
+            self.samples,
+            SAMPLES_PER_PASS,
+            MAX_DEPTH,
+            &self.world,
+            &self.lights,
+            &self.environment,
+            &self.camera,
+            &|tile, sums| {
+                let mut pixels = Vec::with_capacity(sums.len());
+                {
+                    let mut pass = pass.lock().unwrap();
+                    for ((x, y), sum) in tile.pixels().zip(sums) {
+                        let index = (y * WIDTH + x) as usize;
+                        pass[index] = sum;
+                        pixels.push((accumulation[index] + sum) * inv_samples);
+                    }
+                }
+                if updates.send(RenderUpdate::Tile { tile, pixels }).is_err() {
+                    *interrupt.lock().unwrap() = Some(Interrupt::Quit);
+                    return false;
+                }
+                // Pausing blocks here, the other workers wait for the lock once their
+                // tiles are done
+                match check_commands(&commands.lock().unwrap()) {
+                    Some(command) => {
+                        *interrupt.lock().unwrap() = Some(command);
+                        false
+                    }
+                    None => true,
+                }
+            },
+        );
+        if !finished {
+            return Err(interrupt.into_inner().unwrap().unwrap_or(Interrupt::Quit));
+        }
+
+        for (sum, color) in self.accumulation.iter_mut().zip(pass.into_inner().unwrap()) {
+            *sum += color;
         }
+        self.samples += SAMPLES_PER_PASS;
+        Ok(())
     }
 
-    println!("Render took {} seconds", now.elapsed().as_secs());
+    fn average(&self) -> FrameBuffer {
+        let inv_samples = 1.0 / self.samples as f32;
+        let mut frame = FrameBuffer::new(WIDTH, HEIGHT);
+        for (color, sum) in frame.pixels_mut().iter_mut().zip(&self.accumulation) {
+            *color = sum * inv_samples;
+        }
+        frame
+    }
+}
+
+// Renders until MAX_SAMPLES are reached, sending tiles as they finish
+fn render_image(commands: Receiver<RenderCommand>, updates: Sender<RenderUpdate>, scene: Scene) {
+    // Locked by whichever render thread checks for commands
+    let commands = Mutex::new(commands);
+    let mut render = ProgressiveRender::new(scene);
+    let mut rendering = true;
+    let mut now = std::time::Instant::now();
+    loop {
+        if !rendering {
+            // Wait until we are asked to start over
+            let command = commands.lock().unwrap().recv();
+            match command {
+                Ok(RenderCommand::Start) | Ok(RenderCommand::Restart) => {
+                    render.reset();
+                    rendering = true;
+                    now = std::time::Instant::now();
+                }
+                // Used when the render is started again
+                Ok(RenderCommand::SetSamplePattern(pattern)) => {
+                    render.sampler_settings.pattern = pattern;
+                }
+                Ok(_) => {}
+                Err(_) => return,
+            }
+            continue;
+        }
+
+        match render.render_pass(&commands, &updates) {
+            Ok(()) => {
+                let update = RenderUpdate::Pass {
+                    samples: render.samples,
+                };
+                if updates.send(update).is_err() {
+                    return;
+                }
+                if render.samples >= MAX_SAMPLES {
+                    println!("Render took {} seconds", now.elapsed().as_secs());
+                    rendering = false;
+                    if updates.send(RenderUpdate::Finished).is_err() {
+                        return;
+                    }
+                }
+            }
+            Err(Interrupt::Stop) => {
+                println!(
+                    "Render stopped after {} seconds with {} samples per pixel",
+                    now.elapsed().as_secs(),
+                    render.samples
+                );
+                rendering = false;
+                // Drop the tiles of the unfinished pass from the display
+                if render.samples > 0 {
+                    let update = RenderUpdate::Frame {
+                        samples: render.samples,
+                        frame: render.average(),
+                    };
+                    if updates.send(update).is_err() {
+                        return;
+                    }
+                }
+            }
+            Err(Interrupt::Restart) => {
+                render.reset();
+                now = std::time::Instant::now();
+            }
+            Err(Interrupt::SetSamplePattern(pattern)) => {
+                render.sampler_settings.pattern = pattern;
+                render.reset();
+                now = std::time::Instant::now();
+            }
+            Err(Interrupt::Quit) => {
+                println!("Render interrupted at {} seconds", now.elapsed().as_secs());
+                return;
+            }
+        }
+    }
 }
 
 use glium::backend::Facade;
@@ -66,28 +280,84 @@ use glium::Surface;
 use imgui::*;
 use imgui_glium_renderer::Renderer;
 use imgui_winit_support::{HiDpiMode, WinitPlatform};
+use std::path::Path;
 use std::time::Instant;
 
-fn save_render(id: TextureId, renderer: &mut Renderer) {
-    let raw_data: Vec<Vec<(u8, u8, u8, u8)>> = (*renderer.textures().get(id).unwrap()).read();
-    let raw_data_flattened: Vec<u8> = raw_data
-        .into_iter()
-        .flatten()
-        .collect::<Vec<(u8, u8, u8, u8)>>()
-        .into_iter()
-        .flat_map(|data| {
-            let r = std::iter::once(data.0);
-            let g = std::iter::once(data.1);
-            let b = std::iter::once(data.2);
-            r.chain(g).chain(b)
+// The format comes from the extension, EXR and HDR files keep the linear values
+fn save_render(frame: &FrameBuffer, path: &str, tone_mapping: &ToneMapping) {
+    match frame.save(Path::new(path), tone_mapping, ExrPixelType::Half) {
+        Ok(()) => println!("Saved {}", path),
+        Err(err) => eprintln!("{}", err),
+    }
+}
+
+// Writes the tone mapped pixels of the tile into the texture
+fn upload_tile(
+    texture: &glium::Texture2d,
+    frame: &FrameBuffer,
+    tile: Tile,
+    tone_mapping: &ToneMapping,
+) {
+    let rows: Vec<Vec<(u8, u8, u8)>> = (tile.y..tile.y + tile.height)
+        .map(|y| {
+            (tile.x..tile.x + tile.width)
+                .map(|x| {
+                    let [r, g, b] = tone_mapping.map(frame.get(x, y));
+                    (r, g, b)
+                })
+                .collect()
         })
-        .collect::<Vec<u8>>();
-    let image =
-        image::ImageBuffer::<image::Rgb<u8>, _>::from_raw(800, 800, raw_data_flattened).unwrap();
-    image.save("output.bmp").unwrap();
+        .collect();
+    texture.write(
+        glium::Rect {
+            left: tile.x,
+            bottom: tile.y,
+            width: tile.width,
+            height: tile.height,
+        },
+        rows,
+    );
 }
 
-fn ui_code(ui: &mut Ui, id: TextureId, renderer: &mut Renderer) {
+// What the viewer knows about the render thread
+struct RenderStatus {
+    state: RenderState,
+    samples: i32,
+    commands: Sender<RenderCommand>,
+    // How the linear image is turned into the displayed one
+    tone_mapping: ToneMapping,
+    sample_pattern: SamplePattern,
+}
+
+impl RenderStatus {
+    fn send(&mut self, command: RenderCommand) {
+        if self.commands.send(command).is_err() {
+            return;
+        }
+        if let RenderCommand::SetSamplePattern(pattern) = command {
+            self.sample_pattern = pattern;
+        }
+        // A new pattern restarts a running or paused render, a stopped one stays stopped
+        let restarted = match command {
+            RenderCommand::Restart => true,
+            RenderCommand::Start => self.state == RenderState::Stopped,
+            RenderCommand::SetSamplePattern(_) => self.state != RenderState::Stopped,
+            _ => false,
+        };
+        if restarted {
+            self.samples = 0;
+        }
+        self.state = match command {
+            RenderCommand::Start | RenderCommand::Restart => RenderState::Rendering,
+            RenderCommand::Pause => RenderState::Paused,
+            RenderCommand::Stop => RenderState::Stopped,
+            RenderCommand::SetSamplePattern(_) if restarted => RenderState::Rendering,
+            RenderCommand::SetSamplePattern(_) => RenderState::Stopped,
+        };
+    }
+}
+
+fn ui_code(ui: &mut Ui, id: TextureId, frame: Option<&FrameBuffer>, status: &mut RenderStatus) {
     let io = ui.io();
     Window::new(im_str!("Hello textures"))
         .size(io.display_size, Condition::Always)
@@ -104,19 +374,88 @@ fn ui_code(ui: &mut Ui, id: TextureId, renderer: &mut Renderer) {
         .build(ui, || {
             ui.menu_bar(|| {
                 ui.menu(im_str!("File"), true, || {
-                    if MenuItem::new(im_str!("Save Render")).build(ui) {
-                        save_render(id, renderer);
+                    let saves = [
+                        (im_str!("Save Render"), "output.bmp"),
+                        (im_str!("Save EXR"), "output.exr"),
+                        (im_str!("Save HDR"), "output.hdr"),
+                    ];
+                    for (label, path) in saves.iter() {
+                        if MenuItem::new(label).enabled(frame.is_some()).build(ui) {
+                            if let Some(frame) = frame {
+                                save_render(frame, path, &status.tone_mapping);
+                            }
+                        }
                     }
                 });
+                ui.menu(im_str!("Render"), true, || {
+                    if MenuItem::new(im_str!("Start"))
+                        .enabled(status.state != RenderState::Rendering)
+                        .build(ui)
+                    {
+                        status.send(RenderCommand::Start);
+                    }
+                    if MenuItem::new(im_str!("Pause"))
+                        .enabled(status.state == RenderState::Rendering)
+                        .build(ui)
+                    {
+                        status.send(RenderCommand::Pause);
+                    }
+                    if MenuItem::new(im_str!("Stop"))
+                        .enabled(status.state != RenderState::Stopped)
+                        .build(ui)
+                    {
+                        status.send(RenderCommand::Stop);
+                    }
+                    if MenuItem::new(im_str!("Restart")).build(ui) {
+                        status.send(RenderCommand::Restart);
+                    }
+                    ui.separator();
+                    for &pattern in SamplePattern::ALL.iter() {
+                        let label = ImString::new(pattern.name());
+                        if MenuItem::new(&label)
+                            .selected(status.sample_pattern == pattern)
+                            .build(ui)
+                            && status.sample_pattern != pattern
+                        {
+                            status.send(RenderCommand::SetSamplePattern(pattern));
+                        }
+                    }
+                });
+                ui.menu(im_str!("View"), true, || {
+                    for &tone_mapper in ToneMapper::ALL.iter() {
+                        let label = ImString::new(tone_mapper.name());
+                        if MenuItem::new(&label)
+                            .selected(status.tone_mapping.tone_mapper == tone_mapper)
+                            .build(ui)
+                        {
+                            status.tone_mapping.tone_mapper = tone_mapper;
+                        }
+                    }
+                    ui.separator();
+                    Slider::new(im_str!("Exposure"), -10.0..=10.0)
+                        .display_format(im_str!("%.1f stops"))
+                        .build(ui, &mut status.tone_mapping.exposure);
+                });
             });
-            ui.text(im_str!("Rendered Imaged:"));
-            Image::new(id, [800.0, 800.0])
+            let state = match status.state {
+                RenderState::Rendering => "Rendering",
+                RenderState::Paused => "Paused",
+                RenderState::Stopped => "Stopped",
+            };
+            ui.text(format!(
+                "{}: {} / {} samples per pixel",
+                state, status.samples, MAX_SAMPLES
+            ));
+            Image::new(id, [WIDTH as f32, HEIGHT as f32])
                 .border_col([1.0, 1.0, 1.0, 1.0])
                 .build(ui);
         });
 }
 
 fn main() {
+    let dim: (u32, u32) = (WIDTH, HEIGHT);
+    let scene = load_scene_from_args(dim.0 as f32 / dim.1 as f32);
+
     let mut events_loop = glutin::EventsLoop::new();
     let context = glutin::ContextBuilder::new().with_vsync(true);
     let builder = glutin::WindowBuilder::new()
@@ -133,7 +472,7 @@ fn main() {
     let mut platform = WinitPlatform::init(&mut imgui);
     let gl_window = display.gl_window();
     let window = gl_window.window();
-    platform.attach_window(imgui.io_mut(), &window, HiDpiMode::Rounded);
+    platform.attach_window(imgui.io_mut(), window, HiDpiMode::Rounded);
 
     let hidpi_factor = platform.hidpi_factor();
     let font_size = (13.0 * hidpi_factor) as f32;
@@ -150,7 +489,6 @@ fn main() {
     let mut last_frame = Instant::now();
     let mut run = true;
 
-    let dim: (u32, u32) = (800, 800);
     let black_data: Vec<u8> = vec![0; (dim.0 * dim.1 * 3) as usize];
 
     let raw = glium::texture::RawImage2d {
@@ -163,52 +501,97 @@ fn main() {
         glium::Texture2d::new(display.get_context(), raw).expect("Failed to create gl texture");
     let texture_id = renderer.textures().insert(std::rc::Rc::new(gl_texture));
 
-    let (sender, receiver) = std::sync::mpsc::channel();
+    let (command_sender, command_receiver) = std::sync::mpsc::channel();
+    let (update_sender, update_receiver) = std::sync::mpsc::channel();
     let thread_handle = std::thread::spawn(move || {
-        render_image(sender);
+        render_image(command_receiver, update_sender, scene);
     });
+    let mut status = RenderStatus {
+        state: RenderState::Rendering,
+        samples: 0,
+        commands: command_sender,
+        tone_mapping: ToneMapping::default(),
+        sample_pattern: SamplePattern::Sobol,
+    };
+    // Latest linear image and the tone mapping the texture was last built with
+    let mut frame: Option<FrameBuffer> = None;
+    let mut displayed_tone_mapping: Option<ToneMapping> = None;
+    // Tiles that changed since the texture was last written
+    let mut dirty_tiles: Vec<Tile> = Vec::new();
 
     while run {
         events_loop.poll_events(|event| {
-            platform.handle_event(imgui.io_mut(), &window, &event);
+            platform.handle_event(imgui.io_mut(), window, &event);
 
-            if let Event::WindowEvent { event, .. } = event {
-                if let WindowEvent::CloseRequested = event {
-                    run = false;
-                }
+            if let Event::WindowEvent {
+                event: WindowEvent::CloseRequested,
+                ..
+            } = event
+            {
+                run = false;
             }
         });
 
-        for pixel in receiver.try_iter() {
-            (*renderer.textures().get(texture_id).unwrap()).write(
-                glium::Rect {
-                    left: 0,
-                    bottom: pixel.y,
+        for update in update_receiver.try_iter() {
+            match update {
+                RenderUpdate::Tile { tile, pixels } => {
+                    let frame = frame.get_or_insert_with(|| FrameBuffer::new(WIDTH, HEIGHT));
+                    for ((x, y), color) in tile.pixels().zip(pixels) {
+                        frame.set(x, y, color);
+                    }
+                    dirty_tiles.push(tile);
+                }
+                RenderUpdate::Pass { samples } => status.samples = samples,
+                RenderUpdate::Frame {
+                    samples,
+                    frame: stopped_frame,
+                } => {
+                    frame = Some(stopped_frame);
+                    displayed_tone_mapping = None;
+                    status.samples = samples;
+                }
+                RenderUpdate::Finished => status.state = RenderState::Stopped,
+            }
+        }
+
+        if let Some(frame) = &frame {
+            // Only new tiles need uploading, unless the tone mapping changed
+            if displayed_tone_mapping != Some(status.tone_mapping) {
+                dirty_tiles.clear();
+                dirty_tiles.push(Tile {
+                    x: 0,
+                    y: 0,
                     width: dim.0,
-                    height: 1,
-                },
-                vec![pixel.data],
-            );
+                    height: dim.1,
+                });
+                displayed_tone_mapping = Some(status.tone_mapping);
+            }
+            let texture = renderer.textures().get(texture_id).unwrap();
+            for tile in dirty_tiles.drain(..) {
+                upload_tile(texture, frame, tile, &status.tone_mapping);
+            }
         }
 
         let io = imgui.io_mut();
         platform
-            .prepare_frame(io, &window)
+            .prepare_frame(io, window)
             .expect("Failed to start frame");
         last_frame = io.update_delta_time(last_frame);
         let mut ui = imgui.frame();
-        ui_code(&mut ui, texture_id, &mut renderer);
+        ui_code(&mut ui, texture_id, frame.as_ref(), &mut status);
 
         let mut target = display.draw();
         target.clear_color_srgb(0.0, 0.0, 0.0, 1.0);
-        platform.prepare_render(&ui, &window);
+        platform.prepare_render(&ui, window);
         let draw_data = ui.render();
         renderer
             .render(&mut target, draw_data)
             .expect("Rendering failed");
         target.finish().expect("Failed to swap buffers");
     }
-    drop(receiver);
+    // Dropping the channels tells the render thread to quit
+    drop(status);
+    drop(update_receiver);
 
     thread_handle.join().unwrap();
 }